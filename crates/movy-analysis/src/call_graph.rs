@@ -1,5 +1,5 @@
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, VecDeque},
     fmt::Display,
 };
 
@@ -7,8 +7,9 @@ use itertools::Itertools;
 use movy_types::{
     abi::{MoveAbiSignatureToken, MoveModuleId},
     bytecode::MoveModuleBytecodeAnalysis,
+    input::FunctionIdent,
 };
-use petgraph::graph::NodeIndex;
+use petgraph::{Direction, graph::NodeIndex};
 
 #[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct MoveCallGraphNode {
@@ -96,6 +97,36 @@ impl MoveCallGraph {
         }
     }
 
+    /// Shortest call distance from every function that can (transitively)
+    /// call `target` to `target` itself. The target has distance 0 and
+    /// functions that never reach it are absent from the result.
+    pub fn distances_to(&self, target: &FunctionIdent) -> BTreeMap<FunctionIdent, u64> {
+        let mut out = BTreeMap::new();
+        let node = MoveCallGraphNode {
+            module_id: target.0.clone(),
+            function: target.1.clone(),
+        };
+        let Some(start) = self.functions.get(&node) else {
+            return out;
+        };
+
+        let mut visited = BTreeSet::from([*start]);
+        let mut queue = VecDeque::from([(*start, 0u64)]);
+        while let Some((idx, distance)) = queue.pop_front() {
+            let node = &self.graph[idx];
+            out.insert(
+                FunctionIdent(node.module_id.clone(), node.function.clone()),
+                distance,
+            );
+            for caller in self.graph.neighbors_directed(idx, Direction::Incoming) {
+                if visited.insert(caller) {
+                    queue.push_back((caller, distance + 1));
+                }
+            }
+        }
+        out
+    }
+
     fn may_add_function(&mut self, fcall: MoveCallGraphNode) -> NodeIndex {
         if let Some(idx) = self.functions.get(&fcall) {
             *idx
@@ -106,3 +137,50 @@ impl MoveCallGraph {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use movy_types::input::MoveAddress;
+
+    use super::*;
+
+    fn ident(function: &str) -> FunctionIdent {
+        FunctionIdent::new(&MoveAddress::from_str("0xa").unwrap(), "pool", function)
+    }
+
+    fn call(cg: &mut MoveCallGraph, caller: &str, callee: &str) {
+        let node = |function: &str| {
+            let ident = ident(function);
+            MoveCallGraphNode {
+                module_id: ident.0,
+                function: ident.1,
+            }
+        };
+        let src = cg.may_add_function(node(caller));
+        let dst = cg.may_add_function(node(callee));
+        cg.graph.add_edge(
+            src,
+            dst,
+            MoveCallGraphEdge {
+                type_parameters: vec![],
+            },
+        );
+    }
+
+    #[test]
+    fn distances_follow_the_shortest_caller_chain() {
+        let mut cg = MoveCallGraph::new();
+        call(&mut cg, "entry", "swap");
+        call(&mut cg, "swap", "math");
+        call(&mut cg, "entry", "math");
+        call(&mut cg, "swap", "swap");
+        call(&mut cg, "math", "unrelated");
+
+        let distances = cg.distances_to(&ident("math"));
+        assert_eq!(distances.get(&ident("math")), Some(&0));
+        assert_eq!(distances.get(&ident("swap")), Some(&1));
+        assert_eq!(distances.get(&ident("entry")), Some(&1));
+        assert!(!distances.contains_key(&ident("unrelated")));
+        assert!(cg.distances_to(&ident("missing")).is_empty());
+    }
+}
@@ -5,3 +5,7 @@ pub const SCORE_TICK: u64 = 5000;
 pub const ADD_MOVECALL_PROB: f64 = 0.6;
 pub const MUTATE_SEQ_PROB: f64 = 0.2;
 pub const MUTATE_ARG_TIME: (f64, usize) = (1.0, 10);
+pub const DIRECTED_FUNCTION_SCORE: u64 = 10000;
+pub const DIRECTED_POWER_EXPONENT: f64 = 10.0;
+pub const MAX_PRELUDE_TRANSACTIONS: usize = 4;
+pub const MAX_STATE_PATCHES: usize = 4;
@@ -0,0 +1,144 @@
+use std::{collections::BTreeSet, path::Path, str::FromStr};
+
+use alloy_primitives::U256;
+use color_eyre::eyre::eyre;
+use movy_types::{
+    error::MovyError,
+    input::{MoveAddress, MoveTypeTag},
+};
+use serde::{Deserialize, Serialize};
+
+/// A number in a dictionary file. Small values can be written as plain
+/// integers, anything beyond i64 (or hex) must be quoted.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DictNumber {
+    Int(u64),
+    Text(String),
+}
+
+impl DictNumber {
+    fn to_bytes(&self, width: usize) -> Result<Vec<u8>, MovyError> {
+        let value = match self {
+            Self::Int(v) => U256::from(*v),
+            Self::Text(s) => U256::from_str(s.trim())
+                .map_err(|e| eyre!("can not parse dictionary number {} with {}", s, e))?,
+        };
+        if value.bit_len() > width * 8 {
+            return Err(eyre!("dictionary number {} does not fit in u{}", value, width * 8).into());
+        }
+        Ok(value.to_le_bytes::<32>()[..width].to_vec())
+    }
+}
+
+/// User supplied constants for `--dict`, in TOML or JSON:
+///
+/// ```toml
+/// u64 = [1000000000, "0xffffffffffffffff"]
+/// address = ["0x2"]
+/// bytes = ["0x68656c6c6f", "hello"]
+/// types = ["0x2::sui::SUI", "my_pkg::pool::LP"]
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FuzzDictionary {
+    pub u8: Vec<DictNumber>,
+    pub u16: Vec<DictNumber>,
+    pub u32: Vec<DictNumber>,
+    pub u64: Vec<DictNumber>,
+    pub u128: Vec<DictNumber>,
+    pub u256: Vec<DictNumber>,
+    pub address: Vec<String>,
+    /// Hex when prefixed with `0x`, raw utf8 otherwise
+    pub bytes: Vec<String>,
+    pub types: Vec<String>,
+}
+
+impl FuzzDictionary {
+    pub fn from_file(path: &Path) -> Result<Self, MovyError> {
+        let content = std::fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(toml::from_str(&content)?)
+        }
+    }
+
+    /// Little-endian encoded constants, in the same layout as the magic
+    /// numbers collected from comparisons.
+    pub fn magic_numbers(&self) -> Result<BTreeSet<Vec<u8>>, MovyError> {
+        let mut out = BTreeSet::new();
+        for (width, values) in [
+            (1, &self.u8),
+            (2, &self.u16),
+            (4, &self.u32),
+            (8, &self.u64),
+            (16, &self.u128),
+            (32, &self.u256),
+        ] {
+            for value in values {
+                out.insert(value.to_bytes(width)?);
+            }
+        }
+        for bytes in &self.bytes {
+            let bytes = if let Some(hex) = bytes.strip_prefix("0x") {
+                const_hex::decode(hex)
+                    .map_err(|e| eyre!("can not decode dictionary bytes {} with {}", bytes, e))?
+            } else {
+                bytes.as_bytes().to_vec()
+            };
+            if !bytes.is_empty() {
+                out.insert(bytes);
+            }
+        }
+        Ok(out)
+    }
+
+    pub fn addresses(&self) -> Result<Vec<MoveAddress>, MovyError> {
+        self.address
+            .iter()
+            .map(|s| MoveAddress::from_str(s.trim()))
+            .collect()
+    }
+
+    /// The types, resolved like the ones of seed sequences so that package
+    /// names can be used.
+    pub fn type_tags(
+        &self,
+        resolve_type: &impl Fn(&str) -> Result<MoveTypeTag, MovyError>,
+    ) -> Result<Vec<MoveTypeTag>, MovyError> {
+        self.types.iter().map(|s| resolve_type(s.trim())).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_are_encoded_by_width() {
+        let dict: FuzzDictionary = toml::from_str(
+            r#"
+u16 = [258]
+u64 = [1000000000, "0xffffffffffffffff"]
+bytes = ["0x0102", "ab"]
+"#,
+        )
+        .unwrap();
+        let magic = dict.magic_numbers().unwrap();
+        assert!(magic.contains(&vec![2, 1]));
+        assert!(magic.contains(&1_000_000_000u64.to_le_bytes().to_vec()));
+        assert!(magic.contains(&u64::MAX.to_le_bytes().to_vec()));
+        assert!(magic.contains(&vec![1, 2]));
+        assert!(magic.contains(&b"ab".to_vec()));
+    }
+
+    #[test]
+    fn oversized_numbers_are_rejected() {
+        let dict = FuzzDictionary {
+            u8: vec![DictNumber::Int(256)],
+            ..Default::default()
+        };
+        assert!(dict.magic_numbers().is_err());
+    }
+}
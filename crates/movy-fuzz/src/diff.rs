@@ -0,0 +1,576 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use move_trace_format::{
+    format::{TraceIndex, TraceValue},
+    value::SerializableMoveValue,
+};
+use movy_replay::{
+    db::{ObjectStoreInfo, ObjectStoreMintObject},
+    exec::SuiExecutor,
+    meta::Metadata,
+    tracer::{
+        MovySuiTracerExt,
+        state::{TraceState, format_serializable_move_value},
+    },
+};
+use movy_sui::database::cache::ObjectSuiStoreCommit;
+use movy_types::{
+    error::MovyError,
+    input::{
+        FunctionIdent, InputArgument, MoveAddress, MoveSequence, MoveSequenceCall, MoveStructTag,
+        MoveTypeTag, SuiObjectInputArgument,
+    },
+};
+use serde::{Deserialize, Serialize};
+use sui_types::{
+    base_types::ObjectID,
+    effects::TransactionEffectsAPI,
+    execution_status::{ExecutionFailureStatus, ExecutionStatus},
+    object::Owner,
+    storage::{BackingPackageStore, BackingStore, ObjectStore},
+};
+use tracing::{debug, warn};
+
+/// Relates the old version of a package to the new one deployed next to it,
+/// so a sequence generated against the old version can be replayed on the
+/// new one.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VersionMapping {
+    /// Package ids and module addresses, old to new
+    pub packages: BTreeMap<MoveAddress, MoveAddress>,
+    /// Objects created when deploying each version, paired by type where
+    /// each version holds a single object of it
+    pub objects: BTreeMap<MoveAddress, MoveAddress>,
+    /// Functions of the new version
+    pub functions: BTreeSet<FunctionIdent>,
+}
+
+impl VersionMapping {
+    pub fn new(
+        old_meta: &Metadata,
+        new_meta: &Metadata,
+        old_package: MoveAddress,
+        new_package: MoveAddress,
+    ) -> Self {
+        let mut mapping = Self::default();
+        mapping.packages.insert(old_package, new_package);
+        let module_address = |meta: &Metadata, package: &MoveAddress| {
+            meta.abis
+                .get(package)
+                .and_then(|abi| abi.modules.first())
+                .map(|module| module.module_id.module_address)
+        };
+        if let (Some(old), Some(new)) = (
+            module_address(old_meta, &old_package),
+            module_address(new_meta, &new_package),
+        ) {
+            mapping.packages.insert(old, new);
+        }
+
+        for (ty, old_objects) in old_meta.types_pool.iter() {
+            let mapped = mapping.map_type(ty);
+            if &mapped == ty {
+                continue;
+            }
+            let Some(new_objects) = new_meta.types_pool.get(&mapped) else {
+                continue;
+            };
+            // Each deployment creates its objects under fresh ids, so only a
+            // single object per type pairs up unambiguously
+            if old_objects.len() != 1 || new_objects.len() != 1 {
+                warn!(
+                    "{} objects of {} in the old version and {} of {} in the new one, not pairing them",
+                    old_objects.len(),
+                    ty,
+                    new_objects.len(),
+                    mapped
+                );
+                continue;
+            }
+            mapping
+                .objects
+                .extend(old_objects.iter().copied().zip(new_objects.iter().copied()));
+        }
+
+        if let Some(abi) = new_meta.abis.get(&new_package) {
+            for module in abi.modules.iter() {
+                for function in module.functions.iter() {
+                    mapping.functions.insert(FunctionIdent::new(
+                        &new_package,
+                        &module.module_id.module_name,
+                        &function.name,
+                    ));
+                }
+            }
+        }
+        mapping
+    }
+
+    pub fn map_address(&self, address: &MoveAddress) -> MoveAddress {
+        self.packages
+            .get(address)
+            .or_else(|| self.objects.get(address))
+            .copied()
+            .unwrap_or(*address)
+    }
+
+    /// New addresses back to the old ones, to compare the outcomes of both
+    /// versions.
+    pub fn reversed(&self) -> BTreeMap<MoveAddress, MoveAddress> {
+        self.packages
+            .iter()
+            .chain(self.objects.iter())
+            .map(|(old, new)| (*new, *old))
+            .collect()
+    }
+
+    pub fn map_type(&self, ty: &MoveTypeTag) -> MoveTypeTag {
+        map_type_with(ty, &|address| {
+            self.packages.get(address).copied().unwrap_or(*address)
+        })
+    }
+
+    fn map_input<T: ObjectStore>(&self, input: &InputArgument, db: &T) -> Option<InputArgument> {
+        Some(match input {
+            InputArgument::Address(address) => InputArgument::Address(self.map_address(address)),
+            InputArgument::Vector(ty, items) => InputArgument::Vector(
+                self.map_type(ty),
+                items
+                    .iter()
+                    .map(|item| self.map_input(item, db))
+                    .collect::<Option<Vec<_>>>()?,
+            ),
+            InputArgument::Object(ty, arg) => {
+                let id = match arg {
+                    SuiObjectInputArgument::ImmOrOwnedObject(r)
+                    | SuiObjectInputArgument::Receiving(r) => r.0,
+                    SuiObjectInputArgument::SharedObject { id, .. } => *id,
+                };
+                let id: ObjectID = self.map_address(&id.into()).into();
+                // Versions differ between both stores, resolve them again
+                let Some(object) = db.get_object(&id) else {
+                    debug!("Object {} is missing in the new version", id);
+                    return None;
+                };
+                let arg = match arg {
+                    SuiObjectInputArgument::ImmOrOwnedObject(_) => {
+                        SuiObjectInputArgument::ImmOrOwnedObject(object.compute_object_reference())
+                    }
+                    SuiObjectInputArgument::Receiving(_) => {
+                        SuiObjectInputArgument::Receiving(object.compute_object_reference())
+                    }
+                    SuiObjectInputArgument::SharedObject {
+                        initial_shared_version,
+                        mutable,
+                        ..
+                    } => SuiObjectInputArgument::SharedObject {
+                        id,
+                        initial_shared_version: match object.owner() {
+                            Owner::Shared {
+                                initial_shared_version: version,
+                            } => *version,
+                            _ => *initial_shared_version,
+                        },
+                        mutable: *mutable,
+                    },
+                };
+                InputArgument::Object(self.map_type(ty), arg)
+            }
+            other => other.clone(),
+        })
+    }
+
+    /// Rewrite `sequence` onto the new version, objects are resolved in `db`.
+    /// None if it calls a function the new version lacks or uses an object
+    /// missing there.
+    pub fn map_sequence<T: ObjectStore>(
+        &self,
+        sequence: &MoveSequence,
+        db: &T,
+    ) -> Option<MoveSequence> {
+        let inputs = sequence
+            .inputs
+            .iter()
+            .map(|input| self.map_input(input, db))
+            .collect::<Option<Vec<_>>>()?;
+        let mut commands = Vec::with_capacity(sequence.commands.len());
+        for cmd in sequence.commands.iter() {
+            let cmd = match cmd {
+                MoveSequenceCall::Call(call) => {
+                    let mut call = call.clone();
+                    call.module_id = self.map_address(&call.module_id);
+                    call.type_arguments = call
+                        .type_arguments
+                        .iter()
+                        .map(|ty| self.map_type(ty))
+                        .collect();
+                    if self.packages.values().any(|p| *p == call.module_id)
+                        && !self.functions.contains(&FunctionIdent::new(
+                            &call.module_id,
+                            &call.module_name,
+                            &call.function,
+                        ))
+                    {
+                        debug!(
+                            "{}::{} is gone in the new version",
+                            call.module_name, call.function
+                        );
+                        return None;
+                    }
+                    MoveSequenceCall::Call(call)
+                }
+                MoveSequenceCall::MakeMoveVec(ty, args) => {
+                    MoveSequenceCall::MakeMoveVec(self.map_type(ty), args.clone())
+                }
+                other => other.clone(),
+            };
+            commands.push(cmd);
+        }
+        Some(MoveSequence { inputs, commands })
+    }
+}
+
+fn map_type_with(ty: &MoveTypeTag, f: &impl Fn(&MoveAddress) -> MoveAddress) -> MoveTypeTag {
+    match ty {
+        MoveTypeTag::Vector(inner) => MoveTypeTag::Vector(Box::new(map_type_with(inner, f))),
+        MoveTypeTag::Struct(st) => MoveTypeTag::Struct(MoveStructTag {
+            address: f(&st.address),
+            module: st.module.clone(),
+            name: st.name.clone(),
+            tys: st.tys.iter().map(|ty| map_type_with(ty, f)).collect(),
+        }),
+        other => other.clone(),
+    }
+}
+
+/// Records the values returned by the calls of a sequence.
+#[derive(Debug, Default)]
+pub struct ReturnValueTracer {
+    pub returns: Vec<Vec<TraceValue>>,
+}
+
+impl MovySuiTracerExt for ReturnValueTracer {
+    fn on_raw_event(
+        &mut self,
+        _state: &TraceState,
+        _ev: &move_trace_format::format::TraceEvent,
+    ) -> bool {
+        true
+    }
+
+    fn close_frame(
+        &mut self,
+        state: &TraceState,
+        _frame_id: TraceIndex,
+        return_: &Vec<TraceValue>,
+        _gas_left: u64,
+    ) {
+        // Only the frames called by the sequence itself
+        if state.call_stack.len() == 1 {
+            self.returns.push(return_.clone());
+        }
+    }
+}
+
+/// Renders values of one side. Addresses of the new version are mapped to
+/// the old ones and those not existing before the execution, e.g. fresh
+/// object ids, are numbered by their first appearance.
+struct Normalizer<'a, T> {
+    db: &'a T,
+    mapping: &'a BTreeMap<MoveAddress, MoveAddress>,
+    fresh: BTreeMap<MoveAddress, usize>,
+}
+
+impl<'a, T: ObjectStore> Normalizer<'a, T> {
+    fn address(&mut self, address: MoveAddress) -> String {
+        if let Some(old) = self.mapping.get(&address) {
+            return old.to_canonical_string(true);
+        }
+        if self.db.get_object(&address.into()).is_some() {
+            return address.to_canonical_string(true);
+        }
+        let next = self.fresh.len();
+        format!("fresh#{}", self.fresh.entry(address).or_insert(next))
+    }
+
+    fn ty(&self, ty: &MoveTypeTag) -> String {
+        map_type_with(ty, &|address| {
+            self.mapping.get(address).copied().unwrap_or(*address)
+        })
+        .to_string()
+    }
+
+    fn value(&mut self, value: &SerializableMoveValue) -> String {
+        match value {
+            SerializableMoveValue::Address(address) => self.address((*address).into()),
+            SerializableMoveValue::Struct(st) => {
+                let ty = self.ty(&MoveTypeTag::Struct(st.type_.clone().into()));
+                let fields = st
+                    .fields
+                    .iter()
+                    .map(|(name, v)| format!("{}: {}", name, self.value(v)))
+                    .collect::<Vec<_>>();
+                format!("{} {{ {} }}", ty, fields.join(", "))
+            }
+            SerializableMoveValue::Variant(variant) => {
+                let ty = self.ty(&MoveTypeTag::Struct(variant.type_.clone().into()));
+                let fields = variant
+                    .fields
+                    .iter()
+                    .map(|(name, v)| format!("{}: {}", name, self.value(v)))
+                    .collect::<Vec<_>>();
+                format!("{}::{}({})", ty, variant.variant_name, fields.join(", "))
+            }
+            SerializableMoveValue::Vector(items) if !items.iter().all(is_u8) => {
+                let items = items.iter().map(|v| self.value(v)).collect::<Vec<_>>();
+                format!("Vector[{}]", items.join(", "))
+            }
+            other => format_serializable_move_value(other),
+        }
+    }
+
+    fn trace_value(&mut self, value: &TraceValue) -> String {
+        match value {
+            TraceValue::RuntimeValue { value } => self.value(value),
+            TraceValue::ImmRef { snapshot, .. } => format!("&{}", self.value(snapshot)),
+            TraceValue::MutRef { snapshot, .. } => format!("&mut {}", self.value(snapshot)),
+        }
+    }
+
+    fn json(&mut self, value: &serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::String(s) if s.starts_with("0x") && s.len() == 66 => {
+                match s.parse::<MoveAddress>() {
+                    Ok(address) => serde_json::Value::String(self.address(address)),
+                    Err(_) => value.clone(),
+                }
+            }
+            serde_json::Value::Array(items) => {
+                serde_json::Value::Array(items.iter().map(|v| self.json(v)).collect())
+            }
+            serde_json::Value::Object(fields) => serde_json::Value::Object(
+                fields
+                    .iter()
+                    .map(|(k, v)| (k.clone(), self.json(v)))
+                    .collect(),
+            ),
+            other => other.clone(),
+        }
+    }
+
+    fn owner(&mut self, owner: &Owner) -> String {
+        match owner {
+            Owner::AddressOwner(address) => format!("owned by {}", self.address((*address).into())),
+            Owner::ObjectOwner(address) => format!("child of {}", self.address((*address).into())),
+            Owner::Shared { .. } => "shared".to_string(),
+            Owner::Immutable => "immutable".to_string(),
+            other => format!("{:?}", other),
+        }
+    }
+}
+
+fn is_u8(value: &SerializableMoveValue) -> bool {
+    matches!(value, SerializableMoveValue::U8(_))
+}
+
+/// What a sequence did on one version, comparable across versions.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffOutcome {
+    pub status: String,
+    pub returns: Vec<String>,
+    pub events: Vec<String>,
+    pub objects: Vec<String>,
+}
+
+impl DiffOutcome {
+    /// Which parts differ between `self` and `other`.
+    pub fn divergences(&self, other: &Self) -> Vec<&'static str> {
+        let mut kinds = vec![];
+        if self.status != other.status {
+            kinds.push(
+                if self.status.starts_with("abort") || other.status.starts_with("abort") {
+                    "abort_code"
+                } else {
+                    "status"
+                },
+            );
+        }
+        if self.returns != other.returns {
+            kinds.push("return_values");
+        }
+        if self.events != other.events {
+            kinds.push("events");
+        }
+        if self.objects != other.objects {
+            kinds.push("object_changes");
+        }
+        kinds
+    }
+}
+
+fn render_status<T: ObjectStore>(
+    status: &ExecutionStatus,
+    normalizer: &mut Normalizer<T>,
+) -> String {
+    match status {
+        ExecutionStatus::Success => "success".to_string(),
+        ExecutionStatus::Failure { error, command } => {
+            let command = command.map_or("-".to_string(), |c| c.to_string());
+            match error {
+                ExecutionFailureStatus::MoveAbort(location, code) => format!(
+                    "abort {} in {}::{}::{} at command {}",
+                    code,
+                    normalizer.address((*location.module.address()).into()),
+                    location.module.name(),
+                    location.function_name.as_deref().unwrap_or("?"),
+                    command
+                ),
+                other => {
+                    let name = format!("{:?}", other);
+                    let name = name
+                        .split(|c: char| !c.is_alphanumeric())
+                        .next()
+                        .unwrap_or_default()
+                        .to_string();
+                    format!("failure {} at command {}", name, command)
+                }
+            }
+        }
+    }
+}
+
+/// Execute `sequence` on a layer above `executor` and render its outcome.
+/// `mapping` takes addresses of this side back to the old version. None if
+/// the transaction could not be executed at all.
+pub fn diff_outcome<T>(
+    executor: &SuiExecutor<T>,
+    meta: &Metadata,
+    mapping: &BTreeMap<MoveAddress, MoveAddress>,
+    sequence: &MoveSequence,
+    sender: MoveAddress,
+    gas: MoveAddress,
+    epoch: u64,
+    epoch_ms: u64,
+) -> Result<Option<DiffOutcome>, MovyError>
+where
+    T: ObjectStore
+        + BackingStore
+        + BackingPackageStore
+        + ObjectSuiStoreCommit
+        + ObjectStoreMintObject
+        + ObjectStoreInfo
+        + Clone,
+{
+    let layered = executor.layered();
+    let out = match layered.run_ptb_with_movy_tracer_gas(
+        sequence.to_ptb()?,
+        epoch,
+        epoch_ms,
+        sender.into(),
+        gas.into(),
+        Some(ReturnValueTracer::default()),
+    ) {
+        Ok(out) => out,
+        Err(e) => {
+            debug!("Sequence can not be executed: {}", e);
+            return Ok(None);
+        }
+    };
+    let mut normalizer = Normalizer {
+        db: &executor.db,
+        mapping,
+        fresh: BTreeMap::new(),
+    };
+    let effects = &out.results.effects;
+    let status = render_status(effects.status(), &mut normalizer);
+
+    let returns = out
+        .tracer
+        .map(|tracer| tracer.returns)
+        .unwrap_or_default()
+        .iter()
+        .map(|values| {
+            values
+                .iter()
+                .map(|v| normalizer.trace_value(v))
+                .collect::<Vec<_>>()
+                .join(", ")
+        })
+        .collect();
+
+    let mut events = vec![];
+    for event in out.results.store.events.data.iter() {
+        let ty = normalizer.ty(&MoveTypeTag::Struct(event.type_.clone().into()));
+        match meta.decode_sui_event(event)? {
+            Some((_, value)) => events.push(format!("{} {}", ty, normalizer.json(&value))),
+            None => events.push(format!("{} 0x{}", ty, const_hex::encode(&event.contents))),
+        }
+    }
+
+    // Sorted before numbering, the order of effects follows the object ids
+    let mut changes = effects
+        .all_changed_objects()
+        .into_iter()
+        .map(|(r, owner, kind)| {
+            let ty = out
+                .results
+                .store
+                .written
+                .get(&r.0)
+                .and_then(|o| o.struct_tag())
+                .map(|st| normalizer.ty(&MoveTypeTag::Struct(st.into())))
+                .unwrap_or_default();
+            (format!("{:?}", kind), ty, Some(owner), r.0)
+        })
+        .chain(
+            effects
+                .all_removed_objects()
+                .into_iter()
+                .map(|(r, kind)| (format!("{:?}", kind), String::new(), None, r.0)),
+        )
+        .collect::<Vec<_>>();
+    changes.sort_by(|a, b| (&a.0, &a.1).cmp(&(&b.0, &b.1)));
+    let mut objects = changes
+        .into_iter()
+        .map(|(kind, ty, owner, id)| {
+            let id = normalizer.address(id.into());
+            let owner = owner.map(|o| normalizer.owner(&o)).unwrap_or_default();
+            format!("{} {} {} {}", kind, id, ty, owner)
+        })
+        .collect::<Vec<_>>();
+    objects.sort();
+
+    Ok(Some(DiffOutcome {
+        status,
+        returns,
+        events,
+        objects,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn addr(s: &str) -> MoveAddress {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn types_follow_the_new_package() {
+        let mapping = VersionMapping {
+            packages: BTreeMap::from([(addr("0xa"), addr("0xb"))]),
+            ..Default::default()
+        };
+        let ty: MoveTypeTag = "0x2::coin::Coin<0xa::pool::LP<0x2::sui::SUI>>"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            mapping.map_type(&ty),
+            "0x2::coin::Coin<0xb::pool::LP<0x2::sui::SUI>>"
+                .parse::<MoveTypeTag>()
+                .unwrap()
+        );
+        assert_eq!(mapping.reversed().get(&addr("0xb")), Some(&addr("0xa")));
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use color_eyre::eyre::eyre;
+use libafl_bolts::impl_serdeany;
+use move_binary_format::CompiledModule;
+use movy_analysis::call_graph::MoveCallGraph;
+use movy_replay::tracer::directed::DirectedTrace;
+use movy_sui::lcov::BytecodeLocation;
+use movy_types::{bytecode::MoveModuleBytecodeAnalysis, error::MovyError, input::FunctionIdent};
+use serde::{Deserialize, Serialize};
+use serde_json_any_key::any_key_map;
+use sui_types::storage::ObjectStore;
+
+use crate::{
+    r#const::{DIRECTED_FUNCTION_SCORE, DIRECTED_POWER_EXPONENT},
+    meta::FuzzMetadata,
+};
+
+/// State of a directed campaign: how far every function is from the target
+/// in the call graph, and which bytecode locations make up the target line.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectedMetadata {
+    pub target: FunctionIdent,
+    pub line: Option<usize>,
+    pub locations: BTreeSet<BytecodeLocation>,
+    #[serde(with = "any_key_map")]
+    pub distances: BTreeMap<FunctionIdent, u64>,
+    pub max_distance: u64,
+    pub best_distance: Option<u64>,
+}
+
+impl_serdeany!(DirectedMetadata);
+
+/// Functions are identified by their runtime module address in traces and
+/// bytecode, while the CLI and the fuzz metadata use package ids.
+fn runtime_ident(meta: &FuzzMetadata, function: &FunctionIdent) -> FunctionIdent {
+    let package = meta
+        .module_address_to_package
+        .get(&function.0.module_address)
+        .unwrap_or(&function.0.module_address);
+    let module_address = meta
+        .module_address_to_package
+        .iter()
+        .find(|(_, pkg)| *pkg == package)
+        .map(|(module, _)| *module)
+        .unwrap_or(function.0.module_address);
+    FunctionIdent::new(&module_address, &function.0.module_name, &function.1)
+}
+
+impl DirectedMetadata {
+    pub fn from_store<T: ObjectStore>(
+        meta: &FuzzMetadata,
+        db: &T,
+        target: &FunctionIdent,
+        line: Option<usize>,
+        locations: BTreeSet<BytecodeLocation>,
+    ) -> Result<Self, MovyError> {
+        let mut cg = MoveCallGraph::new();
+        for package_id in meta.abis.keys() {
+            let Some(object) = db.get_object(&(*package_id).into()) else {
+                continue;
+            };
+            let Some(package) = object.data.try_as_package() else {
+                continue;
+            };
+            for bytes in package.serialized_module_map().values() {
+                let module = CompiledModule::deserialize_with_defaults(bytes)?;
+                cg.add_bytecode_analysis(&MoveModuleBytecodeAnalysis::from_sui_module(&module));
+            }
+        }
+
+        let target = runtime_ident(meta, target);
+        let distances = cg.distances_to(&target);
+        if distances.is_empty() {
+            return Err(eyre!("target {} is not found in the call graph", target).into());
+        }
+        let max_distance = distances.values().copied().max().unwrap_or_default()
+            + u64::from(!locations.is_empty());
+        Ok(Self {
+            target,
+            line,
+            locations,
+            distances,
+            max_distance,
+            best_distance: None,
+        })
+    }
+
+    /// Distance of one execution to the target: the closest function entered,
+    /// plus one when a target line is set but was not executed.
+    pub fn distance(&self, trace: &DirectedTrace) -> Option<u64> {
+        let closest = trace
+            .functions
+            .iter()
+            .filter_map(|f| self.distances.get(f))
+            .min()
+            .copied()?;
+        if self.locations.is_empty() {
+            Some(closest)
+        } else if trace.reached_location {
+            Some(0)
+        } else {
+            Some(closest + 1)
+        }
+    }
+
+    /// Power schedule multiplier, from 2^(-E/2) for inputs that never get on a
+    /// path to the target up to 2^(E/2) for inputs hitting it.
+    pub fn power_factor(&self, distance: Option<u64>) -> f64 {
+        let max = (self.max_distance + 1) as f64;
+        let distance = distance.map(|d| d as f64).unwrap_or(max).min(max);
+        let closeness = 1.0 - distance / max;
+        2.0f64.powf(DIRECTED_POWER_EXPONENT * (closeness - 0.5))
+    }
+
+    /// Make entry functions that can reach the target more likely to be
+    /// appended to sequences.
+    pub fn boost_function_scores(&self, meta: &mut FuzzMetadata) {
+        let boosts = meta
+            .function_scores
+            .keys()
+            .filter_map(|f| {
+                self.distances
+                    .get(&runtime_ident(meta, f))
+                    .map(|d| (f.clone(), DIRECTED_FUNCTION_SCORE / (d + 1)))
+            })
+            .collect::<Vec<_>>();
+        for (function, boost) in boosts {
+            if let Some(score) = meta.function_scores.get_mut(&function) {
+                *score += boost;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn closer_inputs_get_more_energy() {
+        let target = FunctionIdent::from_str("0xa::pool::math").unwrap();
+        let metadata = DirectedMetadata {
+            target: target.clone(),
+            line: None,
+            locations: BTreeSet::new(),
+            distances: BTreeMap::from([(target, 0)]),
+            max_distance: 3,
+            best_distance: None,
+        };
+        let hit = metadata.power_factor(Some(0));
+        let near = metadata.power_factor(Some(1));
+        let missed = metadata.power_factor(None);
+        assert_eq!(hit, 2.0f64.powf(DIRECTED_POWER_EXPONENT / 2.0));
+        assert_eq!(missed, 2.0f64.powf(-DIRECTED_POWER_EXPONENT / 2.0));
+        assert!(hit > near && near > missed);
+        assert_eq!(metadata.power_factor(Some(100)), missed);
+    }
+}
@@ -4,6 +4,7 @@ use std::{
     fmt::Display,
     marker::PhantomData,
     ops::AddAssign,
+    str::FromStr,
 };
 
 use libafl::{
@@ -15,24 +16,31 @@ use libafl::{
 use libafl_bolts::tuples::{Handle, MatchNameRef, RefIndexable};
 use movy_replay::{
     db::{ObjectStoreInfo, ObjectStoreMintObject},
-    exec::{ExecutionTracedResults, SuiExecutor},
+    exec::{ExecutionTracedResults, SuiExecutor, very_big_gas},
     tracer::{
-        SelectiveTracer, TeeTracer,
+        NopTracer, SelectiveTracer, TeeTracer,
         concolic::ConcolicState,
+        directed::DirectedCollector,
         fuzz::{PackageResolvedCache, PackageResolver, SuiFuzzTracer},
         lcov::LineCoverageCollector,
         op::Log,
         oracle::SuiGeneralOracle,
     },
 };
-use movy_sui::database::cache::{CachedStore, ObjectSuiStoreCommit};
-use movy_sui::lcov::BytecodeLocation;
+use movy_sui::database::cache::{CachedSnapshot, ObjectSuiStoreCommit};
+use movy_sui::lcov::{BytecodeLocation, LineCoverageMap};
 use movy_types::{
-    input::{FunctionIdent, MoveAddress},
+    error::MovyError,
+    input::{
+        FunctionIdent, InputArgument, MoveAddress, MoveOwner, MoveSequence, MoveTypeTag,
+        SuiObjectInputArgument,
+    },
     oracle::{Event, OracleFinding},
 };
 use serde::{Deserialize, Serialize};
 use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    digests::TransactionDigest,
     effects::TransactionEffectsAPI,
     execution_status::ExecutionStatus,
     storage::{BackingStore, ObjectStore},
@@ -40,9 +48,13 @@ use sui_types::{
 use tracing::{info, trace};
 
 use crate::{
-    input::MoveInput,
+    directed::DirectedMetadata,
+    input::{FuzzTransaction, MoveInput},
     meta::HasFuzzMetadata,
+    oracles::sui::FrameGasTracer,
     state::{ExtraNonSerdeFuzzState, HasExtraState, HasFuzzEnv},
+    state_fuzz::{apply_state_patches, mark_requires_state},
+    upgrade::UpgradePlan,
 };
 
 pub const CODE_OBSERVER_NAME: &str = "code_observer";
@@ -63,6 +75,8 @@ pub struct ExecutionOutcome {
     pub allowed_success: bool,
     #[serde(default)]
     pub findings: Vec<OracleFinding>,
+    #[serde(default)]
+    pub distance: Option<u64>,
 }
 
 impl Display for ExecutionOutcome {
@@ -88,11 +102,20 @@ pub struct SuiFuzzExecutor<T, OT, RT, I, S> {
     pub oracles: RT,
     pub packages_cache: PackageResolvedCache,
     pub line_coverage: Option<LineCoverageCollector>,
+    pub directed: Option<DirectedCollector>,
+    /// Source maps of the local packages to locate findings with
+    pub source_map: Option<LineCoverageMap>,
     // pub minted_gas: Object,
     // pub log_tracer: Option<SuiLogTracer>,
     pub ph: PhantomData<(I, S)>,
     pub epoch: u64,
     pub epoch_ms: u64,
+    /// Trace integers as bit-vectors of their width instead of integers
+    pub concolic_bitvector: bool,
+    /// Version N+1 published on the forked state by
+    /// [`UpgradePlan::publish`], reused by inputs without a prelude. When
+    /// missing, every input upgrades on its own.
+    pub upgraded: Option<CachedSnapshot>,
 }
 
 impl<T, OT, RT, I, S> SuiFuzzExecutor<T, OT, RT, I, S> {
@@ -102,6 +125,134 @@ impl<T, OT, RT, I, S> SuiFuzzExecutor<T, OT, RT, I, S> {
             .map(|collector| collector.hits())
             .unwrap_or_default()
     }
+
+    /// Fill in the location, transactions and fingerprint shared by all
+    /// findings, where `sequence` is the attacker transaction as executed.
+    fn complete_findings(
+        &self,
+        findings: &mut [OracleFinding],
+        prelude: &[FuzzTransaction],
+        sequence: &MoveSequence,
+    ) {
+        for finding in findings.iter_mut() {
+            if finding.location.is_none() {
+                finding.location = finding.extra_location();
+            }
+            if let Some(location) = finding.location.as_mut()
+                && let (Some(map), Some(pc)) = (&self.source_map, location.pc)
+                && let Some((file, line)) =
+                    map.source_line(&location.module, &location.function, pc)
+            {
+                location.file = Some(file);
+                location.line = Some(line);
+            }
+            finding.prelude = prelude
+                .iter()
+                .map(|tx| (tx.sender, tx.sequence.clone()))
+                .collect();
+            finding.sequence = Some(sequence.clone());
+            finding.fingerprint = finding.compute_fingerprint();
+        }
+    }
+}
+
+/// Point owned and receiving objects of `sequence` at their latest versions,
+/// which earlier transactions of the same input may have bumped.
+pub(crate) fn refresh_object_refs<T: ObjectStoreInfo>(db: &T, sequence: &mut MoveSequence) {
+    for input in sequence.inputs.iter_mut() {
+        if let InputArgument::Object(
+            _,
+            SuiObjectInputArgument::ImmOrOwnedObject(obj) | SuiObjectInputArgument::Receiving(obj),
+        ) = input
+            && let Ok(info) = db.get_move_object_info(obj.0.into())
+        {
+            *obj = info.sui_reference();
+        }
+    }
+}
+
+/// Gas coin used by a non-attacker sender, minted on first use.
+pub(crate) fn sender_gas<T: ObjectStoreMintObject + ObjectStore>(
+    db: &T,
+    sender: MoveAddress,
+) -> Result<ObjectID, MovyError> {
+    let id = ObjectID::derive_id(
+        TransactionDigest::new(SuiAddress::from(sender).to_inner()),
+        0,
+    );
+    if db.get_object(&id).is_none() {
+        db.mint_coin_id(
+            MoveTypeTag::from_str("0x2::sui::SUI")?,
+            MoveOwner::AddressOwner(sender),
+            id.into(),
+            very_big_gas(),
+        )?;
+    }
+    Ok(id)
+}
+
+/// Execute and commit the `prelude` of an input in order, returning `sequence`
+/// updated for the resulting objects together with the epoch and clock it runs
+/// at. With an `upgrade`, the prelude runs on the version being upgraded and
+/// the upgrade is committed right before `sequence`. With `prelude_gas`, the
+/// gas spent by the calls of each function of the prelude is collected.
+pub fn commit_prelude<T>(
+    executor: &mut SuiExecutor<T>,
+    prelude: &[FuzzTransaction],
+    sequence: &MoveSequence,
+    attacker: MoveAddress,
+    gas_id: MoveAddress,
+    mut epoch: u64,
+    mut epoch_ms: u64,
+    upgrade: Option<&UpgradePlan>,
+    mut prelude_gas: Option<&mut BTreeMap<FunctionIdent, Vec<u64>>>,
+) -> Result<(MoveSequence, u64, u64), MovyError>
+where
+    T: ObjectStore + BackingStore + ObjectSuiStoreCommit + ObjectStoreMintObject + ObjectStoreInfo,
+{
+    for (idx, tx) in prelude.iter().enumerate() {
+        let mut tx_sequence = match upgrade {
+            Some(plan) => plan.downgrade(&tx.sequence),
+            None => tx.sequence.clone(),
+        };
+        refresh_object_refs(&executor.db, &mut tx_sequence);
+        let gas = if tx.sender == attacker {
+            gas_id.into()
+        } else {
+            sender_gas(&executor.db, tx.sender)?
+        };
+        let out = executor.run_ptb_with_movy_tracer_gas(
+            tx_sequence.to_ptb()?,
+            epoch,
+            epoch_ms,
+            tx.sender.into(),
+            gas,
+            prelude_gas.is_some().then(FrameGasTracer::default),
+        )?;
+        if let (Some(prelude_gas), Some(tracer)) = (prelude_gas.as_deref_mut(), out.tracer) {
+            for (function, costs) in tracer.costs.calls {
+                prelude_gas.entry(function).or_default().extend(costs);
+            }
+        }
+        let results = out.results;
+        trace!(
+            "Prelude transaction {} finished with status: {:?}",
+            idx,
+            results.effects.status()
+        );
+        executor.db.commit_store(results.store, &results.effects)?;
+        epoch += tx.elapsed_epochs;
+        epoch_ms += tx.elapsed_ms;
+    }
+    if let Some(plan) = upgrade {
+        plan.apply(executor, epoch, epoch_ms)?;
+        trace!("Upgraded {} to {}", plan.package, plan.upgraded);
+    }
+    let mut sequence = sequence.clone();
+    if !prelude.is_empty() || upgrade.is_some() {
+        refresh_object_refs(&executor.db, &mut sequence);
+    }
+    Ok((sequence, epoch, epoch_ms))
 }
 
 impl<T, OT, RT, I, S> HasObservers for SuiFuzzExecutor<T, OT, RT, I, S> {
@@ -152,10 +303,39 @@ where
             code_ob[0] = 1;
         }
 
-        self.oracles
-            .pre_execution(&self.executor.db, state, input.sequence())?;
+        // Every transaction of the input commits into a layer that is dropped
+        // afterwards, so the forked state is the same for all executions.
+        let mut executor = self.executor.layered();
+        // Without a prelude the upgrade happens on the forked state as is
+        let published = match &self.upgraded {
+            Some(upgraded) if input.prelude().is_empty() => {
+                executor.db.restore_snapshot(upgraded.clone());
+                true
+            }
+            _ => false,
+        };
+        apply_state_patches(&executor, input.state_patches())?;
+        let gas_id = state.fuzz_state().gas_id;
+        let mut prelude_gas = BTreeMap::new();
+        let (mut sequence, epoch, epoch_ms) = commit_prelude(
+            &mut executor,
+            input.prelude(),
+            input.sequence(),
+            self.attacker,
+            gas_id,
+            epoch,
+            epoch_ms,
+            state.fuzz_state().upgrade.as_ref().filter(|_| !published),
+            Some(&mut prelude_gas),
+        )?;
+        if published {
+            refresh_object_refs(&executor.db, &mut sequence);
+        }
+        state.extra_state_mut().prelude_gas = prelude_gas;
+
+        self.oracles.pre_execution(&executor.db, state, &sequence)?;
 
-        let sequence_text = input.sequence().to_string();
+        let sequence_text = sequence.to_string();
         let tracks_flash_loan = ["flash_loan", "test_flash_loan", "repay_flash_loan"]
             .iter()
             .any(|needle| sequence_text.contains(needle));
@@ -163,28 +343,35 @@ where
             info!("executing flash-loan candidate: {}", sequence_text);
         }
 
-        trace!("Executing input: {}", input.sequence());
+        trace!("Executing input: {}", sequence);
         state.executions_mut().add_assign(1);
-        let gas_id = state.fuzz_state().gas_id;
         let resolver = PackageResolver {
-            db: &self.executor.db,
+            db: &executor.db,
             cache: std::mem::take(&mut self.packages_cache),
         };
-        let fuzz_tracer = SuiFuzzTracer::new(
+        let mut fuzz_tracer = SuiFuzzTracer::new(
             &mut self.ob,
             state,
             &mut self.oracles,
             CODE_OBSERVER_NAME,
             resolver,
         );
-        let tracer = if let Some(collector) = &self.line_coverage {
-            SelectiveTracer::T1(TeeTracer(fuzz_tracer, collector.tracer()))
+        fuzz_tracer.concolic_mut().bitvector = self.concolic_bitvector;
+        fuzz_tracer.concolic_mut().link_sequence(&sequence);
+        let lcov_tracer = if let Some(collector) = &self.line_coverage {
+            SelectiveTracer::T1(collector.tracer())
+        } else {
+            SelectiveTracer::T2(NopTracer)
+        };
+        let directed_tracer = if let Some(collector) = &self.directed {
+            SelectiveTracer::T1(collector.tracer())
         } else {
-            SelectiveTracer::T2(fuzz_tracer)
+            SelectiveTracer::T2(NopTracer)
         };
+        let tracer = TeeTracer(fuzz_tracer, TeeTracer(lcov_tracer, directed_tracer));
 
-        let result = self.executor.run_ptb_with_movy_tracer_gas(
-            input.sequence().to_ptb()?,
+        let result = executor.run_ptb_with_movy_tracer_gas(
+            sequence.to_ptb()?,
             epoch,
             epoch_ms,
             self.attacker.into(),
@@ -195,14 +382,11 @@ where
         let ExecutionTracedResults { results, tracer } = result;
         let effects = results.effects;
         let events = results.store.events.data.clone();
-        let db = CachedStore::new(&self.executor.db);
+        let db = &executor.db;
         db.commit_store(results.store, &effects)
             .map_err(|e| libafl::Error::unknown(format!("commit store failed: {e}")))?;
         let tracer = tracer.expect("tracer should be present when tracing is enabled");
-        let mut tracer = match tracer {
-            SelectiveTracer::T1(TeeTracer(fuzz_tracer, _)) => fuzz_tracer,
-            SelectiveTracer::T2(fuzz_tracer) => fuzz_tracer,
-        };
+        let TeeTracer(mut tracer, _) = tracer;
 
         self.packages_cache = std::mem::take(&mut tracer.resolver.cache);
         let mut trace_outcome = tracer.outcome();
@@ -218,7 +402,7 @@ where
         let (stage_idx, success) = match effects.status() {
             ExecutionStatus::Failure { command, .. } => (
                 // command index may be out of bound when meeting non-aborted error
-                if command.is_some_and(|c| c < input.sequence().commands.len()) {
+                if command.is_some_and(|c| c < sequence.commands.len()) {
                     *command
                 } else {
                     None
@@ -234,6 +418,21 @@ where
                 .expect("no code ob installed");
             code_ob.reset_map()?;
         }
+        let distance = self.directed.as_ref().and_then(|collector| {
+            let trace = collector.take();
+            let directed = state.metadata_mut::<DirectedMetadata>().ok()?;
+            let distance = directed.distance(&trace);
+            if let Some(d) = distance
+                && directed.best_distance.is_none_or(|best| d < best)
+            {
+                info!(
+                    "Directed: closest distance to {} is now {}",
+                    directed.target, d
+                );
+                directed.best_distance = Some(d);
+            }
+            distance
+        });
         let extra = ExecutionExtraOutcome {
             logs: trace_outcome.logs,
             solver: trace_outcome.concolic,
@@ -266,16 +465,23 @@ where
             events: events.clone(),
             allowed_success: success,
             findings: trace_outcome.findings.clone(),
+            distance,
         };
         state.extra_state_mut().global_outcome = Some(GlobalOutcome {
             exec: exec.clone(),
             extra: extra.clone(),
         });
 
-        let oracle_vulns = self.oracles.done_execution(&db, state, &effects)?;
+        let oracle_vulns = self.oracles.done_execution(db, state, &effects)?;
         if !oracle_vulns.is_empty() {
             trace_outcome.findings.extend(oracle_vulns.iter().cloned());
         }
+        if !input.state_patches().is_empty() {
+            for finding in trace_outcome.findings.iter_mut() {
+                mark_requires_state(finding, input.state_patches());
+            }
+        }
+        self.complete_findings(&mut trace_outcome.findings, input.prelude(), &sequence);
         let has_findings = !trace_outcome.findings.is_empty();
         let kind = if has_findings {
             ExitKind::Crash
@@ -295,6 +501,7 @@ where
             events,
             allowed_success: success,
             findings: trace_outcome.findings.clone(),
+            distance,
         };
         state.extra_state_mut().global_outcome = Some(GlobalOutcome { exec, extra });
 
@@ -1,12 +1,24 @@
-use std::fmt::Display;
+use std::{collections::BTreeMap, fmt::Display, path::Path, sync::Arc};
 
-use movy_types::input::{MoveAddress, MoveStructTag, MoveTypeTag, SuiObjectInputArgument};
+use color_eyre::eyre::eyre;
+use movy_replay::db::ObjectStoreInfo;
+use movy_types::{
+    error::MovyError,
+    input::{MoveAddress, MoveSequence, MoveTypeTag, SequenceArgument},
+};
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    meta::FuzzMetadata,
+    mutators::transaction::merge_sequences,
+    seed::{SeedCall, SeedSequence},
+};
+
+/// The flash loan an input runs inside of.
 #[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq, Eq)]
 pub struct FlashWrapper {
-    pub provider: FlashProvider,
-    pub flash_coin: MoveStructTag,
+    pub provider: String,
+    pub flash_coin: MoveTypeTag,
     pub initial_flash_amount: u64,
 }
 
@@ -19,56 +31,740 @@ impl Display for FlashWrapper {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq, Eq)]
-pub enum FlashProvider {
+impl FlashWrapper {
+    pub fn new(provider: &dyn FlashProvider) -> Self {
+        Self {
+            provider: provider.to_string(),
+            flash_coin: provider.coin().clone(),
+            initial_flash_amount: provider.amount(),
+        }
+    }
+}
+
+/// A source of borrowed capital. Its seed borrows in the first
+/// `head_commands` commands and repays in the rest, and mutators only ever
+/// add calls between the two.
+pub trait FlashProvider: Display + Send + Sync {
+    /// Borrow and repay right away
+    fn seed(&self) -> &MoveSequence;
+    /// How many commands of the seed borrow
+    fn head_commands(&self) -> usize;
+    /// Results of the borrowing commands handed to the calls in between
+    fn borrowed(&self) -> Vec<SequenceArgument>;
+    /// The coin `amount` is counted in
+    fn coin(&self) -> &MoveTypeTag;
+    fn amount(&self) -> u64;
+
+    /// How many commands of the seed repay
+    fn tail_commands(&self) -> usize {
+        self.seed().commands.len() - self.head_commands()
+    }
+
+    /// Inputs of the seed, which mutators leave alone
+    fn reserved_inputs(&self) -> usize {
+        self.seed().inputs.len()
+    }
+
+    /// Rebuild the repaying commands at the end of `ptb`, so that they refer
+    /// to each other again after calls were added or removed before them.
+    fn finish(&self, ptb: &mut MoveSequence) {
+        let seed = self.seed();
+        let tail = self.tail_commands();
+        let Some(start) = ptb.commands.len().checked_sub(tail) else {
+            return;
+        };
+        let seed_start = seed.commands.len() - tail;
+        ptb.commands.truncate(start);
+        for cmd in seed.commands[seed_start..].iter() {
+            let mut cmd = cmd.clone();
+            for arg in cmd.arguments_mut() {
+                match arg {
+                    SequenceArgument::Result(i) | SequenceArgument::NestedResult(i, _)
+                        if *i as usize >= seed_start =>
+                    {
+                        *i = (*i as usize - seed_start + start) as u16;
+                    }
+                    _ => {}
+                }
+            }
+            ptb.commands.push(cmd);
+        }
+    }
+
+    /// Run `sequence` inside the flash loan.
+    fn wrap(&self, sequence: MoveSequence) -> MoveSequence {
+        let seed = self.seed();
+        let head = MoveSequence {
+            inputs: seed.inputs.clone(),
+            commands: seed.commands[..self.head_commands()].to_vec(),
+        };
+        let mut wrapped = merge_sequences(head, sequence);
+        wrapped
+            .commands
+            .extend(seed.commands[self.head_commands()..].iter().cloned());
+        self.finish(&mut wrapped);
+        wrapped
+    }
+}
+
+/// Flash swap on a Cetus CLMM pool, selling `amount` of `coin_a` for
+/// `coin_b`. The attacker pays the swap from its own coin, and whatever is
+/// left of `a` and `b` goes back to it:
+///
+/// ```text
+/// 0: (a, b, receipt) = pool::flash_swap<A, B>(config, pool, true, true, amount, MIN_SQRT_PRICE, clock)
+/// .. calls using `b` and `a`
+/// n-9: owed = pool::swap_pay_amount<A, B>(receipt)
+/// n-8: paid = coin::split<A>(payment, owed)
+/// n-7: pay = coin::into_balance<A>(paid)
+/// n-6: rest_a = coin::from_balance<A>(a)
+/// n-5: transfer::public_transfer<Coin<A>>(rest_a, attacker)
+/// n-4: rest_b = coin::from_balance<B>(b)
+/// n-3: transfer::public_transfer<Coin<B>>(rest_b, attacker)
+/// n-2: zero = balance::zero<B>()
+/// n-1: pool::repay_flash_swap<A, B>(config, pool, pay, zero, receipt)
+/// ```
+pub struct CetusFlash {
+    pub package: MoveAddress,
+    pub coin_a: MoveTypeTag,
+    pub coin_b: MoveTypeTag,
+    pub pool: MoveAddress,
+    pub amount: u64,
+    seed: MoveSequence,
+}
+
+/// Lowest sqrt price a Cetus pool accepts, so an a-to-b swap is not limited
+const CETUS_MIN_SQRT_PRICE: &str = "4295048016";
+
+impl Display for CetusFlash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Cetus(package={}, coins={}/{}, pool={})",
+            self.package, self.coin_a, self.coin_b, self.pool
+        ))
+    }
+}
+
+impl FlashProvider for CetusFlash {
+    fn seed(&self) -> &MoveSequence {
+        &self.seed
+    }
+
+    fn head_commands(&self) -> usize {
+        1
+    }
+
+    fn borrowed(&self) -> Vec<SequenceArgument> {
+        vec![
+            SequenceArgument::NestedResult(0, 1),
+            SequenceArgument::NestedResult(0, 0),
+        ]
+    }
+
+    fn coin(&self) -> &MoveTypeTag {
+        &self.coin_a
+    }
+
+    fn amount(&self) -> u64 {
+        self.amount
+    }
+}
+
+/// Flash loan of principal tokens from a Nemo yield market:
+///
+/// ```text
+/// 0: position = py::init_py_position(version, py_state, clock)
+/// 1: (receipt, borrowed) = py::borrow_pt_amount<C>(version, position, amount, py_state, clock)
+/// .. calls using `borrowed`
+/// n-1: py::repay_pt_amount<C>(version, position, borrowed, receipt, py_state, clock)
+/// ```
+pub struct NemoFlash {
+    pub package: MoveAddress,
+    pub coin: MoveTypeTag,
+    pub py_state: MoveAddress,
+    pub amount: u64,
+    seed: MoveSequence,
+}
+
+impl Display for NemoFlash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!(
+            "Nemo(package={}, coin={}, py_state={})",
+            self.package, self.coin, self.py_state
+        ))
+    }
+}
+
+impl FlashProvider for NemoFlash {
+    fn seed(&self) -> &MoveSequence {
+        &self.seed
+    }
+
+    fn head_commands(&self) -> usize {
+        2
+    }
+
+    fn borrowed(&self) -> Vec<SequenceArgument> {
+        vec![SequenceArgument::NestedResult(1, 1)]
+    }
+
+    fn coin(&self) -> &MoveTypeTag {
+        &self.coin
+    }
+
+    fn amount(&self) -> u64 {
+        self.amount
+    }
+}
+
+/// A provider written out as the calls that borrow and the calls that repay,
+/// for sources such as DeepBook, Scallop or Navi.
+pub struct CallsFlash {
+    pub name: String,
+    pub coin: MoveTypeTag,
+    pub amount: u64,
+    head: usize,
+    borrowed: Vec<SequenceArgument>,
+    seed: MoveSequence,
+}
+
+impl Display for CallsFlash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_fmt(format_args!("{}(coin={})", self.name, self.coin))
+    }
+}
+
+impl FlashProvider for CallsFlash {
+    fn seed(&self) -> &MoveSequence {
+        &self.seed
+    }
+
+    fn head_commands(&self) -> usize {
+        self.head
+    }
+
+    fn borrowed(&self) -> Vec<SequenceArgument> {
+        self.borrowed.clone()
+    }
+
+    fn coin(&self) -> &MoveTypeTag {
+        &self.coin
+    }
+
+    fn amount(&self) -> u64 {
+        self.amount
+    }
+}
+
+/// Flash loan sources for `--flash-config`, in TOML or JSON:
+///
+/// ```toml
+/// [[providers]]
+/// name = "cetus-usdc"
+/// kind = "cetus"
+/// package = "0x1eabed72c53feb3805120a081dc15963c204dc8d091542592abaf7a35689b2fb"
+/// coin_a = "0x2::sui::SUI"
+/// coin_b = "0xdba3...::usdc::USDC"
+/// global_config = "0xdaa4..."
+/// pool = "0xb8d7..."
+/// amount = 1000000000
+/// # A coin_a object of the attacker paying the swap, the gas coin by default
+/// payment = "gas"
+///
+/// [[providers]]
+/// name = "deepbook"
+/// kind = "calls"
+/// coin = "0x2::sui::SUI"
+/// amount = 1000000000
+/// borrowed = ["nested:0:0"]
+///
+/// [[providers.borrow]]
+/// function = "deepbook::pool::borrow_flashloan_base"
+/// type_arguments = ["0x2::sui::SUI", "0xdba3...::usdc::USDC"]
+/// arguments = ["0xe05d...", "amount"]
+///
+/// [[providers.repay]]
+/// function = "deepbook::pool::return_flashloan_base"
+/// type_arguments = ["0x2::sui::SUI", "0xdba3...::usdc::USDC"]
+/// arguments = ["0xe05d...", "nested:0:0", "nested:0:1"]
+/// ```
+///
+/// Calls are written as in seed sequences, `amount` standing for the borrowed
+/// amount. The package of a provider must be loaded, e.g. via `--onchains`.
+///
+/// Built-in Cetus and Nemo providers may also be given inline to `--flash` as
+/// a TOML table, see [`FlashProviderConfig::from_inline`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FlashConfig {
+    pub providers: Vec<FlashProviderConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum FlashProviderConfig {
     Cetus {
-        package: MoveAddress,
-        coin_a: MoveTypeTag,
-        coin_b: MoveTypeTag,
-        global_config: SuiObjectInputArgument,
-        pool: SuiObjectInputArgument,
-        clock: SuiObjectInputArgument,
+        name: String,
+        package: String,
+        coin_a: String,
+        coin_b: String,
+        global_config: String,
+        pool: String,
+        #[serde(default = "default_clock")]
+        clock: String,
+        amount: u64,
+        #[serde(default = "default_payment")]
+        payment: String,
     },
     Nemo {
-        package: MoveAddress,
-        coin: MoveTypeTag,
-        version: SuiObjectInputArgument,
-        py_state: SuiObjectInputArgument,
-        clock: SuiObjectInputArgument,
+        name: String,
+        package: String,
+        coin: String,
+        version: String,
+        py_state: String,
+        #[serde(default = "default_clock")]
+        clock: String,
+        amount: u64,
+    },
+    Calls {
+        name: String,
+        coin: String,
+        amount: u64,
+        borrow: Vec<SeedCall>,
+        repay: Vec<SeedCall>,
+        #[serde(default)]
+        borrowed: Vec<String>,
     },
 }
 
-impl Display for FlashProvider {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+fn default_clock() -> String {
+    "0x6".to_string()
+}
+
+fn default_payment() -> String {
+    "gas".to_string()
+}
+
+fn call(function: String, type_arguments: Vec<String>, arguments: &[&str]) -> SeedCall {
+    SeedCall {
+        function,
+        type_arguments,
+        arguments: arguments.iter().map(|arg| arg.to_string()).collect(),
+    }
+}
+
+/// Seed calls of [`CetusFlash`], see there.
+#[allow(clippy::too_many_arguments)]
+fn cetus_calls(
+    package: &str,
+    coin_a: &str,
+    coin_b: &str,
+    global_config: &str,
+    pool: &str,
+    clock: &str,
+    amount: u64,
+    payment: &str,
+    attacker: MoveAddress,
+) -> Vec<SeedCall> {
+    let amount = amount.to_string();
+    let attacker = attacker.to_string();
+    let coins = vec![coin_a.to_string(), coin_b.to_string()];
+    let a = vec![coin_a.to_string()];
+    let b = vec![coin_b.to_string()];
+    vec![
+        call(
+            format!("{}::pool::flash_swap", package),
+            coins.clone(),
+            &[
+                global_config,
+                pool,
+                "true",
+                "true",
+                &amount,
+                CETUS_MIN_SQRT_PRICE,
+                clock,
+            ],
+        ),
+        call(
+            format!("{}::pool::swap_pay_amount", package),
+            coins.clone(),
+            &["nested:0:2"],
+        ),
+        call(
+            "0x2::coin::split".to_string(),
+            a.clone(),
+            &[payment, "result:1"],
+        ),
+        call(
+            "0x2::coin::into_balance".to_string(),
+            a.clone(),
+            &["result:2"],
+        ),
+        call("0x2::coin::from_balance".to_string(), a, &["nested:0:0"]),
+        call(
+            "0x2::transfer::public_transfer".to_string(),
+            vec![format!("0x2::coin::Coin<{}>", coin_a)],
+            &["result:4", &attacker],
+        ),
+        call(
+            "0x2::coin::from_balance".to_string(),
+            b.clone(),
+            &["nested:0:1"],
+        ),
+        call(
+            "0x2::transfer::public_transfer".to_string(),
+            vec![format!("0x2::coin::Coin<{}>", coin_b)],
+            &["result:6", &attacker],
+        ),
+        call("0x2::balance::zero".to_string(), b, &[]),
+        call(
+            format!("{}::pool::repay_flash_swap", package),
+            coins,
+            &[global_config, pool, "result:3", "result:8", "nested:0:2"],
+        ),
+    ]
+}
+
+fn parse_borrowed(raw: &str) -> Result<SequenceArgument, MovyError> {
+    let index = |s: &str| {
+        s.parse::<u16>()
+            .map_err(|e| eyre!("can not parse {} with {}", raw, e))
+    };
+    if let Some(result) = raw.strip_prefix("result:") {
+        Ok(SequenceArgument::Result(index(result)?))
+    } else if let Some((cmd, idx)) = raw.strip_prefix("nested:").and_then(|s| s.split_once(':')) {
+        Ok(SequenceArgument::NestedResult(index(cmd)?, index(idx)?))
+    } else {
+        Err(eyre!(
+            "borrowed results should be result:N or nested:N:M, got {}",
+            raw
+        )
+        .into())
+    }
+}
+
+impl FlashProviderConfig {
+    /// Parse a built-in provider written as an inline TOML table, e.g.
+    /// `{ kind = "nemo", name = "nemo", package = "0x...", coin = "...", ... }`.
+    /// Calls providers are only read from a config file.
+    pub fn from_inline(raw: &str) -> Result<Self, MovyError> {
+        #[derive(Deserialize)]
+        struct Inline {
+            provider: FlashProviderConfig,
+        }
+        let inline: Inline = toml::from_str(&format!("provider = {}", raw))?;
+        if let Self::Calls { name, .. } = &inline.provider {
+            return Err(eyre!(
+                "flash provider {} lists calls, give it in a --flash-config file",
+                name
+            )
+            .into());
+        }
+        Ok(inline.provider)
+    }
+
+    pub fn name(&self) -> &str {
         match self {
+            Self::Cetus { name, .. } | Self::Nemo { name, .. } | Self::Calls { name, .. } => name,
+        }
+    }
+
+    pub fn build(
+        &self,
+        meta: &FuzzMetadata,
+        store: &impl ObjectStoreInfo,
+        names: &BTreeMap<String, MoveAddress>,
+        resolve_type: &impl Fn(&str) -> Result<MoveTypeTag, MovyError>,
+    ) -> Result<Arc<dyn FlashProvider>, MovyError> {
+        let package_of = |raw: &str| match names.get(raw) {
+            Some(address) => Ok(*address),
+            None => MoveAddress::from_str(raw),
+        };
+        let to_seed = |calls: Vec<SeedCall>| {
+            SeedSequence { calls }
+                .to_sequence(meta, store, names, resolve_type)
+                .map_err(|e| eyre!("flash provider {}: {}", self.name(), e))
+        };
+        let provider: Arc<dyn FlashProvider> = match self {
             Self::Cetus {
                 package,
                 coin_a,
                 coin_b,
                 global_config,
                 pool,
-                clock: _,
-            } => f.write_fmt(format_args!(
-                "Cetus(package={}, coins={}/{}, config={}, pool={})",
-                package,
-                coin_a,
-                coin_b,
-                global_config.id(),
-                pool.id()
-            )),
+                clock,
+                amount,
+                payment,
+                ..
+            } => {
+                let seed = to_seed(cetus_calls(
+                    package,
+                    coin_a,
+                    coin_b,
+                    global_config,
+                    pool,
+                    clock,
+                    *amount,
+                    payment,
+                    meta.attacker,
+                ))?;
+                Arc::new(CetusFlash {
+                    package: package_of(package)?,
+                    coin_a: resolve_type(coin_a)?,
+                    coin_b: resolve_type(coin_b)?,
+                    pool: MoveAddress::from_str(pool)?,
+                    amount: *amount,
+                    seed,
+                })
+            }
             Self::Nemo {
                 package,
                 coin,
                 version,
                 py_state,
-                clock: _,
-            } => f.write_fmt(format_args!(
-                "Nemo(package={}, coin={}, version={}, py_state={})",
-                package,
+                clock,
+                amount,
+                ..
+            } => {
+                let amount_str = amount.to_string();
+                let seed = to_seed(vec![
+                    call(
+                        format!("{}::py::init_py_position", package),
+                        vec![],
+                        &[version, py_state, clock],
+                    ),
+                    call(
+                        format!("{}::py::borrow_pt_amount", package),
+                        vec![coin.clone()],
+                        &[version, "result:0", &amount_str, py_state, clock],
+                    ),
+                    call(
+                        format!("{}::py::repay_pt_amount", package),
+                        vec![coin.clone()],
+                        &[
+                            version,
+                            "result:0",
+                            "nested:1:1",
+                            "nested:1:0",
+                            py_state,
+                            clock,
+                        ],
+                    ),
+                ])?;
+                Arc::new(NemoFlash {
+                    package: package_of(package)?,
+                    coin: resolve_type(coin)?,
+                    py_state: MoveAddress::from_str(py_state)?,
+                    amount: *amount,
+                    seed,
+                })
+            }
+            Self::Calls {
+                name,
                 coin,
-                version.id(),
-                py_state.id()
-            )),
+                amount,
+                borrow,
+                repay,
+                borrowed,
+            } => {
+                if borrow.is_empty() || repay.is_empty() {
+                    return Err(
+                        eyre!("flash provider {} needs borrow and repay calls", name).into(),
+                    );
+                }
+                let amount_str = amount.to_string();
+                let calls = borrow
+                    .iter()
+                    .chain(repay.iter())
+                    .map(|c| SeedCall {
+                        arguments: c
+                            .arguments
+                            .iter()
+                            .map(|arg| {
+                                if arg.trim() == "amount" {
+                                    amount_str.clone()
+                                } else {
+                                    arg.clone()
+                                }
+                            })
+                            .collect(),
+                        ..c.clone()
+                    })
+                    .collect();
+                Arc::new(CallsFlash {
+                    name: name.clone(),
+                    coin: resolve_type(coin)?,
+                    amount: *amount,
+                    head: borrow.len(),
+                    borrowed: borrowed
+                        .iter()
+                        .map(|raw| parse_borrowed(raw.trim()))
+                        .collect::<Result<_, _>>()?,
+                    seed: to_seed(calls)?,
+                })
+            }
+        };
+        Ok(provider)
+    }
+}
+
+impl FlashConfig {
+    pub fn from_file(path: &Path) -> Result<Self, MovyError> {
+        let content = std::fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(toml::from_str(&content)?)
         }
     }
+
+    pub fn provider(
+        &self,
+        name: &str,
+        meta: &FuzzMetadata,
+        store: &impl ObjectStoreInfo,
+        names: &BTreeMap<String, MoveAddress>,
+        resolve_type: &impl Fn(&str) -> Result<MoveTypeTag, MovyError>,
+    ) -> Result<Arc<dyn FlashProvider>, MovyError> {
+        self.providers
+            .iter()
+            .find(|p| p.name() == name)
+            .ok_or_else(|| eyre!("flash provider {} is not configured", name))?
+            .build(meta, store, names, resolve_type)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use movy_types::input::{InputArgument, MoveCall, MoveSequenceCall};
+
+    use super::*;
+
+    fn call(name: &str, arguments: Vec<SequenceArgument>) -> MoveSequenceCall {
+        MoveSequenceCall::Call(MoveCall {
+            module_id: MoveAddress::two(),
+            module_name: "lend".to_string(),
+            function: name.to_string(),
+            type_arguments: vec![],
+            arguments,
+        })
+    }
+
+    #[test]
+    fn wrapped_repay_follows_the_calls_in_between() {
+        let seed = MoveSequence {
+            inputs: vec![InputArgument::U64(100)],
+            commands: vec![
+                call("borrow", vec![SequenceArgument::Input(0)]),
+                call("fee", vec![SequenceArgument::NestedResult(0, 1)]),
+                call(
+                    "repay",
+                    vec![
+                        SequenceArgument::NestedResult(0, 0),
+                        SequenceArgument::Result(1),
+                    ],
+                ),
+            ],
+        };
+        let provider = CallsFlash {
+            name: "lend".to_string(),
+            coin: MoveTypeTag::U64,
+            amount: 100,
+            head: 1,
+            borrowed: vec![SequenceArgument::NestedResult(0, 0)],
+            seed: seed.clone(),
+        };
+        let inner = MoveSequence {
+            inputs: vec![InputArgument::U64(7)],
+            commands: vec![
+                call("a", vec![SequenceArgument::Input(0)]),
+                call("b", vec![SequenceArgument::Result(0)]),
+            ],
+        };
+        let wrapped = provider.wrap(inner);
+        assert_eq!(wrapped.inputs.len(), 2);
+        assert_eq!(wrapped.commands.len(), 5);
+        assert_eq!(
+            wrapped.commands[2].arguments(),
+            vec![&SequenceArgument::Result(1)]
+        );
+        assert_eq!(
+            wrapped.commands[4].arguments(),
+            vec![
+                &SequenceArgument::NestedResult(0, 0),
+                &SequenceArgument::Result(3)
+            ]
+        );
+    }
+
+    #[test]
+    fn cetus_seed_repays_what_the_receipt_asks() {
+        let attacker = MoveAddress::two();
+        let calls = cetus_calls(
+            "cetus",
+            "0x2::sui::SUI",
+            "0x5::usdc::USDC",
+            "0xc",
+            "0xd",
+            "0x6",
+            100,
+            "gas",
+            attacker,
+        );
+        let functions = calls
+            .iter()
+            .map(|c| c.function.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            functions,
+            vec![
+                "cetus::pool::flash_swap",
+                "cetus::pool::swap_pay_amount",
+                "0x2::coin::split",
+                "0x2::coin::into_balance",
+                "0x2::coin::from_balance",
+                "0x2::transfer::public_transfer",
+                "0x2::coin::from_balance",
+                "0x2::transfer::public_transfer",
+                "0x2::balance::zero",
+                "cetus::pool::repay_flash_swap",
+            ]
+        );
+        assert_eq!(calls[0].arguments[4], "100");
+        assert_eq!(calls[1].arguments, vec!["nested:0:2"]);
+        assert_eq!(calls[2].arguments, vec!["gas", "result:1"]);
+        assert_eq!(calls[4].arguments, vec!["nested:0:0"]);
+        assert_eq!(
+            calls[5].arguments,
+            vec!["result:4".to_string(), attacker.to_string()]
+        );
+        assert_eq!(calls[6].arguments, vec!["nested:0:1"]);
+        assert_eq!(
+            calls[7].arguments,
+            vec!["result:6".to_string(), attacker.to_string()]
+        );
+        assert_eq!(calls[8].type_arguments, vec!["0x5::usdc::USDC"]);
+        assert_eq!(
+            calls[9].arguments,
+            vec!["0xc", "0xd", "result:3", "result:8", "nested:0:2"]
+        );
+        assert_eq!(calls[9].type_arguments, calls[0].type_arguments);
+    }
+
+    #[test]
+    fn only_builtin_providers_parse_inline() {
+        let cetus = FlashProviderConfig::from_inline(
+            r#"{ kind = "cetus", name = "cetus", package = "0x1e", coin_a = "0x2::sui::SUI", coin_b = "0x5::usdc::USDC", global_config = "0xc", pool = "0xd", amount = 100 }"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            &cetus,
+            FlashProviderConfig::Cetus { clock, payment, amount: 100, .. }
+                if clock == "0x6" && payment == "gas"
+        ));
+
+        let calls = FlashProviderConfig::from_inline(
+            r#"{ kind = "calls", name = "lend", coin = "0x2::sui::SUI", amount = 1, borrow = [], repay = [] }"#,
+        );
+        assert!(calls.is_err());
+    }
 }
@@ -0,0 +1,390 @@
+use std::collections::BTreeMap;
+
+use alloy_primitives::{U128, U256};
+use color_eyre::eyre::eyre;
+use movy_replay::db::ObjectStoreInfo;
+use movy_types::{
+    abi::MoveAbiSignatureToken,
+    error::MovyError,
+    input::{
+        InputArgument, MoveAddress, MoveCall, MoveSequence, MoveSequenceCall, MoveTypeTag,
+        SequenceArgument, SuiObjectInputArgument,
+    },
+    object::{MoveObjectInfo, MoveOwner},
+};
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    transaction::{
+        Argument, CallArg, Command, ObjectArg, ProgrammableTransaction, TransactionData,
+        TransactionDataAPI, TransactionKind,
+    },
+};
+use tracing::{debug, info};
+
+use crate::{
+    input::{FuzzTransaction, MoveFuzzInput},
+    meta::FuzzMetadata,
+};
+
+/// Turn on-chain transactions into corpus seeds, dropping the ones that can
+/// not be replayed on the fork.
+pub fn seeds_from_transactions(
+    meta: &FuzzMetadata,
+    store: &impl ObjectStoreInfo,
+    txs: &[TransactionData],
+) -> Vec<MoveFuzzInput> {
+    let mut out = vec![];
+    for tx in txs {
+        match sequence_from_transaction(meta, store, tx) {
+            Ok((sender, sequence)) if sender == meta.attacker => out.push(MoveFuzzInput {
+                sequence,
+                ..Default::default()
+            }),
+            // Other callers act before the attacker, who starts from the
+            // empty sequence
+            Ok((sender, sequence)) => out.push(MoveFuzzInput {
+                prelude: vec![FuzzTransaction {
+                    sender,
+                    elapsed_ms: 0,
+                    elapsed_epochs: 0,
+                    sequence,
+                }],
+                ..Default::default()
+            }),
+            Err(e) => debug!("Skip transaction {} as a seed: {}", tx.digest(), e),
+        }
+    }
+    info!(
+        "Converted {} out of {} on-chain transactions into seeds",
+        out.len(),
+        txs.len()
+    );
+    out
+}
+
+/// Convert a programmable transaction into a sequence runnable on the fork,
+/// together with the caller sending it. The sender becomes a caller from the
+/// pool and every object input is replaced by its fork counterpart, or by a
+/// fork object of the same type usable by that caller when it is missing or
+/// owned by someone else.
+pub fn sequence_from_transaction(
+    meta: &FuzzMetadata,
+    store: &impl ObjectStoreInfo,
+    tx: &TransactionData,
+) -> Result<(MoveAddress, MoveSequence), MovyError> {
+    let TransactionKind::ProgrammableTransaction(ptb) = tx.kind() else {
+        return Err(eyre!("not a programmable transaction").into());
+    };
+    let sender = MoveAddress::from(tx.sender());
+    let caller = pick_caller(meta, sender);
+    let input_tys = infer_input_types(meta, ptb)?;
+
+    let mut sequence = MoveSequence {
+        inputs: vec![],
+        commands: vec![],
+    };
+    for (idx, input) in ptb.inputs.iter().enumerate() {
+        let ty = input_tys.get(&(idx as u16));
+        let arg = match input {
+            CallArg::Pure(bytes) => {
+                let ty = ty.ok_or_else(|| eyre!("can not infer the type of input {}", idx))?;
+                let mut cursor = bytes.as_slice();
+                let value = decode_pure(ty, &mut cursor)?;
+                if !cursor.is_empty() {
+                    return Err(eyre!("trailing bytes in input {} of type {}", idx, ty).into());
+                }
+                replace_address(value, sender, caller)
+            }
+            CallArg::Object(ObjectArg::ImmOrOwnedObject((id, _, _))) => {
+                remap_object(meta, store, caller, (*id).into(), ty, true)?
+            }
+            CallArg::Object(ObjectArg::SharedObject { id, mutability, .. }) => remap_object(
+                meta,
+                store,
+                caller,
+                (*id).into(),
+                ty,
+                mutability.is_exclusive(),
+            )?,
+            _ => return Err(eyre!("unsupported input {}", idx).into()),
+        };
+        sequence.inputs.push(arg);
+    }
+
+    for cmd in ptb.commands.iter() {
+        let cmd = match cmd {
+            Command::MoveCall(call) => MoveSequenceCall::Call(MoveCall {
+                module_id: call.package.into(),
+                module_name: call.module.clone(),
+                function: call.function.clone(),
+                type_arguments: call
+                    .type_arguments
+                    .iter()
+                    .map(|ty| ty.as_type_tag().map(MoveTypeTag::from))
+                    .collect::<Result<Vec<_>, _>>()?,
+                arguments: call.arguments.iter().map(convert_argument).collect(),
+            }),
+            Command::TransferObjects(srcs, dst) => MoveSequenceCall::TransferObjects(
+                srcs.iter().map(convert_argument).collect(),
+                convert_argument(dst),
+            ),
+            Command::SplitCoins(src, amounts) => MoveSequenceCall::SplitCoins(
+                convert_argument(src),
+                amounts.iter().map(convert_argument).collect(),
+            ),
+            Command::MergeCoins(dst, srcs) => MoveSequenceCall::MergeCoins(
+                convert_argument(dst),
+                srcs.iter().map(convert_argument).collect(),
+            ),
+            Command::MakeMoveVec(Some(ty), args) => MoveSequenceCall::MakeMoveVec(
+                ty.as_type_tag()?.into(),
+                args.iter().map(convert_argument).collect(),
+            ),
+            _ => {
+                return Err(eyre!("publish, upgrade and untyped vectors are not supported").into());
+            }
+        };
+        sequence.commands.push(cmd);
+    }
+
+    Ok((caller, sequence))
+}
+
+/// Keep the on-chain sender when it is a known caller, otherwise spread the
+/// senders over the caller pool.
+fn pick_caller(meta: &FuzzMetadata, sender: MoveAddress) -> MoveAddress {
+    if meta.callers_pool.is_empty() {
+        return meta.attacker;
+    }
+    if meta.callers_pool.contains(&sender) {
+        return sender;
+    }
+    let idx = SuiAddress::from(sender).to_inner()[31] as usize % meta.callers_pool.len();
+    meta.callers_pool[idx]
+}
+
+fn convert_argument(arg: &Argument) -> SequenceArgument {
+    match arg {
+        Argument::GasCoin => SequenceArgument::GasCoin,
+        Argument::Input(idx) => SequenceArgument::Input(*idx),
+        Argument::Result(idx) => SequenceArgument::Result(*idx),
+        Argument::NestedResult(cmd, idx) => SequenceArgument::NestedResult(*cmd, *idx),
+    }
+}
+
+/// Pure inputs are untyped bytes on chain, so recover their types from the
+/// first command consuming them.
+fn infer_input_types(
+    meta: &FuzzMetadata,
+    ptb: &ProgrammableTransaction,
+) -> Result<BTreeMap<u16, MoveTypeTag>, MovyError> {
+    let mut out = BTreeMap::new();
+    let mut record = |arg: &Argument, ty: MoveTypeTag| {
+        if let Argument::Input(idx) = arg {
+            out.entry(*idx).or_insert(ty);
+        }
+    };
+    for cmd in ptb.commands.iter() {
+        match cmd {
+            Command::MoveCall(call) => {
+                let package = MoveAddress::from(call.package);
+                let abi = meta
+                    .get_function(&package, &call.module, &call.function)
+                    .ok_or_else(|| {
+                        eyre!(
+                            "function {}::{}::{} is not loaded",
+                            package,
+                            call.module,
+                            call.function
+                        )
+                    })?;
+                let ty_args = call
+                    .type_arguments
+                    .iter()
+                    .enumerate()
+                    .map(|(idx, ty)| Ok((idx as u16, ty.as_type_tag()?.into())))
+                    .collect::<Result<BTreeMap<u16, MoveTypeTag>, MovyError>>()?;
+                let parameters = abi.parameters.iter().filter(|p| !p.is_tx_context());
+                for (param, arg) in parameters.zip(call.arguments.iter()) {
+                    let inner = match param {
+                        MoveAbiSignatureToken::Reference(inner)
+                        | MoveAbiSignatureToken::MutableReference(inner) => inner.as_ref(),
+                        _ => param,
+                    };
+                    if let Some(ty) = inner.subst(&ty_args) {
+                        record(arg, ty);
+                    }
+                }
+            }
+            Command::SplitCoins(_, amounts) => {
+                for amount in amounts {
+                    record(amount, MoveTypeTag::U64);
+                }
+            }
+            Command::TransferObjects(_, dst) => record(dst, MoveTypeTag::Address),
+            Command::MakeMoveVec(Some(ty), args) => {
+                let ty = MoveTypeTag::from(ty.as_type_tag()?);
+                for arg in args {
+                    record(arg, ty.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(out)
+}
+
+fn take<'a>(bytes: &mut &'a [u8], len: usize) -> Result<&'a [u8], MovyError> {
+    if bytes.len() < len {
+        return Err(eyre!("pure input is too short").into());
+    }
+    let (head, tail) = bytes.split_at(len);
+    *bytes = tail;
+    Ok(head)
+}
+
+fn decode_uleb128(bytes: &mut &[u8]) -> Result<usize, MovyError> {
+    let mut value = 0usize;
+    for shift in (0..64).step_by(7) {
+        let byte = take(bytes, 1)?[0];
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(eyre!("invalid uleb128 length").into())
+}
+
+pub(crate) fn decode_pure(ty: &MoveTypeTag, bytes: &mut &[u8]) -> Result<InputArgument, MovyError> {
+    let value = match ty {
+        MoveTypeTag::Bool => match take(bytes, 1)?[0] {
+            0 => InputArgument::Bool(false),
+            1 => InputArgument::Bool(true),
+            v => return Err(eyre!("invalid bool {}", v).into()),
+        },
+        MoveTypeTag::U8 => InputArgument::U8(take(bytes, 1)?[0]),
+        MoveTypeTag::U16 => {
+            InputArgument::U16(u16::from_le_bytes(take(bytes, 2)?.try_into().unwrap()))
+        }
+        MoveTypeTag::U32 => {
+            InputArgument::U32(u32::from_le_bytes(take(bytes, 4)?.try_into().unwrap()))
+        }
+        MoveTypeTag::U64 => {
+            InputArgument::U64(u64::from_le_bytes(take(bytes, 8)?.try_into().unwrap()))
+        }
+        MoveTypeTag::U128 => InputArgument::U128(U128::from_le_slice(take(bytes, 16)?)),
+        MoveTypeTag::U256 => InputArgument::U256(U256::from_le_slice(take(bytes, 32)?)),
+        MoveTypeTag::Address => {
+            InputArgument::Address(ObjectID::new(take(bytes, 32)?.try_into().unwrap()).into())
+        }
+        MoveTypeTag::Vector(inner) => {
+            let len = decode_uleb128(bytes)?;
+            let elements = (0..len)
+                .map(|_| decode_pure(inner, bytes))
+                .collect::<Result<Vec<_>, _>>()?;
+            InputArgument::Vector(inner.as_ref().clone(), elements)
+        }
+        MoveTypeTag::Signer | MoveTypeTag::Struct(_) => {
+            return Err(eyre!("pure input of type {} is not supported", ty).into());
+        }
+    };
+    Ok(value)
+}
+
+fn replace_address(value: InputArgument, from: MoveAddress, to: MoveAddress) -> InputArgument {
+    match value {
+        InputArgument::Address(addr) if addr == from => InputArgument::Address(to),
+        InputArgument::Vector(ty, elements) => InputArgument::Vector(
+            ty,
+            elements
+                .into_iter()
+                .map(|v| replace_address(v, from, to))
+                .collect(),
+        ),
+        v => v,
+    }
+}
+
+fn object_input(id: MoveAddress, info: &MoveObjectInfo, mutable: bool) -> Option<InputArgument> {
+    match info.owner {
+        MoveOwner::AddressOwner(_) | MoveOwner::Immutable => Some(InputArgument::Object(
+            info.ty.clone(),
+            SuiObjectInputArgument::ImmOrOwnedObject(info.sui_reference()),
+        )),
+        MoveOwner::Shared {
+            initial_shared_version,
+        } => Some(InputArgument::Object(
+            info.ty.clone(),
+            SuiObjectInputArgument::shared_object(id, initial_shared_version, mutable),
+        )),
+        _ => None,
+    }
+}
+
+/// Objects owned by anyone but the sending caller are off limits.
+fn usable_by(caller: MoveAddress, owner: &MoveOwner) -> bool {
+    match owner {
+        MoveOwner::AddressOwner(addr) => *addr == caller,
+        MoveOwner::Immutable | MoveOwner::Shared { .. } => true,
+        _ => false,
+    }
+}
+
+fn remap_object(
+    meta: &FuzzMetadata,
+    store: &impl ObjectStoreInfo,
+    caller: MoveAddress,
+    id: MoveAddress,
+    ty: Option<&MoveTypeTag>,
+    mutable: bool,
+) -> Result<InputArgument, MovyError> {
+    if let Ok(info) = store.get_move_object_info(id)
+        && usable_by(caller, &info.owner)
+        && let Some(input) = object_input(id, &info, mutable)
+    {
+        return Ok(input);
+    }
+    let ty = ty.ok_or_else(|| eyre!("object {} is not usable and its type is unknown", id))?;
+    meta.types_pool
+        .get(ty)
+        .into_iter()
+        .flatten()
+        .filter_map(|candidate| {
+            let info = store.get_move_object_info(*candidate).ok()?;
+            if !usable_by(caller, &info.owner) {
+                return None;
+            }
+            object_input(*candidate, &info, mutable)
+        })
+        .next()
+        .ok_or_else(|| eyre!("no fork object of type {} can replace {}", ty, id).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pure_inputs_are_decoded_by_type() {
+        let bytes = bcs::to_bytes(&vec![vec![1u64, 2], vec![]]).unwrap();
+        let ty = MoveTypeTag::Vector(Box::new(MoveTypeTag::Vector(Box::new(MoveTypeTag::U64))));
+        let mut cursor = bytes.as_slice();
+        assert_eq!(
+            decode_pure(&ty, &mut cursor).unwrap(),
+            InputArgument::Vector(
+                MoveTypeTag::Vector(Box::new(MoveTypeTag::U64)),
+                vec![
+                    InputArgument::Vector(
+                        MoveTypeTag::U64,
+                        vec![InputArgument::U64(1), InputArgument::U64(2)]
+                    ),
+                    InputArgument::Vector(MoveTypeTag::U64, vec![]),
+                ]
+            )
+        );
+        assert!(cursor.is_empty());
+
+        let mut short = [1u8, 2].as_slice();
+        assert!(decode_pure(&MoveTypeTag::U64, &mut short).is_err());
+    }
+}
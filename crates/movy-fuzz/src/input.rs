@@ -13,11 +13,32 @@ use movy_types::input::{FunctionIdent, MoveAddress, MoveSequence};
 use serde::{Deserialize, Serialize};
 
 use crate::executor::{ExecutionExtraOutcome, ExecutionOutcome};
-use crate::flash::FlashWrapper;
+use crate::flash::{FlashProvider, FlashWrapper};
 use crate::meta::FuzzMetadata;
+use crate::state_fuzz::StatePatch;
+
+/// A transaction executed before the main sequence of an input, e.g. a victim
+/// depositing before the attacker acts.
+#[derive(Debug, Clone, Serialize, Deserialize, Hash)]
+pub struct FuzzTransaction {
+    pub sender: MoveAddress,
+    /// How far the clock advances once this transaction is done
+    pub elapsed_ms: u64,
+    /// How many epochs pass once this transaction is done
+    #[serde(default)]
+    pub elapsed_epochs: u64,
+    pub sequence: MoveSequence,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct MoveFuzzInput {
+    /// Transactions committed one by one before `sequence`
+    #[serde(default)]
+    pub prelude: Vec<FuzzTransaction>,
+    /// Object fields overwritten before anything runs
+    #[serde(default)]
+    pub state_patches: Vec<StatePatch>,
+    /// The attacker transaction, which all oracles observe
     pub sequence: MoveSequence,
 
     // Input Metadata
@@ -35,6 +56,8 @@ impl Display for MoveFuzzInput {
 
 impl Hash for MoveFuzzInput {
     fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.prelude.hash(state);
+        self.state_patches.hash(state);
         self.sequence.hash(state);
         // self.flash.hash(state);
         // ignore metadata
@@ -44,6 +67,8 @@ impl Hash for MoveFuzzInput {
 impl Default for MoveFuzzInput {
     fn default() -> Self {
         Self {
+            prelude: vec![],
+            state_patches: vec![],
             sequence: MoveSequence {
                 commands: vec![],
                 inputs: vec![],
@@ -73,9 +98,25 @@ impl MoveFuzzInput {
         } else {
             "No outcome".to_string()
         };
+        let patches = self
+            .state_patches
+            .iter()
+            .map(|patch| format!("State patch {}\n", patch))
+            .collect::<String>();
+        let prelude = self
+            .prelude
+            .iter()
+            .enumerate()
+            .map(|(idx, tx)| {
+                format!(
+                    "Transaction {} from {}, then {} epochs and {} ms elapse:\n{}\n",
+                    idx, tx.sender, tx.elapsed_epochs, tx.elapsed_ms, tx.sequence
+                )
+            })
+            .collect::<String>();
         format!(
-            "{}\nFlash: |\n{}\nOutcome: |\n{}",
-            self.sequence, flash, outcome
+            "{}{}{}\nFlash: |\n{}\nOutcome: |\n{}",
+            patches, prelude, self.sequence, flash, outcome
         )
     }
 
@@ -85,15 +126,14 @@ impl MoveFuzzInput {
         }
     }
 
-    // pub fn flash(flash: FlashWrapper) -> Result<Self, MovyError> {
-    //     let ptb = flash.flash_seed(&flash.flash_coin, flash.initial_flash_amount)?;
-
-    //     Ok(Self {
-    //         sequence: ptb,
-    //         flash: Some(flash),
-    //         ..Default::default()
-    //     })
-    // }
+    /// Borrow and repay through `provider`, with nothing in between yet.
+    pub fn flash(provider: &dyn FlashProvider) -> Self {
+        Self {
+            sequence: provider.seed().clone(),
+            flash: Some(FlashWrapper::new(provider)),
+            ..Default::default()
+        }
+    }
 }
 
 pub trait MoveInput: Display {
@@ -101,6 +141,12 @@ pub trait MoveInput: Display {
 
     fn sequence_mut(&mut self) -> &mut MoveSequence;
 
+    fn prelude(&self) -> &[FuzzTransaction];
+    fn prelude_mut(&mut self) -> &mut Vec<FuzzTransaction>;
+
+    fn state_patches(&self) -> &[StatePatch];
+    fn state_patches_mut(&mut self) -> &mut Vec<StatePatch>;
+
     fn outcome(&self) -> &Option<ExecutionOutcome>;
     fn outcome_mut(&mut self) -> &mut Option<ExecutionOutcome>;
 
@@ -121,6 +167,22 @@ impl MoveInput for MoveFuzzInput {
         &mut self.sequence
     }
 
+    fn prelude(&self) -> &[FuzzTransaction] {
+        &self.prelude
+    }
+
+    fn prelude_mut(&mut self) -> &mut Vec<FuzzTransaction> {
+        &mut self.prelude
+    }
+
+    fn state_patches(&self) -> &[StatePatch] {
+        &self.state_patches
+    }
+
+    fn state_patches_mut(&mut self) -> &mut Vec<StatePatch> {
+        &mut self.state_patches
+    }
+
     fn outcome(&self) -> &Option<ExecutionOutcome> {
         &self.outcome
     }
@@ -148,7 +210,13 @@ impl MoveInput for MoveFuzzInput {
                 let Log::CmpLog(cmp) = cmp else {
                     continue;
                 };
-                if matches!(cmp.op, CmpOp::EQ | CmpOp::GE | CmpOp::LE) {
+                // `sender != admin` guards are as common as `==` ones
+                let wanted = match cmp.op {
+                    CmpOp::EQ | CmpOp::GE | CmpOp::LE => true,
+                    CmpOp::NEQ => matches!(cmp.lhs, Magic::Bytes(_)),
+                    _ => false,
+                };
+                if wanted {
                     for magic in [&cmp.lhs, &cmp.rhs] {
                         match magic {
                             Magic::U8(v) => {
@@ -1,14 +1,23 @@
 pub mod r#const;
+pub mod dict;
+pub mod diff;
+pub mod directed;
 pub mod executor;
 pub mod flash;
+pub mod history;
 pub mod input;
 pub mod meta;
 pub mod mutators;
 pub mod operations;
 pub mod oracles;
+pub mod round_trip;
 pub mod sched;
+pub mod seed;
 pub mod solver;
 pub mod state;
+pub mod state_fuzz;
+pub mod stats;
+pub mod upgrade;
 pub mod utils;
 
 pub use movy_static_analysis as static_analysis;
@@ -21,14 +21,17 @@ use movy_types::{
         MovePackageAbi,
     },
     error::MovyError,
-    input::{FunctionIdent, MoveAddress, MoveTypeTag},
+    input::{FunctionIdent, MoveAddress, MoveSequence, MoveTypeTag},
 };
 use serde::{Deserialize, Serialize};
 use serde_json_any_key::any_key_map;
 use sui_types::storage::{BackingPackageStore, BackingStore, ObjectStore};
-use tracing::debug;
+use tracing::{debug, warn};
 
-use crate::{r#const::INIT_FUNCTION_SCORE, utils::SuperRand};
+use crate::{
+    r#const::INIT_FUNCTION_SCORE, dict::FuzzDictionary, round_trip::RoundTrip,
+    upgrade::UpgradePlan, utils::SuperRand,
+};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ObjectWithversion {
@@ -546,6 +549,69 @@ fn derive_sequence_hooks(abis: &BTreeMap<MoveAddress, MovePackageAbi>) -> Functi
     sequence_hooks
 }
 
+/// `movy_event_<EventName>` hooks keyed by the event struct. The name is
+/// looked up in the hook's own package first, then in the target packages.
+fn derive_event_hooks(
+    abis: &BTreeMap<MoveAddress, MovePackageAbi>,
+    base: &Metadata,
+    target_packages: &[MoveAddress],
+) -> BTreeMap<(MoveModuleId, String), Vec<FunctionIdent>> {
+    let module_addresses = |abi: &MovePackageAbi| {
+        abi.modules
+            .iter()
+            .map(|module| module.module_id.module_address)
+            .collect::<Vec<_>>()
+    };
+    let targets = target_packages
+        .iter()
+        .filter_map(|package| base.abis.get(package))
+        .flat_map(module_addresses)
+        .collect::<BTreeSet<_>>();
+    let mut event_hooks: BTreeMap<(MoveModuleId, String), Vec<FunctionIdent>> = BTreeMap::new();
+
+    for (package_addr, package_abi) in abis.iter() {
+        let own = module_addresses(package_abi)
+            .into_iter()
+            .collect::<BTreeSet<_>>();
+        for module in package_abi.modules.iter() {
+            for func in module.functions.iter() {
+                let Some(event) = func.try_derive_movy_event() else {
+                    continue;
+                };
+                let resolve = |addresses: &BTreeSet<MoveAddress>| {
+                    base.structs_mapping
+                        .keys()
+                        .filter(|(id, name)| {
+                            name == event && addresses.contains(&id.module_address)
+                        })
+                        .cloned()
+                        .collect::<Vec<_>>()
+                };
+                let mut structs = resolve(&own);
+                if structs.is_empty() {
+                    structs = resolve(&targets);
+                }
+                if structs.is_empty() {
+                    warn!(
+                        "{}::{}::{} hooks an event {} that no target package declares",
+                        package_addr, module.module_id.module_name, func.name, event
+                    );
+                    continue;
+                }
+                for key in structs {
+                    event_hooks.entry(key).or_default().push(FunctionIdent::new(
+                        package_addr,
+                        &module.module_id.module_name,
+                        &func.name,
+                    ));
+                }
+            }
+        }
+    }
+
+    event_hooks
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct FunctionHook {
     pub pre_hooks: Vec<FunctionIdent>,
@@ -558,6 +624,9 @@ pub struct FuzzMetadata {
     pub rand: SuperRand,
 
     pub attacker: MoveAddress,
+    /// Deploys the target packages and runs their `movy_init`
+    #[serde(default)]
+    pub deployer: MoveAddress,
     pub callers_pool: Vec<MoveAddress>,
     pub addresses_pool: Vec<MoveAddress>,
 
@@ -573,11 +642,27 @@ pub struct FuzzMetadata {
     #[serde(with = "any_key_map")]
     pub function_hooks: BTreeMap<FunctionIdent, FunctionHook>,
     pub sequence_hooks: FunctionHook,
+    /// Invoked with the fields of each matching event after a sequence
+    #[serde(default, with = "any_key_map")]
+    pub event_hooks: BTreeMap<(MoveModuleId, String), Vec<FunctionIdent>>,
 
     pub gas_id: MoveAddress,
     pub checkpoint: u64,
     pub epoch: u64,
     pub epoch_ms: u64,
+
+    /// Constants supplied with `--dict`, little-endian encoded
+    #[serde(default)]
+    pub dictionary: BTreeSet<Vec<u8>>,
+    /// Package upgrade performed right before the attacker sequence
+    #[serde(default)]
+    pub upgrade: Option<UpgradePlan>,
+    /// Sequences supplied with `--liveness-probes`, sent by the deployer
+    #[serde(default)]
+    pub liveness_probes: Vec<MoveSequence>,
+    /// Paired functions supplied with `--round-trips`
+    #[serde(default)]
+    pub round_trips: Vec<RoundTrip>,
 }
 
 impl Deref for FuzzMetadata {
@@ -726,10 +811,13 @@ impl FuzzMetadata {
 
         let sequence_hooks = derive_sequence_hooks(&local_testing_abis);
 
+        let event_hooks = derive_event_hooks(&local_testing_abis, &base, &target_packages);
+
         Self {
             base,
             rand,
             attacker,
+            deployer: admin,
             callers_pool: vec![attacker],
             addresses_pool: vec![attacker, admin],
             function_scores,
@@ -739,13 +827,48 @@ impl FuzzMetadata {
             target_packages,
             function_hooks,
             sequence_hooks,
+            event_hooks,
             gas_id,
             checkpoint,
             epoch,
             epoch_ms,
+            dictionary: BTreeSet::new(),
+            upgrade: None,
+            liveness_probes: vec![],
+            round_trips: vec![],
         }
     }
 
+    /// Let sequences call the public functions of packages published by the
+    /// attacker. They do not become target packages, so the oracles keep
+    /// ignoring their effects.
+    pub fn add_attacker_functions(&mut self, packages: &[MoveAddress]) {
+        self.add_target_functions(packages);
+    }
+
+    fn add_target_functions(&mut self, packages: &[MoveAddress]) {
+        for function in collect_target_functions(&self.base, packages) {
+            if self.target_functions.contains(&function) {
+                continue;
+            }
+            self.function_scores
+                .entry(function.clone())
+                .or_insert(INIT_FUNCTION_SCORE);
+            self.target_functions.push(function);
+        }
+        self.target_functions.sort();
+    }
+
+    /// Upgrade a target package in the middle of every input. Functions of
+    /// both versions are targets, so sequences can keep calling the old one.
+    pub fn set_upgrade(&mut self, plan: UpgradePlan) {
+        self.add_target_functions(&[plan.upgraded]);
+        if !self.target_packages.contains(&plan.upgraded) {
+            self.target_packages.push(plan.upgraded);
+        }
+        self.upgrade = Some(plan);
+    }
+
     pub fn iter_target_functions(
         &self,
     ) -> impl Iterator<
@@ -775,7 +898,34 @@ impl FuzzMetadata {
             })
     }
 
-    pub fn generate_magic_number_pool(&self) -> BTreeSet<Vec<u8>> {
-        BTreeSet::new()
+    pub fn generate_magic_number_pool(&self) -> &BTreeSet<Vec<u8>> {
+        &self.dictionary
+    }
+
+    pub fn apply_dictionary(
+        &mut self,
+        dict: &FuzzDictionary,
+        resolve_type: &impl Fn(&str) -> Result<MoveTypeTag, MovyError>,
+    ) -> Result<(), MovyError> {
+        self.dictionary.extend(dict.magic_numbers()?);
+        for address in dict.addresses()? {
+            if !self.addresses_pool.contains(&address) {
+                self.addresses_pool.push(address);
+            }
+        }
+        for ty in dict.type_tags(resolve_type)? {
+            let ability = match &ty {
+                MoveTypeTag::Signer => MoveAbility::DROP,
+                MoveTypeTag::Struct(tag) => self
+                    .get_abilities(&tag.address, &tag.module, &tag.name)
+                    .ok_or_else(|| eyre!("dictionary type {} is not found in any package", ty))?,
+                _ => MoveAbility::PRIMITIVES,
+            };
+            let tys = self.ability_to_type_tag.entry(ability).or_default();
+            if !tys.contains(&ty) {
+                tys.push(ty);
+            }
+        }
+        Ok(())
     }
 }
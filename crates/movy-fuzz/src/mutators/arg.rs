@@ -1,4 +1,8 @@
-use std::{collections::BTreeMap, marker::PhantomData};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    marker::PhantomData,
+    sync::Arc,
+};
 
 use libafl::{
     HasMetadata,
@@ -7,7 +11,7 @@ use libafl::{
 };
 use libafl_bolts::{Named, rands::Rand};
 use movy_replay::tracer::{concolic::ConcolicState, op::Log};
-use movy_types::input::{FunctionIdent, MoveSequenceCall, SequenceArgument};
+use movy_types::input::{FunctionIdent, InputArgument, MoveSequenceCall, SequenceArgument};
 use tracing::{debug, trace};
 
 use crate::{
@@ -18,14 +22,16 @@ use crate::{
         HasFlash, StageReplay, StageReplayAction, candidate_move_call_indices,
         flash_command_limits, mutate_arg, ptb_fingerprint,
     },
-    solver::solve,
+    solver::{PathCondition, SolveTarget, solve, solve_flips},
     state::{ExtraNonSerdeFuzzState, HasExtraState, HasFuzzEnv},
 };
 
 pub struct ArgMutator<I, S> {
     pub ph: PhantomData<(I, S)>,
-    pub flash: Option<FlashProvider>,
+    pub flash: Option<Arc<dyn FlashProvider>>,
     stage: StageReplay,
+    /// Further branch flips solved for the sequence with this fingerprint
+    solved: VecDeque<(u64, BTreeMap<usize, InputArgument>)>,
 }
 
 impl<I, S> Default for ArgMutator<I, S> {
@@ -40,6 +46,7 @@ impl<I, S> ArgMutator<I, S> {
             ph: PhantomData,
             flash: None,
             stage: StageReplay::new(MutatorKind::Magic),
+            solved: VecDeque::new(),
         }
     }
 }
@@ -51,11 +58,13 @@ impl<I, S> Named for ArgMutator<I, S> {
 }
 
 impl<I, S> HasFlash for ArgMutator<I, S> {
-    fn flash(&self) -> &Option<FlashProvider> {
+    fn flash(&self) -> &Option<Arc<dyn FlashProvider>> {
         &self.flash
     }
 }
 
+/// Solve for the pure inputs of a call, returning the other branch flips
+/// solved on the way, which still apply to the unmutated sequence.
 pub fn solve_arg<I, S>(
     mutator: &impl HasFlash,
     state: &mut S,
@@ -63,25 +72,25 @@ pub fn solve_arg<I, S>(
     cmps: &BTreeMap<FunctionIdent, Vec<Log>>,
     solver: &ConcolicState,
     stage_idx: &Option<usize>,
-) -> MutationResult
+) -> (MutationResult, Vec<BTreeMap<usize, InputArgument>>)
 where
     I: MoveInput,
     S: HasRand + HasFuzzMetadata + HasFuzzEnv,
 {
     let ptb = input.sequence_mut();
     if ptb.commands.is_empty() {
-        return MutationResult::Skipped;
+        return (MutationResult::Skipped, vec![]);
     }
     let (_, input_limit) = flash_command_limits(mutator);
     let mut result = MutationResult::Skipped;
     let cmd_candidates = candidate_move_call_indices(mutator, state, ptb, stage_idx);
     if cmd_candidates.is_empty() {
-        return MutationResult::Skipped;
+        return (MutationResult::Skipped, vec![]);
     }
     let idx = state.rand_mut().below_or_zero(cmd_candidates.len());
     let cmd_idx = *cmd_candidates.get(idx).unwrap();
-    let MoveSequenceCall::Call(movecall) = ptb.commands.get_mut(cmd_idx).unwrap() else {
-        return MutationResult::Skipped;
+    let MoveSequenceCall::Call(movecall) = ptb.commands.get(cmd_idx).unwrap() else {
+        return (MutationResult::Skipped, vec![]);
     };
 
     let function = {
@@ -94,34 +103,58 @@ where
         .unwrap()
         .clone()
     };
-    let arg_idx_candidates = function
+    let has_mutable_arg = function
         .parameters
         .iter()
         .zip(movecall.arguments.iter())
-        .enumerate()
-        .filter_map(|(i, (param, arg))| {
-            if param.is_mutable()
+        .any(|(param, arg)| {
+            param.is_mutable()
                 && matches!(arg, SequenceArgument::Input(input_idx) if *input_idx as usize >= input_limit)
-            {
-                Some(i as u16)
-            } else {
-                None
+        });
+    if !has_mutable_arg {
+        return (MutationResult::Skipped, vec![]); // No valid arguments to mutate
+    }
+
+    // Earlier calls feed their results into the target, so their pure
+    // inputs are solved for as well
+    let mut targets = BTreeMap::new();
+    for (call_idx, cmd) in ptb.commands.iter().enumerate().take(cmd_idx + 1) {
+        let MoveSequenceCall::Call(call) = cmd else {
+            continue;
+        };
+        let Some(symbols) = solver.args.get(call_idx) else {
+            continue;
+        };
+        let meta = state.fuzz_state();
+        let Some(abi) = meta.get_function(&call.module_id, &call.module_name, &call.function)
+        else {
+            continue;
+        };
+        for (param_idx, (param, arg)) in
+            abi.parameters.iter().zip(call.arguments.iter()).enumerate()
+        {
+            let SequenceArgument::Input(input_idx) = arg else {
+                continue;
+            };
+            let input_idx = *input_idx as usize;
+            if !param.is_mutable() || input_idx < input_limit {
+                continue;
             }
-        })
-        .collect::<Vec<_>>();
-    if arg_idx_candidates.is_empty() {
-        return MutationResult::Skipped; // No valid arguments to mutate
+            let (Some(symbol), Some(current)) =
+                (symbols.get(&param_idx), ptb.inputs.get(input_idx))
+            else {
+                continue;
+            };
+            targets.entry(input_idx).or_insert_with(|| SolveTarget {
+                ty: param.clone(),
+                symbol: symbol.clone(),
+                current: current.clone(),
+            });
+        }
+    }
+    if targets.is_empty() {
+        return (MutationResult::Skipped, vec![]);
     }
-    let idx = state.rand_mut().choose(&arg_idx_candidates).unwrap();
-    let arg = &movecall.arguments.get(*idx as usize).unwrap_or_else(|| {
-        panic!(
-            "Argument index {} out of bounds for MoveCall command, {:?}",
-            idx, movecall
-        )
-    });
-    let Some(solving_arg) = solver.args.get(cmd_idx) else {
-        return MutationResult::Skipped;
-    };
 
     let target_function = FunctionIdent::new(
         &movecall.module_id,
@@ -136,8 +169,39 @@ where
     };
     let Some(target_function_logs) = cmps.get(&target_function) else {
         trace!("No logs found for target function: {:?}", target_function);
-        return MutationResult::Skipped;
+        return (MutationResult::Skipped, vec![]);
     };
+    if solver.bitvector && state.rand_mut().below_or_zero(2) == 0 {
+        // Flip the branches one by one, keeping the checks before each of
+        // them passing
+        let path = target_function_logs
+            .iter()
+            .filter_map(|log| match log {
+                Log::CmpLog(cmp) => cmp.constraint.clone().map(|constraint| PathCondition {
+                    constraint,
+                    flip: true,
+                }),
+                Log::CastLog(c) => c.constraint.clone().map(|constraint| PathCondition {
+                    constraint,
+                    flip: false,
+                }),
+                Log::ArithLog(a) => a.constraint.clone().map(|constraint| PathCondition {
+                    constraint,
+                    flip: false,
+                }),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        let mut solutions = solve_flips(&targets, &solver.fixed, &path).into_iter();
+        if let Some(solution) = solutions.next() {
+            for (input_idx, new_value) in solution {
+                ptb.inputs[input_idx] = new_value;
+                result = MutationResult::Mutated;
+            }
+        }
+        return (result, solutions.collect());
+    }
+
     let mut cmp_constraints = target_function_logs
         .iter()
         .filter_map(|log| match log {
@@ -157,20 +221,19 @@ where
         .chain(target_function_logs.iter().filter_map(|log| match log {
             Log::CastLog(c) => c.constraint.clone(),
             Log::ShlLog(s) => s.constraint.clone(),
+            Log::ArithLog(a) => a.constraint.clone(),
             _ => None,
         }))
+        .chain(solver.fixed.iter().cloned())
         .collect::<Vec<_>>();
 
-    let solution = solve(function.clone(), solving_arg, &constraints);
-    if let Some(solution) = solution
-        && let Some(new_value) = solution.get(&(*idx as usize))
-        && let SequenceArgument::Input(input_idx) = arg
-    {
-        let call_input = &mut ptb.inputs[*input_idx as usize];
-        *call_input = new_value.clone();
-        result = MutationResult::Mutated;
+    if let Some(solution) = solve(&targets, &constraints) {
+        for (input_idx, new_value) in solution {
+            ptb.inputs[input_idx] = new_value;
+            result = MutationResult::Mutated;
+        }
     }
-    result
+    (result, vec![])
 }
 
 impl<I, S, E> Mutator<I, S> for ArgMutator<I, S>
@@ -216,7 +279,30 @@ where
 
         let res = if let Some(ex) = extra.cloned() {
             if state.rand_mut().below_or_zero(2) == 0 {
-                solve_arg(self, state, input, &ex.logs, &ex.solver, &stage_idx)
+                // Flips solved on an earlier trace of this very sequence are
+                // still valid
+                let fingerprint = ptb_fingerprint(input.sequence());
+                if self
+                    .solved
+                    .front()
+                    .is_some_and(|(fp, _)| *fp == fingerprint)
+                {
+                    let (_, solution) = self.solved.pop_front().unwrap();
+                    let ptb = input.sequence_mut();
+                    for (input_idx, new_value) in solution {
+                        ptb.inputs[input_idx] = new_value;
+                    }
+                    MutationResult::Mutated
+                } else {
+                    self.solved.clear();
+                    let (res, pending) =
+                        solve_arg(self, state, input, &ex.logs, &ex.solver, &stage_idx);
+                    // The other flips are applied when this sequence is picked
+                    // again
+                    self.solved
+                        .extend(pending.into_iter().map(|solution| (fingerprint, solution)));
+                    res
+                }
             } else {
                 mutate_arg(self, state, input, false, &stage_idx)
             }
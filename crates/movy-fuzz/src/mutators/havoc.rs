@@ -1,4 +1,4 @@
-use std::{collections::BTreeSet, marker::PhantomData};
+use std::marker::PhantomData;
 
 use libafl::{
     mutators::{MutationResult, Mutator},
@@ -16,7 +16,10 @@ use tracing::debug;
 use crate::{
     input::MoveInput,
     meta::{HasCaller, HasFuzzMetadata},
-    mutators::{mutation_utils::MutableValue, object_data::try_construct_args_from_db},
+    mutators::{
+        mutation_utils::{MagicPools, MutableValue},
+        object_data::try_construct_args_from_db,
+    },
     state::HasFuzzEnv,
 };
 
@@ -104,7 +107,7 @@ impl<I, S> HavocMuator<I, S> {
                     continue;
                 }
                 let mut new_value = MutableValue::new(input.clone());
-                new_value.mutate(state, &BTreeSet::new(), false);
+                new_value.mutate(state, MagicPools::default(), false);
                 *input = new_value.value;
             }
         }
@@ -4,4 +4,8 @@ pub mod havoc;
 pub mod mutation_utils;
 pub mod object_data;
 pub mod sequence;
+pub mod state;
+pub mod transaction;
+pub mod ty_arg;
 pub mod utils;
+pub mod weighted;
@@ -6,36 +6,50 @@ use libafl::{
     state::HasRand,
 };
 use libafl_bolts::{HasLen, Named, rands::Rand, tuples::tuple_list};
+use move_core_types::account_address::AccountAddress;
 use movy_types::input::{InputArgument, MoveTypeTag};
 use std::collections::BTreeSet;
 
 use crate::{r#const::MAX_STACK_POW, meta::HasCaller};
 
+/// Magic numbers spread over several pools, e.g. the ones collected for a
+/// function and the dictionary, sampled as one without merging them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MagicPools<'a>(pub &'a [&'a BTreeSet<Vec<u8>>]);
+
+impl<'a> MagicPools<'a> {
+    pub fn iter(&self) -> impl Iterator<Item = &'a Vec<u8>> {
+        self.0.iter().flat_map(|pool| pool.iter())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.iter().all(|pool| pool.is_empty())
+    }
+}
+
 /// [`MagicNumberMutator`] is a mutator that mutates the input to a constant
 /// in the contract
 ///
 /// We discover that sometimes directly setting the bytes to the constants allow
 /// us to increase test coverage.
 #[derive(Default)]
-pub struct MagicNumberMutator {
-    magic_number_pool: Vec<Vec<u8>>,
+pub struct MagicNumberMutator<'a> {
+    magic_number_pool: MagicPools<'a>,
 }
 
-impl Named for MagicNumberMutator {
+impl Named for MagicNumberMutator<'_> {
     fn name(&self) -> &std::borrow::Cow<'static, str> {
         &std::borrow::Cow::Borrowed("constant_hinted_mutator")
     }
 }
 
-impl MagicNumberMutator {
-    pub fn new(magic_number_pool: BTreeSet<Vec<u8>>) -> Self {
-        Self {
-            magic_number_pool: magic_number_pool.into_iter().collect(),
-        }
+impl<'a> MagicNumberMutator<'a> {
+    pub fn new(magic_number_pool: MagicPools<'a>) -> Self {
+        Self { magic_number_pool }
     }
 }
 
-impl<I, S> Mutator<I, S> for MagicNumberMutator
+impl<I, S> Mutator<I, S> for MagicNumberMutator<'_>
 where
     S: HasRand,
     I: HasMutatorBytes,
@@ -56,7 +70,7 @@ where
         if fit_pool.is_empty() {
             return Ok(MutationResult::Skipped);
         }
-        let magic_number = fit_pool[state.rand_mut().below_or_zero(fit_pool.len())].clone();
+        let magic_number = fit_pool[state.rand_mut().below_or_zero(fit_pool.len())];
 
         let magic_number_len = magic_number.len();
 
@@ -163,12 +177,12 @@ impl MutableValue {
     fn mutate_by(
         &mut self,
         state: &mut impl HasRand,
-        magic_number_pool: &BTreeSet<Vec<u8>>,
+        magic_number_pool: MagicPools<'_>,
         split: bool,
     ) -> MutationResult {
         let mut bytes = sync(&self.value);
 
-        let mutations = tuple_list!(MagicNumberMutator::new(magic_number_pool.clone()),);
+        let mutations = tuple_list!(MagicNumberMutator::new(magic_number_pool),);
 
         let mut mutator = HavocScheduledMutator::with_max_stack_pow(mutations, MAX_STACK_POW);
         let mut res = mutator.mutate(state, self).unwrap();
@@ -219,7 +233,7 @@ impl MutableValue {
     pub fn mutate<S>(
         &mut self,
         state: &mut S,
-        magic_number_pool: &BTreeSet<Vec<u8>>,
+        magic_number_pool: MagicPools<'_>,
         split: bool,
     ) -> MutationResult
     where
@@ -236,6 +250,13 @@ impl MutableValue {
             }};
         }
 
+        if matches!(self.value, InputArgument::Address(_))
+            && state.rand_mut().below_or_zero(2) == 0
+            && self.splice_bytes(state, magic_number_pool) == MutationResult::Mutated
+        {
+            return MutationResult::Mutated;
+        }
+
         match self.value {
             // value level mutation
             InputArgument::Bool(ref mut v) => {
@@ -252,6 +273,9 @@ impl MutableValue {
             | InputArgument::U256(_) => {
                 return self.mutate_by(state, magic_number_pool, split);
             }
+            InputArgument::Vector(MoveTypeTag::U8, _) => {
+                return self.splice_bytes(state, magic_number_pool);
+            }
             InputArgument::Address(ref mut v) => {
                 if state.rand_mut().below_or_zero(2) == 1 {
                     *v = state.get_rand_address();
@@ -269,7 +293,7 @@ impl MutableValue {
     pub fn sample_magic_number<S>(
         &mut self,
         state: &mut S,
-        magic_number_pool: &BTreeSet<Vec<u8>>,
+        magic_number_pool: MagicPools<'_>,
     ) -> MutationResult
     where
         S: HasRand + HasCaller,
@@ -299,7 +323,104 @@ impl MutableValue {
                 self.value = commit(&bytes, &self.value);
                 MutationResult::Mutated
             }
+            InputArgument::Address(_) => self.splice_bytes(state, magic_number_pool),
             _ => MutationResult::Skipped,
         }
     }
+
+    /// Replace a byte string or an address with one it was compared against,
+    /// regardless of its current length.
+    pub fn splice_bytes<S>(
+        &mut self,
+        state: &mut S,
+        magic_number_pool: MagicPools<'_>,
+    ) -> MutationResult
+    where
+        S: HasRand,
+    {
+        let current = match self.value {
+            InputArgument::Vector(MoveTypeTag::U8, _) => sync(&self.value),
+            _ => vec![],
+        };
+        let fit_values = magic_number_pool
+            .iter()
+            .filter(|bytes| match self.value {
+                InputArgument::Address(_) => bytes.len() == AccountAddress::LENGTH,
+                InputArgument::Vector(MoveTypeTag::U8, _) => {
+                    !bytes.is_empty() && current != **bytes
+                }
+                _ => false,
+            })
+            .collect::<Vec<_>>();
+        let Some(bytes) = state.rand_mut().choose(fit_values) else {
+            return MutationResult::Skipped;
+        };
+        self.value = match self.value {
+            InputArgument::Address(_) => {
+                let Ok(address) = AccountAddress::from_bytes(bytes) else {
+                    return MutationResult::Skipped;
+                };
+                InputArgument::Address(address.into())
+            }
+            _ => InputArgument::Vector(
+                MoveTypeTag::U8,
+                bytes.iter().map(|b| InputArgument::U8(*b)).collect(),
+            ),
+        };
+        MutationResult::Mutated
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::rands::StdRand;
+    use movy_types::input::MoveAddress;
+
+    use super::*;
+
+    struct RandState(StdRand);
+
+    impl HasRand for RandState {
+        type Rand = StdRand;
+
+        fn rand(&self) -> &StdRand {
+            &self.0
+        }
+
+        fn rand_mut(&mut self) -> &mut StdRand {
+            &mut self.0
+        }
+    }
+
+    #[test]
+    fn splices_fitting_magic_bytes() {
+        let mut state = RandState(StdRand::with_seed(7));
+        let address = MoveAddress::two();
+        let pool = BTreeSet::from([vec![1u8, 2, 3], AccountAddress::from(address).to_vec()]);
+        let pools = [&pool];
+
+        let mut bytes = MutableValue::new(InputArgument::Vector(MoveTypeTag::U8, vec![]));
+        assert_eq!(
+            bytes.splice_bytes(&mut state, MagicPools(&pools)),
+            MutationResult::Mutated
+        );
+        assert!(matches!(
+            bytes.value,
+            InputArgument::Vector(MoveTypeTag::U8, _)
+        ));
+
+        // Only the 32 byte entry fits an address
+        let mut addr = MutableValue::new(InputArgument::Address(MoveAddress::zero()));
+        assert_eq!(
+            addr.splice_bytes(&mut state, MagicPools(&pools)),
+            MutationResult::Mutated
+        );
+        assert_eq!(addr.value, InputArgument::Address(address));
+
+        let mut number = MutableValue::new(InputArgument::U64(1));
+        assert_eq!(
+            number.splice_bytes(&mut state, MagicPools(&pools)),
+            MutationResult::Skipped
+        );
+    }
 }
@@ -1,4 +1,4 @@
-use std::{collections::BTreeMap, marker::PhantomData};
+use std::{collections::BTreeMap, marker::PhantomData, sync::Arc};
 
 use libafl::{
     HasMetadata,
@@ -30,14 +30,13 @@ mod hooks;
 mod post;
 mod remap;
 
-pub use append::append_function;
-use append::weighted_sample;
-pub use hooks::apply_hooks;
-use hooks::strip_generated;
+pub use append::{append_function, weighted_sample};
+pub use hooks::{apply_hooks, context_idents, strip_generated};
+pub use remap::remap_command_with_map;
 
 pub struct SequenceMutator<I, S> {
     pub ph: PhantomData<(I, S)>,
-    pub flash: Option<FlashProvider>,
+    pub flash: Option<Arc<dyn FlashProvider>>,
     stage: StageReplay,
 }
 
@@ -64,7 +63,7 @@ impl<I, S> Named for SequenceMutator<I, S> {
 }
 
 impl<I, S> HasFlash for SequenceMutator<I, S> {
-    fn flash(&self) -> &Option<FlashProvider> {
+    fn flash(&self) -> &Option<Arc<dyn FlashProvider>> {
         &self.flash
     }
 }
@@ -80,18 +79,11 @@ impl<I, S> SequenceMutator<I, S> {
         S: HasFuzzEnv,
     {
         if let Some(provider) = &self.flash {
-            match provider {
-                FlashProvider::Cetus { .. } => {
-                    if idx as usize >= ptb.commands.len() - 3 || idx == 0 {
-                        // We cannot remove the first or last commands (flash & repay)
-                        return;
-                    }
-                }
-                FlashProvider::Nemo { .. } => {
-                    if idx as usize >= ptb.commands.len() - 1 || idx <= 1 {
-                        return;
-                    }
-                }
+            // We cannot remove the borrowing or repaying commands
+            if (idx as usize) < provider.head_commands()
+                || idx as usize + provider.tail_commands() >= ptb.commands.len()
+            {
+                return;
             }
         } else if idx as usize >= ptb.commands.len() {
             return;
@@ -232,69 +224,13 @@ impl<I, S> SequenceMutator<I, S> {
         MutationResult::Mutated
     }
 
-    fn finish(&self, _state: &mut S, _ptb: &mut MoveSequence)
+    fn finish(&self, _state: &mut S, ptb: &mut MoveSequence)
     where
         I: MoveInput,
         S: HasFuzzMetadata + HasRand + HasFuzzEnv,
     {
-        if let Some(_provider) = &self.flash {
-            // match provider {
-            //     FlashProvider::Cetus { coin_a, coin_b, .. } => {
-            //         // Ensure repay coins are set empty
-            //         *provider.repay_coin0(ptb).unwrap() =
-            //             SequenceArgument::Result(ptb.commands.len() as u16 - 3);
-            //         *provider.repay_coin1(ptb).unwrap() =
-            //             SequenceArgument::Result(ptb.commands.len() as u16 - 2);
-            //         // Set split coins to to flash coin first, trying to sample from other functions
-            //         *provider.repay_split_coin0(ptb).unwrap() =
-            //             SequenceArgument::NestedResult(0, 0);
-            //         *provider.repay_split_coin1(ptb).unwrap() =
-            //             SequenceArgument::NestedResult(0, 1);
-            //         let mut object_data = ObjectData::from_ptb(ptb, state, &self.db);
-            //         object_data
-            //             .existing_objects
-            //             .iter_mut()
-            //             .for_each(|(_, objs)| {
-            //                 objs.retain(|(obj, _)| {
-            //                     obj != &SequenceArgument::NestedResult(0, 0)
-            //                         && obj != &SequenceArgument::NestedResult(0, 1)
-            //                 });
-            //             });
-            //         let coin_a_type = MoveAbiSignatureToken::StructInstantiation(
-            //             state.fuzz_state().get_struct(MoveAddress::two(), "balance", "Balance").unwrap(), vec![coin_a]
-            //         );
-            //         let coin_b_type = MoveAbiSignatureToken::StructInstantiation(
-            //             state.fuzz_state().get_struct(MoveAddress::two(), "balance", "Balance").unwrap(), vec![coin_b]
-            //         );
-
-            //         if let ConstructResult::Ok(args, _, _) = try_construct_args(
-            //             ptb.inputs.len() - 1,
-            //             &vec![coin_a_type.clone()],
-            //             &[],
-            //             &BTreeMap::from([(0, coin_a.clone()), (1, coin_b.clone())]),
-            //             &object_data,
-            //             &self.db,
-            //             state,
-            //         ) {
-            //             debug!("Found repay coin0: {:?}", args[0]);
-            //             *provider.repay_split_coin0(ptb).unwrap() = args[0];
-            //         }
-
-            //         if let ConstructResult::Ok(args, _, _) = try_construct_args(
-            //             ptb.inputs.len() - 1,
-            //             &vec![coin_b_type.clone()],
-            //             &[],
-            //             &BTreeMap::from([(0, coin_a.clone()), (1, coin_b.clone())]),
-            //             &object_data,
-            //             &self.db,
-            //             state,
-            //         ) {
-            //             debug!("Found repay coin1: {:?}", args[0]);
-            //             *provider.repay_split_coin1(ptb).unwrap() = args[0];
-            //         }
-            //     }
-            //     FlashProvider::Nemo { .. } => {}
-            // }
+        if let Some(provider) = &self.flash {
+            provider.finish(ptb);
         }
     }
 
@@ -304,6 +240,12 @@ impl<I, S> SequenceMutator<I, S> {
         S: HasRand + HasFuzzMetadata + HasFuzzEnv,
     {
         let ptb = input.sequence_mut();
+        if let Some(provider) = &self.flash
+            && ptb.commands.len() < provider.head_commands() + provider.tail_commands()
+        {
+            // No longer inside the flash loan, e.g. after its transaction was split
+            return MutationResult::Skipped;
+        }
         let functions = state.fuzz_state().target_functions.clone();
         assert!(!functions.is_empty(), "No target functions available");
         let inc = if ptb.commands.len() <= 3 {
@@ -348,16 +290,10 @@ impl<I, S> SequenceMutator<I, S> {
             }
             let function = weighted_sample(&functions, &weights, state).clone();
             let (idx, used_arguments) = if let Some(provider) = &self.flash {
-                match provider {
-                    FlashProvider::Cetus { .. } => (
-                        ptb.commands.len() - 3,
-                        vec![SequenceArgument::NestedResult(0, 2)],
-                    ),
-                    FlashProvider::Nemo { .. } => (
-                        ptb.commands.len() - 1,
-                        vec![SequenceArgument::NestedResult(1, 1)],
-                    ),
-                }
+                (
+                    ptb.commands.len() - provider.tail_commands(),
+                    provider.borrowed(),
+                )
             } else {
                 (ptb.commands.len(), vec![])
             };
@@ -1,4 +1,4 @@
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::BTreeMap;
 
 use libafl::state::HasRand;
 use libafl_bolts::rands::Rand;
@@ -14,7 +14,7 @@ use tracing::debug;
 use crate::{
     meta::HasFuzzMetadata,
     mutators::{
-        mutation_utils::MutableValue,
+        mutation_utils::{MagicPools, MutableValue},
         object_data::{ConstructResult, ObjectData, gen_type_tag_by_abilities, try_construct_args},
     },
     state::HasFuzzEnv,
@@ -149,7 +149,7 @@ where
                         )
                     });
                     let mut init_value = MutableValue::new(init_value);
-                    init_value.mutate(state, &BTreeSet::new(), false);
+                    init_value.mutate(state, MagicPools::default(), false);
                     let init_value = init_value.value;
                     inputs.push(init_value);
                     movecall.arguments.push(SequenceArgument::Input(
@@ -321,7 +321,7 @@ where
                     debug!("Generating initial value for parameter {}: {:?}", i, param);
                     let init_value = param.gen_input_arg().unwrap();
                     let mut init_value = MutableValue::new(init_value);
-                    init_value.mutate(state, &BTreeSet::new(), false);
+                    init_value.mutate(state, MagicPools::default(), false);
                     let init_value = init_value.value;
                     inputs.push(init_value);
                     movecall.arguments.push(SequenceArgument::Input(
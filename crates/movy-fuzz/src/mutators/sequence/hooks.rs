@@ -43,6 +43,9 @@ fn hook_idents(meta: &FuzzMetadata) -> BTreeSet<FunctionIdent> {
         hooks.extend(hook.pre_hooks.iter().cloned());
         hooks.extend(hook.post_hooks.iter().cloned());
     }
+    for hook in meta.event_hooks.values() {
+        hooks.extend(hook.iter().cloned());
+    }
     if let Some((create, destroy)) = context_idents(meta) {
         hooks.insert(create);
         hooks.insert(destroy);
@@ -0,0 +1,160 @@
+use std::{borrow::Cow, marker::PhantomData};
+
+use alloy_primitives::U256;
+use libafl::{
+    HasMetadata,
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+};
+use libafl_bolts::{Named, rands::Rand};
+use tracing::debug;
+
+use crate::{
+    r#const::MAX_STATE_PATCHES,
+    input::MoveInput,
+    meta::HasFuzzMetadata,
+    state_fuzz::{NumericField, StateFuzzMetadata, StatePatch},
+};
+
+/// Overwrites numeric fields of shared objects, so that sequences run on
+/// protocol states the fork has not reached yet.
+pub struct StateHavocMutator<I, S> {
+    pub ph: PhantomData<(I, S)>,
+}
+
+impl<I, S> Default for StateHavocMutator<I, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, S> StateHavocMutator<I, S> {
+    pub fn new() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<I, S> Named for StateHavocMutator<I, S> {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("state_havoc_mutator")
+    }
+}
+
+fn mutate_value(
+    field: &NumericField,
+    current: U256,
+    magic: &[Vec<u8>],
+    state: &mut impl HasRand,
+) -> Vec<u8> {
+    let one = U256::from(1);
+    let value = match state.rand_mut().below_or_zero(8) {
+        0 => U256::ZERO,
+        1 => one,
+        2 => field.max(),
+        3 => current.saturating_add(one),
+        4 => current.saturating_sub(one),
+        5 => current.saturating_mul(U256::from(2)),
+        6 => current / U256::from(2),
+        _ => match state.rand_mut().choose(magic) {
+            Some(bytes) => U256::from_le_slice(bytes),
+            None => current.saturating_mul(U256::from(10)),
+        },
+    };
+    field.encode(value)
+}
+
+impl<I, S> Mutator<I, S> for StateHavocMutator<I, S>
+where
+    I: MoveInput,
+    S: HasRand + HasMetadata + HasFuzzMetadata,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, libafl::Error> {
+        let Ok(meta) = state.metadata::<StateFuzzMetadata>() else {
+            return Ok(MutationResult::Skipped);
+        };
+        if meta.objects.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let objects = meta.objects.len();
+
+        let patches = input.state_patches().len();
+        if patches > 0 && state.rand_mut().below_or_zero(4) == 0 {
+            let idx = state.rand_mut().below_or_zero(patches);
+            input.state_patches_mut().remove(idx);
+            *input.outcome_mut() = None;
+            return Ok(MutationResult::Mutated);
+        }
+
+        let (object, field) = if patches > 0 && state.rand_mut().below_or_zero(2) == 0 {
+            let idx = state.rand_mut().below_or_zero(patches);
+            let patch = &input.state_patches()[idx];
+            let meta = state.metadata::<StateFuzzMetadata>()?;
+            let Some(field) = meta.objects.get(&patch.object).and_then(|obj| {
+                obj.fields
+                    .iter()
+                    .find(|f| f.offset == patch.offset)
+                    .cloned()
+            }) else {
+                return Ok(MutationResult::Skipped);
+            };
+            (patch.object, field)
+        } else if patches < MAX_STATE_PATCHES {
+            let obj_idx = state.rand_mut().below_or_zero(objects);
+            let (object, fields) = {
+                let meta = state.metadata::<StateFuzzMetadata>()?;
+                let (id, obj) = meta.objects.iter().nth(obj_idx).unwrap();
+                (*id, obj.fields.len())
+            };
+            let field_idx = state.rand_mut().below_or_zero(fields);
+            let meta = state.metadata::<StateFuzzMetadata>()?;
+            (object, meta.objects[&object].fields[field_idx].clone())
+        } else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        // Keep mutating from the patched value rather than the fork one
+        let current = input
+            .state_patches()
+            .iter()
+            .find(|p| p.object == object && p.offset == field.offset)
+            .map(|p| U256::from_le_slice(&p.value))
+            .unwrap_or_else(|| field.current());
+        let function_pools = input.magic_number_pool(state.fuzz_state());
+        let magic = state
+            .fuzz_state()
+            .generate_magic_number_pool()
+            .iter()
+            .chain(function_pools.values().flatten())
+            .filter(|bytes| bytes.len() == field.width)
+            .cloned()
+            .collect::<Vec<_>>();
+        let value = mutate_value(&field, current, &magic, state);
+
+        let patch = StatePatch {
+            object,
+            field: field.path.clone(),
+            offset: field.offset,
+            value,
+        };
+        debug!("state havoc: {}", patch);
+        let patches = input.state_patches_mut();
+        if let Some(existing) = patches
+            .iter_mut()
+            .find(|p| p.object == patch.object && p.offset == patch.offset)
+        {
+            *existing = patch;
+        } else {
+            patches.push(patch);
+        }
+        *input.outcome_mut() = None;
+        Ok(MutationResult::Mutated)
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _new_corpus_id: Option<libafl::corpus::CorpusId>,
+    ) -> Result<(), libafl::Error> {
+        Ok(())
+    }
+}
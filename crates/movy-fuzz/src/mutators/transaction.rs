@@ -0,0 +1,316 @@
+use std::{borrow::Cow, collections::BTreeMap, marker::PhantomData};
+
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+};
+use libafl_bolts::{Named, rands::Rand};
+use movy_types::input::{InputArgument, MoveSequence, SequenceArgument, SuiObjectInputArgument};
+use tracing::debug;
+
+use crate::{
+    r#const::MAX_PRELUDE_TRANSACTIONS,
+    input::{FuzzTransaction, MoveInput},
+    meta::{HasCaller, HasFuzzMetadata},
+};
+
+const ELAPSED_MS_CHOICES: [u64; 5] = [0, 1_000, 60_000, 3_600_000, 86_400_000];
+const ELAPSED_EPOCHS_CHOICES: [u64; 4] = [0, 0, 1, 7];
+
+/// Drop inputs no command refers to and renumber the rest, so that objects
+/// consumed by one transaction are not declared again by another.
+fn prune_inputs(sequence: &mut MoveSequence) {
+    let mut used = BTreeMap::new();
+    for cmd in sequence.commands.iter() {
+        for arg in cmd.arguments() {
+            if let SequenceArgument::Input(i) = arg {
+                used.insert(*i, 0u16);
+            }
+        }
+    }
+    let inputs = std::mem::take(&mut sequence.inputs);
+    for (idx, input) in inputs.into_iter().enumerate() {
+        if let Some(new_idx) = used.get_mut(&(idx as u16)) {
+            *new_idx = sequence.inputs.len() as u16;
+            sequence.inputs.push(input);
+        }
+    }
+    for cmd in sequence.commands.iter_mut() {
+        for arg in cmd.arguments_mut() {
+            if let SequenceArgument::Input(i) = arg {
+                *i = used[&*i];
+            }
+        }
+    }
+}
+
+/// Split off the first `at` commands as a transaction of their own. Fails if
+/// any later command consumes one of their results.
+pub fn split_sequence(sequence: &mut MoveSequence, at: usize) -> Option<MoveSequence> {
+    if at == 0 || at >= sequence.commands.len() {
+        return None;
+    }
+    let crosses = sequence.commands[at..].iter().any(|cmd| {
+        cmd.arguments().into_iter().any(|arg| {
+            matches!(arg, SequenceArgument::Result(i) | SequenceArgument::NestedResult(i, _) if (*i as usize) < at)
+        })
+    });
+    if crosses {
+        return None;
+    }
+    let mut head = MoveSequence {
+        inputs: sequence.inputs.clone(),
+        commands: sequence.commands.drain(..at).collect(),
+    };
+    for cmd in sequence.commands.iter_mut() {
+        for arg in cmd.arguments_mut() {
+            match arg {
+                SequenceArgument::Result(i) | SequenceArgument::NestedResult(i, _) => {
+                    *i -= at as u16;
+                }
+                _ => {}
+            }
+        }
+    }
+    prune_inputs(&mut head);
+    prune_inputs(sequence);
+    Some(head)
+}
+
+/// Whether another sender can run `sequence` as is: its inputs are pure
+/// values or shared objects, and coins only come from the sender's gas.
+fn replayable_by_others(sequence: &MoveSequence) -> bool {
+    fn replayable(input: &InputArgument) -> bool {
+        match input {
+            InputArgument::Object(_, object) => {
+                matches!(object, SuiObjectInputArgument::SharedObject { .. })
+            }
+            InputArgument::Vector(_, elements) => elements.iter().all(replayable),
+            InputArgument::Signer(_) => false,
+            _ => true,
+        }
+    }
+    sequence.inputs.iter().all(replayable)
+}
+
+/// Concatenate two transactions into one, sharing identical inputs.
+pub fn merge_sequences(head: MoveSequence, tail: MoveSequence) -> MoveSequence {
+    let mut merged = head;
+    let offset = merged.commands.len() as u16;
+    let mut input_map = Vec::with_capacity(tail.inputs.len());
+    for input in tail.inputs {
+        let idx = match merged.inputs.iter().position(|v| v == &input) {
+            Some(idx) => idx,
+            None => {
+                merged.inputs.push(input);
+                merged.inputs.len() - 1
+            }
+        };
+        input_map.push(idx as u16);
+    }
+    for mut cmd in tail.commands {
+        for arg in cmd.arguments_mut() {
+            match arg {
+                SequenceArgument::Input(i) => *i = input_map[*i as usize],
+                SequenceArgument::Result(i) | SequenceArgument::NestedResult(i, _) => {
+                    *i += offset;
+                }
+                SequenceArgument::GasCoin => {}
+            }
+        }
+        merged.commands.push(cmd);
+    }
+    merged
+}
+
+/// Mutates the transactions running before the attacker sequence: adding,
+/// splitting and merging them, or changing their sender and timing.
+pub struct TransactionMutator<I, S> {
+    pub ph: PhantomData<(I, S)>,
+}
+
+impl<I, S> Default for TransactionMutator<I, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, S> TransactionMutator<I, S> {
+    pub fn new() -> Self {
+        Self { ph: PhantomData }
+    }
+}
+
+impl<I, S> Named for TransactionMutator<I, S> {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("transaction_mutator")
+    }
+}
+
+impl<I, S> Mutator<I, S> for TransactionMutator<I, S>
+where
+    I: MoveInput,
+    S: HasRand + HasFuzzMetadata,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, libafl::Error> {
+        let full = input.prelude().len() >= MAX_PRELUDE_TRANSACTIONS;
+        let res = match state.rand_mut().below_or_zero(4) {
+            // Another sender does what the attacker does, right before it
+            0 if !full
+                && !input.sequence().commands.is_empty()
+                && replayable_by_others(input.sequence()) =>
+            {
+                let sender = state.get_rand_address();
+                let elapsed_ms = state.rand_mut().choose(ELAPSED_MS_CHOICES).unwrap();
+                let elapsed_epochs = state.rand_mut().choose(ELAPSED_EPOCHS_CHOICES).unwrap();
+                let sequence = input.sequence().clone();
+                input.prelude_mut().push(FuzzTransaction {
+                    sender,
+                    elapsed_ms,
+                    elapsed_epochs,
+                    sequence,
+                });
+                MutationResult::Mutated
+            }
+            1 if !full && input.sequence().commands.len() > 1 => {
+                let at = 1 + state
+                    .rand_mut()
+                    .below_or_zero(input.sequence().commands.len() - 1);
+                match split_sequence(input.sequence_mut(), at) {
+                    Some(head) => {
+                        let attacker = state.fuzz_state().attacker;
+                        input.prelude_mut().push(FuzzTransaction {
+                            sender: attacker,
+                            elapsed_ms: 0,
+                            elapsed_epochs: 0,
+                            sequence: head,
+                        });
+                        MutationResult::Mutated
+                    }
+                    None => MutationResult::Skipped,
+                }
+            }
+            // Only the attacker's own transactions fold into its sequence
+            2 if input
+                .prelude()
+                .last()
+                .is_some_and(|tx| tx.sender == state.fuzz_state().attacker) =>
+            {
+                let head = input.prelude_mut().pop().unwrap();
+                let tail = std::mem::take(input.sequence_mut());
+                *input.sequence_mut() = merge_sequences(head.sequence, tail);
+                MutationResult::Mutated
+            }
+            3 if !input.prelude().is_empty() => {
+                let idx = state.rand_mut().below_or_zero(input.prelude().len());
+                match state.rand_mut().below_or_zero(3) {
+                    0 => {
+                        let sender = state.get_rand_address();
+                        input.prelude_mut()[idx].sender = sender;
+                    }
+                    1 => {
+                        let elapsed_ms = state.rand_mut().choose(ELAPSED_MS_CHOICES).unwrap();
+                        input.prelude_mut()[idx].elapsed_ms = elapsed_ms;
+                    }
+                    _ => {
+                        let elapsed_epochs =
+                            state.rand_mut().choose(ELAPSED_EPOCHS_CHOICES).unwrap();
+                        input.prelude_mut()[idx].elapsed_epochs = elapsed_epochs;
+                    }
+                }
+                MutationResult::Mutated
+            }
+            _ => MutationResult::Skipped,
+        };
+        if res == MutationResult::Mutated {
+            debug!(
+                "transaction mutator: {} transactions before the attacker",
+                input.prelude().len()
+            );
+            *input.outcome_mut() = None;
+        }
+        Ok(res)
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _new_corpus_id: Option<libafl::corpus::CorpusId>,
+    ) -> Result<(), libafl::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use movy_types::input::{MoveAddress, MoveCall, MoveSequenceCall, MoveTypeTag};
+
+    use super::*;
+
+    fn call(arguments: Vec<SequenceArgument>) -> MoveSequenceCall {
+        MoveSequenceCall::Call(MoveCall {
+            module_id: MoveAddress::two(),
+            module_name: "m".to_string(),
+            function: "f".to_string(),
+            type_arguments: vec![],
+            arguments,
+        })
+    }
+
+    #[test]
+    fn split_then_merge_roundtrips() {
+        let sequence = MoveSequence {
+            inputs: vec![InputArgument::U64(1), InputArgument::U64(2)],
+            commands: vec![
+                call(vec![SequenceArgument::Input(0)]),
+                call(vec![SequenceArgument::Result(0)]),
+                call(vec![SequenceArgument::Input(1)]),
+                call(vec![SequenceArgument::NestedResult(2, 0)]),
+            ],
+        };
+        let mut tail = sequence.clone();
+        assert!(split_sequence(&mut tail, 1).is_none());
+
+        let head = split_sequence(&mut tail, 2).unwrap();
+        assert_eq!(head.inputs, vec![InputArgument::U64(1)]);
+        assert_eq!(tail.inputs, vec![InputArgument::U64(2)]);
+        assert_eq!(
+            tail.commands[0].arguments(),
+            vec![&SequenceArgument::Input(0)]
+        );
+        assert_eq!(
+            tail.commands[1].arguments(),
+            vec![&SequenceArgument::NestedResult(0, 0)]
+        );
+
+        let merged = merge_sequences(head, tail);
+        assert_eq!(merged.inputs, sequence.inputs);
+        for (lhs, rhs) in merged.commands.iter().zip(sequence.commands.iter()) {
+            assert_eq!(lhs.arguments(), rhs.arguments());
+        }
+    }
+
+    #[test]
+    fn only_pure_and_shared_inputs_replay() {
+        let mut sequence = MoveSequence {
+            inputs: vec![
+                InputArgument::U64(1),
+                InputArgument::Object(
+                    MoveTypeTag::Address,
+                    SuiObjectInputArgument::shared_object(MoveAddress::two(), 1, true),
+                ),
+            ],
+            commands: vec![call(vec![
+                SequenceArgument::Input(0),
+                SequenceArgument::Input(1),
+                SequenceArgument::GasCoin,
+            ])],
+        };
+        assert!(replayable_by_others(&sequence));
+        sequence.inputs.push(InputArgument::Object(
+            MoveTypeTag::Address,
+            SuiObjectInputArgument::imm_or_owned_object(MoveAddress::two(), 1, [0; 32]),
+        ));
+        assert!(!replayable_by_others(&sequence));
+    }
+}
@@ -0,0 +1,398 @@
+use std::{
+    borrow::Cow,
+    collections::{BTreeMap, BTreeSet},
+    marker::PhantomData,
+    sync::Arc,
+};
+
+use libafl::{
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+};
+use libafl_bolts::{Named, rands::Rand};
+use movy_types::{
+    abi::{MoveAbility, MoveFunctionAbi},
+    input::{FunctionIdent, MoveSequence, MoveSequenceCall, MoveTypeTag, SequenceArgument},
+};
+use tracing::debug;
+
+use crate::{
+    flash::FlashProvider,
+    input::MoveInput,
+    meta::HasFuzzMetadata,
+    mutators::{
+        object_data::ObjectData,
+        sequence::{append_function, apply_hooks, remap_command_with_map, strip_generated},
+        utils::{HasFlash, flash_command_limits},
+    },
+    state::HasFuzzEnv,
+};
+
+/// Re-instantiates a generic call of the sequence with other type arguments,
+/// e.g. a fake coin type for `deposit<T>` or the swapped pair for
+/// `swap<A, B>`. Unlike `mutate_ty_arg`, type parameters used by parameters
+/// are changed too: the dependent objects are sampled again for the new
+/// types and later commands are remapped onto the rebuilt call.
+pub struct TypeArgMutator<I, S> {
+    pub ph: PhantomData<(I, S)>,
+    pub flash: Option<Arc<dyn FlashProvider>>,
+}
+
+impl<I, S> Default for TypeArgMutator<I, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, S> TypeArgMutator<I, S> {
+    pub fn new() -> Self {
+        Self {
+            ph: PhantomData,
+            flash: None,
+        }
+    }
+}
+
+impl<I, S> Named for TypeArgMutator<I, S> {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("type_arg_mutator")
+    }
+}
+
+impl<I, S> HasFlash for TypeArgMutator<I, S> {
+    fn flash(&self) -> &Option<Arc<dyn FlashProvider>> {
+        &self.flash
+    }
+}
+
+/// Which commands of `suffix`, starting with the call at `idx` that gets
+/// retyped, can stay: those not consuming one of its `changed` results,
+/// directly or through a command that is dropped.
+fn kept_consumers(suffix: &[MoveSequenceCall], idx: usize, changed: &BTreeSet<u16>) -> Vec<bool> {
+    let mut kept = vec![true; suffix.len()];
+    for offset in 1..suffix.len() {
+        kept[offset] = suffix[offset].arguments().into_iter().all(|arg| match arg {
+            SequenceArgument::Result(i) | SequenceArgument::NestedResult(i, _)
+                if (*i as usize) < idx =>
+            {
+                true
+            }
+            SequenceArgument::Result(i) if *i as usize == idx => !changed.contains(&0),
+            SequenceArgument::NestedResult(i, k) if *i as usize == idx => !changed.contains(k),
+            SequenceArgument::Result(i) | SequenceArgument::NestedResult(i, _) => {
+                kept.get(*i as usize - idx).copied().unwrap_or(false)
+            }
+            _ => true,
+        });
+    }
+    kept
+}
+
+/// Concrete types satisfying the `constraint` of a type parameter, other than
+/// `current`. Every monomorphic struct of the loaded packages is a candidate,
+/// so coin types published by the attacker are picked up as well.
+pub fn type_arg_candidates(
+    ability_to_type_tag: &BTreeMap<MoveAbility, Vec<MoveTypeTag>>,
+    constraint: &MoveAbility,
+    current: &MoveTypeTag,
+) -> Vec<MoveTypeTag> {
+    ability_to_type_tag
+        .iter()
+        .filter(|(abilities, _)| constraint.is_subset_of(abilities))
+        .flat_map(|(_, tys)| tys.iter())
+        .filter(|ty| *ty != current)
+        .cloned()
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+impl<I, S> TypeArgMutator<I, S>
+where
+    S: HasRand + HasFuzzMetadata + HasFuzzEnv,
+{
+    fn retype(
+        &self,
+        state: &mut S,
+        function: &MoveFunctionAbi,
+        current: &[MoveTypeTag],
+    ) -> Option<Vec<MoveTypeTag>> {
+        let mut type_arguments = current.to_vec();
+        let mut swappable = vec![];
+        for i in 0..current.len() {
+            for j in i + 1..current.len() {
+                if function.type_parameters[i] == function.type_parameters[j]
+                    && current[i] != current[j]
+                {
+                    swappable.push((i, j));
+                }
+            }
+        }
+        if !swappable.is_empty() && state.rand_mut().below_or_zero(2) == 0 {
+            // Swap the pool order, e.g. swap<A, B> into swap<B, A>
+            let (i, j) = swappable[state.rand_mut().below_or_zero(swappable.len())];
+            type_arguments.swap(i, j);
+            return Some(type_arguments);
+        }
+        let idx = state.rand_mut().below_or_zero(current.len());
+        let candidates = type_arg_candidates(
+            &state.fuzz_state().ability_to_type_tag,
+            &function.type_parameters[idx],
+            &current[idx],
+        );
+        type_arguments[idx] = state.rand_mut().choose(candidates)?;
+        Some(type_arguments)
+    }
+
+    fn mutate_call(&self, state: &mut S, ptb: &mut MoveSequence) -> MutationResult {
+        let (head, _) = flash_command_limits(self);
+        let tail = self.flash.as_ref().map_or(0, |p| p.tail_commands());
+        let end = ptb.commands.len().saturating_sub(tail);
+        let candidates = (head..end)
+            .filter(|i| {
+                matches!(&ptb.commands[*i], MoveSequenceCall::Call(mc)
+                    if !mc.type_arguments.is_empty() && !mc.is_split())
+            })
+            .collect::<Vec<_>>();
+        let Some(idx) = state.rand_mut().choose(candidates) else {
+            return MutationResult::Skipped;
+        };
+        let MoveSequenceCall::Call(movecall) = ptb.commands[idx].clone() else {
+            unreachable!()
+        };
+        let Some(function) = state
+            .fuzz_state()
+            .get_function(
+                &movecall.module_id,
+                &movecall.module_name,
+                &movecall.function,
+            )
+            .cloned()
+        else {
+            return MutationResult::Skipped;
+        };
+        if function.type_parameters.len() != movecall.type_arguments.len() {
+            return MutationResult::Skipped;
+        }
+        let Some(type_arguments) = self.retype(state, &function, &movecall.type_arguments) else {
+            return MutationResult::Skipped;
+        };
+        debug!(
+            "Retyping command {} from {:?} to {:?}",
+            idx, movecall.type_arguments, type_arguments
+        );
+        let changed = (0..type_arguments.len())
+            .filter(|i| type_arguments[*i] != movecall.type_arguments[*i])
+            .map(|i| i as u16)
+            .collect::<Vec<_>>();
+        let fixed_ty_args = type_arguments
+            .iter()
+            .enumerate()
+            .map(|(i, ty)| (i as u16, ty.clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        // Arguments not depending on a changed type parameter are kept as is
+        let mut fixed_args = BTreeMap::new();
+        for (i, (param, arg)) in function
+            .parameters
+            .iter()
+            .zip(movecall.arguments.iter())
+            .enumerate()
+        {
+            if changed.iter().any(|t| param.contains_type_param(*t)) {
+                continue;
+            }
+            let Some(ty) = param.subst(&fixed_ty_args) else {
+                return MutationResult::Skipped;
+            };
+            fixed_args.insert(i as u16, (*arg, ty));
+        }
+
+        let snapshot = ptb.clone();
+        let suffix = ptb.commands.split_off(idx);
+        let body = suffix.len() - tail;
+        // Results of the retyped call keep their type unless it mentions a
+        // changed type parameter
+        let changed_results = function
+            .return_paramters
+            .iter()
+            .enumerate()
+            .filter(|(_, ty)| changed.iter().any(|t| ty.contains_type_param(*t)))
+            .map(|(i, _)| i as u16)
+            .collect::<BTreeSet<_>>();
+        let kept = kept_consumers(&suffix[..body], idx, &changed_results);
+
+        // Earlier results the kept commands still consume are not free
+        let mut used_arguments = fixed_args.values().map(|(arg, _)| *arg).collect::<Vec<_>>();
+        if let Some(provider) = &self.flash {
+            used_arguments.extend(provider.borrowed());
+        }
+        used_arguments.extend(
+            suffix[..body]
+                .iter()
+                .zip(kept.iter())
+                .skip(1)
+                .filter(|(_, kept)| **kept)
+                .flat_map(|(cmd, _)| cmd.arguments())
+                .filter(|arg| {
+                    matches!(
+                        arg,
+                        SequenceArgument::Result(i) | SequenceArgument::NestedResult(i, _)
+                            if (*i as usize) < idx
+                    )
+                })
+                .copied(),
+        );
+        let ident = FunctionIdent::new(
+            &movecall.module_id,
+            &movecall.module_name,
+            &movecall.function,
+        );
+        if append_function(
+            state,
+            ptb,
+            &ident,
+            fixed_args,
+            fixed_ty_args,
+            &used_arguments,
+            false,
+            0,
+        )
+        .is_none()
+            || !matches!(ptb.commands.last(), Some(MoveSequenceCall::Call(mc))
+                if mc.type_arguments == type_arguments)
+        {
+            debug!("Failed to retype {}, reverting", ident);
+            *ptb = snapshot;
+            return MutationResult::Skipped;
+        }
+
+        // Commands consuming results whose type changed are dropped along with
+        // everything depending on those, the rest follow the rebuilt call.
+        let mut mapping = (0..idx as u16)
+            .map(Some)
+            .chain(std::iter::repeat_n(None, suffix.len()))
+            .collect::<Vec<_>>();
+        mapping[idx] = Some(ptb.commands.len() as u16 - 1);
+        for (offset, cmd) in suffix.iter().enumerate().take(body).skip(1) {
+            if !kept[offset] {
+                continue;
+            }
+            if let Some(cmd) = remap_command_with_map(cmd, &mapping) {
+                mapping[idx + offset] = Some(ptb.commands.len() as u16);
+                ptb.commands.push(cmd);
+            }
+        }
+        if let Some(provider) = &self.flash {
+            ptb.commands.extend(suffix[body..].iter().cloned());
+            provider.finish(ptb);
+        }
+
+        let object_data = ObjectData::from_ptb(ptb, state);
+        if !object_data.hot_potatoes.is_empty() {
+            debug!(
+                "Retyped sequence leaves hot potatoes {:?}, reverting",
+                object_data.hot_potatoes
+            );
+            *ptb = snapshot;
+            return MutationResult::Skipped;
+        }
+        MutationResult::Mutated
+    }
+}
+
+impl<I, S> Mutator<I, S> for TypeArgMutator<I, S>
+where
+    I: MoveInput,
+    S: HasRand + HasFuzzMetadata + HasFuzzEnv,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, libafl::Error> {
+        let base = strip_generated(input.sequence(), state.fuzz_state());
+        *input.sequence_mut() = base;
+
+        let res = self.mutate_call(state, input.sequence_mut());
+
+        let decorated = apply_hooks(state, input.sequence());
+        *input.sequence_mut() = decorated;
+        *input.outcome_mut() = None;
+        Ok(res)
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _new_corpus_id: Option<libafl::corpus::CorpusId>,
+    ) -> Result<(), libafl::Error> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use movy_types::input::{MoveAddress, MoveStructTag};
+
+    use super::*;
+
+    fn struct_tag(name: &str) -> MoveTypeTag {
+        MoveTypeTag::Struct(MoveStructTag {
+            address: MoveAddress::two(),
+            module: "fake".to_string(),
+            name: name.to_string(),
+            tys: vec![],
+        })
+    }
+
+    #[test]
+    fn candidates_satisfy_the_constraint() {
+        let pool = BTreeMap::from([
+            (
+                MoveAbility::DROP,
+                vec![struct_tag("FAKE"), struct_tag("OTHER")],
+            ),
+            (MoveAbility::KEY, vec![struct_tag("Object")]),
+            (
+                MoveAbility::PRIMITIVES,
+                vec![MoveTypeTag::U64, MoveTypeTag::Bool],
+            ),
+        ]);
+        let candidates = type_arg_candidates(&pool, &MoveAbility::DROP, &struct_tag("OTHER"));
+        assert_eq!(
+            candidates,
+            vec![MoveTypeTag::Bool, MoveTypeTag::U64, struct_tag("FAKE")]
+                .into_iter()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(
+            type_arg_candidates(&pool, &MoveAbility::empty(), &MoveTypeTag::U64).len(),
+            4
+        );
+    }
+
+    #[test]
+    fn consumers_of_unchanged_results_are_kept() {
+        let call = |arguments| {
+            MoveSequenceCall::Call(movy_types::input::MoveCall {
+                module_id: MoveAddress::two(),
+                module_name: "m".to_string(),
+                function: "f".to_string(),
+                type_arguments: vec![],
+                arguments,
+            })
+        };
+        // Command 2 is retyped, its second result changes type
+        let suffix = vec![
+            call(vec![SequenceArgument::Result(0)]),
+            call(vec![SequenceArgument::NestedResult(2, 0)]),
+            call(vec![SequenceArgument::NestedResult(2, 1)]),
+            call(vec![SequenceArgument::Result(4)]),
+            call(vec![
+                SequenceArgument::Result(3),
+                SequenceArgument::Result(1),
+            ]),
+        ];
+        let kept = kept_consumers(&suffix, 2, &BTreeSet::from([1]));
+        assert_eq!(kept, vec![true, true, false, false, true]);
+    }
+}
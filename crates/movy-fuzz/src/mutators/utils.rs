@@ -1,4 +1,8 @@
-use std::hash::{Hash, Hasher};
+use std::{
+    collections::BTreeSet,
+    hash::{Hash, Hasher},
+    sync::Arc,
+};
 
 use libafl::{mutators::MutationResult, state::HasRand};
 use libafl_bolts::rands::Rand;
@@ -13,12 +17,12 @@ use crate::{
     flash::FlashProvider,
     input::MoveInput,
     meta::{HasFuzzMetadata, MutatorKind},
-    mutators::mutation_utils::MutableValue,
+    mutators::mutation_utils::{MagicPools, MutableValue},
     state::HasFuzzEnv,
 };
 
 pub trait HasFlash {
-    fn flash(&self) -> &Option<FlashProvider>;
+    fn flash(&self) -> &Option<Arc<dyn FlashProvider>>;
 }
 
 const MAX_STAGE_REPLAY_ATTEMPTS: u64 = 30;
@@ -140,10 +144,7 @@ pub fn ptb_fingerprint(ptb: &MoveSequence) -> u64 {
 
 pub fn flash_command_limits(mutator: &impl HasFlash) -> (usize, usize) {
     if let Some(provider) = mutator.flash() {
-        match provider {
-            FlashProvider::Cetus { .. } => (1, 8),
-            FlashProvider::Nemo { .. } => (2, 4),
-        }
+        (provider.head_commands(), provider.reserved_inputs())
     } else {
         (0, 0)
     }
@@ -379,7 +380,7 @@ where
     I: MoveInput,
     S: HasRand + HasFuzzMetadata + HasFuzzEnv,
 {
-    let magic_number_pool = input.magic_number_pool(state.fuzz_state()).clone();
+    let magic_number_pool = input.magic_number_pool(state.fuzz_state());
     let ptb = input.sequence_mut();
     if ptb.commands.is_empty() {
         return MutationResult::Skipped;
@@ -483,24 +484,17 @@ where
         if !param.is_mutable() {
             return MutationResult::Skipped;
         }
+        let empty = BTreeSet::new();
+        let function_pool = magic_number_pool
+            .get(&magic_function_ident)
+            .unwrap_or(&empty);
+        let dictionary = state.fuzz_state().generate_magic_number_pool().clone();
+        let pools = [function_pool, &dictionary];
         let mut new_value = MutableValue::new(call_input.clone());
         if magic {
-            new_value.sample_magic_number(
-                state,
-                &magic_number_pool
-                    .get(&magic_function_ident)
-                    .cloned()
-                    .unwrap_or_default(),
-            );
+            new_value.sample_magic_number(state, MagicPools(&pools));
         } else {
-            new_value.mutate(
-                state,
-                &magic_number_pool
-                    .get(&magic_function_ident)
-                    .cloned()
-                    .unwrap_or_default(),
-                split,
-            );
+            new_value.mutate(state, MagicPools(&pools), split);
         }
         *call_input = new_value.value;
         result = MutationResult::Mutated;
@@ -0,0 +1,156 @@
+use std::borrow::Cow;
+
+use libafl::{
+    corpus::CorpusId,
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+};
+use libafl_bolts::Named;
+
+use crate::mutators::sequence::weighted_sample;
+
+/// Picks one of the registered mutators by weight for every mutation. Used to
+/// run mutators supplied by library users in a single extra stage.
+pub struct WeightedMutators<I, S> {
+    pub mutators: Vec<(Box<dyn Mutator<I, S>>, u64)>,
+    last: Option<usize>,
+}
+
+impl<I, S> Default for WeightedMutators<I, S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I, S> WeightedMutators<I, S> {
+    pub fn new() -> Self {
+        Self {
+            mutators: vec![],
+            last: None,
+        }
+    }
+
+    /// Mutators with a zero weight are never picked.
+    pub fn push(&mut self, mutator: Box<dyn Mutator<I, S>>, weight: u64) {
+        if weight > 0 {
+            self.mutators.push((mutator, weight));
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.mutators.is_empty()
+    }
+}
+
+impl<I, S> Named for WeightedMutators<I, S> {
+    fn name(&self) -> &Cow<'static, str> {
+        &Cow::Borrowed("weighted_mutators")
+    }
+}
+
+impl<I, S> Mutator<I, S> for WeightedMutators<I, S>
+where
+    S: HasRand,
+{
+    fn mutate(&mut self, state: &mut S, input: &mut I) -> Result<MutationResult, libafl::Error> {
+        if self.mutators.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let indices = (0..self.mutators.len()).collect::<Vec<_>>();
+        let weights = self.mutators.iter().map(|(_, w)| *w).collect::<Vec<_>>();
+        let idx = *weighted_sample(&indices, &weights, state);
+        self.last = Some(idx);
+        self.mutators[idx].0.mutate(state, input)
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        new_corpus_id: Option<CorpusId>,
+    ) -> Result<(), libafl::Error> {
+        match self.last.take() {
+            Some(idx) => self.mutators[idx].0.post_exec(state, new_corpus_id),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use libafl_bolts::rands::StdRand;
+
+    use super::*;
+
+    struct RandState(StdRand);
+
+    impl HasRand for RandState {
+        type Rand = StdRand;
+
+        fn rand(&self) -> &StdRand {
+            &self.0
+        }
+
+        fn rand_mut(&mut self) -> &mut StdRand {
+            &mut self.0
+        }
+    }
+
+    /// Counts how often it was picked and told about the execution.
+    struct Counting(usize, Rc<RefCell<Vec<(usize, usize)>>>);
+
+    impl Named for Counting {
+        fn name(&self) -> &Cow<'static, str> {
+            &Cow::Borrowed("counting")
+        }
+    }
+
+    impl Mutator<(), RandState> for Counting {
+        fn mutate(
+            &mut self,
+            _state: &mut RandState,
+            _input: &mut (),
+        ) -> Result<MutationResult, libafl::Error> {
+            self.1.borrow_mut()[self.0].0 += 1;
+            Ok(MutationResult::Mutated)
+        }
+
+        fn post_exec(
+            &mut self,
+            _state: &mut RandState,
+            _new_corpus_id: Option<CorpusId>,
+        ) -> Result<(), libafl::Error> {
+            self.1.borrow_mut()[self.0].1 += 1;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn mutators_are_picked_by_weight() {
+        let counts = Rc::new(RefCell::new(vec![(0, 0); 3]));
+        let mut mutators = WeightedMutators::new();
+        mutators.push(Box::new(Counting(0, counts.clone())), 1);
+        mutators.push(Box::new(Counting(1, counts.clone())), 0);
+        mutators.push(Box::new(Counting(2, counts.clone())), 3);
+        assert_eq!(mutators.mutators.len(), 2);
+
+        let mut state = RandState(StdRand::with_seed(7));
+        for _ in 0..1000 {
+            mutators.mutate(&mut state, &mut ()).unwrap();
+            mutators.post_exec(&mut state, None).unwrap();
+        }
+        let counts = counts.borrow();
+        assert_eq!(counts[1], (0, 0));
+        assert_eq!(counts[0].0 + counts[2].0, 1000);
+        assert!(counts[0].0 > 150 && counts[2].0 > 3 * counts[0].0 / 2);
+        assert!(counts.iter().all(|(picked, told)| picked == told));
+
+        let mut empty = WeightedMutators::<(), RandState>::new();
+        assert!(empty.is_empty());
+        assert_eq!(
+            empty.mutate(&mut state, &mut ()).unwrap(),
+            MutationResult::Skipped
+        );
+    }
+}
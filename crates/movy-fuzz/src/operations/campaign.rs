@@ -0,0 +1,611 @@
+use std::{collections::BTreeSet, num::NonZero, path::PathBuf, sync::Arc, time::Duration};
+
+use libafl::{
+    Evaluator, Fuzzer, HasMetadata, StdFuzzer,
+    corpus::{InMemoryCorpus, InMemoryOnDiskCorpus},
+    events::{ProgressReporter, SimpleEventManager},
+    feedback_and_fast, feedback_or,
+    feedbacks::{ConstFeedback, CrashFeedback, ExitKindFeedback, Feedback, MaxMapPow2Feedback},
+    monitors::SimpleMonitor,
+    mutators::Mutator,
+    schedulers::{Scheduler, WeightedScheduler},
+    stages::{CalibrationStage, StdMutationalStage},
+    state::StdState,
+};
+use libafl_bolts::{tuple_list, tuple_list_type};
+use movy_replay::{
+    db::{ObjectStoreCachedStore, ObjectStoreInfo},
+    env::SuiTestingEnv,
+    exec::SuiExecutor,
+    tracer::{
+        directed::DirectedCollector, fuzz::PackageResolvedCache, lcov::LineCoverageCollector,
+        oracle::SuiGeneralOracle,
+    },
+};
+use movy_sui::{
+    database::cache::ObjectSuiStoreCommit,
+    lcov::{BytecodeLocation, LineCoverageMap},
+};
+use movy_types::error::MovyError;
+use sui_types::storage::{BackingPackageStore, BackingStore, ObjectStore};
+use tracing::{info, warn};
+
+use crate::{
+    directed::DirectedMetadata,
+    executor::SuiFuzzExecutor,
+    flash::{FlashProvider, FlashWrapper},
+    input::MoveFuzzInput,
+    meta::{FuzzMetadata, HasFuzzMetadata},
+    mutators::{
+        arg::ArgMutator, sequence::SequenceMutator, state::StateHavocMutator,
+        transaction::TransactionMutator, ty_arg::TypeArgMutator, weighted::WeightedMutators,
+    },
+    operations::{
+        fuzz::{COVERAGE_FEEDBACK_NAME, CodeObserver, OkFeedback, code_observer},
+        sui_fuzz::oracles,
+    },
+    oracles::config::OracleSuiteConfig,
+    sched::MoveFuzzInputScore,
+    state::{HasExtraState, HasFuzzEnv, SuperState},
+    state_fuzz::StateFuzzMetadata,
+    stats::{STATS_FILE, StatsTracker},
+    utils::{AppendOutcomeFeedback, OptionalStage, SelectiveCorpus, SuperRand},
+};
+
+/// Queue and crashes of a campaign, on disk when an output directory is set.
+pub type SuiFuzzCorpus = SelectiveCorpus<
+    InMemoryOnDiskCorpus<MoveFuzzInput>,
+    InMemoryCorpus<MoveFuzzInput>,
+    MoveFuzzInput,
+>;
+/// The state extra oracles, mutators, feedbacks and schedulers work on.
+pub type SuiFuzzState<T> =
+    SuperState<StdState<SuiFuzzCorpus, MoveFuzzInput, SuperRand, SuiFuzzCorpus>, T>;
+pub type SuiFuzzObservers = tuple_list_type!(CodeObserver);
+pub type SuiFuzzEventManager<T> =
+    SimpleEventManager<MoveFuzzInput, SimpleMonitor<fn(&str)>, SuiFuzzState<T>>;
+
+/// The weighted scheduler over the edge coverage, used unless
+/// [`FuzzCampaignBuilder::scheduler`] is called.
+pub struct DefaultScheduler;
+
+/// A scheduler built by a user closure once the state exists.
+pub struct CustomScheduler<SF>(SF);
+
+/// Plain settings of a campaign, see the setters of [`FuzzCampaignBuilder`].
+#[derive(Default)]
+pub struct FuzzCampaignOptions {
+    pub output: Option<PathBuf>,
+    pub time_limit: Option<Duration>,
+    pub cycle_limit: Option<u64>,
+    pub lcov: Option<(PathBuf, LineCoverageMap)>,
+    pub source_map: Option<LineCoverageMap>,
+    pub directed: Option<DirectedMetadata>,
+    pub seeds: Vec<MoveFuzzInput>,
+    pub typed_bug_abort: bool,
+    pub disable_profit_oracle: bool,
+    pub disable_defects_oracle: bool,
+    pub oracle_config: OracleSuiteConfig,
+    pub state_fuzz: bool,
+    pub bitvector: bool,
+    pub flash: Option<Arc<dyn FlashProvider>>,
+    pub tui: bool,
+}
+
+/// Sets up and runs a fuzzing campaign. Besides the builtin stages and
+/// oracles, library users can plug in their own oracles, mutators, feedbacks
+/// and a scheduler:
+///
+/// ```ignore
+/// FuzzCampaignBuilder::new(meta, env)
+///     .output_dir("out")
+///     .time_limit(Duration::from_secs(3600))
+///     .oracle(MyInvariantOracle::default())
+///     .mutator(MyPoolMutator::new(), 4)
+///     .run()?;
+/// ```
+pub struct FuzzCampaignBuilder<T, O = (), F = ConstFeedback, SF = DefaultScheduler> {
+    meta: FuzzMetadata,
+    env: SuiTestingEnv<T>,
+    options: FuzzCampaignOptions,
+    oracles: O,
+    mutators: WeightedMutators<MoveFuzzInput, SuiFuzzState<T>>,
+    feedback: F,
+    scheduler: SF,
+}
+
+impl<T> FuzzCampaignBuilder<T> {
+    pub fn new(meta: FuzzMetadata, env: SuiTestingEnv<T>) -> Self {
+        Self {
+            meta,
+            env,
+            options: FuzzCampaignOptions::default(),
+            oracles: (),
+            mutators: WeightedMutators::new(),
+            feedback: ConstFeedback::new(false),
+            scheduler: DefaultScheduler,
+        }
+    }
+}
+
+impl<T, O, F, SF> FuzzCampaignBuilder<T, O, F, SF> {
+    /// Keep the queue, the crashes and the stats file under `output`.
+    pub fn output_dir(mut self, output: impl Into<PathBuf>) -> Self {
+        self.options.output = Some(output.into());
+        self
+    }
+
+    pub fn time_limit(mut self, limit: Duration) -> Self {
+        self.options.time_limit = Some(limit);
+        self
+    }
+
+    pub fn cycle_limit(mut self, limit: u64) -> Self {
+        self.options.cycle_limit = Some(limit);
+        self
+    }
+
+    /// Rewrite the LCOV report at `output` whenever line coverage grows.
+    pub fn lcov(mut self, output: impl Into<PathBuf>, map: LineCoverageMap) -> Self {
+        self.options.lcov = Some((output.into(), map));
+        self
+    }
+
+    /// Locate findings in the sources of the local packages of `map`.
+    pub fn source_map(mut self, map: LineCoverageMap) -> Self {
+        self.options.source_map = Some(map);
+        self
+    }
+
+    pub fn directed(mut self, directed: DirectedMetadata) -> Self {
+        self.options.directed = Some(directed);
+        self
+    }
+
+    pub fn seeds(mut self, seeds: impl IntoIterator<Item = MoveFuzzInput>) -> Self {
+        self.options.seeds.extend(seeds);
+        self
+    }
+
+    pub fn typed_bug_abort(mut self, enabled: bool) -> Self {
+        self.options.typed_bug_abort = enabled;
+        self
+    }
+
+    pub fn disable_profit_oracle(mut self, disabled: bool) -> Self {
+        self.options.disable_profit_oracle = disabled;
+        self
+    }
+
+    pub fn disable_defects_oracle(mut self, disabled: bool) -> Self {
+        self.options.disable_defects_oracle = disabled;
+        self
+    }
+
+    /// Settings of the builtin oracles, on top of which the switches above
+    /// apply.
+    pub fn oracle_config(mut self, config: OracleSuiteConfig) -> Self {
+        self.options.oracle_config = config;
+        self
+    }
+
+    pub fn state_fuzz(mut self, enabled: bool) -> Self {
+        self.options.state_fuzz = enabled;
+        self
+    }
+
+    pub fn bitvector(mut self, enabled: bool) -> Self {
+        self.options.bitvector = enabled;
+        self
+    }
+
+    pub fn flash(mut self, flash: Option<Arc<dyn FlashProvider>>) -> Self {
+        self.options.flash = flash;
+        self
+    }
+
+    pub fn tui(mut self, enabled: bool) -> Self {
+        self.options.tui = enabled;
+        self
+    }
+
+    /// Add a mutator, picked by `weight` against the other added mutators in
+    /// a stage after the builtin ones. It sees the sequence with the hooks
+    /// applied, see `strip_generated` and `apply_hooks`.
+    pub fn mutator<M>(mut self, mutator: M, weight: u64) -> Self
+    where
+        M: Mutator<MoveFuzzInput, SuiFuzzState<T>> + 'static,
+    {
+        self.mutators.push(Box::new(mutator), weight);
+        self
+    }
+
+    /// Add an oracle checked after the builtin ones.
+    pub fn oracle<O2>(self, oracle: O2) -> FuzzCampaignBuilder<T, (O, O2), F, SF> {
+        FuzzCampaignBuilder {
+            meta: self.meta,
+            env: self.env,
+            options: self.options,
+            oracles: (self.oracles, oracle),
+            mutators: self.mutators,
+            feedback: self.feedback,
+            scheduler: self.scheduler,
+        }
+    }
+
+    /// Set a feedback deciding whether an input is kept in the queue. It is
+    /// or-ed with the edge coverage, inputs must still execute successfully.
+    pub fn feedback<F2>(self, feedback: F2) -> FuzzCampaignBuilder<T, O, F2, SF> {
+        FuzzCampaignBuilder {
+            meta: self.meta,
+            env: self.env,
+            options: self.options,
+            oracles: self.oracles,
+            mutators: self.mutators,
+            feedback,
+            scheduler: self.scheduler,
+        }
+    }
+
+    /// Replace the corpus scheduler. `factory` is called with the state and
+    /// the coverage observer once they exist.
+    pub fn scheduler<SF2, CS>(
+        self,
+        factory: SF2,
+    ) -> FuzzCampaignBuilder<T, O, F, CustomScheduler<SF2>>
+    where
+        SF2: FnOnce(&mut SuiFuzzState<T>, &CodeObserver) -> CS,
+    {
+        FuzzCampaignBuilder {
+            meta: self.meta,
+            env: self.env,
+            options: self.options,
+            oracles: self.oracles,
+            mutators: self.mutators,
+            feedback: self.feedback,
+            scheduler: CustomScheduler(factory),
+        }
+    }
+}
+
+fn log_monitor(s: &str) {
+    info!("{}", s);
+}
+
+impl<T, O, F> FuzzCampaignBuilder<T, O, F, DefaultScheduler>
+where
+    T: ObjectStoreCachedStore
+        + ObjectStoreInfo
+        + ObjectStore
+        + ObjectSuiStoreCommit
+        + BackingStore
+        + BackingPackageStore
+        + Clone
+        + 'static,
+    O: SuiGeneralOracle<SuiFuzzState<T>>,
+    F: Feedback<SuiFuzzEventManager<T>, MoveFuzzInput, SuiFuzzObservers, SuiFuzzState<T>>,
+{
+    pub fn run(self) -> Result<(), MovyError> {
+        self.run_with(|state, observer| {
+            WeightedScheduler::<_, MoveFuzzInputScore, _>::new(state, observer)
+        })
+    }
+}
+
+impl<T, O, F, SF, CS> FuzzCampaignBuilder<T, O, F, CustomScheduler<SF>>
+where
+    T: ObjectStoreCachedStore
+        + ObjectStoreInfo
+        + ObjectStore
+        + ObjectSuiStoreCommit
+        + BackingStore
+        + BackingPackageStore
+        + Clone
+        + 'static,
+    O: SuiGeneralOracle<SuiFuzzState<T>>,
+    F: Feedback<SuiFuzzEventManager<T>, MoveFuzzInput, SuiFuzzObservers, SuiFuzzState<T>>,
+    SF: FnOnce(&mut SuiFuzzState<T>, &CodeObserver) -> CS,
+    CS: Scheduler<MoveFuzzInput, SuiFuzzState<T>>,
+{
+    pub fn run(self) -> Result<(), MovyError> {
+        let builder = FuzzCampaignBuilder {
+            meta: self.meta,
+            env: self.env,
+            options: self.options,
+            oracles: self.oracles,
+            mutators: self.mutators,
+            feedback: self.feedback,
+            scheduler: DefaultScheduler,
+        };
+        builder.run_with(self.scheduler.0)
+    }
+}
+
+impl<T, O, F> FuzzCampaignBuilder<T, O, F, DefaultScheduler>
+where
+    T: ObjectStoreCachedStore
+        + ObjectStoreInfo
+        + ObjectStore
+        + ObjectSuiStoreCommit
+        + BackingStore
+        + BackingPackageStore
+        + Clone
+        + 'static,
+    O: SuiGeneralOracle<SuiFuzzState<T>>,
+    F: Feedback<SuiFuzzEventManager<T>, MoveFuzzInput, SuiFuzzObservers, SuiFuzzState<T>>,
+{
+    fn run_with<CS>(
+        self,
+        scheduler: impl FnOnce(&mut SuiFuzzState<T>, &CodeObserver) -> CS,
+    ) -> Result<(), MovyError>
+    where
+        CS: Scheduler<MoveFuzzInput, SuiFuzzState<T>>,
+    {
+        let Self {
+            meta,
+            env,
+            options,
+            oracles: extra_oracles,
+            mutators,
+            feedback,
+            scheduler: _,
+        } = self;
+        let FuzzCampaignOptions {
+            output,
+            time_limit,
+            cycle_limit,
+            lcov,
+            source_map,
+            directed,
+            seeds,
+            typed_bug_abort,
+            disable_profit_oracle,
+            disable_defects_oracle,
+            mut oracle_config,
+            state_fuzz,
+            bitvector,
+            flash,
+            tui,
+        } = options;
+        oracle_config.apply_flags(
+            typed_bug_abort,
+            disable_profit_oracle,
+            disable_defects_oracle,
+        );
+
+        let code_observer = code_observer();
+        let coverage_feedback =
+            MaxMapPow2Feedback::with_name(COVERAGE_FEEDBACK_NAME, &code_observer);
+
+        let calib = CalibrationStage::new(&coverage_feedback);
+        let mut corpus_feedback = feedback_and_fast!(
+            ExitKindFeedback::<OkFeedback>::new(),
+            AppendOutcomeFeedback {},
+            feedback_or!(coverage_feedback, feedback)
+        );
+        let mut crash_feedback = feedback_and_fast!(CrashFeedback::new(), AppendOutcomeFeedback {});
+
+        let corpus = if let Some(output) = &output {
+            let corpus = output.join("queue");
+            std::fs::create_dir_all(&corpus)?;
+            SelectiveCorpus::corpus1(InMemoryOnDiskCorpus::<MoveFuzzInput>::new(corpus)?)
+        } else {
+            SelectiveCorpus::corpus2(InMemoryCorpus::<MoveFuzzInput>::new())
+        };
+
+        let crashes = if let Some(output) = &output {
+            let crash = output.join("crashes");
+            std::fs::create_dir_all(&crash)?;
+            SelectiveCorpus::corpus1(InMemoryOnDiskCorpus::new(crash)?)
+        } else {
+            SelectiveCorpus::corpus2(InMemoryCorpus::new())
+        };
+
+        let state = StdState::new(
+            meta.rand.clone(),
+            corpus,
+            crashes,
+            &mut corpus_feedback,
+            &mut crash_feedback,
+        )?;
+        let attacker = meta.attacker;
+
+        let mut state: SuiFuzzState<T> = SuperState::new(state, env);
+
+        info!("target functions: {:?}", meta.target_functions);
+        info!(
+            "module address to package: {:?}",
+            meta.module_address_to_package
+        );
+        let mut meta = meta;
+        if let Some(directed) = &directed {
+            info!(
+                "Directed fuzzing towards {}{}, {} functions on the way",
+                directed.target,
+                directed.line.map(|l| format!(":{}", l)).unwrap_or_default(),
+                directed.distances.len()
+            );
+            directed.boost_function_scores(&mut meta);
+        }
+        state.add_metadata::<FuzzMetadata>(meta);
+        let directed_collector = directed.map(|directed| {
+            let collector = DirectedCollector::new(directed.locations.clone());
+            state.add_metadata(directed);
+            collector
+        });
+
+        if state_fuzz {
+            let state_meta =
+                StateFuzzMetadata::from_store(state.fuzz_state(), state.fuzz_env().inner());
+            info!(
+                "State fuzzing on {} shared objects, {} numeric fields",
+                state_meta.objects.len(),
+                state_meta
+                    .objects
+                    .values()
+                    .map(|obj| obj.fields.len())
+                    .sum::<usize>()
+            );
+            state.add_metadata(state_meta);
+        }
+
+        let executor_inner = SuiExecutor::new(state.fuzz_env().inner().clone())?;
+        let upgraded = state
+            .fuzz_state()
+            .upgrade
+            .as_ref()
+            .map(|plan| {
+                plan.publish(
+                    &executor_inner,
+                    state.fuzz_state().epoch,
+                    state.fuzz_state().epoch_ms,
+                )
+            })
+            .transpose()?;
+
+        let sched = scheduler(&mut state, &code_observer);
+        let mut executor = SuiFuzzExecutor {
+            executor: executor_inner,
+            ob: tuple_list!(code_observer),
+            attacker,
+            oracles: (
+                oracles(&oracle_config, state.fuzz_env(), state.fuzz_state())?,
+                extra_oracles,
+            ),
+            packages_cache: PackageResolvedCache::default(),
+            line_coverage: lcov.as_ref().map(|_| LineCoverageCollector::new()),
+            directed: directed_collector,
+            source_map,
+            epoch: state.fuzz_state().epoch,
+            epoch_ms: state.fuzz_state().epoch_ms,
+            concolic_bitvector: bitvector,
+            upgraded,
+            ph: std::marker::PhantomData,
+        };
+
+        let mut sequence_mutator = SequenceMutator::new();
+        sequence_mutator.flash = flash.clone();
+        let mut arg_mutator = ArgMutator::new();
+        arg_mutator.flash = flash.clone();
+        let mut ty_arg_mutator = TypeArgMutator::new();
+        ty_arg_mutator.flash = flash.clone();
+        let mut stages = tuple_list!(
+            calib,
+            StdMutationalStage::with_max_iterations(sequence_mutator, NonZero::new(256).unwrap()),
+            StdMutationalStage::with_max_iterations(arg_mutator, NonZero::new(256).unwrap()),
+            StdMutationalStage::with_max_iterations(ty_arg_mutator, NonZero::new(32).unwrap()),
+            StdMutationalStage::with_max_iterations(
+                TransactionMutator::new(),
+                NonZero::new(16).unwrap()
+            ),
+            OptionalStage(state_fuzz.then(|| {
+                StdMutationalStage::with_max_iterations(
+                    StateHavocMutator::new(),
+                    NonZero::new(16).unwrap(),
+                )
+            })),
+            OptionalStage((!mutators.is_empty()).then(|| {
+                StdMutationalStage::with_max_iterations(mutators, NonZero::new(128).unwrap())
+            })),
+        );
+
+        let mut fuzzer = StdFuzzer::new(sched, corpus_feedback, crash_feedback);
+        let mut mgr: SuiFuzzEventManager<T> =
+            SimpleEventManager::new(SimpleMonitor::new(log_monitor as fn(&str)));
+        let mut last_lcov_hits = BTreeSet::<BytecodeLocation>::new();
+
+        info!("Adding initial input...");
+        let initial_input = match &flash {
+            Some(provider) => {
+                info!("Fuzzing inside the flash loan of {}", provider);
+                MoveFuzzInput::flash(provider.as_ref())
+            }
+            None => MoveFuzzInput::new(),
+        };
+        fuzzer.add_input(&mut state, &mut executor, &mut mgr, initial_input)?;
+        if !seeds.is_empty() {
+            info!("Adding {} seeds...", seeds.len());
+        }
+        for mut seed in seeds {
+            // Corpus entries of an earlier flash campaign are wrapped already
+            if let Some(provider) = &flash
+                && seed.flash.is_none()
+            {
+                seed.sequence = provider.wrap(std::mem::take(&mut seed.sequence));
+                seed.flash = Some(FlashWrapper::new(provider.as_ref()));
+            }
+            fuzzer.add_input(&mut state, &mut executor, &mut mgr, seed)?;
+        }
+
+        #[cfg(feature = "pprof")]
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(1000)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()
+            .unwrap();
+
+        let mut stats = StatsTracker::new(output.as_ref().map(|o| o.join(STATS_FILE)), tui);
+        let start = std::time::SystemTime::now();
+        let mut cycle = 1u64;
+        loop {
+            if let Some(limit) = time_limit {
+                let current = std::time::SystemTime::now();
+
+                let elapsed = current.duration_since(start).expect("non mono clock?!");
+                if elapsed > limit {
+                    break;
+                }
+            }
+
+            if let Some(climit) = cycle_limit
+                && cycle >= climit
+            {
+                break;
+            }
+
+            if let Err(e) = fuzzer.fuzz_one(&mut stages, &mut executor, &mut state, &mut mgr) {
+                match e {
+                    libafl::Error::InvalidCorpus(msg, _) => {
+                        warn!("Skipping invalid corpus testcase: {}", msg);
+                        cycle += 1;
+                        mgr.report_progress(&mut state)?;
+                        continue;
+                    }
+                    other => {
+                        warn!("Getting fuzz error: {:?}", other);
+                        break;
+                    }
+                }
+            }
+
+            // Clear per-round execution outcome to avoid leaking stage indices into the next round.
+            state.extra_state_mut().global_outcome = None;
+
+            if let Some((lcov_path, lcov_map)) = &lcov {
+                let hits = executor.line_coverage_hits();
+                if hits != last_lcov_hits {
+                    lcov_map.write_lcov(hits.clone(), lcov_path)?;
+                    last_lcov_hits = hits;
+                }
+            }
+
+            info!("Cycle {} done", cycle);
+            let lines = lcov
+                .as_ref()
+                .map(|(_, lcov_map)| lcov_map.covered_lines(&last_lcov_hits));
+            stats.update(&state, cycle, lines)?;
+            cycle += 1;
+            mgr.report_progress(&mut state)?;
+        }
+        stats.report()?;
+
+        #[cfg(feature = "pprof")]
+        {
+            let report = guard.report().build().expect("generate report");
+            let file = std::fs::File::create("flamegraph.svg").unwrap();
+            report.flamegraph(file).unwrap();
+        }
+
+        Ok(())
+    }
+}
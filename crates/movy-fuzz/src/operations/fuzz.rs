@@ -2,6 +2,10 @@ use libafl::{feedbacks::ExitKindLogic, observers::StdMapObserver};
 
 use crate::executor::CODE_OBSERVER_NAME;
 
+/// Name of the edge coverage feedback, which keeps the covered map in the
+/// named metadata of the state.
+pub const COVERAGE_FEEDBACK_NAME: &str = "code-fb";
+
 pub struct OkFeedback;
 
 impl ExitKindLogic for OkFeedback {
@@ -11,6 +15,9 @@ impl ExitKindLogic for OkFeedback {
     }
 }
 
-pub fn code_observer() -> StdMapObserver<'static, u8, false> {
+/// The edge coverage map observed by the fuzz executor.
+pub type CodeObserver = StdMapObserver<'static, u8, false>;
+
+pub fn code_observer() -> CodeObserver {
     StdMapObserver::owned(CODE_OBSERVER_NAME, vec![0u8; 16384])
 }
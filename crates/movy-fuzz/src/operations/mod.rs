@@ -1,4 +1,6 @@
+pub mod campaign;
 pub mod fuzz;
+pub mod sui_diff;
 pub mod sui_fuzz;
 pub mod sui_replay;
 pub mod sui_test;
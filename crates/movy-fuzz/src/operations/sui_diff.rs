@@ -0,0 +1,65 @@
+use std::{path::PathBuf, time::Duration};
+
+use movy_replay::{
+    db::{ObjectStoreCachedStore, ObjectStoreInfo, ObjectStoreMintObject},
+    env::SuiTestingEnv,
+    exec::SuiExecutor,
+    meta::Metadata,
+};
+use movy_sui::database::cache::ObjectSuiStoreCommit;
+use movy_types::error::MovyError;
+use sui_types::storage::{BackingPackageStore, BackingStore, ObjectStore};
+use tracing::info;
+
+use crate::{
+    diff::VersionMapping, meta::FuzzMetadata, operations::campaign::FuzzCampaignBuilder,
+    oracles::sui::DifferentialOracle,
+};
+
+/// Fuzz the old version of a package, `meta` and `old_env`, and replay every
+/// sequence on the new version in `new_env`. Divergences end up in the
+/// crashes of `output`.
+pub fn diff_fuzz<T>(
+    meta: FuzzMetadata,
+    old_env: SuiTestingEnv<T>,
+    new_env: SuiTestingEnv<T>,
+    new_meta: Metadata,
+    mapping: VersionMapping,
+    output: &Option<PathBuf>,
+    time_limit: Option<u64>,
+    cycles_limit: Option<u64>,
+) -> Result<(), MovyError>
+where
+    T: ObjectStoreCachedStore
+        + ObjectStoreInfo
+        + ObjectStoreMintObject
+        + ObjectStore
+        + ObjectSuiStoreCommit
+        + BackingStore
+        + BackingPackageStore
+        + Clone
+        + 'static,
+{
+    info!(
+        "Mapping packages {:?} and {} objects onto the new version",
+        mapping.packages,
+        mapping.objects.len()
+    );
+    let old = SuiExecutor::new(old_env.inner().clone())?;
+    let new = SuiExecutor::new(new_env.into_inner())?;
+    let mut builder = FuzzCampaignBuilder::new(meta, old_env)
+        // Only divergences are interesting here
+        .disable_profit_oracle(true)
+        .disable_defects_oracle(true)
+        .oracle(DifferentialOracle::new(old, new, new_meta, mapping));
+    if let Some(output) = output {
+        builder = builder.output_dir(output);
+    }
+    if let Some(limit) = time_limit {
+        builder = builder.time_limit(Duration::from_secs(limit));
+    }
+    if let Some(limit) = cycles_limit {
+        builder = builder.cycle_limit(limit);
+    }
+    builder.run()
+}
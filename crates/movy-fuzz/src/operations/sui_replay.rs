@@ -23,11 +23,13 @@ use sui_types::{
 };
 
 use crate::{
-    executor::SuiFuzzExecutor,
+    executor::{SuiFuzzExecutor, commit_prelude},
     input::MoveFuzzInput,
     meta::{FuzzMetadata, HasFuzzMetadata},
-    operations::fuzz::{OkFeedback, code_observer},
+    operations::fuzz::{COVERAGE_FEEDBACK_NAME, OkFeedback, code_observer},
+    oracles::config::OracleSuiteConfig,
     state::{HasFuzzEnv, SuperState},
+    state_fuzz::apply_state_patches,
     utils::AppendOutcomeFeedback,
 };
 
@@ -48,12 +50,24 @@ where
         + 'static,
 {
     let inner = env.into_inner();
-    let executor = SuiExecutor::new(inner)?;
-    let tracer = if trace { Some(TreeTracer::new()) } else { None };
-    let out = executor.run_ptb_with_movy_tracer_gas(
-        seed.sequence.to_ptb()?,
+    let mut executor = SuiExecutor::new(inner)?.layered();
+    apply_state_patches(&executor, &seed.state_patches)?;
+    let (sequence, epoch, epoch_ms) = commit_prelude(
+        &mut executor,
+        &seed.prelude,
+        &seed.sequence,
+        meta.attacker,
+        meta.gas_id,
         meta.epoch,
         meta.epoch_ms,
+        meta.upgrade.as_ref(),
+        None,
+    )?;
+    let tracer = if trace { Some(TreeTracer::new()) } else { None };
+    let out = executor.run_ptb_with_movy_tracer_gas(
+        sequence.to_ptb()?,
+        epoch,
+        epoch_ms,
         meta.attacker.into(),
         meta.gas_id.into(),
         tracer,
@@ -66,10 +80,13 @@ where
     Ok(())
 }
 
+/// Run `seed` through the fuzzing executor, checked by the oracles of
+/// `oracle_config` as in the campaign that found it.
 pub fn sui_fuzz_replay_seed<T>(
     env: SuiTestingEnv<T>,
     meta: FuzzMetadata,
     seed: MoveFuzzInput,
+    oracle_config: &OracleSuiteConfig,
 ) -> Result<(), MovyError>
 where
     T: ObjectStoreCachedStore
@@ -82,7 +99,7 @@ where
         + 'static,
 {
     let code_observer = code_observer();
-    let coverage_feedback = MaxMapPow2Feedback::with_name("code-fb", &code_observer);
+    let coverage_feedback = MaxMapPow2Feedback::with_name(COVERAGE_FEEDBACK_NAME, &code_observer);
 
     let mut corpus_feedback = feedback_and_fast!(
         ExitKindFeedback::<OkFeedback>::new(),
@@ -112,11 +129,15 @@ where
         executor: executor_inner,
         ob: tuple_list!(code_observer),
         attacker,
-        oracles: super::sui_fuzz::oracles(false, false, false),
+        oracles: super::sui_fuzz::oracles(oracle_config, state.fuzz_env(), state.fuzz_state())?,
         packages_cache: PackageResolvedCache::default(),
         line_coverage: None,
+        directed: None,
+        source_map: None,
         epoch: state.fuzz_state().epoch,
         epoch_ms: state.fuzz_state().epoch_ms,
+        concolic_bitvector: false,
+        upgraded: None,
         ph: std::marker::PhantomData,
     };
 
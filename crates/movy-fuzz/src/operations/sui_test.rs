@@ -173,33 +173,42 @@ fn build_test_fixed_args(
     Ok(fixed)
 }
 
-/// Decode the human-readable reason carried by a `movy::oracle::Crash` event. The event wraps a
-/// `movy::log::Log` (a list of optionally-keyed strings); `crash_because` stores the message under
-/// the `reason` key. Returns `None` if the payload doesn't decode or carries no message.
-fn decode_crash_reason(contents: &[u8]) -> Option<String> {
-    #[derive(serde::Deserialize)]
-    struct Crash {
-        reason: Log,
-    }
-    #[derive(serde::Deserialize)]
-    struct Log {
-        msg: Vec<MayKeyedString>,
-    }
-    #[derive(serde::Deserialize)]
-    struct MayKeyedString {
-        key: Option<String>,
-        value: String,
-    }
+#[derive(serde::Deserialize)]
+struct Crash {
+    reason: Log,
+}
+
+#[derive(serde::Deserialize)]
+struct Log {
+    msg: Vec<MayKeyedString>,
+}
 
+#[derive(serde::Deserialize)]
+struct MayKeyedString {
+    key: Option<String>,
+    value: String,
+}
+
+/// The message `crash_because` stores under the `reason` key of a
+/// `movy::oracle::Crash` event, without any other details it carries.
+pub(crate) fn keyed_crash_reason(contents: &[u8]) -> Option<String> {
     let crash: Crash = bcs::from_bytes(contents).ok()?;
-    if let Some(entry) = crash
+    crash
         .reason
         .msg
-        .iter()
+        .into_iter()
         .find(|entry| entry.key.as_deref() == Some("reason"))
-    {
-        return Some(entry.value.clone());
+        .map(|entry| entry.value)
+}
+
+/// Decode the human-readable reason carried by a `movy::oracle::Crash` event. The event wraps a
+/// `movy::log::Log` (a list of optionally-keyed strings); `crash_because` stores the message under
+/// the `reason` key. Returns `None` if the payload doesn't decode or carries no message.
+fn decode_crash_reason(contents: &[u8]) -> Option<String> {
+    if let Some(reason) = keyed_crash_reason(contents) {
+        return Some(reason);
     }
+    let crash: Crash = bcs::from_bytes(contents).ok()?;
     if crash.reason.msg.is_empty() {
         return None;
     }
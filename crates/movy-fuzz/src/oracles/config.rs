@@ -0,0 +1,376 @@
+use std::{collections::BTreeMap, path::Path};
+
+use color_eyre::eyre::eyre;
+use glob::Pattern;
+use move_binary_format::file_format::Bytecode;
+use move_trace_format::format::Frame;
+use movy_replay::tracer::{concolic::ConcolicState, oracle::SuiGeneralOracle, state::TraceState};
+use movy_types::{
+    error::MovyError,
+    input::{FunctionIdent, MoveSequence},
+    oracle::{OracleFinding, Severity},
+};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sui_types::{effects::TransactionEffects, storage::ObjectStore};
+use tracing::warn;
+
+/// Oracles muted by `--disable-defects-oracle`
+pub const DEFECT_ORACLES: &[&str] = &[
+    "AccessControlOracle",
+    "ArithmeticAbortOracle",
+    "BoolJudgementOracle",
+    "ConservationOracle",
+    "GasGriefingOracle",
+    "InfiniteLoopOracle",
+    "LivenessOracle",
+    "OverflowOracle",
+    "OwnershipOracle",
+    "PrecisionLossOracle",
+    "RoundingOracle",
+    "TypeConversionOracle",
+];
+
+/// Oracles muted by `--disable-profit-oracle`
+pub const PROFIT_ORACLES: &[&str] = &["ProceedsOracle"];
+
+/// Oracles which are always on unless configured otherwise
+pub const OTHER_ORACLES: &[&str] = &["EventHookOracle", "TypedBugOracle", "UpgradeOracle"];
+
+/// Settings of the builtin oracles for `--oracles`, in TOML or JSON, keyed by
+/// the oracle name as it appears in findings:
+///
+/// ```toml
+/// [PrecisionLossOracle]
+/// enabled = false
+///
+/// [OverflowOracle]
+/// exclude = ["*::math::*", "*::full_math_u128::*"]
+///
+/// [InfiniteLoopOracle]
+/// threshold = 5000
+///
+/// [GasGriefingOracle]
+/// min_calls = 6
+///
+/// [ProceedsOracle]
+/// severity = "Major"
+/// min_amount = 1000000
+/// ```
+///
+/// Globs match `address::module::function` or `module::function`. A finding
+/// is dropped if the function or location it names is out of scope; findings
+/// naming neither are kept. Oracles without an entry keep their defaults.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct OracleSuiteConfig {
+    pub oracles: BTreeMap<String, OracleConfig>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OracleConfig {
+    pub enabled: Option<bool>,
+    /// Severity of every finding of the oracle
+    pub severity: Option<Severity>,
+    #[serde(default)]
+    pub include: Vec<String>,
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// Parameters of the oracle, e.g. `threshold` of `InfiniteLoopOracle`
+    #[serde(flatten)]
+    pub params: BTreeMap<String, serde_json::Value>,
+}
+
+impl OracleConfig {
+    pub fn param<T: DeserializeOwned>(&self, name: &str) -> Result<Option<T>, MovyError> {
+        let Some(value) = self.params.get(name) else {
+            return Ok(None);
+        };
+        Ok(Some(serde_json::from_value(value.clone()).map_err(
+            |e| eyre!("invalid oracle parameter {}: {}", name, e),
+        )?))
+    }
+}
+
+impl OracleSuiteConfig {
+    pub fn from_file(path: &Path) -> Result<Self, MovyError> {
+        let content = std::fs::read_to_string(path)?;
+        let config: Self = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&content)?
+        } else {
+            toml::from_str(&content)?
+        };
+        for name in config.oracles.keys() {
+            if !DEFECT_ORACLES
+                .iter()
+                .chain(PROFIT_ORACLES)
+                .chain(OTHER_ORACLES)
+                .any(|known| known == name)
+            {
+                warn!("Unknown oracle {} in {}", name, path.display());
+            }
+        }
+        Ok(config)
+    }
+
+    /// Fold the command line switches in, unless an oracle is explicitly
+    /// enabled or disabled.
+    pub fn apply_flags(
+        &mut self,
+        typed_bug_abort: bool,
+        disable_profit_oracle: bool,
+        disable_defects_oracle: bool,
+    ) {
+        for (names, disabled) in [
+            (DEFECT_ORACLES, disable_defects_oracle),
+            (PROFIT_ORACLES, disable_profit_oracle),
+        ] {
+            if !disabled {
+                continue;
+            }
+            for name in names {
+                self.oracles
+                    .entry(name.to_string())
+                    .or_default()
+                    .enabled
+                    .get_or_insert(false);
+            }
+        }
+        if typed_bug_abort {
+            self.oracles
+                .entry("TypedBugOracle".to_string())
+                .or_default()
+                .params
+                .entry("abort".to_string())
+                .or_insert(serde_json::Value::Bool(true));
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&OracleConfig> {
+        self.oracles.get(name)
+    }
+
+    pub fn param<T: DeserializeOwned>(
+        &self,
+        oracle: &str,
+        name: &str,
+    ) -> Result<Option<T>, MovyError> {
+        match self.get(oracle) {
+            Some(config) => config.param(name),
+            None => Ok(None),
+        }
+    }
+
+    /// Wrap `oracle` with the settings of `name`.
+    pub fn wrap<O>(&self, name: &str, oracle: O) -> Result<ConfiguredOracle<O>, MovyError> {
+        let Some(config) = self.get(name) else {
+            return Ok(ConfiguredOracle::new(oracle));
+        };
+        let compile = |globs: &[String]| {
+            globs
+                .iter()
+                .map(|glob| {
+                    Pattern::new(glob)
+                        .map_err(|e| eyre!("invalid glob {} of {}: {}", glob, name, e))
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        Ok(ConfiguredOracle {
+            oracle,
+            enabled: config.enabled.unwrap_or(true),
+            severity: config.severity.clone(),
+            include: compile(&config.include)?,
+            exclude: compile(&config.exclude)?,
+        })
+    }
+}
+
+/// An oracle with the settings of [`OracleSuiteConfig`] applied.
+pub struct ConfiguredOracle<O> {
+    pub oracle: O,
+    pub enabled: bool,
+    pub severity: Option<Severity>,
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl<O> ConfiguredOracle<O> {
+    pub fn new(oracle: O) -> Self {
+        Self {
+            oracle,
+            enabled: true,
+            severity: None,
+            include: vec![],
+            exclude: vec![],
+        }
+    }
+
+    fn in_scope(&self, function: &str) -> bool {
+        let mut parts = function.rsplitn(3, "::");
+        let short = match (parts.next(), parts.next()) {
+            (Some(function), Some(module)) => format!("{}::{}", module, function),
+            _ => function.to_string(),
+        };
+        let matches = |patterns: &[Pattern]| {
+            patterns
+                .iter()
+                .any(|pattern| pattern.matches(function) || pattern.matches(&short))
+        };
+        (self.include.is_empty() || matches(&self.include)) && !matches(&self.exclude)
+    }
+
+    fn adjust(&self, findings: Vec<OracleFinding>) -> Vec<OracleFinding> {
+        findings
+            .into_iter()
+            .filter(|finding| {
+                ["function", "location"].iter().all(|key| {
+                    finding
+                        .extra
+                        .get(key)
+                        .and_then(|value| value.as_str())
+                        .is_none_or(|function| self.in_scope(function))
+                })
+            })
+            .map(|mut finding| {
+                if let Some(severity) = &self.severity {
+                    finding.severity = severity.clone();
+                }
+                finding
+            })
+            .collect()
+    }
+}
+
+impl<O, S> SuiGeneralOracle<S> for ConfiguredOracle<O>
+where
+    O: SuiGeneralOracle<S>,
+{
+    fn pre_execution<T: ObjectStore>(
+        &mut self,
+        db: T,
+        state: &mut S,
+        sequence: &MoveSequence,
+    ) -> Result<(), MovyError> {
+        if !self.enabled {
+            return Ok(());
+        }
+        self.oracle.pre_execution(db, state, sequence)
+    }
+
+    fn open_frame(
+        &mut self,
+        frame: &Box<Frame>,
+        trace_state: &TraceState,
+        symbol_stack: &ConcolicState,
+        current_function: &FunctionIdent,
+        gas_left: u64,
+        state: &mut S,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        if !self.enabled {
+            return Ok(vec![]);
+        }
+        let findings = self.oracle.open_frame(
+            frame,
+            trace_state,
+            symbol_stack,
+            current_function,
+            gas_left,
+            state,
+        )?;
+        Ok(self.adjust(findings))
+    }
+
+    fn close_frame(
+        &mut self,
+        trace_state: &TraceState,
+        current_function: &FunctionIdent,
+        gas_left: u64,
+        state: &mut S,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        if !self.enabled {
+            return Ok(vec![]);
+        }
+        let findings = self
+            .oracle
+            .close_frame(trace_state, current_function, gas_left, state)?;
+        Ok(self.adjust(findings))
+    }
+
+    fn before_instruction(
+        &mut self,
+        pc: u16,
+        bytecode: &Bytecode,
+        trace_state: &TraceState,
+        symbol_stack: &ConcolicState,
+        current_function: &FunctionIdent,
+        state: &mut S,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        if !self.enabled {
+            return Ok(vec![]);
+        }
+        let findings = self.oracle.before_instruction(
+            pc,
+            bytecode,
+            trace_state,
+            symbol_stack,
+            current_function,
+            state,
+        )?;
+        Ok(self.adjust(findings))
+    }
+
+    fn done_execution<T: ObjectStore>(
+        &mut self,
+        db: T,
+        state: &mut S,
+        effects: &TransactionEffects,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        if !self.enabled {
+            return Ok(vec![]);
+        }
+        let findings = self.oracle.done_execution(db, state, effects)?;
+        Ok(self.adjust(findings))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn findings_out_of_scope_are_dropped() {
+        let config: OracleSuiteConfig = toml::from_str(
+            r#"
+            [OverflowOracle]
+            severity = "Minor"
+            exclude = ["*::math::*"]
+            "#,
+        )
+        .unwrap();
+        let oracle = config.wrap("OverflowOracle", ()).unwrap();
+        let finding = |function: &str| {
+            OracleFinding::new(
+                "OverflowOracle",
+                Severity::Medium,
+                json!({ "function": function }),
+            )
+        };
+        let findings = oracle.adjust(vec![
+            finding("0x2::math::mul_div"),
+            finding("0x3::pool::swap"),
+        ]);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].extra["function"], "0x3::pool::swap");
+        assert_eq!(findings[0].severity, Severity::Minor);
+
+        let mut config = OracleSuiteConfig::default();
+        config.apply_flags(true, true, false);
+        assert_eq!(config.get("ProceedsOracle").unwrap().enabled, Some(false));
+        assert_eq!(
+            config.param::<bool>("TypedBugOracle", "abort").unwrap(),
+            Some(true)
+        );
+        assert!(config.get("OverflowOracle").is_none());
+    }
+}
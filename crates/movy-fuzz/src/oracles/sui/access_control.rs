@@ -0,0 +1,313 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use movy_replay::{db::ObjectStoreInfo, tracer::oracle::SuiGeneralOracle};
+use movy_types::{
+    abi::{MoveAbility, MoveModuleId},
+    error::MovyError,
+    input::{FunctionIdent, MoveSequence, MoveSequenceCall, MoveTypeTag},
+    oracle::{OracleFinding, Severity},
+};
+use serde_json::json;
+use sui_types::{
+    base_types::{ObjectID, ObjectRef, SuiAddress},
+    effects::{TransactionEffects, TransactionEffectsAPI},
+    execution_status::ExecutionStatus,
+    object::Owner,
+    storage::ObjectStore,
+};
+use tracing::debug;
+
+use crate::meta::{FuzzMetadata, HasFuzzMetadata};
+
+type StructKey = (MoveModuleId, String);
+
+/// Reports capabilities of the target packages taken over by the attacker
+/// and successful attacker calls to `--privilege-functions`.
+///
+/// A type counts as a capability if it is named `*AdminCap`, or if the
+/// deployer holds one before fuzzing and it is named `*Cap` or has `key`
+/// without `store`.
+#[derive(Debug, Clone, Default)]
+pub struct AccessControlOracle {
+    capabilities: BTreeSet<StructKey>,
+    sequence: Option<MoveSequence>,
+}
+
+impl AccessControlOracle {
+    /// Capabilities are found in `db`, the state before fuzzing.
+    pub fn new<T: ObjectStore>(meta: &FuzzMetadata, db: &T) -> Self {
+        Self {
+            capabilities: capability_types(meta, db),
+            sequence: None,
+        }
+    }
+}
+
+fn is_capability(name: &str, abilities: MoveAbility, held_by_deployer: bool) -> bool {
+    let key_only = abilities.contains(MoveAbility::KEY) && !abilities.contains(MoveAbility::STORE);
+    name.ends_with("AdminCap") || (held_by_deployer && (name.ends_with("Cap") || key_only))
+}
+
+fn capability_types<T: ObjectStore>(meta: &FuzzMetadata, db: &T) -> BTreeSet<StructKey> {
+    let module_addresses = meta
+        .target_packages
+        .iter()
+        .filter_map(|package| meta.get_package_metadata(package))
+        .flat_map(|abi| abi.modules.iter().map(|md| md.module_id.module_address))
+        .collect::<BTreeSet<_>>();
+    let deployer = Owner::AddressOwner(meta.deployer.into());
+    let mut out = BTreeSet::new();
+    for (ty, objects) in meta.types_pool.iter() {
+        let MoveTypeTag::Struct(tag) = ty else {
+            continue;
+        };
+        if !module_addresses.contains(&tag.address) {
+            continue;
+        }
+        let key = (
+            MoveModuleId {
+                module_address: tag.address,
+                module_name: tag.module.clone(),
+            },
+            tag.name.clone(),
+        );
+        let Some(abi) = meta.structs_mapping.get(&key) else {
+            continue;
+        };
+        let held = objects.iter().any(|id| {
+            db.get_object(&(*id).into())
+                .is_some_and(|object| object.owner == deployer)
+        });
+        if is_capability(&tag.name, abi.abilities, held) {
+            debug!("Treating {} as a capability", ty);
+            out.insert(key);
+        }
+    }
+    out
+}
+
+/// Capabilities which `changed` hands over to `attacker`, `old_owners` holds
+/// the owners of the objects before the call.
+fn acquired_capabilities<T: ObjectStore>(
+    db: &T,
+    capabilities: &BTreeSet<StructKey>,
+    attacker: SuiAddress,
+    changed: impl Iterator<Item = (ObjectRef, Owner)>,
+    old_owners: &BTreeMap<ObjectID, Owner>,
+) -> Vec<OracleFinding> {
+    let mut findings = vec![];
+    for (object, owner) in changed {
+        if owner != Owner::AddressOwner(attacker) {
+            continue;
+        }
+        let Some(old_owner) = old_owners.get(&object.0) else {
+            continue;
+        };
+        if *old_owner == Owner::AddressOwner(attacker) {
+            continue;
+        }
+        let Ok(info) = db.get_move_object_info(object.0.into()) else {
+            continue;
+        };
+        let MoveTypeTag::Struct(tag) = &info.ty else {
+            continue;
+        };
+        let key = (
+            MoveModuleId {
+                module_address: tag.address,
+                module_name: tag.module.clone(),
+            },
+            tag.name.clone(),
+        );
+        if capabilities.contains(&key) {
+            debug!("Capability {} now belongs to the attacker", object.0);
+            findings.push(OracleFinding::new(
+                "AccessControlOracle",
+                Severity::Critical,
+                json!({
+                    "kind": "capability_acquired",
+                    "object": object.0,
+                    "type": info.ty.to_string(),
+                    "previous_owner": old_owner.to_string(),
+                }),
+            ));
+        }
+    }
+    findings
+}
+
+impl<S> SuiGeneralOracle<S> for AccessControlOracle
+where
+    S: HasFuzzMetadata,
+{
+    fn pre_execution<T: ObjectStore>(
+        &mut self,
+        _db: T,
+        _state: &mut S,
+        sequence: &MoveSequence,
+    ) -> Result<(), MovyError> {
+        self.sequence = Some(sequence.clone());
+        Ok(())
+    }
+
+    fn done_execution<T: ObjectStore>(
+        &mut self,
+        db: T,
+        state: &mut S,
+        effects: &TransactionEffects,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        let sequence = self.sequence.take();
+        if !matches!(effects.status(), ExecutionStatus::Success) {
+            return Ok(vec![]);
+        }
+        let meta = state.fuzz_state();
+        // Capabilities minted by the call are not acquired from anyone, only
+        // objects which existed before the call count.
+        let modified = effects
+            .modified_at_versions()
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect::<BTreeSet<_>>();
+        let old_owners = effects
+            .old_object_metadata()
+            .into_iter()
+            .filter(|(object, _)| modified.contains(&object.0))
+            .map(|(object, owner)| (object.0, owner))
+            .collect::<BTreeMap<_, _>>();
+        let changed = effects
+            .all_changed_objects()
+            .into_iter()
+            .map(|(object, owner, _)| (object, owner));
+        let mut findings = acquired_capabilities(
+            &db,
+            &self.capabilities,
+            meta.attacker.into(),
+            changed,
+            &old_owners,
+        );
+
+        let mut called = BTreeSet::new();
+        for cmd in sequence
+            .iter()
+            .flat_map(|sequence| sequence.commands.iter())
+        {
+            let MoveSequenceCall::Call(call) = cmd else {
+                continue;
+            };
+            let function = FunctionIdent::new(&call.module_id, &call.module_name, &call.function);
+            if meta.specific_function_scores.contains_key(&function)
+                && called.insert(function.clone())
+            {
+                debug!("Attacker called the privileged {:?}", function);
+                findings.push(OracleFinding::new(
+                    "AccessControlOracle",
+                    Severity::Major,
+                    json!({
+                        "kind": "privileged_call",
+                        "function": function.to_string(),
+                    }),
+                ));
+            }
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use movy_sui::database::{
+        cache::{CachedStore, ObjectSuiStoreCommit},
+        empty::EmptyStore,
+    };
+    use movy_types::input::MoveAddress;
+    use sui_types::{
+        TypeTag,
+        base_types::SequenceNumber,
+        digests::TransactionDigest,
+        object::{MoveObject, Object},
+    };
+
+    use super::*;
+
+    fn coin(db: &CachedStore<EmptyStore>, id: ObjectID, owner: Owner) -> ObjectRef {
+        let object = Object::new_move(
+            MoveObject::new_coin(
+                TypeTag::from_str("0x2::sui::SUI").unwrap(),
+                SequenceNumber::from_u64(2),
+                id,
+                1,
+            ),
+            owner,
+            TransactionDigest::genesis_marker(),
+        );
+        let object_ref = object.compute_object_reference();
+        db.commit_single_object(object).unwrap();
+        object_ref
+    }
+
+    #[test]
+    fn capabilities_are_named_or_held_by_the_deployer() {
+        let key = MoveAbility::KEY;
+        let key_store = MoveAbility::KEY | MoveAbility::STORE;
+        assert!(is_capability("AdminCap", key_store, false));
+        assert!(!is_capability("TreasuryCap", key_store, false));
+        assert!(is_capability("TreasuryCap", key_store, true));
+        assert!(is_capability("Config", key, true));
+        assert!(!is_capability("Config", key_store, true));
+        assert!(!is_capability("Config", key, false));
+    }
+
+    #[test]
+    fn only_capabilities_taken_from_others_are_reported() {
+        let db = CachedStore::new(EmptyStore);
+        let attacker = SuiAddress::random_for_testing_only();
+        let deployer = Owner::AddressOwner(SuiAddress::random_for_testing_only());
+        let mine = Owner::AddressOwner(attacker);
+        // Coins stand in for a capability type
+        let capabilities = BTreeSet::from([(
+            MoveModuleId {
+                module_address: MoveAddress::two(),
+                module_name: "coin".to_string(),
+            },
+            "Coin".to_string(),
+        )]);
+
+        let taken = coin(&db, ObjectID::random(), mine.clone());
+        let kept = coin(&db, ObjectID::random(), mine.clone());
+        let minted = coin(&db, ObjectID::random(), mine.clone());
+        let given = coin(&db, ObjectID::random(), deployer.clone());
+        let old_owners = BTreeMap::from([
+            (taken.0, deployer.clone()),
+            (kept.0, mine.clone()),
+            (given.0, deployer.clone()),
+        ]);
+        let changed = vec![
+            (taken, mine.clone()),
+            (kept, mine.clone()),
+            (minted, mine.clone()),
+            (given, deployer.clone()),
+        ];
+        let findings = acquired_capabilities(
+            &db,
+            &capabilities,
+            attacker,
+            changed.into_iter(),
+            &old_owners,
+        );
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].extra["object"], json!(taken.0));
+
+        assert!(
+            acquired_capabilities(
+                &db,
+                &BTreeSet::new(),
+                attacker,
+                vec![(taken, mine)].into_iter(),
+                &old_owners
+            )
+            .is_empty()
+        );
+    }
+}
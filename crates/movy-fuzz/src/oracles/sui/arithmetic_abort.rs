@@ -0,0 +1,355 @@
+use std::collections::BTreeSet;
+
+use move_binary_format::file_format::Bytecode;
+use movy_replay::{
+    db::{ObjectStoreInfo, ObjectStoreMintObject},
+    exec::SuiExecutor,
+    tracer::{NopTracer, concolic::ConcolicState, oracle::SuiGeneralOracle, state::TraceState},
+};
+use movy_sui::database::cache::ObjectSuiStoreCommit;
+use movy_types::{
+    error::MovyError,
+    input::{FunctionIdent, InputArgument, MoveSequence, MoveSequenceCall, SequenceArgument},
+    oracle::{FindingLocation, OracleFinding, Severity},
+};
+use serde_json::json;
+use sui_types::{
+    base_types::ObjectID,
+    effects::{TransactionEffects, TransactionEffectsAPI},
+    execution_status::{ExecutionFailureStatus, ExecutionStatus},
+    storage::{BackingPackageStore, BackingStore, ObjectStore},
+};
+use tracing::warn;
+
+use super::common::standalone_call;
+use crate::{
+    executor::{refresh_object_refs, sender_gas},
+    meta::{FuzzMetadata, HasFuzzMetadata},
+};
+
+/// Reports calls on shared objects which abort on an arithmetic error, e.g.
+/// an overflowing interest accrual, after earlier commands of the sequence or
+/// the prelude changed the objects while the call alone succeeds on the
+/// forked state.
+///
+/// Aborts are told apart by the arithmetic instruction raising them and each
+/// location is reported once.
+pub struct ArithmeticAbortOracle<T> {
+    /// The forked state before fuzzing
+    pub executor: SuiExecutor<T>,
+    sequence: Option<MoveSequence>,
+    /// Objects of the sequence the prelude or state patches changed
+    changed_before: Vec<ObjectID>,
+    /// Whether the last instruction executed was arithmetic
+    arithmetic: bool,
+    reported: BTreeSet<(FunctionIdent, u16)>,
+}
+
+impl<T> ArithmeticAbortOracle<T> {
+    pub fn new(executor: SuiExecutor<T>) -> Self {
+        Self {
+            executor,
+            sequence: None,
+            changed_before: vec![],
+            arithmetic: false,
+            reported: BTreeSet::new(),
+        }
+    }
+}
+
+fn is_arithmetic(instruction: &Bytecode) -> bool {
+    matches!(
+        instruction,
+        Bytecode::Add
+            | Bytecode::Sub
+            | Bytecode::Mul
+            | Bytecode::Div
+            | Bytecode::Mod
+            | Bytecode::Shl
+            | Bytecode::Shr
+            | Bytecode::CastU8
+            | Bytecode::CastU16
+            | Bytecode::CastU32
+            | Bytecode::CastU64
+            | Bytecode::CastU128
+            | Bytecode::CastU256
+    )
+}
+
+/// Function and instruction raising a primitive runtime error.
+fn abort_location(status: &ExecutionStatus) -> Option<(FunctionIdent, u16)> {
+    let ExecutionStatus::Failure {
+        error: ExecutionFailureStatus::MovePrimitiveRuntimeError(location),
+        ..
+    } = status
+    else {
+        return None;
+    };
+    let location = location.0.as_ref()?;
+    let function = FunctionIdent::new(
+        &(*location.module.address()).into(),
+        location.module.name().as_str(),
+        location.function_name.as_deref()?,
+    );
+    Some((function, location.instruction))
+}
+
+/// Objects taken by command `idx` of `sequence` which an earlier command
+/// takes as well or which are in `changed_before`.
+fn touched_before(
+    sequence: &MoveSequence,
+    idx: usize,
+    objects: &[ObjectID],
+    changed_before: &[ObjectID],
+) -> Vec<ObjectID> {
+    let mut out = objects
+        .iter()
+        .filter(|id| changed_before.contains(id))
+        .copied()
+        .collect::<Vec<_>>();
+    for cmd in sequence.commands.iter().take(idx) {
+        let MoveSequenceCall::Call(call) = cmd else {
+            continue;
+        };
+        for arg in call.arguments.iter() {
+            if let SequenceArgument::Input(i) = arg
+                && let Some(InputArgument::Object(_, object)) = sequence.inputs.get(*i as usize)
+                && objects.contains(&object.id())
+                && !out.contains(&object.id())
+            {
+                out.push(object.id());
+            }
+        }
+    }
+    out
+}
+
+impl<T> ArithmeticAbortOracle<T>
+where
+    T: ObjectStore
+        + BackingStore
+        + BackingPackageStore
+        + ObjectSuiStoreCommit
+        + ObjectStoreMintObject
+        + ObjectStoreInfo
+        + Clone,
+{
+    /// Whether `sequence` succeeds on the forked state.
+    fn succeeds_alone(
+        &self,
+        meta: &FuzzMetadata,
+        sequence: &MoveSequence,
+    ) -> Result<bool, MovyError> {
+        let executor = self.executor.layered();
+        let gas = sender_gas(&executor.db, meta.attacker)?;
+        let mut sequence = sequence.clone();
+        refresh_object_refs(&executor.db, &mut sequence);
+        let Ok(out) = executor.run_ptb_with_gas(
+            sequence.to_ptb()?,
+            meta.epoch,
+            meta.epoch_ms,
+            meta.attacker.into(),
+            gas,
+            None::<NopTracer>,
+        ) else {
+            return Ok(false);
+        };
+        Ok(matches!(
+            out.results.effects.status(),
+            ExecutionStatus::Success
+        ))
+    }
+}
+
+impl<S, E> SuiGeneralOracle<S> for ArithmeticAbortOracle<E>
+where
+    S: HasFuzzMetadata,
+    E: ObjectStore
+        + BackingStore
+        + BackingPackageStore
+        + ObjectSuiStoreCommit
+        + ObjectStoreMintObject
+        + ObjectStoreInfo
+        + Clone,
+{
+    fn pre_execution<T: ObjectStore>(
+        &mut self,
+        db: T,
+        _state: &mut S,
+        sequence: &MoveSequence,
+    ) -> Result<(), MovyError> {
+        // Versions bumped since the forked state tell the objects changed
+        // before the sequence
+        self.changed_before = sequence
+            .inputs
+            .iter()
+            .filter_map(|input| match input {
+                InputArgument::Object(_, object) => Some(object.id()),
+                _ => None,
+            })
+            .filter(|id| {
+                db.get_object(id).map(|o| o.version())
+                    != self.executor.db.get_object(id).map(|o| o.version())
+            })
+            .collect();
+        self.sequence = Some(sequence.clone());
+        self.arithmetic = false;
+        Ok(())
+    }
+
+    fn before_instruction(
+        &mut self,
+        _pc: u16,
+        instruction: &Bytecode,
+        _trace_state: &TraceState,
+        _symbol_stack: &ConcolicState,
+        _current_function: &FunctionIdent,
+        _state: &mut S,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        self.arithmetic = is_arithmetic(instruction);
+        Ok(vec![])
+    }
+
+    fn done_execution<T: ObjectStore>(
+        &mut self,
+        _db: T,
+        state: &mut S,
+        effects: &TransactionEffects,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        let Some(sequence) = self.sequence.take() else {
+            return Ok(vec![]);
+        };
+        // Move reports arithmetic errors as primitive runtime errors
+        let ExecutionStatus::Failure {
+            command: Some(idx), ..
+        } = effects.status()
+        else {
+            return Ok(vec![]);
+        };
+        if !self.arithmetic {
+            return Ok(vec![]);
+        }
+        let Some(location) = abort_location(effects.status()) else {
+            return Ok(vec![]);
+        };
+        if self.reported.contains(&location) {
+            return Ok(vec![]);
+        }
+        let Some(MoveSequenceCall::Call(call)) = sequence.commands.get(*idx) else {
+            return Ok(vec![]);
+        };
+        let Some((alone, shared)) = standalone_call(&sequence, call) else {
+            return Ok(vec![]);
+        };
+        let objects = touched_before(&sequence, *idx, &shared, &self.changed_before);
+        if objects.is_empty() {
+            return Ok(vec![]);
+        }
+        let meta = state.fuzz_state();
+        if !self.succeeds_alone(meta, &alone)? {
+            return Ok(vec![]);
+        }
+
+        let function = format!(
+            "{}::{}::{}",
+            call.module_id, call.module_name, call.function
+        );
+        warn!(
+            "{} aborts on an arithmetic error at {}:{} after {:?} changed:\n{}",
+            function, location.0, location.1, objects, sequence
+        );
+        let mut finding = OracleFinding::new(
+            "ArithmeticAbortOracle",
+            Severity::Medium,
+            json!({
+                "kind": "arithmetic_abort",
+                "function": function,
+                "location": location.0.to_string(),
+                "pc": location.1,
+                "objects": objects,
+            }),
+        );
+        // The abort lies in a callee rather than at the `pc` of `function`
+        finding.location = Some(FindingLocation::new(&location.0, Some(location.1)));
+        self.reported.insert(location);
+        Ok(vec![finding])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use move_core_types::{identifier::Identifier, language_storage::ModuleId};
+    use movy_types::input::{MoveAddress, MoveCall, SuiObjectInputArgument};
+    use sui_types::{
+        base_types::SequenceNumber,
+        execution_status::{MoveLocation, MoveLocationOpt},
+    };
+
+    use super::*;
+
+    fn shared(id: ObjectID) -> InputArgument {
+        InputArgument::Object(
+            "0xa::pool::Pool".parse().unwrap(),
+            SuiObjectInputArgument::SharedObject {
+                id,
+                initial_shared_version: SequenceNumber::from_u64(1),
+                mutable: true,
+            },
+        )
+    }
+
+    fn call(function: &str, input: u16) -> MoveSequenceCall {
+        MoveSequenceCall::Call(MoveCall {
+            module_id: MoveAddress::from_str("0xa").unwrap(),
+            module_name: "pool".to_string(),
+            function: function.to_string(),
+            type_arguments: vec![],
+            arguments: vec![SequenceArgument::Input(input)],
+        })
+    }
+
+    #[test]
+    fn objects_changed_by_earlier_commands_or_the_prelude() {
+        let (pool, other) = (ObjectID::random(), ObjectID::random());
+        let sequence = MoveSequence {
+            inputs: vec![shared(pool), shared(other)],
+            commands: vec![call("deposit", 0), call("accrue", 0), call("accrue", 1)],
+        };
+        assert_eq!(touched_before(&sequence, 1, &[pool], &[]), vec![pool]);
+        assert!(touched_before(&sequence, 2, &[other], &[]).is_empty());
+        assert_eq!(
+            touched_before(&sequence, 2, &[other], &[other]),
+            vec![other]
+        );
+        assert!(touched_before(&sequence, 0, &[pool], &[]).is_empty());
+    }
+
+    #[test]
+    fn locations_come_from_the_status() {
+        let module = ModuleId::new(
+            MoveAddress::from_str("0xa").unwrap().into(),
+            Identifier::new("pool").unwrap(),
+        );
+        let status = ExecutionStatus::Failure {
+            error: ExecutionFailureStatus::MovePrimitiveRuntimeError(MoveLocationOpt(Some(
+                MoveLocation {
+                    module,
+                    function: 2,
+                    instruction: 7,
+                    function_name: Some("accrue".to_string()),
+                },
+            ))),
+            command: Some(1),
+        };
+        let (function, pc) = abort_location(&status).unwrap();
+        assert_eq!(
+            function,
+            FunctionIdent::new(&MoveAddress::from_str("0xa").unwrap(), "pool", "accrue")
+        );
+        assert_eq!(pc, 7);
+        assert!(abort_location(&ExecutionStatus::Success).is_none());
+        assert!(is_arithmetic(&Bytecode::Add) && !is_arithmetic(&Bytecode::Pop));
+    }
+}
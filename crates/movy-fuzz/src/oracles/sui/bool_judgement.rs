@@ -2,7 +2,7 @@ use move_binary_format::file_format::Bytecode;
 use serde_json::json;
 use z3::{
     DeclKind,
-    ast::{Ast, Dynamic, Int},
+    ast::{Ast, Dynamic},
 };
 
 use movy_replay::tracer::{
@@ -46,7 +46,12 @@ impl<S> SuiGeneralOracle<S> for BoolJudgementOracle {
                 let lhs = &stack_syms[stack_len - 2];
                 match (lhs, rhs) {
                     (SymbolValue::Value(l), SymbolValue::Value(r)) => {
-                        int_has_variable(l) == Some(false) && int_has_variable(r) == Some(false)
+                        has_variable(Dynamic::from(l.clone())) == Some(false)
+                            && has_variable(Dynamic::from(r.clone())) == Some(false)
+                    }
+                    (SymbolValue::Bits(l), SymbolValue::Bits(r)) => {
+                        has_variable(Dynamic::from(l.clone())) == Some(false)
+                            && has_variable(Dynamic::from(r.clone())) == Some(false)
                     }
                     _ => false,
                 }
@@ -59,19 +64,19 @@ impl<S> SuiGeneralOracle<S> for BoolJudgementOracle {
                 current.as_ref(),
                 Some(pc),
             );
-            Ok(vec![OracleFinding {
-                oracle: "BoolJudgementOracle".to_string(),
-                severity: Severity::Minor,
-                extra: json!(info),
-            }])
+            Ok(vec![OracleFinding::new(
+                "BoolJudgementOracle",
+                Severity::Minor,
+                json!(info),
+            )])
         } else {
             Ok(vec![])
         }
     }
 }
 
-fn int_has_variable(expr: &Int) -> Option<bool> {
-    let mut stack = vec![Dynamic::from(expr.clone())];
+fn has_variable(expr: Dynamic) -> Option<bool> {
+    let mut stack = vec![expr];
     let mut count = 0;
     while let Some(node) = stack.pop() {
         count += 1;
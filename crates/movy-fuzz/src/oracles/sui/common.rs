@@ -1,6 +1,17 @@
+use std::collections::BTreeSet;
+
 use move_core_types::language_storage::ModuleId;
 use move_core_types::{account_address::AccountAddress, identifier::Identifier};
-use movy_types::input::FunctionIdent;
+use movy_sui::database::cache::CachedSnapshot;
+use movy_types::input::{
+    FunctionIdent, InputArgument, MoveCall, MoveSequence, MoveSequenceCall, SequenceArgument,
+    SuiObjectInputArgument,
+};
+use sui_types::{
+    base_types::ObjectID,
+    effects::{TransactionEffects, TransactionEffectsAPI},
+    storage::ObjectStore,
+};
 
 pub fn format_vulnerability_info(
     base: &str,
@@ -29,3 +40,69 @@ pub fn to_module_func(fid: &FunctionIdent) -> Option<(ModuleId, String)> {
     };
     Some((ModuleId::new(addr, name), fid.1.clone()))
 }
+
+/// `call` of `sequence` as a sequence of its own, with the shared objects it
+/// takes, if its arguments are all shared objects or pure values.
+pub fn standalone_call(
+    sequence: &MoveSequence,
+    call: &MoveCall,
+) -> Option<(MoveSequence, Vec<ObjectID>)> {
+    let mut inputs = vec![];
+    let mut arguments = vec![];
+    let mut shared = vec![];
+    for arg in call.arguments.iter() {
+        let SequenceArgument::Input(idx) = arg else {
+            return None;
+        };
+        let input = sequence.inputs.get(*idx as usize)?;
+        match input {
+            InputArgument::Object(_, SuiObjectInputArgument::SharedObject { id, .. }) => {
+                shared.push(*id)
+            }
+            InputArgument::Object(..) => return None,
+            _ => {}
+        }
+        arguments.push(SequenceArgument::Input(inputs.len() as u16));
+        inputs.push(input.clone());
+    }
+    if shared.is_empty() {
+        return None;
+    }
+    let call = MoveCall {
+        arguments,
+        ..call.clone()
+    };
+    Some((
+        MoveSequence {
+            inputs,
+            commands: vec![MoveSequenceCall::Call(call)],
+        },
+        shared,
+    ))
+}
+
+/// The objects `effects` changed as they are in `db`, together with their ids,
+/// so that the state after a transaction can be restored on another layer.
+pub fn effects_snapshot<T: ObjectStore>(
+    db: &T,
+    effects: &TransactionEffects,
+) -> (CachedSnapshot, BTreeSet<ObjectID>) {
+    let mut snapshot = CachedSnapshot::default();
+    let mut changed = BTreeSet::new();
+    for (object, _, _) in effects.all_changed_objects() {
+        if let Some(object) = db.get_object(&object.0) {
+            snapshot.cache_object_only(object);
+        }
+        changed.insert(object.0);
+    }
+    for object in effects
+        .deleted()
+        .into_iter()
+        .chain(effects.wrapped())
+        .chain(effects.unwrapped_then_deleted())
+    {
+        snapshot.cache_query(object.0, object.1.into(), None);
+        changed.insert(object.0);
+    }
+    (snapshot, changed)
+}
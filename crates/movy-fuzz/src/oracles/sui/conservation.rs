@@ -0,0 +1,273 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use movy_replay::tracer::oracle::SuiGeneralOracle;
+use movy_types::{
+    abi::{MoveAbiSignatureToken, MoveModuleId, MoveStructAbi},
+    error::MovyError,
+    input::{MoveAddress, MoveStructTag, MoveTypeTag},
+    object::MoveObjectInfo,
+    oracle::{OracleFinding, Severity},
+};
+use serde_json::json;
+use sui_types::{
+    effects::{TransactionEffects, TransactionEffectsAPI},
+    execution_status::ExecutionStatus,
+    gas_coin::GAS,
+    object::Object,
+    storage::ObjectStore,
+};
+use tracing::debug;
+
+use crate::{meta::HasFuzzMetadata, state_fuzz::read_uleb128};
+
+/// Changes of a coin type within the objects of a transaction
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+struct Totals {
+    /// Every `Balance<T>`, coins included
+    balance: i128,
+    /// Every `Supply<T>`, e.g. in a `TreasuryCap<T>`
+    supply: i128,
+}
+
+/// Reports transactions changing the total value of a coin type without
+/// minting or burning it, whether value is created out of thin air or lost
+/// e.g. to rounding, regardless of the attacker's profit.
+///
+/// Every `Balance<T>` and `Supply<T>` inside the objects a transaction changes
+/// is summed up before and after, the gas coin included, and the net gas fee
+/// is added back to SUI. Transactions touching objects which can not be laid
+/// out are skipped.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConservationOracle;
+
+/// `token` with its type parameters replaced by `typs`.
+fn instantiate(token: &MoveAbiSignatureToken, typs: &[MoveTypeTag]) -> Option<MoveTypeTag> {
+    match token {
+        MoveAbiSignatureToken::TypeParameter(idx, _) => typs.get(*idx as usize).cloned(),
+        MoveAbiSignatureToken::Vector(inner) => {
+            Some(MoveTypeTag::Vector(Box::new(instantiate(inner, typs)?)))
+        }
+        MoveAbiSignatureToken::StructInstantiation(st, insts) => {
+            Some(MoveTypeTag::Struct(MoveStructTag {
+                address: st.module_id.module_address,
+                module: st.module_id.module_name.clone(),
+                name: st.struct_name.clone(),
+                tys: insts
+                    .iter()
+                    .map(|inst| instantiate(inst, typs))
+                    .collect::<Option<Vec<_>>>()?,
+            }))
+        }
+        _ => token.to_type_tag(),
+    }
+}
+
+/// Walk the BCS encoding of a value of type `ty`, adding its balances and
+/// supplies times `sign` to `out`.
+fn collect_balances(
+    structs: &BTreeMap<(MoveModuleId, String), MoveStructAbi>,
+    ty: &MoveTypeTag,
+    bytes: &[u8],
+    pos: &mut usize,
+    sign: i128,
+    out: &mut BTreeMap<MoveTypeTag, Totals>,
+) -> Option<()> {
+    let width = match ty {
+        MoveTypeTag::Bool | MoveTypeTag::U8 => 1,
+        MoveTypeTag::U16 => 2,
+        MoveTypeTag::U32 => 4,
+        MoveTypeTag::U64 => 8,
+        MoveTypeTag::U128 => 16,
+        MoveTypeTag::U256 | MoveTypeTag::Address | MoveTypeTag::Signer => 32,
+        MoveTypeTag::Vector(inner) => {
+            let len = read_uleb128(bytes, pos)?;
+            if matches!(inner.as_ref(), MoveTypeTag::U8) {
+                *pos += len;
+                return (*pos <= bytes.len()).then_some(());
+            }
+            for _ in 0..len {
+                collect_balances(structs, inner, bytes, pos, sign, out)?;
+            }
+            return Some(());
+        }
+        MoveTypeTag::Struct(tag) => {
+            if tag.address == MoveAddress::two()
+                && tag.module == "balance"
+                && (tag.name == "Balance" || tag.name == "Supply")
+                && tag.tys.len() == 1
+            {
+                let value = u64::from_le_bytes(bytes.get(*pos..*pos + 8)?.try_into().ok()?);
+                *pos += 8;
+                let totals = out.entry(tag.tys[0].clone()).or_default();
+                if tag.name == "Balance" {
+                    totals.balance += sign * value as i128;
+                } else {
+                    totals.supply += sign * value as i128;
+                }
+                return Some(());
+            }
+            let abi = structs.get(&(
+                MoveModuleId {
+                    module_address: tag.address,
+                    module_name: tag.module.clone(),
+                },
+                tag.name.clone(),
+            ))?;
+            for field in abi.fields.iter() {
+                let ty = instantiate(&field.ty, &tag.tys)?;
+                collect_balances(structs, &ty, bytes, pos, sign, out)?;
+            }
+            return Some(());
+        }
+    };
+    *pos += width;
+    (*pos <= bytes.len()).then_some(())
+}
+
+fn object_balances(
+    structs: &BTreeMap<(MoveModuleId, String), MoveStructAbi>,
+    object: &Object,
+    sign: i128,
+    out: &mut BTreeMap<MoveTypeTag, Totals>,
+) -> Option<()> {
+    let Some(move_object) = object.data.try_as_move() else {
+        // Packages hold no value
+        return Some(());
+    };
+    let info = MoveObjectInfo::try_from(object).ok()?;
+    let bytes = move_object.contents();
+    let mut pos = 0;
+    collect_balances(structs, &info.ty, bytes, &mut pos, sign, out)?;
+    (pos == bytes.len()).then_some(())
+}
+
+impl<S> SuiGeneralOracle<S> for ConservationOracle
+where
+    S: HasFuzzMetadata,
+{
+    fn done_execution<T: ObjectStore>(
+        &mut self,
+        db: T,
+        state: &mut S,
+        effects: &TransactionEffects,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        if !matches!(effects.status(), ExecutionStatus::Success) {
+            return Ok(vec![]);
+        }
+        let meta = state.fuzz_state();
+        let unwrapped_then_deleted = effects
+            .unwrapped_then_deleted()
+            .iter()
+            .map(|object| object.0)
+            .collect::<BTreeSet<_>>();
+
+        let mut totals = BTreeMap::new();
+        let before = effects
+            .modified_at_versions()
+            .into_iter()
+            .filter(|(id, _)| !unwrapped_then_deleted.contains(id))
+            .map(|(id, version)| (id, version, -1));
+        let after = effects
+            .all_changed_objects()
+            .into_iter()
+            .map(|((id, version, _), _, _)| (id, version, 1));
+        for (id, version, sign) in before.chain(after) {
+            let Some(object) = db.get_object_by_key(&id, version) else {
+                debug!("Object {}:{} is missing, skip conservation", id, version);
+                return Ok(vec![]);
+            };
+            if object_balances(&meta.structs_mapping, &object, sign, &mut totals).is_none() {
+                debug!("Can not lay out {}:{}, skip conservation", id, version);
+                return Ok(vec![]);
+            }
+        }
+
+        // The gas coin also paid the fee, which leaves SUI without burning it
+        let fee = effects.gas_cost_summary().net_gas_usage() as i128;
+        totals
+            .entry(MoveTypeTag::from(GAS::type_tag()))
+            .or_default()
+            .balance += fee;
+
+        let mut findings = vec![];
+        for (coin, change) in totals {
+            if change.balance == change.supply {
+                continue;
+            }
+            debug!(
+                "{} changed by {} while its supply changed by {}",
+                coin, change.balance, change.supply
+            );
+            let (kind, severity) = if change.balance > change.supply {
+                ("value_created", Severity::Critical)
+            } else {
+                ("value_destroyed", Severity::Medium)
+            };
+            findings.push(OracleFinding::new(
+                "ConservationOracle",
+                severity,
+                json!({
+                    "kind": kind,
+                    "coin_type": coin.to_string(),
+                    "balance_change": change.balance.to_string(),
+                    "supply_change": change.supply.to_string(),
+                }),
+            ));
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn balances_and_supplies_are_summed_per_coin() {
+        let sui = MoveTypeTag::from_str("0x2::sui::SUI").unwrap();
+        let balance = |name: &str| {
+            MoveTypeTag::Struct(MoveStructTag {
+                address: MoveAddress::two(),
+                module: "balance".to_string(),
+                name: name.to_string(),
+                tys: vec![sui.clone()],
+            })
+        };
+        let mut bytes = vec![2];
+        bytes.extend(5u64.to_le_bytes());
+        bytes.extend(7u64.to_le_bytes());
+        let mut pos = 0;
+        let mut out = BTreeMap::new();
+        collect_balances(
+            &BTreeMap::new(),
+            &MoveTypeTag::Vector(Box::new(balance("Balance"))),
+            &bytes,
+            &mut pos,
+            1,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(pos, bytes.len());
+
+        let bytes = 3u64.to_le_bytes();
+        let mut pos = 0;
+        collect_balances(
+            &BTreeMap::new(),
+            &balance("Supply"),
+            &bytes,
+            &mut pos,
+            -1,
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            out.get(&sui),
+            Some(&Totals {
+                balance: 12,
+                supply: -3,
+            })
+        );
+    }
+}
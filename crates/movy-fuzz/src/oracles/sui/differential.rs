@@ -0,0 +1,257 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use movy_replay::{
+    db::{ObjectStoreInfo, ObjectStoreMintObject},
+    exec::SuiExecutor,
+    meta::Metadata,
+    tracer::oracle::SuiGeneralOracle,
+};
+use movy_sui::database::cache::ObjectSuiStoreCommit;
+use movy_types::{
+    error::MovyError,
+    input::{FunctionIdent, MoveSequence, MoveSequenceCall},
+    oracle::{OracleFinding, Severity},
+};
+use serde_json::json;
+use sui_types::{
+    effects::TransactionEffects,
+    storage::{BackingPackageStore, BackingStore, ObjectStore},
+};
+use tracing::{debug, warn};
+
+use crate::{
+    diff::{DiffOutcome, VersionMapping, diff_outcome},
+    meta::HasFuzzMetadata,
+};
+
+/// Replays every sequence on the old and the new version of a package and
+/// reports where they behave differently. Both executors sit on their own
+/// copy of the forked state, the sequence is mapped onto the new version with
+/// [`VersionMapping`]. Sequences relying on a prelude or state patches are
+/// replayed without them and mostly skipped.
+///
+/// Outcomes of the old version only depend on the sequence and are cached,
+/// the same sequence is executed again e.g. when calibrating new inputs.
+pub struct DifferentialOracle<T> {
+    pub old: SuiExecutor<T>,
+    pub new: SuiExecutor<T>,
+    pub new_meta: Metadata,
+    pub mapping: VersionMapping,
+    sequence: Option<MoveSequence>,
+    old_outcomes: BTreeMap<String, Option<DiffOutcome>>,
+    reported: BTreeSet<String>,
+}
+
+/// Cached outcomes of the old version before the cache is dropped.
+const MAX_CACHED_OUTCOMES: usize = 4096;
+
+/// The functions called by `sequence`, in order.
+fn called_functions(sequence: &MoveSequence) -> Vec<String> {
+    sequence
+        .commands
+        .iter()
+        .filter_map(|cmd| match cmd {
+            MoveSequenceCall::Call(call) => Some(
+                FunctionIdent::new(&call.module_id, &call.module_name, &call.function).to_string(),
+            ),
+            _ => None,
+        })
+        .collect()
+}
+
+/// One finding per kind of divergence, called functions and outcome of both
+/// versions.
+fn divergence_key(
+    kinds: &[&str],
+    functions: &[String],
+    old: &DiffOutcome,
+    new: &DiffOutcome,
+) -> String {
+    format!(
+        "{:?} {} {} {}",
+        kinds,
+        functions.join(","),
+        old.status,
+        new.status
+    )
+}
+
+impl<T> DifferentialOracle<T> {
+    pub fn new(
+        old: SuiExecutor<T>,
+        new: SuiExecutor<T>,
+        new_meta: Metadata,
+        mapping: VersionMapping,
+    ) -> Self {
+        Self {
+            old,
+            new,
+            new_meta,
+            mapping,
+            sequence: None,
+            old_outcomes: BTreeMap::new(),
+            reported: BTreeSet::new(),
+        }
+    }
+}
+
+impl<S, E> SuiGeneralOracle<S> for DifferentialOracle<E>
+where
+    S: HasFuzzMetadata,
+    E: ObjectStore
+        + BackingStore
+        + BackingPackageStore
+        + ObjectSuiStoreCommit
+        + ObjectStoreMintObject
+        + ObjectStoreInfo
+        + Clone,
+{
+    fn pre_execution<T: ObjectStore>(
+        &mut self,
+        _db: T,
+        _state: &mut S,
+        sequence: &MoveSequence,
+    ) -> Result<(), MovyError> {
+        self.sequence = Some(sequence.clone());
+        Ok(())
+    }
+
+    fn done_execution<T: ObjectStore>(
+        &mut self,
+        _db: T,
+        state: &mut S,
+        _effects: &TransactionEffects,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        let Some(sequence) = self.sequence.take() else {
+            return Ok(vec![]);
+        };
+        let Some(mapped) = self.mapping.map_sequence(&sequence, &self.new.db) else {
+            return Ok(vec![]);
+        };
+        let meta = state.fuzz_state();
+        let cache_key = sequence.to_string();
+        let old = match self.old_outcomes.get(&cache_key) {
+            Some(old) => old.clone(),
+            None => {
+                let old = diff_outcome(
+                    &self.old,
+                    &meta.base,
+                    &Default::default(),
+                    &sequence,
+                    meta.attacker,
+                    meta.gas_id,
+                    meta.epoch,
+                    meta.epoch_ms,
+                )?;
+                if self.old_outcomes.len() >= MAX_CACHED_OUTCOMES {
+                    self.old_outcomes.clear();
+                }
+                self.old_outcomes.insert(cache_key, old.clone());
+                old
+            }
+        };
+        let Some(old) = old else {
+            return Ok(vec![]);
+        };
+        let Some(new) = diff_outcome(
+            &self.new,
+            &self.new_meta,
+            &self.mapping.reversed(),
+            &mapped,
+            meta.attacker,
+            meta.gas_id,
+            meta.epoch,
+            meta.epoch_ms,
+        )?
+        else {
+            return Ok(vec![]);
+        };
+        let kinds = old.divergences(&new);
+        if kinds.is_empty() {
+            return Ok(vec![]);
+        }
+        let functions = called_functions(&sequence);
+        if !self
+            .reported
+            .insert(divergence_key(&kinds, &functions, &old, &new))
+        {
+            debug!("Divergence {:?} has been reported", kinds);
+            return Ok(vec![]);
+        }
+        warn!(
+            "Versions diverge in {}:\n{}\nold: {:?}\nnew: {:?}",
+            kinds.join(", "),
+            sequence,
+            old,
+            new
+        );
+        Ok(vec![OracleFinding::new(
+            "DifferentialOracle",
+            Severity::Major,
+            json!({
+                "divergences": kinds,
+                "functions": functions,
+                "old": old,
+                "new": new,
+                "new_sequence": mapped.to_string(),
+            }),
+        )])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use movy_types::input::{MoveAddress, MoveCall};
+
+    use super::*;
+
+    fn outcome(status: &str, returns: &[&str]) -> DiffOutcome {
+        DiffOutcome {
+            status: status.to_string(),
+            returns: returns.iter().map(|r| r.to_string()).collect(),
+            events: vec![],
+            objects: vec![],
+        }
+    }
+
+    fn calling(function: &str) -> MoveSequence {
+        MoveSequence {
+            inputs: vec![],
+            commands: vec![MoveSequenceCall::Call(MoveCall {
+                module_id: MoveAddress::from_str("0xa").unwrap(),
+                module_name: "pool".to_string(),
+                function: function.to_string(),
+                type_arguments: vec![],
+                arguments: vec![],
+            })],
+        }
+    }
+
+    #[test]
+    fn divergences_are_keyed_by_the_called_functions() {
+        let old = outcome("success", &["1"]);
+        assert!(old.divergences(&old.clone()).is_empty());
+
+        let new = outcome("success", &["2"]);
+        let kinds = old.divergences(&new);
+        assert_eq!(kinds, vec!["return_values"]);
+        let aborted = outcome("abort 1 in 0xa::pool::swap at command 0", &[]);
+        assert_eq!(
+            old.divergences(&aborted),
+            vec!["abort_code", "return_values"]
+        );
+
+        let swap = called_functions(&calling("swap"));
+        let deposit = called_functions(&calling("deposit"));
+        assert_eq!(
+            divergence_key(&kinds, &swap, &old, &new),
+            divergence_key(&kinds, &swap, &old, &outcome("success", &["3"]))
+        );
+        assert_ne!(
+            divergence_key(&kinds, &swap, &old, &new),
+            divergence_key(&kinds, &deposit, &old, &new)
+        );
+    }
+}
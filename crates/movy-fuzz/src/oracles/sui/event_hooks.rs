@@ -0,0 +1,388 @@
+use std::collections::BTreeSet;
+
+use move_core_types::{identifier::Identifier, language_storage::StructTag};
+use movy_replay::{
+    db::{ObjectStoreInfo, ObjectStoreMintObject},
+    exec::SuiExecutor,
+    tracer::{NopTracer, oracle::SuiGeneralOracle},
+};
+use movy_sui::database::cache::ObjectSuiStoreCommit;
+use movy_types::{
+    abi::{MoveAbiSignatureToken, MoveModuleId},
+    error::MovyError,
+    input::{
+        FunctionIdent, InputArgument, MoveAddress, MoveCall, MoveSequence, MoveSequenceCall,
+        MoveTypeTag, SequenceArgument,
+    },
+    oracle::{Event, OracleFinding, Severity},
+};
+use serde_json::json;
+use sui_types::{
+    base_types::{ObjectID, SuiAddress},
+    effects::{TransactionEffects, TransactionEffectsAPI},
+    execution_status::{ExecutionFailureStatus, ExecutionStatus},
+    storage::{BackingPackageStore, BackingStore, ObjectStore},
+};
+use tracing::{debug, warn};
+
+use super::common::effects_snapshot;
+use crate::{
+    history::decode_pure,
+    meta::{FuzzMetadata, HasFuzzMetadata},
+    mutators::sequence::context_idents,
+    operations::sui_test::keyed_crash_reason,
+    state::{ExtraNonSerdeFuzzState, HasExtraState},
+};
+
+/// Runs the `movy_event_<EventName>` invariants declared in the testing
+/// modules with every matching event a successful sequence emits, and reports
+/// the ones which abort or crash. `<EventName>` refers to a struct of the
+/// hook's own package, or else of the target packages, so same-named events of
+/// unrelated packages do not match.
+///
+/// Hooks take the `MovyContext` followed by the fields of the event in
+/// declaration order, since a PTB can not pass an event struct itself:
+///
+/// ```move
+/// #[test]
+/// public fun movy_event_SwapEvent(movy: &mut MovyContext, pool: ID, amount_in: u64, fee: u64) {
+///     if (amount_in > 0 && fee == 0) {
+///         crash_because(b"Swap without fee".to_string());
+///     }
+/// }
+/// ```
+///
+/// They run in a follow-up PTB sharing a single context, so accounting can be
+/// kept in its bag across the events of a sequence. Generic events pass their
+/// type arguments on. Fields other than primitives, addresses and `ID`s are
+/// not supported.
+pub struct EventHookOracle<T> {
+    /// The forked state before fuzzing
+    pub executor: SuiExecutor<T>,
+    reported: BTreeSet<String>,
+}
+
+impl<T> EventHookOracle<T> {
+    pub fn new(executor: SuiExecutor<T>) -> Self {
+        Self {
+            executor,
+            reported: BTreeSet::new(),
+        }
+    }
+}
+
+/// Decode a field of an event as an argument of type `ty`.
+fn decode_field(ty: &MoveTypeTag, bytes: &mut &[u8]) -> Result<InputArgument, MovyError> {
+    match ty {
+        MoveTypeTag::Struct(tag)
+            if tag.address == MoveAddress::two() && tag.module == "object" && tag.name == "ID" =>
+        {
+            decode_pure(&MoveTypeTag::Address, bytes)
+        }
+        _ => decode_pure(ty, bytes),
+    }
+}
+
+/// The fields of `event` as arguments of a hook taking `parameters` after
+/// the context. `None` if the parameters do not mirror the fields.
+fn hook_inputs(parameters: &[MoveAbiSignatureToken], event: &Event) -> Option<Vec<InputArgument>> {
+    let ty_args = event
+        .ty
+        .tys
+        .iter()
+        .enumerate()
+        .map(|(i, ty)| (i as u16, ty.clone()))
+        .collect();
+
+    let mut bytes = event.contents.as_slice();
+    let mut inputs = vec![];
+    for param in parameters.iter().skip(1) {
+        if param.is_tx_context() {
+            continue;
+        }
+        let ty = param.subst(&ty_args)?;
+        // Vectors become commands of their own, which would shift the results
+        if matches!(ty, MoveTypeTag::Vector(_)) {
+            return None;
+        }
+        inputs.push(decode_field(&ty, &mut bytes).ok()?);
+    }
+    bytes.is_empty().then_some(inputs)
+}
+
+/// The call of `hook` with `ctx` and the fields of `event`, whose values are
+/// pushed to the inputs of `sequence`. `None` if the parameters of the hook
+/// do not mirror the fields.
+fn hook_call(
+    meta: &FuzzMetadata,
+    hook: &FunctionIdent,
+    event: &Event,
+    ctx: SequenceArgument,
+    sequence: &mut MoveSequence,
+) -> Option<MoveCall> {
+    let abi = meta.get_function(&hook.0.module_address, &hook.0.module_name, &hook.1)?;
+    if abi.type_parameters.len() != event.ty.tys.len() {
+        return None;
+    }
+    let inputs = hook_inputs(&abi.parameters, event)?;
+
+    let mut arguments = vec![ctx];
+    for input in inputs {
+        arguments.push(SequenceArgument::Input(sequence.inputs.len() as u16));
+        sequence.inputs.push(input);
+    }
+    Some(MoveCall {
+        module_id: hook.0.module_address,
+        module_name: hook.0.module_name.clone(),
+        function: hook.1.clone(),
+        type_arguments: event.ty.tys.clone(),
+        arguments,
+    })
+}
+
+/// `event` decoded with the layouts of the metadata, raw if unknown.
+fn decode_event(meta: &FuzzMetadata, event: &Event) -> serde_json::Value {
+    let raw =
+        || json!({ "type": event.ty.to_string(), "contents": const_hex::encode(&event.contents) });
+    let (Ok(type_), Ok(module)) = (
+        StructTag::try_from(event.ty.clone()),
+        Identifier::new(event.ty.module.clone()),
+    ) else {
+        return raw();
+    };
+    let event = sui_types::event::Event {
+        package_id: ObjectID::from(event.ty.address),
+        transaction_module: module,
+        sender: SuiAddress::ZERO,
+        type_,
+        contents: event.contents.clone(),
+    };
+    match meta.decode_sui_event(&event) {
+        Ok(Some((_, value))) => value,
+        _ => raw(),
+    }
+}
+
+impl<S, E, X> SuiGeneralOracle<S> for EventHookOracle<E>
+where
+    S: HasFuzzMetadata + HasExtraState<ExtraState = ExtraNonSerdeFuzzState<X>>,
+    E: ObjectStore
+        + BackingStore
+        + BackingPackageStore
+        + ObjectSuiStoreCommit
+        + ObjectStoreMintObject
+        + ObjectStoreInfo
+        + Clone,
+{
+    fn done_execution<T: ObjectStore>(
+        &mut self,
+        db: T,
+        state: &mut S,
+        effects: &TransactionEffects,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        let meta = state.fuzz_state();
+        if meta.event_hooks.is_empty() || !matches!(effects.status(), ExecutionStatus::Success) {
+            return Ok(vec![]);
+        }
+        let Some(outcome) = state.extra_state().global_outcome.as_ref() else {
+            return Ok(vec![]);
+        };
+        let Some((create, destroy)) = context_idents(meta) else {
+            debug!("No movy context to run event hooks with");
+            return Ok(vec![]);
+        };
+
+        let mut sequence = MoveSequence::default();
+        sequence.commands.push(MoveSequenceCall::Call(MoveCall {
+            module_id: create.0.module_address,
+            module_name: create.0.module_name.clone(),
+            function: create.1.clone(),
+            type_arguments: vec![],
+            arguments: vec![],
+        }));
+        let ctx = SequenceArgument::Result(0);
+        // The hook and the event of each command after the context creation
+        let mut invoked = vec![];
+        for event in outcome.exec.events.iter() {
+            let key = (
+                MoveModuleId {
+                    module_address: event.ty.address,
+                    module_name: event.ty.module.clone(),
+                },
+                event.ty.name.clone(),
+            );
+            let Some(hooks) = meta.event_hooks.get(&key) else {
+                continue;
+            };
+            for hook in hooks.iter() {
+                match hook_call(meta, hook, event, ctx, &mut sequence) {
+                    Some(call) => {
+                        sequence.commands.push(MoveSequenceCall::Call(call));
+                        invoked.push((hook, event));
+                    }
+                    None => debug!("{} does not take the fields of {}", hook, event.ty),
+                }
+            }
+        }
+        if invoked.is_empty() {
+            return Ok(vec![]);
+        }
+        sequence.commands.push(MoveSequenceCall::Call(MoveCall {
+            module_id: destroy.0.module_address,
+            module_name: destroy.0.module_name.clone(),
+            function: destroy.1.clone(),
+            type_arguments: vec![],
+            arguments: vec![ctx],
+        }));
+
+        let executor = self.executor.layered();
+        executor
+            .db
+            .restore_snapshot(effects_snapshot(&db, effects).0);
+        let out = match executor.run_ptb_with_gas(
+            sequence.to_ptb()?,
+            meta.epoch,
+            meta.epoch_ms,
+            meta.attacker.into(),
+            meta.gas_id.into(),
+            None::<NopTracer>,
+        ) {
+            Ok(out) => out,
+            Err(e) => {
+                debug!("Event hooks can not run: {}", e);
+                return Ok(vec![]);
+            }
+        };
+
+        let mut findings = vec![];
+        match out.results.effects.status() {
+            ExecutionStatus::Failure {
+                error: ExecutionFailureStatus::MoveAbort(_, code),
+                command: Some(idx),
+            } if (1..=invoked.len()).contains(idx) => {
+                let (hook, event) = invoked[*idx - 1];
+                let key = hook.to_string();
+                if !self.reported.contains(&key) {
+                    warn!("Event hook {} aborts with {} on {}", hook, code, event.ty);
+                    findings.push(OracleFinding::new(
+                        "EventHookOracle",
+                        Severity::Critical,
+                        json!({
+                            "kind": "event_invariant",
+                            "hook": key,
+                            "event_type": event.ty.to_string(),
+                            "event": decode_event(meta, event),
+                            "abort_code": code,
+                        }),
+                    ));
+                    self.reported.insert(key);
+                }
+            }
+            ExecutionStatus::Success => {
+                for crash in out.results.store.events.data.iter() {
+                    if crash.type_.module.as_str() != "oracle"
+                        || crash.type_.name.as_str() != "Crash"
+                    {
+                        continue;
+                    }
+                    let reason = decode_event(meta, &crash.clone().into());
+                    let key = reason.to_string();
+                    if self.reported.contains(&key) {
+                        continue;
+                    }
+                    warn!("Event hooks crash: {}", reason);
+                    findings.push(OracleFinding::new(
+                        "EventHookOracle",
+                        Severity::Critical,
+                        json!({
+                            "kind": "event_invariant",
+                            "hooks": invoked
+                                .iter()
+                                .map(|(hook, _)| hook.to_string())
+                                .collect::<BTreeSet<_>>(),
+                            "crash_reason": keyed_crash_reason(&crash.contents),
+                            "reason": reason,
+                        }),
+                    ));
+                    self.reported.insert(key);
+                }
+            }
+            status => debug!("Event hooks failed: {:?}", status),
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use movy_types::{
+        abi::{MoveAbility, MoveModuleId, MoveStructHandle},
+        input::MoveStructTag,
+    };
+
+    use super::*;
+
+    fn handle(address: MoveAddress, module: &str, name: &str) -> MoveAbiSignatureToken {
+        MoveAbiSignatureToken::Struct(MoveStructHandle {
+            module_id: MoveModuleId {
+                module_address: address,
+                module_name: module.to_string(),
+            },
+            struct_name: name.to_string(),
+            abilities: MoveAbility::empty(),
+            type_parameters: vec![],
+        })
+    }
+
+    #[test]
+    fn event_fields_become_hook_arguments() {
+        let pool = MoveAddress::from_str("0xb").unwrap();
+        let mut contents = ObjectID::from(pool).to_vec();
+        contents.extend(100u64.to_le_bytes());
+        contents.push(3);
+        let event = Event {
+            ty: MoveStructTag {
+                address: MoveAddress::from_str("0xa").unwrap(),
+                module: "pool".to_string(),
+                name: "SwapEvent".to_string(),
+                tys: vec![MoveTypeTag::U8],
+            },
+            contents,
+        };
+        let ctx = MoveAbiSignatureToken::MutableReference(Box::new(handle(
+            MoveAddress::from_str("0xa").unwrap(),
+            "movy",
+            "MovyContext",
+        )));
+        let tx_context = MoveAbiSignatureToken::MutableReference(Box::new(handle(
+            MoveAddress::two(),
+            "tx_context",
+            "TxContext",
+        )));
+        let mut parameters = vec![
+            ctx,
+            handle(MoveAddress::two(), "object", "ID"),
+            MoveAbiSignatureToken::U64,
+            MoveAbiSignatureToken::TypeParameter(0, MoveAbility::empty()),
+            tx_context,
+        ];
+        assert_eq!(
+            hook_inputs(&parameters, &event),
+            Some(vec![
+                InputArgument::Address(pool),
+                InputArgument::U64(100),
+                InputArgument::U8(3),
+            ])
+        );
+
+        // A field left over
+        let last = parameters.remove(3);
+        assert_eq!(hook_inputs(&parameters, &event), None);
+        // More parameters than fields
+        parameters.insert(3, last);
+        parameters.insert(3, MoveAbiSignatureToken::U64);
+        assert_eq!(hook_inputs(&parameters, &event), None);
+    }
+}
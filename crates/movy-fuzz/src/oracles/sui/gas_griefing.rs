@@ -0,0 +1,234 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use move_trace_format::format::{Frame, TraceEvent, TraceIndex, TraceValue};
+use movy_replay::tracer::{
+    MovySuiTracerExt, concolic::ConcolicState, oracle::SuiGeneralOracle, state::TraceState,
+};
+use movy_types::{
+    error::MovyError,
+    input::{FunctionIdent, MoveSequence},
+    oracle::{OracleFinding, Severity},
+};
+use serde_json::json;
+use sui_types::{effects::TransactionEffects, storage::ObjectStore};
+use tracing::warn;
+
+use crate::state::{ExtraNonSerdeFuzzState, HasExtraState};
+
+/// Calls of a function within an execution, prelude included, needed to tell
+/// a growth curve by default
+const DEFAULT_MIN_CALLS: usize = 4;
+
+/// Gas spent by every call of each function. The cost of a call is the gas
+/// spent between opening and closing its frame, callees included, and
+/// recursive calls are folded into the outermost one.
+#[derive(Debug, Clone, Default)]
+pub struct FrameCosts {
+    /// Frames open with the gas left on entry
+    frames: Vec<(FunctionIdent, u64)>,
+    /// Costs of the calls of each function, in order
+    pub calls: BTreeMap<FunctionIdent, Vec<u64>>,
+}
+
+impl FrameCosts {
+    fn open(&mut self, function: FunctionIdent, gas_left: u64) {
+        self.frames.push((function, gas_left));
+    }
+
+    fn close(&mut self, function: &FunctionIdent, gas_left: u64) {
+        let Some((open, entry_gas)) = self.frames.pop() else {
+            return;
+        };
+        if &open != function {
+            // Frames went out of sync, e.g. after an abort
+            self.frames.clear();
+            return;
+        }
+        if self.frames.iter().any(|(open, _)| open == function) {
+            return;
+        }
+        self.calls
+            .entry(open)
+            .or_default()
+            .push(entry_gas.saturating_sub(gas_left));
+    }
+}
+
+/// Collects the [`FrameCosts`] of the prelude transactions, which are not
+/// traced by the oracles.
+#[derive(Debug, Default)]
+pub struct FrameGasTracer {
+    pub costs: FrameCosts,
+}
+
+impl MovySuiTracerExt for FrameGasTracer {
+    fn on_raw_event(&mut self, _state: &TraceState, _ev: &TraceEvent) -> bool {
+        true
+    }
+
+    fn open_frame(&mut self, _state: &TraceState, frame: &Box<Frame>, gas_left: u64) {
+        let function = FunctionIdent::new(
+            &(*frame.module.address()).into(),
+            frame.module.name().as_str(),
+            &frame.function_name,
+        );
+        self.costs.open(function, gas_left);
+    }
+
+    fn close_frame(
+        &mut self,
+        _state: &TraceState,
+        _frame_id: TraceIndex,
+        _return_: &Vec<TraceValue>,
+        gas_left: u64,
+    ) {
+        if let Some((function, _)) = self.costs.frames.last().cloned() {
+            self.costs.close(&function, gas_left);
+        }
+    }
+}
+
+/// Reports functions whose gas cost keeps growing with every call, e.g.
+/// iterating a vector or table anyone can append to, so that repeated
+/// attacker actions eventually push admin or user paths out of gas.
+///
+/// Costs are measured within a single execution, over the calls of the
+/// prelude followed by those of the sequence, so the input reported is the
+/// one showing the growth. A function is reported once its latest
+/// `min_calls` or more calls grow strictly and at least double.
+#[derive(Debug, Clone)]
+pub struct GasGriefingOracle {
+    pub min_calls: usize,
+    costs: FrameCosts,
+    reported: BTreeSet<FunctionIdent>,
+}
+
+impl Default for GasGriefingOracle {
+    fn default() -> Self {
+        Self::new(DEFAULT_MIN_CALLS)
+    }
+}
+
+impl GasGriefingOracle {
+    pub fn new(min_calls: usize) -> Self {
+        Self {
+            min_calls: min_calls.max(2),
+            costs: FrameCosts::default(),
+            reported: BTreeSet::new(),
+        }
+    }
+}
+
+/// Whether the strictly growing tail of `costs` spans at least `min_calls`
+/// calls and its last cost is at least twice its first.
+fn grows(costs: &[u64], min_calls: usize) -> bool {
+    let start = costs
+        .windows(2)
+        .rposition(|pair| pair[0] >= pair[1])
+        .map_or(0, |idx| idx + 1);
+    let tail = &costs[start..];
+    tail.len() >= min_calls && tail[tail.len() - 1] >= tail[0].saturating_mul(2)
+}
+
+impl<S, X> SuiGeneralOracle<S> for GasGriefingOracle
+where
+    S: HasExtraState<ExtraState = ExtraNonSerdeFuzzState<X>>,
+{
+    fn pre_execution<T: ObjectStore>(
+        &mut self,
+        _db: T,
+        state: &mut S,
+        _sequence: &MoveSequence,
+    ) -> Result<(), MovyError> {
+        self.costs = FrameCosts {
+            frames: vec![],
+            calls: state.extra_state().prelude_gas.clone(),
+        };
+        Ok(())
+    }
+
+    fn open_frame(
+        &mut self,
+        _frame: &Box<Frame>,
+        _trace_state: &TraceState,
+        _symbol_stack: &ConcolicState,
+        current_function: &FunctionIdent,
+        gas_left: u64,
+        _state: &mut S,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        self.costs.open(current_function.clone(), gas_left);
+        Ok(vec![])
+    }
+
+    fn close_frame(
+        &mut self,
+        _trace_state: &TraceState,
+        current_function: &FunctionIdent,
+        gas_left: u64,
+        _state: &mut S,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        self.costs.close(current_function, gas_left);
+        Ok(vec![])
+    }
+
+    fn done_execution<T: ObjectStore>(
+        &mut self,
+        _db: T,
+        _state: &mut S,
+        _effects: &TransactionEffects,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        let mut findings = vec![];
+        for (function, costs) in std::mem::take(&mut self.costs.calls) {
+            if self.reported.contains(&function) || !grows(&costs, self.min_calls) {
+                continue;
+            }
+            warn!("Gas of {} grows with every call: {:?}", function, costs);
+            findings.push(OracleFinding::new(
+                "GasGriefingOracle",
+                Severity::Medium,
+                json!({
+                    "kind": "gas_griefing",
+                    "function": function.to_string(),
+                    "costs": costs,
+                }),
+            ));
+            self.reported.insert(function);
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use movy_types::input::MoveAddress;
+
+    use super::*;
+
+    #[test]
+    fn only_strict_and_doubling_growth_is_reported() {
+        assert!(grows(&[100, 150, 210, 260], 4));
+        assert!(!grows(&[100, 150, 210], 4));
+        assert!(!grows(&[100, 150, 150, 260], 4));
+        assert!(!grows(&[100, 110, 120, 130], 4));
+        assert!(grows(&[500, 100, 150, 210, 260], 4));
+        assert!(!grows(&[100, 150, 210, 260, 90], 4));
+    }
+
+    #[test]
+    fn recursive_calls_fold_into_the_outermost() {
+        let f = FunctionIdent::new(&MoveAddress::two(), "table", "sum");
+        let g = FunctionIdent::new(&MoveAddress::two(), "table", "add");
+        let mut costs = FrameCosts::default();
+        costs.open(f.clone(), 1000);
+        costs.open(f.clone(), 900);
+        costs.open(g.clone(), 850);
+        costs.close(&g, 800);
+        costs.close(&f, 700);
+        costs.close(&f, 600);
+        costs.open(f.clone(), 600);
+        costs.close(&f, 100);
+        assert_eq!(costs.calls[&f], vec![400, 500]);
+        assert_eq!(costs.calls[&g], vec![50]);
+        assert!(costs.frames.is_empty());
+    }
+}
@@ -9,28 +9,90 @@ use movy_replay::tracer::{
 };
 use movy_types::{
     error::MovyError,
+    input::{FunctionIdent, MoveSequence},
     oracle::{OracleFinding, Severity},
 };
+use sui_types::storage::ObjectStore;
 
 use crate::utils::hash_to_u64;
 
-#[derive(Debug, Default, Clone)]
+/// Repetitions of a branch on the same condition reported by default
+const DEFAULT_THRESHOLD: usize = 1000;
+
+/// Reports branches taken over and over on the same condition, which hints at
+/// a loop that never makes progress.
+///
+/// Counts are per function and restart whenever the function is entered
+/// from outside itself, so separate calls do not add up. Recursive calls keep
+/// counting into the outermost call.
+#[derive(Debug, Clone)]
 pub struct InfiniteLoopOracle {
     pub branch_counts: BTreeMap<u64, BTreeMap<u16, (u64, usize)>>,
+    /// Repetitions of a branch on the same condition considered a loop
+    pub threshold: usize,
+    /// Frames open for each function
+    depths: BTreeMap<u64, usize>,
+}
+
+impl Default for InfiniteLoopOracle {
+    fn default() -> Self {
+        Self::new(DEFAULT_THRESHOLD)
+    }
+}
+
+impl InfiniteLoopOracle {
+    pub fn new(threshold: usize) -> Self {
+        Self {
+            branch_counts: BTreeMap::new(),
+            threshold,
+            depths: BTreeMap::new(),
+        }
+    }
+}
+
+fn function_key(function: &FunctionIdent) -> u64 {
+    hash_to_u64(&format!("{}::{}", function.0, function.1))
 }
 
 impl<S> SuiGeneralOracle<S> for InfiniteLoopOracle {
+    fn pre_execution<T: ObjectStore>(
+        &mut self,
+        _db: T,
+        _state: &mut S,
+        _sequence: &MoveSequence,
+    ) -> Result<(), MovyError> {
+        self.depths.clear();
+        Ok(())
+    }
+
     fn open_frame(
         &mut self,
-        frame: &Box<move_trace_format::format::Frame>,
+        _frame: &Box<move_trace_format::format::Frame>,
         _trace_state: &movy_replay::tracer::state::TraceState,
         _symbol_stack: &ConcolicState,
-        _current_function: &movy_types::input::FunctionIdent,
+        current_function: &FunctionIdent,
+        _gas_left: u64,
         _state: &mut S,
     ) -> Result<Vec<OracleFinding>, MovyError> {
-        let key = format!("{}::{}", frame.module, frame.function_name);
-        let key = hash_to_u64(&key);
-        self.branch_counts.remove(&key);
+        let key = function_key(current_function);
+        let depth = self.depths.entry(key).or_default();
+        if *depth == 0 {
+            self.branch_counts.remove(&key);
+        }
+        *depth += 1;
+        Ok(vec![])
+    }
+
+    fn close_frame(
+        &mut self,
+        _trace_state: &movy_replay::tracer::state::TraceState,
+        current_function: &FunctionIdent,
+        _gas_left: u64,
+        _state: &mut S,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        if let Some(depth) = self.depths.get_mut(&function_key(current_function)) {
+            *depth = depth.saturating_sub(1);
+        }
         Ok(vec![])
     }
 
@@ -40,7 +102,7 @@ impl<S> SuiGeneralOracle<S> for InfiniteLoopOracle {
         instruction: &Bytecode,
         _trace_state: &movy_replay::tracer::state::TraceState,
         symbol_stack: &ConcolicState,
-        current_function: &movy_types::input::FunctionIdent,
+        current_function: &FunctionIdent,
         _state: &mut S,
     ) -> Result<Vec<OracleFinding>, MovyError> {
         match instruction {
@@ -50,12 +112,15 @@ impl<S> SuiGeneralOracle<S> for InfiniteLoopOracle {
                     return Ok(vec![]);
                 }
                 let cond_symbol = &symbol_stack.stack[symbol_stack.stack.len() - 1];
-                match cond_symbol {
-                    SymbolValue::Unknown => return Ok(vec![]),
-                    SymbolValue::Value(v) => {
-                        let key = format!("{}::{}", func.0, func.1);
-                        let key = hash_to_u64(&key);
-                        let v = hash_to_u64(&v.to_string());
+                let cond = match cond_symbol {
+                    SymbolValue::Value(v) => Some(v.to_string()),
+                    SymbolValue::Bits(v) => Some(v.to_string()),
+                    _ => None,
+                };
+                match cond {
+                    Some(v) => {
+                        let key = function_key(func);
+                        let v = hash_to_u64(&v);
                         let count = self
                             .branch_counts
                             .entry(key)
@@ -66,22 +131,23 @@ impl<S> SuiGeneralOracle<S> for InfiniteLoopOracle {
                             count.0 = v;
                             count.1 = 1;
                         } else {
-                            if count.1 >= 1000 {
+                            if count.1 >= self.threshold {
                                 count.1 = 0;
                                 let info = json!({
                                     "oracle": "InfiniteLoopOracle",
                                     "function": current_function.to_string(),
                                     "pc": pc,
                                 });
-                                return Ok(vec![OracleFinding {
-                                    oracle: "InfiniteLoopOracle".to_string(),
-                                    severity: Severity::Major,
-                                    extra: info,
-                                }]);
+                                return Ok(vec![OracleFinding::new(
+                                    "InfiniteLoopOracle",
+                                    Severity::Major,
+                                    info,
+                                )]);
                             }
                             count.1 += 1;
                         }
                     }
+                    None => return Ok(vec![]),
                 }
             }
             _ => {}
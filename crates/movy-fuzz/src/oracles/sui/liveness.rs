@@ -0,0 +1,329 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use movy_replay::{
+    db::{ObjectStoreInfo, ObjectStoreMintObject},
+    exec::SuiExecutor,
+    tracer::{NopTracer, oracle::SuiGeneralOracle},
+};
+use movy_sui::database::cache::{CachedSnapshot, ObjectSuiStoreCommit};
+use movy_types::{
+    error::MovyError,
+    input::{InputArgument, MoveSequence, MoveSequenceCall},
+    oracle::{OracleFinding, Severity},
+};
+use serde_json::json;
+use sui_types::{
+    base_types::ObjectID,
+    effects::{TransactionEffects, TransactionEffectsAPI},
+    execution_status::ExecutionStatus,
+    storage::{BackingPackageStore, BackingStore, ObjectStore},
+};
+use tracing::{debug, warn};
+
+use super::common::{effects_snapshot, standalone_call};
+use crate::{
+    executor::{refresh_object_refs, sender_gas},
+    meta::{FuzzMetadata, HasFuzzMetadata},
+};
+
+/// Probes replayed after a single attacker sequence at most
+const MAX_PROBES: usize = 8;
+/// Probes learned from the corpus for a single shared object at most
+const MAX_LEARNED_PROBES: usize = 4;
+
+/// Reports shared objects an attacker sequence leaves bricked: after the
+/// sequence succeeds, liveness probes touching the objects it changed are
+/// replayed by the deployer and reported if they succeed on the forked state
+/// but abort afterwards.
+///
+/// Probes are the `--liveness-probes` sequences and calls of successful
+/// sequences taking nothing but shared objects and pure values. They run on
+/// a [`CachedStore`](movy_sui::database::cache::CachedStore) layer holding a snapshot of the objects the attacker
+/// sequence changed, which skips the changes of the prelude and the state
+/// patches to other objects.
+pub struct LivenessOracle<T> {
+    /// The forked state before fuzzing
+    pub executor: SuiExecutor<T>,
+    sequence: Option<MoveSequence>,
+    learned: BTreeMap<ObjectID, Vec<MoveSequence>>,
+    /// Whether a probe succeeds on the forked state
+    baseline: BTreeMap<String, bool>,
+    reported: BTreeSet<String>,
+}
+
+impl<T> LivenessOracle<T> {
+    pub fn new(executor: SuiExecutor<T>) -> Self {
+        Self {
+            executor,
+            sequence: None,
+            learned: BTreeMap::new(),
+            baseline: BTreeMap::new(),
+            reported: BTreeSet::new(),
+        }
+    }
+}
+
+fn probe_objects(probe: &MoveSequence) -> impl Iterator<Item = ObjectID> + '_ {
+    probe.inputs.iter().filter_map(|input| match input {
+        InputArgument::Object(_, object) => Some(object.id()),
+        _ => None,
+    })
+}
+
+fn probe_name(probe: &MoveSequence) -> String {
+    probe
+        .commands
+        .iter()
+        .filter_map(|cmd| match cmd {
+            MoveSequenceCall::Call(call) => {
+                Some(format!("{}::{}", call.module_name, call.function))
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+impl<T> LivenessOracle<T>
+where
+    T: ObjectStore
+        + BackingStore
+        + BackingPackageStore
+        + ObjectSuiStoreCommit
+        + ObjectStoreMintObject
+        + ObjectStoreInfo
+        + Clone,
+{
+    /// Status of `probe` sent by the deployer on top of `snapshot`, `None` if
+    /// it can not run.
+    fn probe(
+        &self,
+        meta: &FuzzMetadata,
+        snapshot: Option<&CachedSnapshot>,
+        probe: &MoveSequence,
+    ) -> Result<Option<ExecutionStatus>, MovyError> {
+        let executor = self.executor.layered();
+        if let Some(snapshot) = snapshot {
+            executor.db.restore_snapshot(snapshot.clone());
+        }
+        let gas = sender_gas(&executor.db, meta.deployer)?;
+        let mut probe = probe.clone();
+        refresh_object_refs(&executor.db, &mut probe);
+        let Ok(out) = executor.run_ptb_with_gas(
+            probe.to_ptb()?,
+            meta.epoch,
+            meta.epoch_ms,
+            meta.deployer.into(),
+            gas,
+            None::<NopTracer>,
+        ) else {
+            return Ok(None);
+        };
+        Ok(Some(out.results.effects.status().clone()))
+    }
+
+    fn succeeds_before(
+        &mut self,
+        meta: &FuzzMetadata,
+        probe: &MoveSequence,
+    ) -> Result<bool, MovyError> {
+        let key = probe.to_string();
+        if let Some(succeeded) = self.baseline.get(&key) {
+            return Ok(*succeeded);
+        }
+        let succeeded = matches!(
+            self.probe(meta, None, probe)?,
+            Some(ExecutionStatus::Success)
+        );
+        self.baseline.insert(key, succeeded);
+        Ok(succeeded)
+    }
+
+    /// Keep the calls of `sequence` which work as probes on the forked state.
+    fn learn(&mut self, meta: &FuzzMetadata, sequence: &MoveSequence) -> Result<(), MovyError> {
+        for cmd in sequence.commands.iter() {
+            let MoveSequenceCall::Call(call) = cmd else {
+                continue;
+            };
+            let Some((probe, shared)) = standalone_call(sequence, call) else {
+                continue;
+            };
+            let name = probe_name(&probe);
+            let wanted = shared.iter().any(|id| {
+                self.learned.get(id).is_none_or(|probes| {
+                    probes.len() < MAX_LEARNED_PROBES
+                        && probes.iter().all(|known| probe_name(known) != name)
+                })
+            });
+            if !wanted || !self.succeeds_before(meta, &probe)? {
+                continue;
+            }
+            debug!("Learned liveness probe {}", name);
+            for id in shared {
+                let probes = self.learned.entry(id).or_default();
+                if probes.len() < MAX_LEARNED_PROBES
+                    && probes.iter().all(|known| probe_name(known) != name)
+                {
+                    probes.push(probe.clone());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<S, E> SuiGeneralOracle<S> for LivenessOracle<E>
+where
+    S: HasFuzzMetadata,
+    E: ObjectStore
+        + BackingStore
+        + BackingPackageStore
+        + ObjectSuiStoreCommit
+        + ObjectStoreMintObject
+        + ObjectStoreInfo
+        + Clone,
+{
+    fn pre_execution<T: ObjectStore>(
+        &mut self,
+        _db: T,
+        _state: &mut S,
+        sequence: &MoveSequence,
+    ) -> Result<(), MovyError> {
+        self.sequence = Some(sequence.clone());
+        Ok(())
+    }
+
+    fn done_execution<T: ObjectStore>(
+        &mut self,
+        db: T,
+        state: &mut S,
+        effects: &TransactionEffects,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        let Some(sequence) = self.sequence.take() else {
+            return Ok(vec![]);
+        };
+        if !matches!(effects.status(), ExecutionStatus::Success) {
+            return Ok(vec![]);
+        }
+        let meta = state.fuzz_state();
+
+        let (snapshot, changed) = effects_snapshot(&db, effects);
+
+        let probes = meta
+            .liveness_probes
+            .iter()
+            .chain(
+                changed
+                    .iter()
+                    .filter_map(|id| self.learned.get(id))
+                    .flatten(),
+            )
+            .filter(|probe| probe_objects(probe).any(|id| changed.contains(&id)))
+            .filter(|probe| !self.reported.contains(&probe_name(probe)))
+            .take(MAX_PROBES)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        let mut findings = vec![];
+        for probe in probes {
+            let name = probe_name(&probe);
+            if self.reported.contains(&name) || !self.succeeds_before(meta, &probe)? {
+                continue;
+            }
+            let after = self.probe(meta, Some(&snapshot), &probe)?;
+            if matches!(after, Some(ExecutionStatus::Success) | None) {
+                continue;
+            }
+            let objects = probe_objects(&probe)
+                .filter(|id| changed.contains(id))
+                .collect::<Vec<_>>();
+            warn!(
+                "Probe {} aborts after the attacker sequence on {:?}:\n{}",
+                name, objects, sequence
+            );
+            findings.push(OracleFinding::new(
+                "LivenessOracle",
+                Severity::Major,
+                json!({
+                    "kind": "shared_object_dos",
+                    "probe": name,
+                    "objects": objects,
+                    "status": format!("{:?}", after),
+                }),
+            ));
+            self.reported.insert(name);
+        }
+
+        self.learn(meta, &sequence)?;
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use movy_types::input::{
+        MoveAddress, MoveCall, MoveTypeTag, SequenceArgument, SuiObjectInputArgument,
+    };
+
+    use super::*;
+
+    fn call(arguments: Vec<SequenceArgument>) -> MoveCall {
+        MoveCall {
+            module_id: MoveAddress::from_str("0xa").unwrap(),
+            module_name: "pool".to_string(),
+            function: "deposit".to_string(),
+            type_arguments: vec![],
+            arguments,
+        }
+    }
+
+    #[test]
+    fn only_calls_on_shared_objects_and_pure_values_are_probes() {
+        let pool = MoveAddress::from_str("0xb").unwrap();
+        let coin = MoveAddress::from_str("0xc").unwrap();
+        let sequence = MoveSequence {
+            inputs: vec![
+                InputArgument::U64(1),
+                InputArgument::Object(
+                    MoveTypeTag::Address,
+                    SuiObjectInputArgument::shared_object(pool, 1, true),
+                ),
+                InputArgument::Object(
+                    MoveTypeTag::Address,
+                    SuiObjectInputArgument::imm_or_owned_object(coin, 1, [0; 32]),
+                ),
+            ],
+            commands: vec![],
+        };
+
+        let (probe, shared) = standalone_call(
+            &sequence,
+            &call(vec![SequenceArgument::Input(1), SequenceArgument::Input(0)]),
+        )
+        .unwrap();
+        assert_eq!(shared, vec![ObjectID::from(pool)]);
+        assert_eq!(probe.inputs.len(), 2);
+        assert_eq!(probe_name(&probe), "pool::deposit");
+
+        assert!(
+            standalone_call(
+                &sequence,
+                &call(vec![SequenceArgument::Input(1), SequenceArgument::Input(2)]),
+            )
+            .is_none()
+        );
+        assert!(standalone_call(&sequence, &call(vec![SequenceArgument::Input(0)])).is_none());
+        assert!(
+            standalone_call(
+                &sequence,
+                &call(vec![
+                    SequenceArgument::Input(1),
+                    SequenceArgument::Result(0)
+                ]),
+            )
+            .is_none()
+        );
+    }
+}
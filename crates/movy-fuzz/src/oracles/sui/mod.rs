@@ -1,16 +1,36 @@
+mod access_control;
+mod arithmetic_abort;
 mod bool_judgement;
 mod common;
+mod conservation;
+mod differential;
+mod event_hooks;
+mod gas_griefing;
 mod infinite_loop;
+mod liveness;
 mod overflow;
+mod ownership;
 mod precision_loss;
 mod proceeds;
+mod rounding;
 mod type_conversion;
 mod typed_bug;
+mod upgrade;
 
+pub use access_control::AccessControlOracle;
+pub use arithmetic_abort::ArithmeticAbortOracle;
 pub use bool_judgement::BoolJudgementOracle;
+pub use conservation::ConservationOracle;
+pub use differential::DifferentialOracle;
+pub use event_hooks::EventHookOracle;
+pub use gas_griefing::{FrameGasTracer, GasGriefingOracle};
 pub use infinite_loop::InfiniteLoopOracle;
+pub use liveness::LivenessOracle;
 pub use overflow::OverflowOracle;
+pub use ownership::OwnershipOracle;
 pub use precision_loss::PrecisionLossOracle;
 pub use proceeds::ProceedsOracle;
+pub use rounding::RoundingOracle;
 pub use type_conversion::TypeConversionOracle;
 pub use typed_bug::TypedBugOracle;
+pub use upgrade::UpgradeOracle;
@@ -1,6 +1,5 @@
 use move_binary_format::file_format::Bytecode;
 use move_core_types::u256::U256;
-use move_trace_format::format::TraceValue;
 use serde_json::json;
 
 use movy_replay::tracer::{
@@ -16,8 +15,7 @@ use movy_types::{
 pub struct OverflowOracle;
 
 /// Count the number of significant bits in the concrete value (0 => 0 bits).
-fn value_sig_bits(v: &TraceValue) -> u32 {
-    let as_u256 = value_to_u256(v);
+fn value_sig_bits(as_u256: U256) -> u32 {
     if as_u256 == U256::zero() {
         0
     } else {
@@ -44,9 +42,12 @@ impl<S> SuiGeneralOracle<S> for OverflowOracle {
         }
         let lhs = &stack[stack.len() - 2];
         let rhs = &stack[stack.len() - 1];
-        let lhs_width_bits = value_bitwidth(lhs); // type width (u8/u16/...)
-        let lhs_sig_bits = value_sig_bits(lhs); // actual significant bits of the value
-        let rhs_bits = value_to_u256(rhs);
+        let (Some(lhs_width_bits), Some(lhs_value), Some(rhs_bits)) =
+            (value_bitwidth(lhs), value_to_u256(lhs), value_to_u256(rhs))
+        else {
+            return Ok(vec![]);
+        };
+        let lhs_sig_bits = value_sig_bits(lhs_value); // actual significant bits of the value
 
         let overflow = if rhs_bits >= U256::from(lhs_width_bits) {
             true
@@ -62,11 +63,11 @@ impl<S> SuiGeneralOracle<S> for OverflowOracle {
                 "function": current_function.to_string(),
                 "pc": pc,
             });
-            return Ok(vec![OracleFinding {
-                oracle: "OverflowOracle".to_string(),
-                severity: Severity::Medium,
-                extra: info,
-            }]);
+            return Ok(vec![OracleFinding::new(
+                "OverflowOracle",
+                Severity::Medium,
+                info,
+            )]);
         }
         Ok(vec![])
     }
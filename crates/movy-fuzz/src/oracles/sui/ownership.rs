@@ -0,0 +1,408 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use movy_replay::tracer::oracle::SuiGeneralOracle;
+use movy_types::{
+    error::MovyError,
+    input::{MoveAddress, MoveStructTag},
+    oracle::{OracleFinding, Severity},
+};
+use serde_json::json;
+use sui_types::{
+    base_types::{ObjectID, ObjectRef, SequenceNumber, SuiAddress},
+    effects::{TransactionEffects, TransactionEffectsAPI},
+    execution_status::ExecutionStatus,
+    object::{Object, Owner},
+    storage::ObjectStore,
+};
+use tracing::{debug, warn};
+
+use crate::meta::{FuzzMetadata, HasFuzzMetadata};
+
+/// Parents followed at most to find the address owning a child object
+const MAX_OWNER_DEPTH: usize = 16;
+
+/// Reports suspicious ownership transitions in the effects of attacker
+/// transactions:
+///
+/// - objects held by another address, e.g. the deployer or a victim, directly
+///   or through parent objects, which get wrapped into an object the attacker
+///   owns
+/// - shared objects which get deleted
+/// - objects of the target packages which get transferred to an address other
+///   than the attacker
+///
+/// Each kind is reported once per object type.
+#[derive(Debug, Clone, Default)]
+pub struct OwnershipOracle {
+    reported: BTreeSet<String>,
+}
+
+/// Whether the BCS `contents` of an object hold the UID `id`.
+fn holds_id(contents: &[u8], id: &ObjectID) -> bool {
+    contents
+        .windows(ObjectID::LENGTH)
+        .any(|window| window == id.as_ref())
+}
+
+/// The type of `object`, `None` for packages.
+fn object_type(object: &Object) -> Option<MoveStructTag> {
+    object.struct_tag().map(MoveStructTag::from)
+}
+
+/// The owner at the root of the ownership chain of `owner`, following the
+/// parents of child objects with `parent_owner`. `None` if a parent is
+/// missing or the chain is too deep.
+fn root_owner(owner: &Owner, parent_owner: impl Fn(&ObjectID) -> Option<Owner>) -> Option<Owner> {
+    let mut owner = owner.clone();
+    for _ in 0..MAX_OWNER_DEPTH {
+        match owner {
+            Owner::ObjectOwner(parent) => owner = parent_owner(&parent.into())?,
+            _ => return Some(owner),
+        }
+    }
+    None
+}
+
+fn target_addresses(meta: &FuzzMetadata) -> BTreeSet<MoveAddress> {
+    meta.target_packages
+        .iter()
+        .filter_map(|package| meta.get_package_metadata(package))
+        .flat_map(|abi| abi.modules.iter().map(|md| md.module_id.module_address))
+        .collect()
+}
+
+/// The ownership transitions of a transaction, as told by its effects
+#[derive(Debug, Clone, Default)]
+struct Transitions {
+    /// Version and owner before the transaction of the objects it touched
+    old: BTreeMap<ObjectID, (SequenceNumber, Owner)>,
+    wrapped: Vec<ObjectID>,
+    deleted: Vec<ObjectID>,
+    /// Objects written by the transaction with their new owners
+    changed: Vec<(ObjectRef, Owner)>,
+}
+
+impl Transitions {
+    fn new(effects: &TransactionEffects) -> Self {
+        Self {
+            old: effects
+                .old_object_metadata()
+                .into_iter()
+                .map(|(object, owner)| (object.0, (object.1, owner)))
+                .collect(),
+            wrapped: effects
+                .wrapped()
+                .into_iter()
+                .map(|object| object.0)
+                .collect(),
+            deleted: effects
+                .deleted()
+                .into_iter()
+                .map(|object| object.0)
+                .collect(),
+            changed: effects
+                .all_changed_objects()
+                .into_iter()
+                .map(|(object, owner, _)| (object, owner))
+                .collect(),
+        }
+    }
+}
+
+impl OwnershipOracle {
+    fn report(
+        &mut self,
+        kind: &str,
+        severity: Severity,
+        object: ObjectID,
+        ty: &MoveStructTag,
+        from: String,
+        to: serde_json::Value,
+    ) -> Option<OracleFinding> {
+        let key = format!("{}:{}", kind, ty);
+        if !self.reported.insert(key) {
+            return None;
+        }
+        warn!("{} of {} ({}) from {}: {}", kind, object, ty, from, to);
+        Some(OracleFinding::new(
+            "OwnershipOracle",
+            severity,
+            json!({
+                "kind": kind,
+                "object": object,
+                "type": ty.to_string(),
+                "from": from,
+                "to": to,
+            }),
+        ))
+    }
+
+    fn check<T: ObjectStore>(
+        &mut self,
+        db: &T,
+        attacker: SuiAddress,
+        targets: &BTreeSet<MoveAddress>,
+        transitions: &Transitions,
+    ) -> Vec<OracleFinding> {
+        let old_type = |id: &ObjectID| {
+            let (version, _) = transitions.old.get(id)?;
+            object_type(&db.get_object_by_key(id, *version)?)
+        };
+        // Parents as they were before the transaction
+        let old_parent_owner = |id: &ObjectID| match transitions.old.get(id) {
+            Some((_, owner)) => Some(owner.clone()),
+            None => db.get_object(id).map(|object| object.owner.clone()),
+        };
+        let new_parent_owner = |id: &ObjectID| db.get_object(id).map(|object| object.owner.clone());
+        let attacker_owner = Owner::AddressOwner(attacker);
+        let mut findings = vec![];
+
+        // Objects of others wrapped into attacker objects
+        for id in transitions.wrapped.iter() {
+            let Some((_, from)) = transitions.old.get(id) else {
+                continue;
+            };
+            let Some(root) = root_owner(from, old_parent_owner) else {
+                continue;
+            };
+            if root == attacker_owner {
+                continue;
+            }
+            let Some(ty) = old_type(id) else {
+                continue;
+            };
+            let wrapper = transitions
+                .changed
+                .iter()
+                .find_map(|((parent, version, _), owner)| {
+                    let object = db.get_object_by_key(parent, *version)?;
+                    let contents = object.data.try_as_move()?.contents();
+                    (holds_id(contents, id)
+                        && root_owner(owner, new_parent_owner) == Some(attacker_owner.clone()))
+                    .then_some(*parent)
+                });
+            let Some(wrapper) = wrapper else {
+                debug!("{} is wrapped, but not into an attacker object", id);
+                continue;
+            };
+            findings.extend(self.report(
+                "object_wrapped_by_attacker",
+                Severity::Critical,
+                *id,
+                &ty,
+                root.to_string(),
+                json!({ "wrapped_in": wrapper, "owner": attacker }),
+            ));
+        }
+
+        // Shared objects deleted
+        for id in transitions.deleted.iter() {
+            let Some((_, from)) = transitions.old.get(id) else {
+                continue;
+            };
+            if !from.is_shared() {
+                continue;
+            }
+            let Some(ty) = old_type(id) else {
+                continue;
+            };
+            findings.extend(self.report(
+                "shared_object_deleted",
+                Severity::Major,
+                *id,
+                &ty,
+                from.to_string(),
+                json!("deleted"),
+            ));
+        }
+
+        // Target objects sent to third parties
+        for ((id, version, _), owner) in transitions.changed.iter() {
+            let Owner::AddressOwner(receiver) = owner else {
+                continue;
+            };
+            if *receiver == attacker {
+                continue;
+            }
+            let from = match transitions.old.get(id) {
+                Some((_, from)) if from == owner => continue,
+                Some((_, from)) => from.to_string(),
+                None => "created".to_string(),
+            };
+            let Some(ty) = db
+                .get_object_by_key(id, *version)
+                .and_then(|object| object_type(&object))
+            else {
+                continue;
+            };
+            if !targets.contains(&ty.address) {
+                continue;
+            }
+            findings.extend(self.report(
+                "object_transferred_to_other",
+                Severity::Medium,
+                *id,
+                &ty,
+                from,
+                json!(owner.to_string()),
+            ));
+        }
+        findings
+    }
+}
+
+impl<S> SuiGeneralOracle<S> for OwnershipOracle
+where
+    S: HasFuzzMetadata,
+{
+    fn done_execution<T: ObjectStore>(
+        &mut self,
+        db: T,
+        state: &mut S,
+        effects: &TransactionEffects,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        if !matches!(effects.status(), ExecutionStatus::Success) {
+            return Ok(vec![]);
+        }
+        let meta = state.fuzz_state();
+        Ok(self.check(
+            &db,
+            SuiAddress::from(meta.attacker),
+            &target_addresses(meta),
+            &Transitions::new(effects),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use movy_sui::database::{
+        cache::{CachedStore, ObjectSuiStoreCommit},
+        empty::EmptyStore,
+    };
+    use sui_types::{
+        TypeTag, base_types::SequenceNumber, digests::TransactionDigest, object::MoveObject,
+    };
+
+    use super::*;
+
+    fn coin(
+        db: &CachedStore<EmptyStore>,
+        ty: &str,
+        id: ObjectID,
+        version: u64,
+        owner: Owner,
+    ) -> ObjectRef {
+        let object = Object::new_move(
+            MoveObject::new_coin(
+                TypeTag::from_str(ty).unwrap(),
+                SequenceNumber::from_u64(version),
+                id,
+                1,
+            ),
+            owner,
+            TransactionDigest::genesis_marker(),
+        );
+        let object_ref = object.compute_object_reference();
+        db.commit_single_object(object).unwrap();
+        object_ref
+    }
+
+    #[test]
+    fn children_are_owned_by_the_old_root() {
+        let victim = SuiAddress::random_for_testing_only();
+        let attacker = SuiAddress::random_for_testing_only();
+        let parent = ObjectID::random();
+        let old = BTreeMap::from([(
+            parent,
+            (SequenceNumber::from_u64(1), Owner::AddressOwner(victim)),
+        )]);
+        let child = Owner::ObjectOwner(parent.into());
+        assert_eq!(
+            root_owner(&child, |id| old.get(id).map(|(_, owner)| owner.clone())),
+            Some(Owner::AddressOwner(victim))
+        );
+        assert_eq!(
+            root_owner(&child, |_| Some(Owner::AddressOwner(attacker))),
+            Some(Owner::AddressOwner(attacker))
+        );
+        assert_eq!(root_owner(&child, |_| None), None);
+    }
+
+    #[test]
+    fn target_objects_sent_to_others_are_reported_once_per_type() {
+        let db = CachedStore::new(EmptyStore);
+        let attacker = SuiAddress::random_for_testing_only();
+        let victim = Owner::AddressOwner(SuiAddress::random_for_testing_only());
+        let sui = "0x2::sui::SUI";
+        let token = "0xa::token::TOKEN";
+
+        let created = coin(&db, sui, ObjectID::random(), 2, victim.clone());
+        let also_created = coin(&db, sui, ObjectID::random(), 2, victim.clone());
+        let moved = ObjectID::random();
+        coin(&db, token, moved, 1, Owner::AddressOwner(attacker));
+        let moved_ref = coin(&db, token, moved, 2, victim.clone());
+        let kept = coin(
+            &db,
+            token,
+            ObjectID::random(),
+            2,
+            Owner::AddressOwner(attacker),
+        );
+        let transitions = Transitions {
+            old: BTreeMap::from([(
+                moved,
+                (SequenceNumber::from_u64(1), Owner::AddressOwner(attacker)),
+            )]),
+            changed: vec![
+                (created, victim.clone()),
+                (also_created, victim.clone()),
+                (moved_ref, victim.clone()),
+                (kept, Owner::AddressOwner(attacker)),
+            ],
+            ..Default::default()
+        };
+
+        let mut oracle = OwnershipOracle::default();
+        let findings = oracle.check(
+            &db,
+            attacker,
+            &BTreeSet::from([MoveAddress::two()]),
+            &transitions,
+        );
+        let froms = findings
+            .iter()
+            .map(|finding| finding.extra["from"].as_str().unwrap().to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            froms,
+            vec![
+                "created".to_string(),
+                Owner::AddressOwner(attacker).to_string()
+            ]
+        );
+        assert!(
+            oracle
+                .check(
+                    &db,
+                    attacker,
+                    &BTreeSet::from([MoveAddress::two()]),
+                    &transitions
+                )
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn wrapped_ids_are_found_in_contents() {
+        let id = ObjectID::random();
+        let mut contents = vec![0u8; 8];
+        contents.extend_from_slice(id.as_ref());
+        contents.extend_from_slice(&[1, 2, 3]);
+        assert!(holds_id(&contents, &id));
+        assert!(!holds_id(&contents[1..ObjectID::LENGTH + 7], &id));
+        assert!(!holds_id(&contents, &ObjectID::random()));
+    }
+}
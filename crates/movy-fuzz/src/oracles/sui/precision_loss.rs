@@ -5,7 +5,7 @@ use movy_replay::tracer::{
     oracle::SuiGeneralOracle,
 };
 use movy_types::{error::MovyError, oracle::OracleFinding};
-use z3::ast::Ast;
+use z3::ast::{Ast, Dynamic};
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct PrecisionLossOracle;
@@ -28,11 +28,11 @@ impl<S> SuiGeneralOracle<S> for PrecisionLossOracle {
                 }
                 let rhs = &symbol_stack.stack[stack_len - 1];
                 let lhs = &symbol_stack.stack[stack_len - 2];
-                match (lhs, rhs) {
-                    (SymbolValue::Value(l), _) => contains_division(l),
-                    (_, SymbolValue::Value(r)) => contains_division(r),
+                [lhs, rhs].into_iter().any(|sym| match sym {
+                    SymbolValue::Value(v) => contains_division(Dynamic::from(v.clone())),
+                    SymbolValue::Bits(v) => contains_division(Dynamic::from(v.clone())),
                     _ => false,
-                }
+                })
             }
             _ => false,
         };
@@ -54,19 +54,19 @@ impl<S> SuiGeneralOracle<S> for PrecisionLossOracle {
                 "function": current_function.to_string(),
                 "pc": pc,
             });
-            Ok(vec![OracleFinding {
-                oracle: "PrecisionLossOracle".to_string(),
-                severity: movy_types::oracle::Severity::Medium,
-                extra: info,
-            }])
+            Ok(vec![OracleFinding::new(
+                "PrecisionLossOracle",
+                movy_types::oracle::Severity::Medium,
+                info,
+            )])
         } else {
             Ok(vec![])
         }
     }
 }
 
-fn contains_division(expr: &z3::ast::Int) -> bool {
-    let mut stack = vec![z3::ast::Dynamic::from(expr.clone())];
+fn contains_division(expr: Dynamic) -> bool {
+    let mut stack = vec![expr];
     let mut count = 0;
     while let Some(node) = stack.pop() {
         count += 1;
@@ -75,7 +75,10 @@ fn contains_division(expr: &z3::ast::Int) -> bool {
         }
         if let Ok(decl) = node.safe_decl() {
             match decl.kind() {
-                z3::DeclKind::Div | z3::DeclKind::Idiv => return true,
+                z3::DeclKind::Div
+                | z3::DeclKind::Idiv
+                | z3::DeclKind::Budiv
+                | z3::DeclKind::BudivI => return true,
                 _ => {}
             }
         }
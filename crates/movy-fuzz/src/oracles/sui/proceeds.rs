@@ -34,6 +34,17 @@ use crate::{
 #[derive(Debug, Default, Clone)]
 pub struct ProceedsOracle {
     input_objects: Vec<InputObjectKind>,
+    /// Proceeds of a coin at most this large are ignored
+    pub min_amount: u64,
+}
+
+impl ProceedsOracle {
+    pub fn new(min_amount: u64) -> Self {
+        Self {
+            input_objects: vec![],
+            min_amount,
+        }
+    }
 }
 
 pub fn get_balance_changes_from_effect<P: ObjectStore>(
@@ -242,16 +253,18 @@ where
                         bc
                     );
                 }
-                if bc.iter().all(|c| c.amount >= 0) && has_positive {
+                if bc.iter().all(|c| c.amount >= 0)
+                    && bc.iter().any(|c| c.amount > self.min_amount as i128)
+                {
                     debug!("Found proceeds: {:?}", bc);
-                    let finding = OracleFinding {
-                        oracle: "ProceedsOracle".to_string(),
-                        severity: movy_types::oracle::Severity::Critical,
-                        extra: json!({
+                    let finding = OracleFinding::new(
+                        "ProceedsOracle",
+                        movy_types::oracle::Severity::Critical,
+                        json!({
                             "message": "Positive proceeds detected",
                             "balance_changes": bc,
                         }),
-                    };
+                    );
                     return Ok(vec![finding]);
                 }
             }
@@ -0,0 +1,271 @@
+use std::collections::BTreeSet;
+
+use movy_replay::{
+    db::{ObjectStoreInfo, ObjectStoreMintObject},
+    exec::SuiExecutor,
+    tracer::{NopTracer, oracle::SuiGeneralOracle},
+};
+use movy_sui::database::cache::{CachedSnapshot, ObjectSuiStoreCommit};
+use movy_types::{
+    error::MovyError,
+    input::{
+        InputArgument, MoveAddress, MoveSequence, MoveTypeTag, SequenceArgument,
+        SuiObjectInputArgument,
+    },
+    object::MoveOwner,
+    oracle::{OracleFinding, Severity},
+};
+use serde_json::json;
+use sui_json_rpc_types::BalanceChange;
+use sui_types::{
+    base_types::SuiAddress,
+    effects::{TransactionEffects, TransactionEffectsAPI},
+    execution_status::ExecutionStatus,
+    object::Owner,
+    storage::{BackingPackageStore, BackingStore, ObjectStore},
+};
+use tracing::{debug, warn};
+
+use super::{common::effects_snapshot, proceeds::get_balance_changes_from_effect};
+use crate::{
+    executor::refresh_object_refs,
+    meta::{FuzzMetadata, HasFuzzMetadata},
+    round_trip::RoundTrip,
+};
+
+/// Reports round trips of `--round-trips` which leave the attacker with more
+/// than it put in, e.g. a deposit of 1 worth a share which withdraws 2.
+///
+/// After a sequence succeeds, the round trips on the objects it changed run as
+/// a separate attacker transaction on a layer holding a snapshot of those
+/// objects, each with a coin minted for it. Each pair is reported once.
+pub struct RoundingOracle<T> {
+    /// The forked state before fuzzing
+    pub executor: SuiExecutor<T>,
+    reported: BTreeSet<String>,
+}
+
+impl<T> RoundingOracle<T> {
+    pub fn new(executor: SuiExecutor<T>) -> Self {
+        Self {
+            executor,
+            reported: BTreeSet::new(),
+        }
+    }
+}
+
+impl<T> RoundingOracle<T>
+where
+    T: ObjectStore
+        + BackingStore
+        + BackingPackageStore
+        + ObjectSuiStoreCommit
+        + ObjectStoreMintObject
+        + ObjectStoreInfo
+        + Clone,
+{
+    /// How much more of the coin the attacker holds after `trip` on top of
+    /// `snapshot`, `None` if the round trip fails.
+    fn gain(
+        &self,
+        meta: &FuzzMetadata,
+        snapshot: &CachedSnapshot,
+        trip: &RoundTrip,
+    ) -> Result<Option<i128>, MovyError> {
+        let executor = self.executor.layered();
+        executor.db.restore_snapshot(snapshot.clone());
+        let coin = executor.db.mint_coin(
+            trip.coin.clone(),
+            MoveOwner::AddressOwner(meta.attacker),
+            trip.amount,
+        )?;
+        let info = executor.db.get_move_object_info(coin)?;
+
+        let mut sequence = trip.sequence.clone();
+        refresh_object_refs(&executor.db, &mut sequence);
+        let coin_input = SequenceArgument::Input(sequence.inputs.len() as u16);
+        sequence.inputs.push(InputArgument::Object(
+            info.ty.clone(),
+            SuiObjectInputArgument::ImmOrOwnedObject(info.sui_reference()),
+        ));
+        spend_coin(&mut sequence, coin_input);
+
+        let Ok(out) = executor.run_ptb_with_gas(
+            sequence.to_ptb()?,
+            meta.epoch,
+            meta.epoch_ms,
+            meta.attacker.into(),
+            meta.gas_id.into(),
+            None::<NopTracer>,
+        ) else {
+            return Ok(None);
+        };
+        let effects = out.results.effects;
+        if !matches!(effects.status(), ExecutionStatus::Success) {
+            debug!(
+                "Round trip {} of {} failed: {:?}",
+                trip.name,
+                trip.amount,
+                effects.status()
+            );
+            return Ok(None);
+        }
+        executor.db.commit_store(out.results.store, &effects)?;
+        let Some(changes) =
+            get_balance_changes_from_effect(&executor.db, &effects, vec![], meta.gas_id.into())
+        else {
+            return Ok(None);
+        };
+        Ok(Some(attacker_gain(&changes, meta.attacker, &trip.coin)))
+    }
+}
+
+/// Make every command of `sequence` spend `coin` instead of the gas coin.
+fn spend_coin(sequence: &mut MoveSequence, coin: SequenceArgument) {
+    for cmd in sequence.commands.iter_mut() {
+        for arg in cmd.arguments_mut() {
+            if matches!(arg, SequenceArgument::GasCoin) {
+                *arg = coin;
+            }
+        }
+    }
+}
+
+/// How much of `coin` the attacker gained over `changes`.
+fn attacker_gain(changes: &[BalanceChange], attacker: MoveAddress, coin: &MoveTypeTag) -> i128 {
+    let attacker = Owner::AddressOwner(SuiAddress::from(attacker));
+    changes
+        .iter()
+        .filter(|change| {
+            change.owner == attacker && MoveTypeTag::from(change.coin_type.clone()) == *coin
+        })
+        .map(|change| change.amount)
+        .sum()
+}
+
+impl<S, E> SuiGeneralOracle<S> for RoundingOracle<E>
+where
+    S: HasFuzzMetadata,
+    E: ObjectStore
+        + BackingStore
+        + BackingPackageStore
+        + ObjectSuiStoreCommit
+        + ObjectStoreMintObject
+        + ObjectStoreInfo
+        + Clone,
+{
+    fn done_execution<T: ObjectStore>(
+        &mut self,
+        db: T,
+        state: &mut S,
+        effects: &TransactionEffects,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        let meta = state.fuzz_state();
+        if meta.round_trips.is_empty() || !matches!(effects.status(), ExecutionStatus::Success) {
+            return Ok(vec![]);
+        }
+        let (snapshot, changed) = effects_snapshot(&db, effects);
+
+        let mut findings = vec![];
+        for trip in meta.round_trips.iter() {
+            if self.reported.contains(&trip.name) {
+                continue;
+            }
+            let touched = trip.sequence.inputs.iter().any(|input| {
+                matches!(input, InputArgument::Object(_, object) if changed.contains(&object.id()))
+            });
+            if !touched {
+                continue;
+            }
+            let Some(gain) = self.gain(meta, &snapshot, trip)? else {
+                continue;
+            };
+            if gain <= 0 {
+                continue;
+            }
+            warn!(
+                "Round trip {} returns {} more than {} after the attacker sequence",
+                trip.name, gain, trip.amount
+            );
+            findings.push(OracleFinding::new(
+                "RoundingOracle",
+                Severity::Major,
+                json!({
+                    "kind": "favorable_rounding",
+                    "round_trip": trip.name,
+                    "coin_type": trip.coin.to_string(),
+                    "amount": trip.amount,
+                    "gain": gain.to_string(),
+                }),
+            ));
+            self.reported.insert(trip.name.clone());
+        }
+        Ok(findings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use movy_types::input::{MoveCall, MoveSequenceCall};
+    use sui_types::TypeTag;
+
+    use super::*;
+
+    #[test]
+    fn the_coin_replaces_the_gas_coin_everywhere() {
+        let coin = SequenceArgument::Input(1);
+        let mut sequence = MoveSequence {
+            inputs: vec![InputArgument::U64(1)],
+            commands: vec![
+                MoveSequenceCall::SplitCoins(
+                    SequenceArgument::GasCoin,
+                    vec![SequenceArgument::Input(0)],
+                ),
+                MoveSequenceCall::MergeCoins(
+                    SequenceArgument::Result(0),
+                    vec![SequenceArgument::GasCoin],
+                ),
+                MoveSequenceCall::Call(MoveCall {
+                    module_id: MoveAddress::from_str("0xa").unwrap(),
+                    module_name: "vault".to_string(),
+                    function: "deposit".to_string(),
+                    type_arguments: vec![],
+                    arguments: vec![SequenceArgument::GasCoin],
+                }),
+                MoveSequenceCall::TransferObjects(
+                    vec![SequenceArgument::GasCoin],
+                    SequenceArgument::Input(0),
+                ),
+            ],
+        };
+        spend_coin(&mut sequence, coin);
+        assert!(sequence.commands.iter().all(|cmd| {
+            cmd.arguments()
+                .into_iter()
+                .all(|arg| !matches!(arg, SequenceArgument::GasCoin))
+        }));
+        assert_eq!(sequence.commands[0].arguments()[0], &coin);
+        assert_eq!(sequence.commands[1].arguments()[1], &coin);
+    }
+
+    #[test]
+    fn only_the_attackers_coin_counts_as_gain() {
+        let attacker = MoveAddress::two();
+        let coin = MoveTypeTag::from_str("0x2::sui::SUI").unwrap();
+        let change = |owner: MoveAddress, coin_type: &str, amount: i128| BalanceChange {
+            owner: Owner::AddressOwner(owner.into()),
+            coin_type: TypeTag::from_str(coin_type).unwrap(),
+            amount,
+        };
+        let changes = vec![
+            change(attacker, "0x2::sui::SUI", 10),
+            change(attacker, "0x2::sui::SUI", -3),
+            change(attacker, "0x5::usdc::USDC", 100),
+            change(MoveAddress::from_str("0xb").unwrap(), "0x2::sui::SUI", 50),
+        ];
+        assert_eq!(attacker_gain(&changes, attacker, &coin), 7);
+        assert_eq!(attacker_gain(&[], attacker, &coin), 0);
+    }
+}
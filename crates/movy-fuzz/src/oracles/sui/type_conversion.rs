@@ -25,12 +25,12 @@ impl<S> SuiGeneralOracle<S> for TypeConversionOracle {
         }
         let val = &stack[stack.len() - 1];
         let unnecessary = match instruction {
-            Bytecode::CastU8 => value_bitwidth(val) == 8,
-            Bytecode::CastU16 => value_bitwidth(val) == 16,
-            Bytecode::CastU32 => value_bitwidth(val) == 32,
-            Bytecode::CastU64 => value_bitwidth(val) == 64,
-            Bytecode::CastU128 => value_bitwidth(val) == 128,
-            Bytecode::CastU256 => value_bitwidth(val) == 256,
+            Bytecode::CastU8 => value_bitwidth(val) == Some(8),
+            Bytecode::CastU16 => value_bitwidth(val) == Some(16),
+            Bytecode::CastU32 => value_bitwidth(val) == Some(32),
+            Bytecode::CastU64 => value_bitwidth(val) == Some(64),
+            Bytecode::CastU128 => value_bitwidth(val) == Some(128),
+            Bytecode::CastU256 => value_bitwidth(val) == Some(256),
             _ => false,
         };
         if unnecessary {
@@ -39,11 +39,11 @@ impl<S> SuiGeneralOracle<S> for TypeConversionOracle {
                 "function": current_function.to_string(),
                 "pc": pc,
             });
-            return Ok(vec![OracleFinding {
-                oracle: "TypeConversionOracle".to_string(),
-                severity: movy_types::oracle::Severity::Minor,
-                extra: info,
-            }]);
+            return Ok(vec![OracleFinding::new(
+                "TypeConversionOracle",
+                movy_types::oracle::Severity::Minor,
+                info,
+            )]);
         }
         Ok(vec![])
     }
@@ -11,6 +11,7 @@ use sui_types::{
 
 use crate::{
     meta::HasFuzzMetadata,
+    operations::sui_test::keyed_crash_reason,
     state::{ExtraNonSerdeFuzzState, HasExtraState},
 };
 
@@ -45,13 +46,13 @@ where
                     ..
                 } if *code == TYPED_BUG_ABORT_CODE => {
                     debug!("Typed bug abort detected: code {}", code);
-                    return Ok(vec![OracleFinding {
-                        oracle: "TypedBugOracle".to_string(),
-                        severity: movy_types::oracle::Severity::Critical,
-                        extra: json!({
+                    return Ok(vec![OracleFinding::new(
+                        "TypedBugOracle",
+                        movy_types::oracle::Severity::Critical,
+                        json!({
                             "abort_code": code,
                         }),
-                    }]);
+                    )]);
                 }
                 _ => return Ok(Vec::new()),
             }
@@ -65,13 +66,15 @@ where
         for event in &global_outcome.exec.events {
             if event.ty.module == "oracle" && event.ty.name == "Crash" {
                 debug!("Typed bug event detected: {:?}", event);
-                return Ok(vec![OracleFinding {
-                    oracle: "TypedBugOracle".to_string(),
-                    severity: movy_types::oracle::Severity::Critical,
-                    extra: json!({
+                return Ok(vec![OracleFinding::new(
+                    "TypedBugOracle",
+                    movy_types::oracle::Severity::Critical,
+                    json!({
+                        "event_type": event.ty.to_string(),
+                        "crash_reason": keyed_crash_reason(&event.contents),
                         "event": event,
                     }),
-                }]);
+                )]);
             }
         }
         Ok(vec![])
@@ -0,0 +1,366 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use movy_replay::{
+    db::{ObjectStoreInfo, ObjectStoreMintObject},
+    exec::SuiExecutor,
+    tracer::{NopTracer, oracle::SuiGeneralOracle},
+};
+use movy_sui::database::cache::{CachedSnapshot, ObjectSuiStoreCommit};
+use movy_types::{
+    error::MovyError,
+    input::{
+        InputArgument, MoveAddress, MoveCall, MoveSequence, MoveSequenceCall, SequenceArgument,
+    },
+    oracle::{OracleFinding, Severity},
+};
+use serde_json::json;
+use sui_types::{
+    base_types::ObjectID,
+    effects::{TransactionEffects, TransactionEffectsAPI},
+    execution_status::{ExecutionFailureStatus, ExecutionStatus},
+    storage::{BackingPackageStore, BackingStore, ObjectStore},
+};
+use tracing::warn;
+
+use crate::{
+    executor::refresh_object_refs,
+    meta::{FuzzMetadata, HasFuzzMetadata},
+};
+
+/// Compares both versions of a package upgraded mid-input, see
+/// [`UpgradePlan`](crate::upgrade::UpgradePlan). A sequence is replayed right
+/// after the upgrade with its calls pointed at the other version, and
+/// reported when
+///
+/// - version N+1 aborts on an object created before the upgrade which version
+///   N accepts, so the object is unusable after the upgrade;
+/// - version N accepts a call which version N+1 aborts on, so the checks of
+///   the new version can be bypassed through the old one;
+/// - a call succeeds on both versions but leaves the objects created before
+///   the upgrade in different states, so version N+1 breaks an invariant
+///   version N kept on them.
+///
+/// Replays skip the prelude and the state patches of an input. Does nothing
+/// without an upgrade.
+pub struct UpgradeOracle<T> {
+    /// The forked state before the upgrade
+    pub executor: SuiExecutor<T>,
+    /// The objects the upgrade changes, published once and layered above
+    /// the forked state for every replay
+    upgraded: Option<CachedSnapshot>,
+    sequence: Option<MoveSequence>,
+    reported: BTreeSet<String>,
+}
+
+impl<T> UpgradeOracle<T>
+where
+    T: ObjectStore
+        + BackingStore
+        + BackingPackageStore
+        + ObjectSuiStoreCommit
+        + ObjectStoreMintObject
+        + ObjectStoreInfo
+        + Clone,
+{
+    pub fn new(executor: SuiExecutor<T>, meta: &FuzzMetadata) -> Result<Self, MovyError> {
+        let upgraded = meta
+            .upgrade
+            .as_ref()
+            .map(|plan| plan.publish(&executor, meta.epoch, meta.epoch_ms))
+            .transpose()?;
+        Ok(Self {
+            executor,
+            upgraded,
+            sequence: None,
+            reported: BTreeSet::new(),
+        })
+    }
+
+    /// Outcome of `sequence` right after the upgrade, `None` if it can not
+    /// run.
+    fn replay(
+        &self,
+        meta: &FuzzMetadata,
+        sequence: &MoveSequence,
+    ) -> Result<Option<Replayed>, MovyError> {
+        let Some(upgraded) = self.upgraded.as_ref() else {
+            return Ok(None);
+        };
+        let executor = self.executor.layered();
+        executor.db.restore_snapshot(upgraded.clone());
+        let mut sequence = sequence.clone();
+        refresh_object_refs(&executor.db, &mut sequence);
+        let Ok(out) = executor.run_ptb_with_gas(
+            sequence.to_ptb()?,
+            meta.epoch,
+            meta.epoch_ms,
+            meta.attacker.into(),
+            meta.gas_id.into(),
+            None::<NopTracer>,
+        ) else {
+            return Ok(None);
+        };
+        let effects = &out.results.effects;
+        // Objects from before the upgrade, the gas coin pays differently
+        // on each version
+        let existing = |id: &ObjectID| {
+            *id != ObjectID::from(meta.gas_id) && self.executor.db.get_object(id).is_some()
+        };
+        let mut objects = BTreeMap::new();
+        for (r, _, _) in effects.all_changed_objects() {
+            if existing(&r.0) {
+                let contents = out
+                    .results
+                    .store
+                    .written
+                    .get(&r.0)
+                    .and_then(|o| o.data.try_as_move())
+                    .map(|o| o.contents().to_vec());
+                objects.insert(r.0, contents);
+            }
+        }
+        for (r, _) in effects.all_removed_objects() {
+            if existing(&r.0) {
+                objects.insert(r.0, None);
+            }
+        }
+        Ok(Some(Replayed {
+            status: effects.status().clone(),
+            objects,
+        }))
+    }
+}
+
+/// What a replay did, see [`UpgradeOracle::replay`].
+struct Replayed {
+    status: ExecutionStatus,
+    /// Contents the objects created before the upgrade were left with, None
+    /// when deleted or wrapped
+    objects: BTreeMap<ObjectID, Option<Vec<u8>>>,
+}
+
+fn aborted(replayed: &Option<Replayed>) -> bool {
+    matches!(
+        replayed,
+        Some(Replayed {
+            status: ExecutionStatus::Failure {
+                error: ExecutionFailureStatus::MoveAbort(..),
+                ..
+            },
+            ..
+        })
+    )
+}
+
+fn succeeded(replayed: &Option<Replayed>) -> bool {
+    matches!(
+        replayed,
+        Some(Replayed {
+            status: ExecutionStatus::Success,
+            ..
+        })
+    )
+}
+
+/// Objects both versions left in different states.
+fn diverging_objects(
+    old: &BTreeMap<ObjectID, Option<Vec<u8>>>,
+    new: &BTreeMap<ObjectID, Option<Vec<u8>>>,
+) -> Vec<ObjectID> {
+    old.keys()
+        .chain(new.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter(|id| old.get(id) != new.get(id))
+        .copied()
+        .collect()
+}
+
+fn call_name(call: &MoveCall) -> String {
+    format!("{}::{}", call.module_name, call.function)
+}
+
+impl<S, E> SuiGeneralOracle<S> for UpgradeOracle<E>
+where
+    S: HasFuzzMetadata,
+    E: ObjectStore
+        + BackingStore
+        + BackingPackageStore
+        + ObjectSuiStoreCommit
+        + ObjectStoreMintObject
+        + ObjectStoreInfo
+        + Clone,
+{
+    fn pre_execution<T: ObjectStore>(
+        &mut self,
+        _db: T,
+        state: &mut S,
+        sequence: &MoveSequence,
+    ) -> Result<(), MovyError> {
+        if state.fuzz_state().upgrade.is_some() {
+            self.sequence = Some(sequence.clone());
+        }
+        Ok(())
+    }
+
+    fn done_execution<T: ObjectStore>(
+        &mut self,
+        _db: T,
+        state: &mut S,
+        effects: &TransactionEffects,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        let Some(sequence) = self.sequence.take() else {
+            return Ok(vec![]);
+        };
+        let meta = state.fuzz_state();
+        let Some(plan) = meta.upgrade.as_ref() else {
+            return Ok(vec![]);
+        };
+
+        let finding = match effects.status() {
+            ExecutionStatus::Failure {
+                error: ExecutionFailureStatus::MoveAbort(..),
+                command: Some(idx),
+            } => {
+                // Version N+1 aborted, on an object from before the upgrade?
+                let Some(MoveSequenceCall::Call(call)) = sequence.commands.get(*idx) else {
+                    return Ok(vec![]);
+                };
+                if call.module_id != plan.upgraded {
+                    return Ok(vec![]);
+                }
+                let objects = call
+                    .arguments
+                    .iter()
+                    .filter_map(|arg| match arg {
+                        SequenceArgument::Input(i) => sequence.inputs.get(*i as usize),
+                        _ => None,
+                    })
+                    .filter_map(|input| match input {
+                        InputArgument::Object(_, object) => Some(object.id()),
+                        _ => None,
+                    })
+                    .filter(|id| self.executor.db.get_object(id).is_some())
+                    .collect::<Vec<_>>();
+                let key = format!("unusable_object {}", call_name(call));
+                if objects.is_empty() || self.reported.contains(&key) {
+                    return Ok(vec![]);
+                }
+                let old = self.replay(meta, &plan.downgrade(&sequence))?;
+                if !succeeded(&old) || !aborted(&self.replay(meta, &sequence)?) {
+                    return Ok(vec![]);
+                }
+                warn!(
+                    "Objects {:?} are rejected by {} after the upgrade:\n{}",
+                    objects,
+                    call_name(call),
+                    sequence
+                );
+                (
+                    key,
+                    Severity::Minor,
+                    json!({
+                        "kind": "unusable_object",
+                        "function": call_name(call),
+                        "objects": objects,
+                        "new_status": format!("{:?}", effects.status()),
+                    }),
+                )
+            }
+            ExecutionStatus::Success => {
+                let find_call = |package: MoveAddress| {
+                    sequence.commands.iter().find_map(|cmd| match cmd {
+                        MoveSequenceCall::Call(call) if call.module_id == package => Some(call),
+                        _ => None,
+                    })
+                };
+                if let Some(call) = find_call(plan.package) {
+                    // Version N still accepts a call version N+1 would reject?
+                    let key = format!("version_gate_bypass {}", call_name(call));
+                    if self.reported.contains(&key) {
+                        return Ok(vec![]);
+                    }
+                    let new = self.replay(meta, &plan.upgrade(&sequence))?;
+                    if !aborted(&new) || !succeeded(&self.replay(meta, &sequence)?) {
+                        return Ok(vec![]);
+                    }
+                    warn!(
+                        "{} of the old version accepts what the upgrade rejects:\n{}",
+                        call_name(call),
+                        sequence
+                    );
+                    (
+                        key,
+                        Severity::Major,
+                        json!({
+                            "kind": "version_gate_bypass",
+                            "function": call_name(call),
+                            "new_status": format!("{:?}", new.map(|r| r.status)),
+                        }),
+                    )
+                } else if let Some(call) = find_call(plan.upgraded) {
+                    // Version N+1 changes objects from before the upgrade
+                    // differently than version N?
+                    let key = format!("broken_invariant {}", call_name(call));
+                    if self.reported.contains(&key) {
+                        return Ok(vec![]);
+                    }
+                    let new = self.replay(meta, &sequence)?;
+                    let old = self.replay(meta, &plan.downgrade(&sequence))?;
+                    let (Some(new), Some(old)) = (new, old) else {
+                        return Ok(vec![]);
+                    };
+                    if !matches!(new.status, ExecutionStatus::Success)
+                        || !matches!(old.status, ExecutionStatus::Success)
+                    {
+                        return Ok(vec![]);
+                    }
+                    let objects = diverging_objects(&old.objects, &new.objects);
+                    if objects.is_empty() {
+                        return Ok(vec![]);
+                    }
+                    warn!(
+                        "{} leaves objects {:?} differently than the old version:\n{}",
+                        call_name(call),
+                        objects,
+                        sequence
+                    );
+                    (
+                        key,
+                        Severity::Minor,
+                        json!({
+                            "kind": "broken_invariant",
+                            "function": call_name(call),
+                            "objects": objects,
+                        }),
+                    )
+                } else {
+                    return Ok(vec![]);
+                }
+            }
+            _ => return Ok(vec![]),
+        };
+
+        let (key, severity, extra) = finding;
+        self.reported.insert(key);
+        Ok(vec![OracleFinding::new("UpgradeOracle", severity, extra)])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn objects_diverge_on_contents_and_deletion() {
+        let (a, b, c) = (
+            ObjectID::from_single_byte(1),
+            ObjectID::from_single_byte(2),
+            ObjectID::from_single_byte(3),
+        );
+        let old = BTreeMap::from([(a, Some(vec![1])), (b, Some(vec![2]))]);
+        let new = BTreeMap::from([(a, Some(vec![1])), (b, None), (c, Some(vec![3]))]);
+        assert_eq!(diverging_objects(&old, &new), vec![b, c]);
+        assert!(diverging_objects(&old, &old).is_empty());
+    }
+}
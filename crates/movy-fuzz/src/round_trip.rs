@@ -0,0 +1,178 @@
+use std::{collections::BTreeMap, path::Path};
+
+use color_eyre::eyre::eyre;
+use movy_replay::db::ObjectStoreInfo;
+use movy_types::{
+    error::MovyError,
+    input::{MoveAddress, MoveSequence, MoveTypeTag},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    meta::FuzzMetadata,
+    seed::{SeedCall, SeedSequence},
+};
+
+/// Paired functions for `--round-trips`, in TOML or JSON:
+///
+/// ```toml
+/// [[pairs]]
+/// name = "vault-shares"
+/// coin = "0x2::sui::SUI"
+/// amounts = [1, 1000, 1000000000]
+///
+/// [[pairs.enter]]
+/// function = "my_pkg::vault::deposit"
+/// type_arguments = ["0x2::sui::SUI"]
+/// arguments = ["0x1234", "coin"]
+///
+/// [[pairs.exit]]
+/// function = "my_pkg::vault::withdraw_all"
+/// type_arguments = ["0x2::sui::SUI"]
+/// arguments = ["0x1234", "result:0"]
+///
+/// [[pairs.exit]]
+/// function = "0x2::transfer::public_transfer"
+/// type_arguments = ["0x2::coin::Coin<0x2::sui::SUI>"]
+/// arguments = ["result:1", "attacker"]
+/// ```
+///
+/// Calls are written as in seed sequences. `coin` stands for a fresh coin of
+/// `amount` owned by the attacker, `amount` for the amount itself and
+/// `attacker` for the attacker address. Values the calls return must be
+/// consumed or transferred. A round trip is reported when the attacker gets
+/// back more of `coin` than it put in.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoundTripConfig {
+    pub pairs: Vec<RoundTripPairConfig>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoundTripPairConfig {
+    pub name: String,
+    pub coin: String,
+    pub amounts: Vec<u64>,
+    pub enter: Vec<SeedCall>,
+    pub exit: Vec<SeedCall>,
+}
+
+/// One round trip of a pair with a fixed amount. The gas coin in `sequence`
+/// stands for the coin put in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoundTrip {
+    pub name: String,
+    pub coin: MoveTypeTag,
+    pub amount: u64,
+    pub sequence: MoveSequence,
+}
+
+/// The calls of `pair` for `amount`, with the placeholders filled in and
+/// `coin` standing for the gas coin.
+fn trip_calls(
+    pair: &RoundTripPairConfig,
+    amount: u64,
+    attacker: MoveAddress,
+) -> Result<Vec<SeedCall>, MovyError> {
+    let amount = amount.to_string();
+    let attacker = attacker.to_string();
+    let mut calls = vec![];
+    for call in pair.enter.iter().chain(pair.exit.iter()) {
+        let mut arguments = vec![];
+        for arg in call.arguments.iter() {
+            let arg = match arg.trim() {
+                "gas" => {
+                    return Err(eyre!("round trip {} can not use the gas coin", pair.name).into());
+                }
+                "coin" => "gas".to_string(),
+                "amount" => amount.clone(),
+                "attacker" => attacker.clone(),
+                _ => arg.clone(),
+            };
+            arguments.push(arg);
+        }
+        calls.push(SeedCall {
+            arguments,
+            ..call.clone()
+        });
+    }
+    Ok(calls)
+}
+
+impl RoundTripConfig {
+    pub fn from_file(path: &Path) -> Result<Self, MovyError> {
+        let content = std::fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(toml::from_str(&content)?)
+        }
+    }
+
+    pub fn build(
+        &self,
+        meta: &FuzzMetadata,
+        store: &impl ObjectStoreInfo,
+        names: &BTreeMap<String, MoveAddress>,
+        resolve_type: &impl Fn(&str) -> Result<MoveTypeTag, MovyError>,
+    ) -> Result<Vec<RoundTrip>, MovyError> {
+        let mut out = vec![];
+        for pair in self.pairs.iter() {
+            if pair.enter.is_empty() || pair.exit.is_empty() {
+                return Err(eyre!("round trip {} needs enter and exit calls", pair.name).into());
+            }
+            let coin = resolve_type(&pair.coin)?;
+            for amount in pair.amounts.iter() {
+                let calls = trip_calls(pair, *amount, meta.attacker)?;
+                let sequence = SeedSequence { calls }
+                    .to_sequence(meta, store, names, resolve_type)
+                    .map_err(|e| eyre!("round trip {}: {}", pair.name, e))?;
+                out.push(RoundTrip {
+                    name: pair.name.clone(),
+                    coin: coin.clone(),
+                    amount: *amount,
+                    sequence,
+                });
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed_call(function: &str, arguments: &[&str]) -> SeedCall {
+        SeedCall {
+            function: function.to_string(),
+            type_arguments: vec![],
+            arguments: arguments.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn placeholders_are_filled_per_amount() {
+        let attacker = MoveAddress::two();
+        let mut pair = RoundTripPairConfig {
+            name: "vault".to_string(),
+            coin: "0x2::sui::SUI".to_string(),
+            amounts: vec![7],
+            enter: vec![seed_call("pkg::vault::deposit", &["0x1234", " coin "])],
+            exit: vec![
+                seed_call("pkg::vault::withdraw", &["0x1234", "result:0", "amount"]),
+                seed_call("0x2::transfer::public_transfer", &["result:1", "attacker"]),
+            ],
+        };
+        let calls = trip_calls(&pair, 7, attacker).unwrap();
+        assert_eq!(calls.len(), 3);
+        assert_eq!(calls[0].arguments, vec!["0x1234", "gas"]);
+        assert_eq!(calls[1].arguments, vec!["0x1234", "result:0", "7"]);
+        assert_eq!(
+            calls[2].arguments,
+            vec!["result:1".to_string(), attacker.to_string()]
+        );
+
+        pair.exit[0].arguments[1] = "gas".to_string();
+        assert!(trip_calls(&pair, 7, attacker).is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use crate::input::MoveInput;
+use crate::{directed::DirectedMetadata, input::MoveInput};
 use libafl::{
     HasMetadata,
     corpus::{SchedulerTestcaseMetadata, Testcase},
@@ -85,6 +85,16 @@ where
             weight /= 4.0f64
         }
 
+        // Directed campaigns favor inputs getting closer to the target location
+        if let Ok(directed) = state.metadata::<DirectedMetadata>() {
+            let distance = entry
+                .input()
+                .as_ref()
+                .and_then(|input| input.outcome().as_ref())
+                .and_then(|outcome| outcome.distance);
+            weight *= directed.power_factor(distance);
+        }
+
         if !weight.is_normal() {
             return Err(libafl::Error::illegal_state(
                 format!(
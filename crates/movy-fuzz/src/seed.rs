@@ -0,0 +1,366 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use alloy_primitives::{U128, U256};
+use color_eyre::eyre::eyre;
+use movy_replay::db::ObjectStoreInfo;
+use movy_types::{
+    abi::MoveAbiSignatureToken,
+    error::MovyError,
+    input::{
+        InputArgument, MoveAddress, MoveCall, MoveSequence, MoveSequenceCall, MoveTypeTag,
+        SequenceArgument, SuiObjectInputArgument,
+    },
+    object::MoveOwner,
+};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::{input::MoveFuzzInput, meta::FuzzMetadata};
+
+/// A hand-written seed for `--seeds`, in TOML or JSON:
+///
+/// ```toml
+/// [[calls]]
+/// function = "my_pkg::pool::deposit"
+/// type_arguments = ["0x2::sui::SUI"]
+/// arguments = ["0x1234", "gas", "1000"]
+///
+/// [[calls]]
+/// function = "my_pkg::pool::withdraw"
+/// arguments = ["0x1234", "result:0"]
+/// ```
+///
+/// Arguments are typed by the function parameters. Object parameters take an
+/// object id, `gas` is the gas coin, and `result:N` / `nested:N:M` refer to the
+/// results of earlier calls. Vectors are written as `[a, b]`, `vector<u8>`
+/// additionally accepts `0x` hex or a plain string.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SeedSequence {
+    pub calls: Vec<SeedCall>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeedCall {
+    /// `package::module::function`, where the package is an address or a
+    /// package name
+    pub function: String,
+    #[serde(default)]
+    pub type_arguments: Vec<String>,
+    #[serde(default)]
+    pub arguments: Vec<String>,
+}
+
+impl SeedSequence {
+    pub fn from_file(path: &Path) -> Result<Self, MovyError> {
+        let content = std::fs::read_to_string(path)?;
+        if path.extension().is_some_and(|ext| ext == "json") {
+            Ok(serde_json::from_str(&content)?)
+        } else {
+            Ok(toml::from_str(&content)?)
+        }
+    }
+
+    pub fn to_sequence(
+        &self,
+        meta: &FuzzMetadata,
+        store: &impl ObjectStoreInfo,
+        names: &BTreeMap<String, MoveAddress>,
+        resolve_type: &impl Fn(&str) -> Result<MoveTypeTag, MovyError>,
+    ) -> Result<MoveSequence, MovyError> {
+        let mut sequence = MoveSequence {
+            inputs: vec![],
+            commands: vec![],
+        };
+        for (idx, call) in self.calls.iter().enumerate() {
+            let cmd = call
+                .to_call(meta, store, names, resolve_type, &mut sequence)
+                .map_err(|e| eyre!("call #{} {}: {}", idx, call.function, e))?;
+            sequence.commands.push(MoveSequenceCall::Call(cmd));
+        }
+        Ok(sequence)
+    }
+}
+
+impl SeedCall {
+    fn to_call(
+        &self,
+        meta: &FuzzMetadata,
+        store: &impl ObjectStoreInfo,
+        names: &BTreeMap<String, MoveAddress>,
+        resolve_type: &impl Fn(&str) -> Result<MoveTypeTag, MovyError>,
+        sequence: &mut MoveSequence,
+    ) -> Result<MoveCall, MovyError> {
+        let parts = self.function.split("::").collect::<Vec<_>>();
+        let [package, module, function] = parts.as_slice() else {
+            return Err(eyre!("function should be package::module::function").into());
+        };
+        let package = match names.get(*package) {
+            Some(address) => *address,
+            None => MoveAddress::from_str(package)?,
+        };
+        let abi = meta
+            .get_function(&package, module, function)
+            .ok_or_else(|| eyre!("function is not found"))?;
+        if abi.type_parameters.len() != self.type_arguments.len() {
+            return Err(eyre!(
+                "expect {} type arguments but {} given",
+                abi.type_parameters.len(),
+                self.type_arguments.len()
+            )
+            .into());
+        }
+        let type_arguments = self
+            .type_arguments
+            .iter()
+            .map(|ty| resolve_type(ty))
+            .collect::<Result<Vec<_>, _>>()?;
+        let ty_args = type_arguments
+            .iter()
+            .enumerate()
+            .map(|(idx, ty)| (idx as u16, ty.clone()))
+            .collect::<BTreeMap<_, _>>();
+
+        let parameters = abi
+            .parameters
+            .iter()
+            .filter(|param| !param.is_tx_context())
+            .collect::<Vec<_>>();
+        if parameters.len() != self.arguments.len() {
+            return Err(eyre!(
+                "expect {} arguments but {} given",
+                parameters.len(),
+                self.arguments.len()
+            )
+            .into());
+        }
+
+        let mut arguments = vec![];
+        for (param, raw) in parameters.into_iter().zip(self.arguments.iter()) {
+            let raw = raw.trim();
+            let arg = if raw == "gas" {
+                SequenceArgument::GasCoin
+            } else if let Some(result) = raw.strip_prefix("result:") {
+                SequenceArgument::Result(parse_index(result)?)
+            } else if let Some(nested) = raw.strip_prefix("nested:") {
+                let (cmd, idx) = nested
+                    .split_once(':')
+                    .ok_or_else(|| eyre!("nested result should be nested:N:M, got {}", raw))?;
+                SequenceArgument::NestedResult(parse_index(cmd)?, parse_index(idx)?)
+            } else {
+                let input = parse_input(param, raw, &ty_args, store)?;
+                sequence.inputs.push(input);
+                SequenceArgument::Input((sequence.inputs.len() - 1) as u16)
+            };
+            arguments.push(arg);
+        }
+
+        Ok(MoveCall {
+            module_id: package,
+            module_name: module.to_string(),
+            function: function.to_string(),
+            type_arguments,
+            arguments,
+        })
+    }
+}
+
+fn parse_index(raw: &str) -> Result<u16, MovyError> {
+    raw.trim()
+        .parse::<u16>()
+        .map_err(|e| eyre!("can not parse index {} with {}", raw, e).into())
+}
+
+fn parse_input(
+    param: &MoveAbiSignatureToken,
+    raw: &str,
+    ty_args: &BTreeMap<u16, MoveTypeTag>,
+    store: &impl ObjectStoreInfo,
+) -> Result<InputArgument, MovyError> {
+    let (inner, immutable_ref) = match param {
+        MoveAbiSignatureToken::Reference(inner) => (inner.as_ref(), true),
+        MoveAbiSignatureToken::MutableReference(inner) => (inner.as_ref(), false),
+        _ => (param, false),
+    };
+    let ty = inner
+        .subst(ty_args)
+        .ok_or_else(|| eyre!("can not instantiate parameter {:?}", param))?;
+    if matches!(ty, MoveTypeTag::Struct(_)) {
+        let id = MoveAddress::from_str(raw)?;
+        let info = store.get_move_object_info(id)?;
+        return match info.owner {
+            MoveOwner::AddressOwner(_) | MoveOwner::Immutable => Ok(InputArgument::Object(
+                info.ty.clone(),
+                SuiObjectInputArgument::ImmOrOwnedObject(info.sui_reference()),
+            )),
+            MoveOwner::Shared {
+                initial_shared_version,
+            } => Ok(InputArgument::Object(
+                info.ty,
+                SuiObjectInputArgument::shared_object(id, initial_shared_version, !immutable_ref),
+            )),
+            other => Err(eyre!("object {} has unsupported owner {:?}", id, other).into()),
+        };
+    }
+    parse_pure(&ty, raw)
+}
+
+fn parse_pure(ty: &MoveTypeTag, raw: &str) -> Result<InputArgument, MovyError> {
+    let raw = raw.trim();
+    let err = |e: &dyn std::fmt::Display| eyre!("can not parse {} as {} with {}", raw, ty, e);
+    let value = match ty {
+        MoveTypeTag::Bool => InputArgument::Bool(raw.parse().map_err(|e| err(&e))?),
+        MoveTypeTag::U8 => InputArgument::U8(parse_uint(raw, 8).map_err(|e| err(&e))?.to()),
+        MoveTypeTag::U16 => InputArgument::U16(parse_uint(raw, 16).map_err(|e| err(&e))?.to()),
+        MoveTypeTag::U32 => InputArgument::U32(parse_uint(raw, 32).map_err(|e| err(&e))?.to()),
+        MoveTypeTag::U64 => InputArgument::U64(parse_uint(raw, 64).map_err(|e| err(&e))?.to()),
+        MoveTypeTag::U128 => {
+            InputArgument::U128(U128::from(parse_uint(raw, 128).map_err(|e| err(&e))?))
+        }
+        MoveTypeTag::U256 => InputArgument::U256(parse_uint(raw, 256).map_err(|e| err(&e))?),
+        MoveTypeTag::Address => InputArgument::Address(MoveAddress::from_str(raw)?),
+        MoveTypeTag::Vector(inner) if **inner == MoveTypeTag::U8 && !raw.starts_with('[') => {
+            let bytes = match raw.strip_prefix("0x") {
+                Some(hex) => const_hex::decode(hex).map_err(|e| err(&e))?,
+                None => raw.as_bytes().to_vec(),
+            };
+            InputArgument::Vector(
+                MoveTypeTag::U8,
+                bytes.into_iter().map(InputArgument::U8).collect(),
+            )
+        }
+        MoveTypeTag::Vector(inner) => {
+            let body = raw
+                .strip_prefix('[')
+                .and_then(|s| s.strip_suffix(']'))
+                .ok_or_else(|| err(&"vectors should be written as [a, b]"))?;
+            let elements = split_elements(body)
+                .into_iter()
+                .map(|element| parse_pure(inner, element))
+                .collect::<Result<Vec<_>, _>>()?;
+            InputArgument::Vector(inner.as_ref().clone(), elements)
+        }
+        MoveTypeTag::Signer | MoveTypeTag::Struct(_) => {
+            return Err(eyre!("{} can not be written as a pure value", ty).into());
+        }
+    };
+    Ok(value)
+}
+
+fn parse_uint(raw: &str, bits: usize) -> Result<U256, String> {
+    let value = U256::from_str(raw).map_err(|e| e.to_string())?;
+    if value.bit_len() > bits {
+        return Err(format!("out of range for u{}", bits));
+    }
+    Ok(value)
+}
+
+/// Split the top level elements of a vector body, keeping nested vectors.
+fn split_elements(body: &str) -> Vec<&str> {
+    let mut out = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (idx, ch) in body.char_indices() {
+        match ch {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                out.push(body[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => {}
+        }
+    }
+    let last = body[start..].trim();
+    if !last.is_empty() {
+        out.push(last);
+    }
+    out
+}
+
+/// Load every seed under `dir`. JSON files may also be corpus entries saved by
+/// a previous campaign.
+pub fn load_seeds(
+    dir: &Path,
+    meta: &FuzzMetadata,
+    store: &impl ObjectStoreInfo,
+    names: &BTreeMap<String, MoveAddress>,
+    resolve_type: impl Fn(&str) -> Result<MoveTypeTag, MovyError>,
+) -> Result<Vec<MoveFuzzInput>, MovyError> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<Vec<PathBuf>, _>>()?;
+    paths.retain(|path| {
+        path.is_file()
+            && path
+                .extension()
+                .is_some_and(|ext| ext == "json" || ext == "toml")
+    });
+    paths.sort();
+
+    let mut out = vec![];
+    for path in paths {
+        if path.extension().is_some_and(|ext| ext == "json") {
+            let content = std::fs::read_to_string(&path)?;
+            if let Ok(input) = serde_json::from_str::<MoveFuzzInput>(&content) {
+                out.push(MoveFuzzInput {
+                    prelude: input.prelude,
+                    state_patches: input.state_patches,
+                    sequence: input.sequence,
+                    flash: input.flash,
+                    ..Default::default()
+                });
+                continue;
+            }
+        }
+        let seed = SeedSequence::from_file(&path)
+            .map_err(|e| eyre!("can not load seed {}: {}", path.display(), e))?;
+        let sequence = seed
+            .to_sequence(meta, store, names, &resolve_type)
+            .map_err(|e| eyre!("invalid seed {}: {}", path.display(), e))?;
+        out.push(MoveFuzzInput {
+            sequence,
+            ..Default::default()
+        });
+    }
+    info!("Loaded {} seeds from {}", out.len(), dir.display());
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pure_values_follow_parameter_types() {
+        assert_eq!(
+            parse_pure(&MoveTypeTag::U64, "0x10").unwrap(),
+            InputArgument::U64(16)
+        );
+        assert!(parse_pure(&MoveTypeTag::U8, "256").is_err());
+        assert_eq!(
+            parse_pure(&MoveTypeTag::Vector(Box::new(MoveTypeTag::U8)), "ab").unwrap(),
+            InputArgument::Vector(
+                MoveTypeTag::U8,
+                vec![InputArgument::U8(b'a'), InputArgument::U8(b'b')]
+            )
+        );
+        let nested = MoveTypeTag::Vector(Box::new(MoveTypeTag::Vector(Box::new(MoveTypeTag::U16))));
+        assert_eq!(
+            parse_pure(&nested, "[[1, 2], []]").unwrap(),
+            InputArgument::Vector(
+                MoveTypeTag::Vector(Box::new(MoveTypeTag::U16)),
+                vec![
+                    InputArgument::Vector(
+                        MoveTypeTag::U16,
+                        vec![InputArgument::U16(1), InputArgument::U16(2)]
+                    ),
+                    InputArgument::Vector(MoveTypeTag::U16, vec![]),
+                ]
+            )
+        );
+    }
+}
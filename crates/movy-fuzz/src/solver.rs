@@ -1,186 +1,513 @@
-use std::{collections::BTreeMap, str::FromStr, sync::mpsc, thread, time::Duration};
+use std::{
+    collections::BTreeMap,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use alloy_primitives::{U128, U256};
+use movy_replay::tracer::concolic::SymbolValue;
 use movy_types::{
-    abi::{MoveAbiSignatureToken, MoveFunctionAbi},
-    input::InputArgument,
+    abi::MoveAbiSignatureToken,
+    input::{InputArgument, MoveAddress, MoveTypeTag},
 };
+use serde::{Deserialize, Serialize};
 use tracing::{debug, info, trace, warn};
 use z3::{
-    Config, Solver,
-    ast::{Ast, Bool, Int},
+    Config, DeclKind, Solver,
+    ast::{Ast, BV, Bool, Dynamic, Int},
     with_z3_config,
 };
 
 const SOLVER_TIMEOUT_MS: u64 = 500;
+/// Time all queries of one [`run_solver`] call may take together, so that
+/// walking a long path does not stall the fuzzer.
+const SOLVER_BUDGET_MS: u64 = 1_000;
+/// Solved vectors never grow beyond this many elements.
+const MAX_SOLVED_VECTOR_LEN: u64 = 256;
+/// Solved string bytes stay ASCII, which is valid for both string modules.
+const MAX_SOLVED_STRING_BYTE: u64 = 0x7f;
+/// Branches flipped per trace by [`solve_flips`].
+pub const MAX_BRANCH_FLIPS: usize = 16;
 
 enum SolveOutcome {
-    Sat(BTreeMap<usize, String>),
+    Sat(BTreeMap<String, String>),
     Unsat,
     Unknown,
     Timeout,
 }
 
-fn run_solver_worker(solver_script: String, arg_names: BTreeMap<usize, String>) -> SolveOutcome {
+static SAT_QUERIES: AtomicU64 = AtomicU64::new(0);
+static UNSAT_QUERIES: AtomicU64 = AtomicU64::new(0);
+static UNKNOWN_QUERIES: AtomicU64 = AtomicU64::new(0);
+static TIMEOUT_QUERIES: AtomicU64 = AtomicU64::new(0);
+
+/// Outcomes of every solver query made so far.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SolverStats {
+    pub sat: u64,
+    pub unsat: u64,
+    pub unknown: u64,
+    pub timeout: u64,
+}
+
+pub fn solver_stats() -> SolverStats {
+    SolverStats {
+        sat: SAT_QUERIES.load(Ordering::Relaxed),
+        unsat: UNSAT_QUERIES.load(Ordering::Relaxed),
+        unknown: UNKNOWN_QUERIES.load(Ordering::Relaxed),
+        timeout: TIMEOUT_QUERIES.load(Ordering::Relaxed),
+    }
+}
+
+impl SolveOutcome {
+    fn record(&self) {
+        let counter = match self {
+            SolveOutcome::Sat(_) => &SAT_QUERIES,
+            SolveOutcome::Unsat => &UNSAT_QUERIES,
+            SolveOutcome::Unknown => &UNKNOWN_QUERIES,
+            SolveOutcome::Timeout => &TIMEOUT_QUERIES,
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A pure input the solver may rewrite, with the symbols its value got
+/// the last time the sequence ran.
+#[derive(Debug, Clone)]
+pub struct SolveTarget {
+    pub ty: MoveAbiSignatureToken,
+    pub symbol: SymbolValue,
+    pub current: InputArgument,
+}
+
+/// An input symbol, with its width if it is a bit-vector.
+type SymbolName = (String, Option<u32>);
+
+fn check_and_eval(solver: &Solver, names: &[SymbolName]) -> SolveOutcome {
+    match solver.check() {
+        z3::SatResult::Sat => {
+            if let Some(model) = solver.get_model() {
+                debug!("Satisfiable with model:");
+                let mut assignments = BTreeMap::new();
+                for (name, bits) in names {
+                    let symbol = match bits {
+                        Some(bits) => Dynamic::from(BV::new_const(name.as_str(), *bits)),
+                        None => Dynamic::from(Int::new_const(name.as_str())),
+                    };
+                    // Symbols the constraints leave free keep their value
+                    let Some(val) = model.eval(&symbol, false) else {
+                        info!("  {} = <could not evaluate>", name);
+                        continue;
+                    };
+                    if let Some(val) = parse_numeric_value(&val.to_string())
+                        && val.chars().all(|c| c.is_ascii_digit())
+                    {
+                        debug!("  {} = {}", name, val);
+                        assignments.insert(name.clone(), val);
+                    }
+                }
+                SolveOutcome::Sat(assignments)
+            } else {
+                info!("Satisfiable but no model found");
+                SolveOutcome::Unknown
+            }
+        }
+        z3::SatResult::Unsat => {
+            info!("Unsatisfiable");
+            SolveOutcome::Unsat
+        }
+        z3::SatResult::Unknown => {
+            info!("Solver returned unknown");
+            SolveOutcome::Unknown
+        }
+    }
+}
+
+/// Check `base`, or with `path` check each flipped condition on top of the
+/// conditions kept before it. Outcomes are sent as soon as they are known.
+fn run_solver_worker(
+    base: String,
+    path: Vec<(Option<String>, String)>,
+    names: Vec<SymbolName>,
+    tx: mpsc::Sender<SolveOutcome>,
+) {
     let mut cfg = Config::new();
     cfg.set_timeout_msec(SOLVER_TIMEOUT_MS);
     with_z3_config(&cfg, move || {
         let solver = Solver::new();
-        solver.from_string(solver_script.as_str());
-
-        let translated_args = arg_names
-            .iter()
-            .map(|(idx, name)| (*idx, Int::new_const(name.as_str())))
-            .collect::<BTreeMap<_, _>>();
-
-        match solver.check() {
-            z3::SatResult::Sat => {
-                if let Some(model) = solver.get_model() {
-                    debug!("Satisfiable with model:");
-                    let mut assignments = BTreeMap::new();
-                    for (i, int) in translated_args.iter() {
-                        if let Some(val) = model.eval(int, true) {
-                            debug!("  arg[{}] = {}", i, val);
-                            assignments.insert(*i, val.to_string());
-                        } else {
-                            info!("  arg[{}] = <could not evaluate>", i);
-                        }
-                    }
-                    SolveOutcome::Sat(assignments)
-                } else {
-                    info!("Satisfiable but no model found");
-                    SolveOutcome::Unknown
+        solver.from_string(base.as_str());
+        if path.is_empty() {
+            let _ = tx.send(check_and_eval(&solver, &names));
+            return;
+        }
+        for (flipped, kept) in path {
+            if let Some(flipped) = flipped {
+                solver.push();
+                solver.from_string(flipped.as_str());
+                let outcome = check_and_eval(&solver, &names);
+                solver.pop(1);
+                if tx.send(outcome).is_err() {
+                    return;
                 }
             }
-            z3::SatResult::Unsat => {
-                info!("Unsatisfiable");
-                SolveOutcome::Unsat
-            }
-            z3::SatResult::Unknown => {
-                info!("Solver returned unknown");
-                SolveOutcome::Unknown
-            }
+            solver.from_string(kept.as_str());
         }
     })
 }
 
-pub fn solve(
-    function: MoveFunctionAbi,
-    args: &BTreeMap<usize, Int>,
-    constraints: &Vec<Bool>,
-) -> Option<BTreeMap<usize, InputArgument>> {
-    if constraints.is_empty() {
-        trace!("No constraints to solve. target function: {:?}", function);
-        return None;
+fn strip_reference(ty: &MoveAbiSignatureToken) -> &MoveAbiSignatureToken {
+    match ty {
+        MoveAbiSignatureToken::Reference(inner)
+        | MoveAbiSignatureToken::MutableReference(inner) => strip_reference(inner),
+        ty => ty,
     }
-    let solver = Solver::new();
-    for c in constraints {
-        solver.assert(c);
+}
+
+/// Standard library structs passed as pure values. Both wrap a vector as their
+/// only field and are encoded as that vector.
+enum PureStruct<'a> {
+    /// `0x1::option::Option<T>`, a vector of at most one `T`
+    Option(&'a MoveAbiSignatureToken),
+    /// `0x1::string::String` or `0x1::ascii::String`, a `vector<u8>`
+    String,
+}
+
+fn pure_struct(ty: &MoveAbiSignatureToken) -> Option<PureStruct<'_>> {
+    match ty {
+        MoveAbiSignatureToken::StructInstantiation(handle, args)
+            if handle.module_id.module_address == MoveAddress::one()
+                && handle.module_id.module_name == "option"
+                && handle.struct_name == "Option"
+                && args.len() == 1 =>
+        {
+            Some(PureStruct::Option(&args[0]))
+        }
+        MoveAbiSignatureToken::Struct(handle)
+            if handle.module_id.module_address == MoveAddress::one()
+                && matches!(handle.module_id.module_name.as_str(), "string" | "ascii")
+                && handle.struct_name == "String" =>
+        {
+            Some(PureStruct::String)
+        }
+        _ => None,
     }
-    let function_params = function.parameters.clone();
-    let mut arg_names = BTreeMap::new();
-    for (i, int) in args.iter() {
-        solver.assert(int.ge(Int::from_u64(0)));
-        match function_params.get(*i).unwrap_or_else(|| {
-            panic!(
-                "Parameter index {} out of bounds for function {:?}",
-                i, function
-            )
-        }) {
-            MoveAbiSignatureToken::Bool => {
-                solver.assert(int.le(Int::from_u64(1)));
-            }
-            MoveAbiSignatureToken::U8 => {
-                solver.assert(int.le(Int::from_u64(u8::MAX as u64)));
-            }
-            MoveAbiSignatureToken::U16 => {
-                solver.assert(int.le(Int::from_u64(u16::MAX as u64)));
-            }
-            MoveAbiSignatureToken::U32 => {
-                solver.assert(int.le(Int::from_u64(u32::MAX as u64)));
-            }
-            MoveAbiSignatureToken::U64 => {
-                solver.assert(int.le(Int::from_u64(u64::MAX)));
+}
+
+fn max_value(ty: &MoveAbiSignatureToken) -> Option<Int> {
+    let max = match ty {
+        MoveAbiSignatureToken::Bool => Int::from_u64(1),
+        MoveAbiSignatureToken::U8 => Int::from_u64(u8::MAX as u64),
+        MoveAbiSignatureToken::U16 => Int::from_u64(u16::MAX as u64),
+        MoveAbiSignatureToken::U32 => Int::from_u64(u32::MAX as u64),
+        MoveAbiSignatureToken::U64 => Int::from_u64(u64::MAX),
+        MoveAbiSignatureToken::U128 => Int::from_str(&u128::MAX.to_string()).unwrap(),
+        MoveAbiSignatureToken::U256 => Int::from_str(&U256::MAX.to_string()).unwrap(),
+        _ => return None,
+    };
+    Some(max)
+}
+
+fn symbol_name(ast: &impl Ast) -> Option<String> {
+    ast.safe_decl()
+        .ok()
+        .filter(|decl| decl.kind() == DeclKind::Uninterpreted)
+        .map(|decl| decl.name())
+}
+
+/// Bound a scalar symbol by `max`, which fits the width of bit-vectors.
+fn constrain_scalar(symbol: &SymbolValue, max: Int, solver: &Solver, names: &mut Vec<SymbolName>) {
+    match symbol {
+        SymbolValue::Value(int) => {
+            let Some(name) = symbol_name(int) else {
+                return;
+            };
+            solver.assert(int.ge(Int::from_u64(0)));
+            solver.assert(int.le(max));
+            names.push((name, None));
+        }
+        SymbolValue::Bits(bv) => {
+            let Some(name) = symbol_name(bv) else {
+                return;
+            };
+            solver.assert(bv.bvule(&BV::from_int(&max, bv.get_size())));
+            names.push((name, Some(bv.get_size())));
+        }
+        _ => {}
+    }
+}
+
+/// Bound every symbol in `symbol` by its type and collect their names.
+fn constrain(
+    ty: &MoveAbiSignatureToken,
+    symbol: &SymbolValue,
+    solver: &Solver,
+    names: &mut Vec<SymbolName>,
+) {
+    match (strip_reference(ty), symbol) {
+        (ty, SymbolValue::Value(_) | SymbolValue::Bits(_)) => {
+            if let Some(max) = max_value(ty) {
+                constrain_scalar(symbol, max, solver, names);
             }
-            MoveAbiSignatureToken::U128 => {
-                let max = "340282366920938463463374607431768211455"; // 2^128 - 1
-                solver.assert(int.le(Int::from_str(max).unwrap()));
+        }
+        (MoveAbiSignatureToken::Vector(inner), SymbolValue::Vector(len, elems)) => {
+            constrain_scalar(len, Int::from_u64(MAX_SOLVED_VECTOR_LEN), solver, names);
+            for elem in elems {
+                constrain(inner, elem, solver, names);
             }
-            MoveAbiSignatureToken::U256 => {
-                let max = "115792089237316195423570985008687907853269984665640564039457584007913129639935"; // 2^256 - 1
-                solver.assert(int.le(Int::from_str(max).unwrap()));
+        }
+        (ty, SymbolValue::Struct(fields)) => {
+            let [SymbolValue::Vector(len, elems)] = fields.as_slice() else {
+                return;
+            };
+            match pure_struct(ty) {
+                Some(PureStruct::Option(inner)) => {
+                    constrain_scalar(len, Int::from_u64(1), solver, names);
+                    for elem in elems {
+                        constrain(inner, elem, solver, names);
+                    }
+                }
+                Some(PureStruct::String) => {
+                    constrain_scalar(len, Int::from_u64(MAX_SOLVED_VECTOR_LEN), solver, names);
+                    for elem in elems {
+                        constrain_scalar(
+                            elem,
+                            Int::from_u64(MAX_SOLVED_STRING_BYTE),
+                            solver,
+                            names,
+                        );
+                    }
+                }
+                None => {}
             }
-            _ => {
-                panic!(
-                    "Unsupported argument type for symbolic execution, function: {:?}, param idx: {}, args: {:?}",
-                    function, i, args
-                );
+        }
+        _ => {}
+    }
+}
+
+fn default_value(ty: &MoveAbiSignatureToken) -> Option<InputArgument> {
+    value_from_assignment(ty, "0")
+}
+
+fn assigned<'a>(symbol: &SymbolValue, model: &'a BTreeMap<String, String>) -> Option<&'a String> {
+    let name = match symbol {
+        SymbolValue::Value(int) => symbol_name(int),
+        SymbolValue::Bits(bv) => symbol_name(bv),
+        _ => None,
+    };
+    name.and_then(|name| model.get(&name))
+}
+
+/// Patch the model into `current`, keeping whatever the model leaves free.
+fn rebuild(
+    ty: &MoveAbiSignatureToken,
+    symbol: &SymbolValue,
+    current: &InputArgument,
+    model: &BTreeMap<String, String>,
+) -> InputArgument {
+    match (strip_reference(ty), symbol, current) {
+        (ty, SymbolValue::Value(_) | SymbolValue::Bits(_), _) => assigned(symbol, model)
+            .and_then(|raw| value_from_assignment(ty, raw))
+            .unwrap_or_else(|| current.clone()),
+        (
+            MoveAbiSignatureToken::Vector(inner),
+            SymbolValue::Vector(len, elems),
+            InputArgument::Vector(tag, values),
+        ) => rebuild_vector(inner, len, elems, tag, values, model),
+        // Pure structs are passed as the vector they wrap
+        (ty, SymbolValue::Struct(fields), InputArgument::Vector(tag, values)) => {
+            let [SymbolValue::Vector(len, elems)] = fields.as_slice() else {
+                return current.clone();
+            };
+            match pure_struct(ty) {
+                Some(PureStruct::Option(inner)) => {
+                    rebuild_vector(inner, len, elems, tag, values, model)
+                }
+                Some(PureStruct::String) => {
+                    rebuild_vector(&MoveAbiSignatureToken::U8, len, elems, tag, values, model)
+                }
+                None => current.clone(),
             }
         }
-        arg_names.insert(*i, int.decl().name());
+        _ => current.clone(),
+    }
+}
+
+fn rebuild_vector(
+    inner: &MoveAbiSignatureToken,
+    len: &SymbolValue,
+    elems: &[SymbolValue],
+    tag: &MoveTypeTag,
+    values: &[InputArgument],
+    model: &BTreeMap<String, String>,
+) -> InputArgument {
+    let mut values = values.to_vec();
+    if let Some(new_len) = assigned(len, model).and_then(|raw| raw.parse::<usize>().ok()) {
+        values.truncate(new_len);
+        while values.len() < new_len {
+            let Some(value) = default_value(inner) else {
+                break;
+            };
+            values.push(value);
+        }
+    }
+    for (value, elem) in values.iter_mut().zip(elems.iter()) {
+        *value = rebuild(inner, elem, value, model);
+    }
+    InputArgument::Vector(tag.clone(), values)
+}
+
+fn script_of(constraints: &[&Bool]) -> String {
+    let solver = Solver::new();
+    for c in constraints {
+        solver.assert(*c);
+    }
+    solver.to_string()
+}
+
+/// Run the worker and turn each model into the changed targets. `None`
+/// stands for a check that failed; outcomes after a timeout are dropped.
+fn run_solver<K: Ord + Clone>(
+    targets: &BTreeMap<K, SolveTarget>,
+    base: &[Bool],
+    path: Vec<(Option<String>, String)>,
+) -> Vec<Option<BTreeMap<K, InputArgument>>> {
+    let solver = Solver::new();
+    for c in base {
+        solver.assert(c);
+    }
+    let mut names = vec![];
+    for target in targets.values() {
+        constrain(&target.ty, &target.symbol, &solver, &mut names);
+    }
+    if names.is_empty() {
+        trace!("No symbolic inputs to solve for");
+        return vec![];
     }
     let solver_script = solver.to_string();
     debug!("Solver script:\n{}", solver_script);
 
+    let expected = path
+        .iter()
+        .filter(|(flipped, _)| flipped.is_some())
+        .count()
+        .max(1);
     let (tx, rx) = mpsc::channel();
-    let solver_script_worker = solver_script;
-    let arg_names_worker = arg_names;
     let worker_handle = thread::spawn(move || {
-        let outcome = run_solver_worker(solver_script_worker, arg_names_worker);
-        let _ = tx.send(outcome);
+        run_solver_worker(solver_script, path, names, tx);
     });
 
-    let solver_outcome = match rx.recv_timeout(Duration::from_millis(SOLVER_TIMEOUT_MS + 50)) {
-        Ok(outcome) => outcome,
-        Err(mpsc::RecvTimeoutError::Timeout) => {
-            debug!("Solver worker timed out after {} ms", SOLVER_TIMEOUT_MS);
-            SolveOutcome::Timeout
-        }
-        Err(mpsc::RecvTimeoutError::Disconnected) => {
-            warn!("Solver worker disconnected before sending a result");
-            SolveOutcome::Unknown
+    let deadline = Instant::now() + Duration::from_millis(SOLVER_BUDGET_MS);
+    let mut outcomes = vec![];
+    while outcomes.len() < expected {
+        let wait = deadline
+            .saturating_duration_since(Instant::now())
+            .min(Duration::from_millis(SOLVER_TIMEOUT_MS + 50));
+        match rx.recv_timeout(wait) {
+            Ok(outcome) => outcomes.push(outcome),
+            Err(mpsc::RecvTimeoutError::Timeout) => {
+                debug!(
+                    "Solver worker timed out after {} of {} checks",
+                    outcomes.len(),
+                    expected
+                );
+                outcomes.push(SolveOutcome::Timeout);
+                break;
+            }
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                warn!("Solver worker disconnected before sending a result");
+                outcomes.push(SolveOutcome::Unknown);
+                break;
+            }
         }
-    };
+    }
 
-    if !matches!(solver_outcome, SolveOutcome::Timeout)
+    if !matches!(outcomes.last(), Some(SolveOutcome::Timeout))
         && let Err(err) = worker_handle.join()
     {
         warn!("Solver worker panicked: {:?}", err);
-        return None;
+        return vec![];
     }
 
-    match solver_outcome {
-        SolveOutcome::Sat(assignments) => {
-            let mut result = BTreeMap::new();
-            for (idx, raw_value) in assignments {
-                let ty = function_params.get(idx).unwrap_or_else(|| {
-                    panic!(
-                        "Parameter index {} out of bounds for function {:?}",
-                        idx, function
-                    )
-                });
-                match value_from_assignment(ty, &raw_value) {
-                    Some(value) => {
-                        debug!("arg[{}] = {}", idx, raw_value);
-                        result.insert(idx, value);
-                    }
-                    None => {
-                        warn!(
-                            "Failed to parse model value '{}' for arg {} ({:?})",
-                            raw_value, idx, ty
-                        );
+    outcomes.iter().for_each(SolveOutcome::record);
+    outcomes
+        .into_iter()
+        .map(|outcome| match outcome {
+            SolveOutcome::Sat(model) => {
+                let mut result = BTreeMap::new();
+                for (key, target) in targets.iter() {
+                    let value = rebuild(&target.ty, &target.symbol, &target.current, &model);
+                    if value != target.current {
+                        debug!("{} => {}", target.current, value);
+                        result.insert(key.clone(), value);
                     }
                 }
+                Some(result)
             }
-            Some(result)
-        }
-        SolveOutcome::Unsat => None,
-        SolveOutcome::Unknown => None,
-        SolveOutcome::Timeout => {
-            debug!("Solver timed out");
-            None
+            SolveOutcome::Unsat => None,
+            SolveOutcome::Unknown => None,
+            SolveOutcome::Timeout => {
+                debug!("Solver timed out");
+                None
+            }
+        })
+        .collect()
+}
+
+/// Solve `constraints` over the symbols of `targets`, returning the targets
+/// whose value has to change.
+pub fn solve<K: Ord + Clone>(
+    targets: &BTreeMap<K, SolveTarget>,
+    constraints: &[Bool],
+) -> Option<BTreeMap<K, InputArgument>> {
+    if constraints.is_empty() {
+        trace!("No constraints to solve");
+        return None;
+    }
+    run_solver(targets, constraints, vec![]).pop().flatten()
+}
+
+/// A condition that held on the path of a trace.
+#[derive(Debug, Clone)]
+pub struct PathCondition {
+    pub constraint: Bool,
+    /// Solve for the other side of it, e.g. for branches
+    pub flip: bool,
+}
+
+/// Solve the path once per condition marked `flip`, negating it and keeping
+/// the conditions before it as they held. A single solver walks the path, so
+/// sibling flips share the work on their common prefix. Returns the flips
+/// that were satisfiable, in path order.
+pub fn solve_flips<K: Ord + Clone>(
+    targets: &BTreeMap<K, SolveTarget>,
+    base: &[Bool],
+    path: &[PathCondition],
+) -> Vec<BTreeMap<K, InputArgument>> {
+    let mut flips = 0;
+    let mut steps = vec![];
+    for cond in path {
+        let flipped = (cond.flip && flips < MAX_BRANCH_FLIPS).then(|| {
+            flips += 1;
+            script_of(&[&cond.constraint.not()])
+        });
+        steps.push((flipped, script_of(&[&cond.constraint])));
+        if flips == MAX_BRANCH_FLIPS {
+            break;
         }
     }
+    if flips == 0 {
+        return vec![];
+    }
+    run_solver(targets, base, steps)
+        .into_iter()
+        .flatten()
+        .filter(|solution| !solution.is_empty())
+        .collect()
 }
 
 fn value_from_assignment(ty: &MoveAbiSignatureToken, raw_value: &str) -> Option<InputArgument> {
@@ -237,9 +564,7 @@ fn value_from_assignment(ty: &MoveAbiSignatureToken, raw_value: &str) -> Option<
             let value = U256::from_str(&num).ok()?;
             Some(InputArgument::U256(value))
         }
-        _ => {
-            panic!("Unsupported argument type for symbolic execution {:?}", ty);
-        }
+        _ => None,
     }
 }
 
@@ -275,3 +600,116 @@ fn parse_numeric_value(raw: &str) -> Option<String> {
     }
     Some(normalize_model_value(trimmed))
 }
+
+#[cfg(test)]
+mod tests {
+    use movy_types::abi::{MoveAbility, MoveModuleId, MoveStructHandle};
+
+    use super::*;
+
+    #[test]
+    fn solves_vector_length_and_elements() {
+        let len = Int::new_const("0.0.len");
+        let first = Int::new_const("0.0[0]");
+        let target = SolveTarget {
+            ty: MoveAbiSignatureToken::Vector(Box::new(MoveAbiSignatureToken::U64)),
+            symbol: SymbolValue::Vector(
+                len.clone(),
+                vec![
+                    SymbolValue::Value(first.clone()),
+                    SymbolValue::Value(Int::new_const("0.0[1]")),
+                ],
+            ),
+            current: InputArgument::Vector(
+                MoveTypeTag::U64,
+                vec![InputArgument::U64(1), InputArgument::U64(2)],
+            ),
+        };
+        let constraints = vec![len._eq(&Int::from_u64(3)), first._eq(&Int::from_u64(7))];
+        let solved = solve(&BTreeMap::from([(0u16, target)]), &constraints).unwrap();
+        let InputArgument::Vector(_, values) = &solved[&0] else {
+            panic!("solved value is not a vector");
+        };
+        assert_eq!(
+            values,
+            &vec![
+                InputArgument::U64(7),
+                InputArgument::U64(2),
+                InputArgument::U64(0)
+            ]
+        );
+    }
+
+    #[test]
+    fn flips_bit_vector_branches_in_order() {
+        let x = BV::new_const("0.0", 8);
+        let target = SolveTarget {
+            ty: MoveAbiSignatureToken::U8,
+            symbol: SymbolValue::Bits(x.clone()),
+            current: InputArgument::U8(5),
+        };
+        // The trace took `x > 3` and then `x != 9`
+        let path = [
+            x.bvugt(&BV::from_u64(3, 8)),
+            x._eq(&BV::from_u64(9, 8)).not(),
+        ]
+        .map(|constraint| PathCondition {
+            constraint,
+            flip: true,
+        });
+        let solved = solve_flips(&BTreeMap::from([(0u16, target)]), &[], &path);
+        assert_eq!(solved.len(), 2);
+        assert!(matches!(solved[0][&0], InputArgument::U8(v) if v <= 3));
+        assert_eq!(solved[1][&0], InputArgument::U8(9));
+    }
+
+    #[test]
+    fn solves_option_branches() {
+        let len = Int::new_const("0.0.vec.len");
+        let inner = Int::new_const("0.0.vec[0]");
+        let option_u64 = MoveAbiSignatureToken::StructInstantiation(
+            MoveStructHandle {
+                module_id: MoveModuleId {
+                    module_address: MoveAddress::one(),
+                    module_name: "option".to_string(),
+                },
+                struct_name: "Option".to_string(),
+                abilities: MoveAbility::PRIMITIVES,
+                type_parameters: vec![],
+            },
+            vec![MoveAbiSignatureToken::U64],
+        );
+        let targets = BTreeMap::from([(
+            0u16,
+            SolveTarget {
+                ty: option_u64,
+                symbol: SymbolValue::Struct(vec![SymbolValue::Vector(
+                    Box::new(SymbolValue::Value(len.clone())),
+                    vec![SymbolValue::Value(inner.clone())],
+                )]),
+                current: InputArgument::Vector(MoveTypeTag::U64, vec![InputArgument::U64(5)]),
+            },
+        )]);
+        // The trace found `Some(x)` and took `x <= 10`
+        let path = [
+            PathCondition {
+                constraint: len._eq(&Int::from_u64(1)),
+                flip: false,
+            },
+            PathCondition {
+                constraint: inner.le(Int::from_u64(10)),
+                flip: true,
+            },
+        ];
+        let solved = solve_flips(&targets, &[], &path);
+        assert_eq!(solved.len(), 1);
+        assert!(matches!(
+            &solved[0][&0],
+            InputArgument::Vector(MoveTypeTag::U64, values)
+                if matches!(values.as_slice(), [InputArgument::U64(v)] if *v > 10)
+        ));
+
+        // An option never holds two values
+        assert!(solve(&targets, &[len._eq(&Int::from_u64(2))]).is_none());
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::BTreeMap;
+
 use libafl::{
     HasMetadata, HasNamedMetadata,
     corpus::{HasCurrentCorpusId, HasTestcase},
@@ -11,12 +13,16 @@ use movy_replay::{
     env::SuiTestingEnv,
 };
 use movy_sui::database::cache::ObjectSuiStoreCommit;
+use movy_types::input::FunctionIdent;
 use sui_types::storage::{BackingPackageStore, BackingStore, ObjectStore};
 
 use crate::executor::GlobalOutcome;
 
 pub struct ExtraNonSerdeFuzzState<T> {
     pub global_outcome: Option<GlobalOutcome>,
+    /// Gas spent by the calls of each function in the prelude of the input
+    /// being executed
+    pub prelude_gas: BTreeMap<FunctionIdent, Vec<u64>>,
     pub fuzz_env: SuiTestingEnv<T>,
 }
 
@@ -24,6 +30,7 @@ impl<T> ExtraNonSerdeFuzzState<T> {
     pub fn from_env(fuzz_env: SuiTestingEnv<T>) -> Self {
         Self {
             global_outcome: None,
+            prelude_gas: BTreeMap::new(),
             fuzz_env,
         }
     }
@@ -0,0 +1,400 @@
+use std::{
+    collections::{BTreeMap, btree_map::Entry},
+    fmt::Display,
+};
+
+use alloy_primitives::U256;
+use color_eyre::eyre::eyre;
+use libafl_bolts::impl_serdeany;
+use move_core_types::{
+    account_address::AccountAddress,
+    annotated_value::{MoveFieldLayout, MoveTypeLayout},
+    language_storage::StructTag,
+};
+use movy_replay::{db::ObjectStoreInfo, exec::SuiExecutor};
+use movy_sui::database::cache::ObjectSuiStoreCommit;
+use movy_types::{
+    abi::MoveAbiSignatureToken,
+    error::MovyError,
+    input::{MoveAddress, MoveTypeTag},
+    object::MoveOwner,
+    oracle::OracleFinding,
+};
+use serde::{Deserialize, Serialize};
+use sui_types::{base_types::ObjectID, object::Object, storage::ObjectStore};
+use tracing::debug;
+
+use crate::meta::FuzzMetadata;
+
+/// Fields beyond this many per object are not mutated.
+const MAX_FIELDS_PER_OBJECT: usize = 64;
+/// Only the leading elements of numeric vectors are mutated.
+const MAX_VECTOR_ELEMENTS: usize = 8;
+
+/// An unsigned integer inside the BCS contents of an object.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct NumericField {
+    pub path: String,
+    pub offset: usize,
+    pub width: usize,
+    /// Value on the fork, little-endian
+    pub current: Vec<u8>,
+}
+
+impl NumericField {
+    pub fn max(&self) -> U256 {
+        if self.width >= 32 {
+            U256::MAX
+        } else {
+            (U256::from(1) << (self.width * 8)) - U256::from(1)
+        }
+    }
+
+    pub fn current(&self) -> U256 {
+        U256::from_le_slice(&self.current)
+    }
+
+    /// Encode `value` in the width of this field, saturating at its maximum.
+    pub fn encode(&self, value: U256) -> Vec<u8> {
+        value.min(self.max()).to_le_bytes::<32>()[..self.width].to_vec()
+    }
+}
+
+/// Overwrite one numeric field of an object before the input runs.
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq, Eq)]
+pub struct StatePatch {
+    pub object: MoveAddress,
+    pub field: String,
+    pub offset: usize,
+    /// New value, little-endian in the width of the field
+    pub value: Vec<u8>,
+}
+
+impl Display for StatePatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}.{} = {}",
+            self.object,
+            self.field,
+            U256::from_le_slice(&self.value)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StateObject {
+    pub ty: MoveTypeTag,
+    pub fields: Vec<NumericField>,
+}
+
+/// Shared objects of the target packages whose numeric fields the state
+/// havoc stage may overwrite.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StateFuzzMetadata {
+    #[serde(with = "serde_json_any_key::any_key_map")]
+    pub objects: BTreeMap<MoveAddress, StateObject>,
+}
+
+impl_serdeany!(StateFuzzMetadata);
+
+pub(crate) fn read_uleb128(bytes: &[u8], pos: &mut usize) -> Option<usize> {
+    let mut value = 0usize;
+    let mut shift = 0;
+    loop {
+        let byte = *bytes.get(*pos)?;
+        *pos += 1;
+        value |= ((byte & 0x7f) as usize).checked_shl(shift)?;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+        shift += 7;
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+fn collect_fields(
+    fields: &[MoveFieldLayout],
+    path: &str,
+    bytes: &[u8],
+    pos: &mut usize,
+    out: &mut Vec<NumericField>,
+) -> Option<()> {
+    for field in fields {
+        collect_numeric(
+            &field.layout,
+            &join(path, field.name.as_str()),
+            bytes,
+            pos,
+            out,
+        )?;
+    }
+    Some(())
+}
+
+fn is_balance(tag: &StructTag) -> bool {
+    tag.address == AccountAddress::TWO
+        && tag.module.as_str() == "balance"
+        && tag.name.as_str() == "Balance"
+}
+
+/// Walk the BCS encoding of a value, recording where its integers are.
+fn collect_numeric(
+    layout: &MoveTypeLayout,
+    path: &str,
+    bytes: &[u8],
+    pos: &mut usize,
+    out: &mut Vec<NumericField>,
+) -> Option<()> {
+    let width = match layout {
+        MoveTypeLayout::U8 => 1,
+        MoveTypeLayout::U16 => 2,
+        MoveTypeLayout::U32 => 4,
+        MoveTypeLayout::U64 => 8,
+        MoveTypeLayout::U128 => 16,
+        MoveTypeLayout::U256 => 32,
+        MoveTypeLayout::Bool => {
+            *pos += 1;
+            return (*pos <= bytes.len()).then_some(());
+        }
+        MoveTypeLayout::Address | MoveTypeLayout::Signer => {
+            *pos += 32;
+            return (*pos <= bytes.len()).then_some(());
+        }
+        MoveTypeLayout::Vector(inner) => {
+            let len = read_uleb128(bytes, pos)?;
+            if matches!(inner.as_ref(), MoveTypeLayout::U8) {
+                *pos += len;
+                return (*pos <= bytes.len()).then_some(());
+            }
+            for idx in 0..len {
+                let mut ignored = vec![];
+                let sink = if idx < MAX_VECTOR_ELEMENTS {
+                    &mut *out
+                } else {
+                    &mut ignored
+                };
+                collect_numeric(inner, &format!("{}[{}]", path, idx), bytes, pos, sink)?;
+            }
+            return Some(());
+        }
+        MoveTypeLayout::Struct(st) => {
+            // Balances are backed by coins elsewhere, havocing them only
+            // mints value out of thin air
+            if is_balance(&st.type_) {
+                let mut ignored = vec![];
+                return collect_fields(&st.fields, path, bytes, pos, &mut ignored);
+            }
+            return collect_fields(&st.fields, path, bytes, pos, out);
+        }
+        MoveTypeLayout::Enum(en) => {
+            let tag = read_uleb128(bytes, pos)?;
+            let ((name, _), fields) = en
+                .variants
+                .iter()
+                .find(|((_, variant), _)| *variant as usize == tag)?;
+            return collect_fields(fields, &join(path, name.as_str()), bytes, pos, out);
+        }
+    };
+    let current = bytes.get(*pos..*pos + width)?.to_vec();
+    out.push(NumericField {
+        path: path.to_string(),
+        offset: *pos,
+        width,
+        current,
+    });
+    *pos += width;
+    Some(())
+}
+
+impl StateFuzzMetadata {
+    fn numeric_fields(
+        meta: &FuzzMetadata,
+        ty: &MoveTypeTag,
+        contents: &[u8],
+    ) -> Option<Vec<NumericField>> {
+        let MoveTypeTag::Struct(st) = ty else {
+            return None;
+        };
+        let abi = meta.get_struct(&st.address, &st.module, &st.name)?;
+        let mut typs = vec![];
+        for ty in st.tys.iter() {
+            typs.push(
+                MoveAbiSignatureToken::from_type_tag_lossy(ty)
+                    .to_move_type_layout(&[], &meta.structs_mapping)?,
+            );
+        }
+        let layout = abi.to_move_struct_layout(&typs, &meta.structs_mapping)?;
+        let mut fields = vec![];
+        let mut pos = 0;
+        collect_fields(&layout.fields, "", contents, &mut pos, &mut fields)?;
+        if pos != contents.len() {
+            return None;
+        }
+        fields.truncate(MAX_FIELDS_PER_OBJECT);
+        Some(fields)
+    }
+
+    pub fn from_store<T: ObjectStore>(meta: &FuzzMetadata, db: &T) -> Self {
+        let mut objects = BTreeMap::new();
+        for (ty, ids) in meta.types_pool.iter() {
+            let MoveTypeTag::Struct(st) = ty else {
+                continue;
+            };
+            let package = meta
+                .module_address_to_package
+                .get(&st.address)
+                .unwrap_or(&st.address);
+            if !meta.target_packages.contains(package) {
+                continue;
+            }
+            for id in ids {
+                let Ok(info) = db.get_move_object_info(*id) else {
+                    continue;
+                };
+                if !matches!(info.owner, MoveOwner::Shared { .. }) {
+                    continue;
+                }
+                let Some(object) = db.get_object(&(*id).into()) else {
+                    continue;
+                };
+                let Some(move_object) = object.data.try_as_move() else {
+                    continue;
+                };
+                match Self::numeric_fields(meta, ty, move_object.contents()) {
+                    Some(fields) if !fields.is_empty() => {
+                        objects.insert(
+                            *id,
+                            StateObject {
+                                ty: ty.clone(),
+                                fields,
+                            },
+                        );
+                    }
+                    Some(_) => {}
+                    None => debug!("Can not lay out the contents of {} as {}", id, ty),
+                }
+            }
+        }
+        Self { objects }
+    }
+}
+
+/// Write `patches` into the store of `executor`, which should be a layer
+/// that is thrown away after the input is done.
+pub fn apply_state_patches<T>(
+    executor: &SuiExecutor<T>,
+    patches: &[StatePatch],
+) -> Result<(), MovyError>
+where
+    T: ObjectStore + ObjectSuiStoreCommit,
+{
+    let mut patched: BTreeMap<ObjectID, Object> = BTreeMap::new();
+    for patch in patches {
+        let id = ObjectID::from(patch.object);
+        let object = match patched.entry(id) {
+            Entry::Occupied(e) => e.into_mut(),
+            Entry::Vacant(e) => e.insert(
+                executor
+                    .db
+                    .get_object(&id)
+                    .ok_or_else(|| eyre!("patched object {} is missing", id))?,
+            ),
+        };
+        let move_object = object
+            .data
+            .try_as_move_mut()
+            .ok_or_else(|| eyre!("patched object {} is a package", id))?;
+        let mut contents = move_object.contents().to_vec();
+        let end = patch.offset + patch.value.len();
+        if end > contents.len() {
+            return Err(eyre!("patch {} is out of the bounds of {}", patch, id).into());
+        }
+        contents[patch.offset..end].copy_from_slice(&patch.value);
+        move_object
+            .update_contents(contents, &executor.protocol_config)
+            .map_err(|e| eyre!("can not patch {}: {:?}", id, e))?;
+    }
+    for object in patched.into_values() {
+        executor.db.commit_single_object(object)?;
+    }
+    Ok(())
+}
+
+/// Record the state a finding depends on, since the fork may never reach it.
+pub fn mark_requires_state(finding: &mut OracleFinding, patches: &[StatePatch]) {
+    let required =
+        serde_json::Value::from(patches.iter().map(ToString::to_string).collect::<Vec<_>>());
+    match &mut finding.extra {
+        serde_json::Value::Object(map) => {
+            map.insert("requires_state".to_string(), required);
+        }
+        other => {
+            *other = serde_json::json!({
+                "finding": other.take(),
+                "requires_state": required,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use move_core_types::{annotated_value::MoveStructLayout, identifier::Identifier};
+
+    use super::*;
+
+    #[test]
+    fn numeric_offsets_follow_bcs() {
+        let field =
+            |name: &str, layout| MoveFieldLayout::new(Identifier::new(name).unwrap(), layout);
+        let layout = MoveStructLayout::new(
+            StructTag::from_str("0x1::m::S").unwrap(),
+            vec![
+                field("id", MoveTypeLayout::Address),
+                field("name", MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U8))),
+                field("rate", MoveTypeLayout::U64),
+                field(
+                    "caps",
+                    MoveTypeLayout::Vector(Box::new(MoveTypeLayout::U16)),
+                ),
+                field(
+                    "reserve",
+                    MoveTypeLayout::Struct(Box::new(MoveStructLayout::new(
+                        StructTag::from_str("0x2::balance::Balance<0x2::sui::SUI>").unwrap(),
+                        vec![field("value", MoveTypeLayout::U64)],
+                    ))),
+                ),
+            ],
+        );
+        let mut bytes = vec![0u8; 32];
+        bytes.extend([2, b'a', b'b']);
+        bytes.extend(7u64.to_le_bytes());
+        bytes.extend([2, 1, 0, 2, 0]);
+        bytes.extend(9u64.to_le_bytes());
+
+        let mut fields = vec![];
+        let mut pos = 0;
+        collect_fields(&layout.fields, "", &bytes, &mut pos, &mut fields).unwrap();
+        assert_eq!(pos, bytes.len());
+        let summary = fields
+            .iter()
+            .map(|f| (f.path.as_str(), f.offset, f.width))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            summary,
+            vec![("rate", 35, 8), ("caps[0]", 44, 2), ("caps[1]", 46, 2)]
+        );
+        assert_eq!(fields[0].current(), U256::from(7));
+        assert_eq!(fields[1].encode(U256::MAX), vec![0xff, 0xff]);
+    }
+}
@@ -0,0 +1,263 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use libafl::{
+    HasNamedMetadata,
+    corpus::Corpus,
+    feedbacks::MapFeedbackMetadata,
+    state::{HasCorpus, HasExecutions, HasSolutions},
+};
+use movy_types::error::MovyError;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input::{MoveFuzzInput, MoveInput},
+    operations::fuzz::COVERAGE_FEEDBACK_NAME,
+    solver::{SolverStats, solver_stats},
+};
+
+/// Written to the output directory of a campaign.
+pub const STATS_FILE: &str = "fuzzer_stats.json";
+/// How often the stats file is rewritten and the dashboard redrawn.
+pub const STATS_INTERVAL: Duration = Duration::from_secs(5);
+
+/// A snapshot of a running campaign. Times are seconds since the Unix epoch,
+/// `last_new_path` tells whether a long campaign has plateaued.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FuzzerStats {
+    pub start_time: u64,
+    pub last_update: u64,
+    pub run_time: u64,
+    pub cycles: u64,
+    pub execs_done: u64,
+    pub execs_per_sec: f64,
+    pub corpus_count: usize,
+    pub crashes: usize,
+    pub crashes_by_oracle: BTreeMap<String, usize>,
+    pub edges_covered: usize,
+    pub edges_total: usize,
+    pub lines_covered: Option<usize>,
+    pub lines_total: Option<usize>,
+    pub last_new_path: Option<u64>,
+    pub last_crash: Option<u64>,
+    pub solver: SolverStats,
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Formats seconds as e.g. `1d 02h 03m 04s`.
+pub fn format_duration(secs: u64) -> String {
+    let (days, hours, minutes, secs) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60, secs % 60);
+    if days > 0 {
+        format!("{}d {:02}h {:02}m {:02}s", days, hours, minutes, secs)
+    } else if hours > 0 {
+        format!("{}h {:02}m {:02}s", hours, minutes, secs)
+    } else if minutes > 0 {
+        format!("{}m {:02}s", minutes, secs)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+pub struct StatsTracker {
+    path: Option<PathBuf>,
+    tui: bool,
+    start: Instant,
+    last_report: Option<Instant>,
+    stats: FuzzerStats,
+}
+
+impl StatsTracker {
+    /// Track the campaign, writing to `path` if given and drawing the
+    /// dashboard if `tui` is set and the `tui` feature is enabled.
+    pub fn new(path: Option<PathBuf>, tui: bool) -> Self {
+        Self {
+            path,
+            tui,
+            start: Instant::now(),
+            last_report: None,
+            stats: FuzzerStats {
+                start_time: unix_now(),
+                ..Default::default()
+            },
+        }
+    }
+
+    pub fn stats(&self) -> &FuzzerStats {
+        &self.stats
+    }
+
+    /// Refresh the counters from `state` and report them once the interval
+    /// has passed. `lines` is the number of covered and of all source lines.
+    pub fn update<S>(
+        &mut self,
+        state: &S,
+        cycles: u64,
+        lines: Option<(usize, usize)>,
+    ) -> Result<(), MovyError>
+    where
+        S: HasCorpus<MoveFuzzInput>
+            + HasSolutions<MoveFuzzInput>
+            + HasExecutions
+            + HasNamedMetadata,
+    {
+        let now = unix_now();
+        let stats = &mut self.stats;
+
+        let corpus_count = state.corpus().count();
+        if corpus_count > stats.corpus_count {
+            stats.last_new_path = Some(now);
+        }
+        stats.corpus_count = corpus_count;
+
+        let solutions = state.solutions();
+        for idx in stats.crashes..solutions.count() {
+            let testcase = solutions.get(solutions.nth(idx))?.borrow();
+            let mut oracles = testcase
+                .input()
+                .as_ref()
+                .and_then(|input| input.outcome().as_ref())
+                .map(|outcome| {
+                    outcome
+                        .findings
+                        .iter()
+                        .map(|finding| finding.oracle.clone())
+                        .collect::<BTreeSet<_>>()
+                })
+                .unwrap_or_default();
+            if oracles.is_empty() {
+                oracles.insert("unknown".to_string());
+            }
+            for oracle in oracles {
+                *stats.crashes_by_oracle.entry(oracle).or_default() += 1;
+            }
+            stats.last_crash = Some(now);
+        }
+        stats.crashes = solutions.count();
+
+        if let Ok(coverage) =
+            state.named_metadata::<MapFeedbackMetadata<u8>>(COVERAGE_FEEDBACK_NAME)
+        {
+            stats.edges_covered = coverage.history_map.iter().filter(|v| **v != 0).count();
+            stats.edges_total = coverage.history_map.len();
+        }
+        stats.lines_covered = lines.map(|(covered, _)| covered);
+        stats.lines_total = lines.map(|(_, total)| total);
+
+        let elapsed = self.start.elapsed();
+        stats.last_update = now;
+        stats.run_time = elapsed.as_secs();
+        stats.cycles = cycles;
+        stats.execs_done = *state.executions();
+        stats.execs_per_sec = if elapsed.as_secs_f64() > 0.0 {
+            stats.execs_done as f64 / elapsed.as_secs_f64()
+        } else {
+            0.0
+        };
+        stats.solver = solver_stats();
+
+        if self
+            .last_report
+            .is_none_or(|last| last.elapsed() >= STATS_INTERVAL)
+        {
+            self.report()?;
+        }
+        Ok(())
+    }
+
+    /// Write the stats file and redraw the dashboard right away.
+    pub fn report(&mut self) -> Result<(), MovyError> {
+        self.last_report = Some(Instant::now());
+        if let Some(path) = &self.path {
+            std::fs::write(path, serde_json::to_vec_pretty(&self.stats)?)?;
+        }
+        if self.tui {
+            self.draw();
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "tui")]
+    fn draw(&self) {
+        use std::io::Write;
+
+        let s = &self.stats;
+        let ago = |at: Option<u64>| match at {
+            Some(at) => format!("{} ago", format_duration(s.last_update.saturating_sub(at))),
+            None => "none yet".to_string(),
+        };
+        let ratio = |covered: usize, total: usize| {
+            if total == 0 {
+                0.0
+            } else {
+                covered as f64 * 100.0 / total as f64
+            }
+        };
+        let mut lines = vec![
+            "movy fuzzer".to_string(),
+            String::new(),
+            format!("  run time   : {}", format_duration(s.run_time)),
+            format!("  cycles     : {}", s.cycles),
+            format!(
+                "  execs      : {} ({:.1}/sec)",
+                s.execs_done, s.execs_per_sec
+            ),
+            format!("  last path  : {}", ago(s.last_new_path)),
+            format!("  last crash : {}", ago(s.last_crash)),
+            String::new(),
+            format!("  corpus     : {}", s.corpus_count),
+            format!(
+                "  edges      : {} / {} ({:.2}%)",
+                s.edges_covered,
+                s.edges_total,
+                ratio(s.edges_covered, s.edges_total)
+            ),
+        ];
+        if let (Some(covered), Some(total)) = (s.lines_covered, s.lines_total) {
+            lines.push(format!(
+                "  lines      : {} / {} ({:.2}%)",
+                covered,
+                total,
+                ratio(covered, total)
+            ));
+        }
+        lines.push(format!(
+            "  solver     : {} sat, {} unsat, {} unknown, {} timeout",
+            s.solver.sat, s.solver.unsat, s.solver.unknown, s.solver.timeout
+        ));
+        lines.push(String::new());
+        lines.push(format!("  crashes    : {}", s.crashes));
+        for (oracle, count) in &s.crashes_by_oracle {
+            lines.push(format!("    {:<28} {}", oracle, count));
+        }
+
+        // Clear the screen and draw from the top left corner
+        let mut out = std::io::stdout().lock();
+        let _ = writeln!(out, "\x1b[2J\x1b[H{}", lines.join("\n"));
+        let _ = out.flush();
+    }
+
+    #[cfg(not(feature = "tui"))]
+    fn draw(&self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn durations_drop_empty_units() {
+        assert_eq!(format_duration(5), "5s");
+        assert_eq!(format_duration(65), "1m 05s");
+        assert_eq!(format_duration(3 * 3600 + 5), "3h 00m 05s");
+        assert_eq!(format_duration(2 * 86400 + 3661), "2d 01h 01m 01s");
+    }
+}
@@ -0,0 +1,229 @@
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use movy_replay::{
+    db::{ObjectStoreCachedStore, ObjectStoreInfo, ObjectStoreMintObject},
+    env::SuiTestingEnv,
+    exec::SuiExecutor,
+};
+use movy_sui::{
+    compile::{BuildIsolation, SuiCompiledPackage, mock_module_address},
+    database::cache::{CachedSnapshot, ObjectSuiStoreCommit},
+};
+use movy_types::{
+    error::MovyError,
+    input::{MoveAddress, MoveSequence, MoveSequenceCall},
+};
+use serde::{Deserialize, Serialize};
+use sui_types::{
+    base_types::ObjectID,
+    digests::TransactionDigest,
+    move_package::UpgradeCap,
+    object::Owner,
+    storage::{BackingPackageStore, BackingStore, ObjectStore},
+};
+
+use crate::executor::sender_gas;
+
+/// Upgrades a target package in the middle of every input: the prelude runs
+/// on version N, the upgrade follows and the attacker sequence runs on version
+/// N+1 against the objects version N left behind. Saved in the fuzz metadata
+/// so that replays upgrade the same way.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradePlan {
+    /// Version N, the package being upgraded
+    pub package: MoveAddress,
+    /// Version N+1, published at a fixed address so that all executions agree
+    pub upgraded: MoveAddress,
+    pub upgrade_cap: MoveAddress,
+    /// The owner of `upgrade_cap`
+    pub deployer: MoveAddress,
+    pub compiled: SuiCompiledPackage,
+}
+
+impl UpgradePlan {
+    /// Compile the local package at `path` as the next version of `package`.
+    pub fn build(
+        path: &Path,
+        package: MoveAddress,
+        upgrade_cap: MoveAddress,
+        deployer: MoveAddress,
+        unpublished: bool,
+        verify_deps: bool,
+        isolation: &BuildIsolation,
+    ) -> Result<Self, MovyError> {
+        tracing::info!(
+            "Compiling {} as an upgrade of {}...",
+            path.display(),
+            package
+        );
+        // Test mode like the deployment in `SuiTestingEnv::load_local`, an
+        // upgrade must keep every module of the previous version
+        let compiled =
+            SuiCompiledPackage::build_checked(path, true, unpublished, verify_deps, isolation)?;
+        let mut compiled = compiled.movy_mock()?;
+        for module in compiled.modules_mut().iter_mut() {
+            mock_module_address(ObjectID::ZERO, module);
+        }
+        let upgraded = ObjectID::derive_id(
+            TransactionDigest::new(ObjectID::from(package).into_bytes()),
+            0,
+        );
+        compiled.package_id = upgraded;
+        Ok(Self {
+            package,
+            upgraded: upgraded.into(),
+            upgrade_cap,
+            deployer,
+            compiled,
+        })
+    }
+
+    /// Publish version N+1 with `executor`.
+    pub fn apply<T>(
+        &self,
+        executor: &mut SuiExecutor<T>,
+        epoch: u64,
+        epoch_ms: u64,
+    ) -> Result<(), MovyError>
+    where
+        T: ObjectStore
+            + BackingStore
+            + ObjectSuiStoreCommit
+            + ObjectStoreMintObject
+            + ObjectStoreInfo,
+    {
+        let gas = sender_gas(&executor.db, self.deployer)?;
+        executor.upgrade_contract(
+            epoch,
+            epoch_ms,
+            self.deployer.into(),
+            gas,
+            self.package.into(),
+            self.upgrade_cap.into(),
+            self.compiled.clone(),
+        )?;
+        Ok(())
+    }
+
+    /// Publish version N+1 on a layer above `executor` and keep the objects
+    /// it changes, so that inputs without a prelude share one upgrade.
+    pub fn publish<T>(
+        &self,
+        executor: &SuiExecutor<T>,
+        epoch: u64,
+        epoch_ms: u64,
+    ) -> Result<CachedSnapshot, MovyError>
+    where
+        T: ObjectStore
+            + BackingStore
+            + BackingPackageStore
+            + ObjectSuiStoreCommit
+            + ObjectStoreMintObject
+            + ObjectStoreInfo
+            + Clone,
+    {
+        let mut upgraded = executor.layered();
+        self.apply(&mut upgraded, epoch, epoch_ms)?;
+        Ok(upgraded.db.dump_snapshot())
+    }
+
+    /// Point the calls of `sequence` to version N+1 at version N.
+    pub fn downgrade(&self, sequence: &MoveSequence) -> MoveSequence {
+        retarget(sequence, self.upgraded, self.package)
+    }
+
+    /// Point the calls of `sequence` to version N at version N+1.
+    pub fn upgrade(&self, sequence: &MoveSequence) -> MoveSequence {
+        retarget(sequence, self.package, self.upgraded)
+    }
+}
+
+fn retarget(sequence: &MoveSequence, from: MoveAddress, to: MoveAddress) -> MoveSequence {
+    let mut sequence = sequence.clone();
+    for cmd in sequence.commands.iter_mut() {
+        if let MoveSequenceCall::Call(call) = cmd
+            && call.module_id == from
+        {
+            call.module_id = to;
+        }
+    }
+    sequence
+}
+
+/// The `UpgradeCap` of `package` in `env` together with its owner.
+pub async fn find_upgrade_cap<T>(
+    env: &SuiTestingEnv<T>,
+    package: MoveAddress,
+) -> Result<(MoveAddress, MoveAddress), MovyError>
+where
+    T: ObjectStoreCachedStore + ObjectStore,
+{
+    for id in env.inner().list_objects().await? {
+        let Some(object) = env.inner().get_object(&id.into()) else {
+            continue;
+        };
+        if !object.type_().is_some_and(|ty| ty.is_upgrade_cap()) {
+            continue;
+        }
+        let Some(data) = object.data.try_as_move() else {
+            continue;
+        };
+        let cap: UpgradeCap = bcs::from_bytes(data.contents())?;
+        if MoveAddress::from(cap.package.bytes) != package {
+            continue;
+        }
+        let Owner::AddressOwner(owner) = &object.owner else {
+            return Err(eyre!(
+                "upgrade cap {} of {} is not owned by an address",
+                id,
+                package
+            )
+            .into());
+        };
+        return Ok((id, (*owner).into()));
+    }
+    Err(eyre!("can not find the upgrade cap of {}", package).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use movy_types::input::{MoveCall, SequenceArgument};
+
+    use super::*;
+
+    fn call(package: MoveAddress) -> MoveSequenceCall {
+        MoveSequenceCall::Call(MoveCall {
+            module_id: package,
+            module_name: "pool".to_string(),
+            function: "deposit".to_string(),
+            type_arguments: vec![],
+            arguments: vec![SequenceArgument::Input(0)],
+        })
+    }
+
+    #[test]
+    fn only_calls_of_the_version_are_retargeted() {
+        let old = MoveAddress::from_str("0xa").unwrap();
+        let new = MoveAddress::from_str("0xb").unwrap();
+        let other = MoveAddress::two();
+        let sequence = MoveSequence {
+            inputs: vec![],
+            commands: vec![call(old), call(other)],
+        };
+        let upgraded = retarget(&sequence, old, new);
+        let targets = upgraded
+            .commands
+            .iter()
+            .map(|cmd| match cmd {
+                MoveSequenceCall::Call(call) => call.module_id,
+                _ => unreachable!(),
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(targets, vec![new, other]);
+        let downgraded = retarget(&upgraded, new, old);
+        assert_eq!(downgraded.to_string(), sequence.to_string());
+    }
+}
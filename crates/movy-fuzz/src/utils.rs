@@ -3,6 +3,7 @@ use libafl::{
     HasMetadata,
     corpus::{Corpus, CorpusId, Testcase},
     feedbacks::{Feedback, StateInitializer},
+    stages::{Restartable, Stage},
 };
 use libafl_bolts::{Named, rands::StdRand};
 use movy_types::input::{MoveAddress, MoveStructTag, MoveTypeTag};
@@ -402,3 +403,44 @@ where
         Ok(())
     }
 }
+
+/// A stage that is only performed when present, so that optional stages
+/// keep the type of the stage tuple.
+pub struct OptionalStage<ST>(pub Option<ST>);
+
+impl<E, EM, S, ST, Z> Stage<E, EM, S, Z> for OptionalStage<ST>
+where
+    ST: Stage<E, EM, S, Z>,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut S,
+        manager: &mut EM,
+    ) -> Result<(), libafl::Error> {
+        match &mut self.0 {
+            Some(stage) => stage.perform(fuzzer, executor, state, manager),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<S, ST> Restartable<S> for OptionalStage<ST>
+where
+    ST: Restartable<S>,
+{
+    fn should_restart(&mut self, state: &mut S) -> Result<bool, libafl::Error> {
+        match &mut self.0 {
+            Some(stage) => stage.should_restart(state),
+            None => Ok(true),
+        }
+    }
+
+    fn clear_progress(&mut self, state: &mut S) -> Result<(), libafl::Error> {
+        match &mut self.0 {
+            Some(stage) => stage.clear_progress(state),
+            None => Ok(()),
+        }
+    }
+}
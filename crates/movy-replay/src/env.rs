@@ -680,6 +680,48 @@ impl<
         Ok((address.into(), abi, non_test_abi, package_names))
     }
 
+    /// Compile and publish a package as the attacker, e.g. a fake coin whose
+    /// `TreasuryCap` then belongs to the attacker. Unlike [`Self::load_local`]
+    /// it is built in non-test mode and no `movy_init` is run, only the `init`
+    /// functions of its modules.
+    pub async fn load_attacker(
+        &self,
+        path: &Path,
+        attacker: MoveAddress,
+        epoch: u64,
+        epoch_ms: u64,
+        gas: ObjectID,
+        unpublished: bool,
+        verify_deps: bool,
+        isolation: &movy_sui::compile::BuildIsolation,
+    ) -> Result<(MoveAddress, MovePackageAbi, Vec<String>), MovyError> {
+        tracing::info!("Compiling attacker package {}...", path.display());
+        let mut compiled_result =
+            SuiCompiledPackage::build_checked(path, false, unpublished, verify_deps, isolation)?;
+        tracing::info!("Compiled summary: {}", &compiled_result);
+        let package_names = compiled_result.package_names.clone();
+        for it in compiled_result.modules_mut().iter_mut() {
+            mock_module_address(ObjectID::ZERO, it);
+        }
+        compiled_result.package_id = ObjectID::ZERO;
+
+        let mut executor = SuiExecutor::new(self.db.clone())?;
+        let (address, _) =
+            executor.deploy_contract(epoch, epoch_ms, attacker.into(), gas, compiled_result)?;
+        tracing::info!(
+            "Attacker package {} deployed to {}",
+            path.display(),
+            address
+        );
+
+        let mut abi = self
+            .db
+            .get_package_info(address.into())?
+            .ok_or_else(|| eyre!("attacker package {} not found after deployment", address))?;
+        abi.published_at(address.into());
+        Ok((address.into(), abi, package_names))
+    }
+
     pub async fn export_abi(&self) -> Result<BTreeMap<MoveAddress, MovePackageAbi>, MovyError> {
         let objects = self.db.list_objects().await?;
 
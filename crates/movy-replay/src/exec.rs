@@ -9,7 +9,10 @@ use color_eyre::eyre::eyre;
 use itertools::Itertools;
 use move_trace_format::{format::MoveTraceBuilder, interface::Tracer};
 use move_vm_runtime::move_vm::MoveVM;
-use movy_sui::{compile::SuiCompiledPackage, database::cache::ObjectSuiStoreCommit};
+use movy_sui::{
+    compile::SuiCompiledPackage,
+    database::cache::{CachedStore, ObjectSuiStoreCommit},
+};
 use movy_types::{
     error::MovyError,
     input::{MoveAddress, pprint_ptb},
@@ -62,6 +65,21 @@ pub struct SuiExecutor<T> {
     pub deploy_ids: u64,
 }
 
+impl<T: Clone> SuiExecutor<T> {
+    /// An executor sharing this VM whose writes land in a fresh cache layer
+    /// above `db`, so several transactions can be committed and then dropped.
+    pub fn layered(&self) -> SuiExecutor<CachedStore<T>> {
+        SuiExecutor {
+            db: CachedStore::new(self.db.clone()),
+            protocol_config: self.protocol_config.clone(),
+            metrics: self.metrics.clone(),
+            registry: self.registry.clone(),
+            movevm: self.movevm.clone(),
+            deploy_ids: self.deploy_ids,
+        }
+    }
+}
+
 pub struct ExecutionResults {
     pub effects: TransactionEffects,
     pub store: InnerTemporaryStore,
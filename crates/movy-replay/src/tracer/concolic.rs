@@ -7,13 +7,14 @@ use std::{
 
 use crate::tracer::{extra::InstructionExtraInformation, state::TraceState};
 use move_binary_format::file_format::Bytecode;
-use move_core_types::{language_storage::TypeTag, u256::U256};
+use move_core_types::u256::U256;
 use move_trace_format::{
-    format::{Effect, Frame, TraceEvent, TraceValue, TypeTagWithRefs},
+    format::{Effect, Frame, Location, TraceEvent, TraceValue},
     value::SerializableMoveValue,
 };
+use movy_types::input::{InputArgument, MoveSequence, MoveSequenceCall, SequenceArgument};
 use tracing::{trace, warn};
-use z3::ast::{Ast, Bool, Int};
+use z3::ast::{Ast, BV, Bool, Int};
 
 #[derive(Clone, Debug, PartialEq, Eq)]
 enum PrimitiveValue {
@@ -26,12 +27,30 @@ enum PrimitiveValue {
     U256(U256),
 }
 
+/// Only the leading elements of a vector argument get symbols.
+pub const MAX_SYMBOLIC_ELEMENTS: usize = 16;
+
 #[derive(Clone, Debug, PartialEq, Eq)]
 pub enum SymbolValue {
     Value(Int),
+    /// An integer as a bit-vector of its width, in bit-vector mode
+    Bits(BV),
+    /// Length and the leading elements of a vector
+    Vector(Box<SymbolValue>, Vec<SymbolValue>),
+    /// Fields of a struct, in declaration order
+    Struct(Vec<SymbolValue>),
     Unknown,
 }
 
+/// Where a parameter of a top-level call gets its value from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ArgumentSource {
+    Pure,
+    Object,
+    /// `NestedResult(command, index)`; a plain `Result` is index 0
+    Result(usize, usize),
+}
+
 impl PrimitiveValue {
     fn bitwidth(&self) -> u32 {
         match self {
@@ -64,47 +83,49 @@ impl PrimitiveValue {
     }
 }
 
-fn extract_primitive_value(v: &TraceValue) -> PrimitiveValue {
-    match v.snapshot() {
-        SerializableMoveValue::Bool(b) => PrimitiveValue::Bool(*b),
-        SerializableMoveValue::U8(u) => PrimitiveValue::U8(*u),
-        SerializableMoveValue::U16(u) => PrimitiveValue::U16(*u),
-        SerializableMoveValue::U32(u) => PrimitiveValue::U32(*u),
-        SerializableMoveValue::U64(u) => PrimitiveValue::U64(*u),
-        SerializableMoveValue::U128(u) => PrimitiveValue::U128(*u),
-        SerializableMoveValue::U256(u) => PrimitiveValue::U256(*u),
-        v => panic!("Unsupported value type {:?} for comparison", v),
+fn primitive_value(v: &SerializableMoveValue) -> Option<PrimitiveValue> {
+    match v {
+        SerializableMoveValue::Bool(b) => Some(PrimitiveValue::Bool(*b)),
+        SerializableMoveValue::U8(u) => Some(PrimitiveValue::U8(*u)),
+        SerializableMoveValue::U16(u) => Some(PrimitiveValue::U16(*u)),
+        SerializableMoveValue::U32(u) => Some(PrimitiveValue::U32(*u)),
+        SerializableMoveValue::U64(u) => Some(PrimitiveValue::U64(*u)),
+        SerializableMoveValue::U128(u) => Some(PrimitiveValue::U128(*u)),
+        SerializableMoveValue::U256(u) => Some(PrimitiveValue::U256(*u)),
+        _ => None,
     }
 }
 
-fn compare_value_impl(v1: &PrimitiveValue, v2: &PrimitiveValue) -> Ordering {
+/// The integer or bool behind `v`, `None` for addresses, vectors, structs and
+/// the like.
+fn extract_primitive_value(v: &TraceValue) -> Option<PrimitiveValue> {
+    primitive_value(v.snapshot())
+}
+
+fn compare_value_impl(v1: &PrimitiveValue, v2: &PrimitiveValue) -> Option<Ordering> {
     match (v1, v2) {
-        (PrimitiveValue::Bool(l), PrimitiveValue::Bool(r)) => l.cmp(r),
-        (PrimitiveValue::U8(l), PrimitiveValue::U8(r)) => l.cmp(r),
-        (PrimitiveValue::U16(l), PrimitiveValue::U16(r)) => l.cmp(r),
-        (PrimitiveValue::U32(l), PrimitiveValue::U32(r)) => l.cmp(r),
-        (PrimitiveValue::U64(l), PrimitiveValue::U64(r)) => l.cmp(r),
-        (PrimitiveValue::U128(l), PrimitiveValue::U128(r)) => l.cmp(r),
-        (PrimitiveValue::U256(l), PrimitiveValue::U256(r)) => l.cmp(r),
-        _ => panic!(
-            "Unsupported value type {:?} and {:?} for comparison",
-            v1, v2
-        ),
+        (PrimitiveValue::Bool(l), PrimitiveValue::Bool(r)) => Some(l.cmp(r)),
+        (PrimitiveValue::U8(l), PrimitiveValue::U8(r)) => Some(l.cmp(r)),
+        (PrimitiveValue::U16(l), PrimitiveValue::U16(r)) => Some(l.cmp(r)),
+        (PrimitiveValue::U32(l), PrimitiveValue::U32(r)) => Some(l.cmp(r)),
+        (PrimitiveValue::U64(l), PrimitiveValue::U64(r)) => Some(l.cmp(r)),
+        (PrimitiveValue::U128(l), PrimitiveValue::U128(r)) => Some(l.cmp(r)),
+        (PrimitiveValue::U256(l), PrimitiveValue::U256(r)) => Some(l.cmp(r)),
+        _ => None,
     }
 }
 
-pub fn compare_value(v1: &TraceValue, v2: &TraceValue) -> Ordering {
-    let p1 = extract_primitive_value(v1);
-    let p2 = extract_primitive_value(v2);
-    compare_value_impl(&p1, &p2)
+/// How two integers of the same type compare, `None` for other values.
+pub fn compare_value(v1: &TraceValue, v2: &TraceValue) -> Option<Ordering> {
+    compare_value_impl(&extract_primitive_value(v1)?, &extract_primitive_value(v2)?)
 }
 
-pub fn value_to_u256(v: &TraceValue) -> U256 {
-    extract_primitive_value(v).as_u256()
+pub fn value_to_u256(v: &TraceValue) -> Option<U256> {
+    extract_primitive_value(v).map(|v| v.as_u256())
 }
 
-pub fn value_bitwidth(v: &TraceValue) -> u32 {
-    extract_primitive_value(v).bitwidth()
+pub fn value_bitwidth(v: &TraceValue) -> Option<u32> {
+    extract_primitive_value(v).map(|v| v.bitwidth())
 }
 
 fn stack_top2(stack: &[TraceValue]) -> (&TraceValue, &TraceValue) {
@@ -233,7 +254,18 @@ fn suppress_warning() -> bool {
 pub struct ConcolicState {
     pub stack: Vec<SymbolValue>,
     pub locals: Vec<Vec<SymbolValue>>,
-    pub args: Vec<BTreeMap<usize, Int>>,
+    /// Symbolic parameters, indexed by command
+    pub args: Vec<BTreeMap<usize, SymbolValue>>,
+    /// Symbolic return values, keyed by command
+    pub results: BTreeMap<usize, Vec<SymbolValue>>,
+    /// Pins fields of object arguments to the values they had on entry
+    pub fixed: Vec<Bool>,
+    pub sources: BTreeMap<(usize, usize), ArgumentSource>,
+    /// Indices of the move calls in the sequence
+    pub calls: Vec<usize>,
+    pub calls_seen: usize,
+    /// Model integers as bit-vectors of their width instead of `Int`
+    pub bitvector: bool,
     pub disable: bool,
 }
 
@@ -243,10 +275,50 @@ impl ConcolicState {
             stack: Vec::new(),
             locals: Vec::new(),
             args: Vec::new(),
+            results: BTreeMap::new(),
+            fixed: Vec::new(),
+            sources: BTreeMap::new(),
+            calls: Vec::new(),
+            calls_seen: 0,
+            bitvector: false,
             disable: false,
         }
     }
 
+    /// Record where every argument of `sequence` comes from, so that values
+    /// returned by one call keep their symbols in the calls consuming them.
+    pub fn link_sequence(&mut self, sequence: &MoveSequence) {
+        self.calls.clear();
+        self.sources.clear();
+        for (cmd_idx, cmd) in sequence.commands.iter().enumerate() {
+            let MoveSequenceCall::Call(call) = cmd else {
+                continue;
+            };
+            self.calls.push(cmd_idx);
+            for (param, arg) in call.arguments.iter().enumerate() {
+                let source = match arg {
+                    SequenceArgument::Input(i) => match sequence.inputs.get(*i as usize) {
+                        Some(InputArgument::Object(..)) => ArgumentSource::Object,
+                        Some(_) => ArgumentSource::Pure,
+                        None => continue,
+                    },
+                    SequenceArgument::Result(i) => ArgumentSource::Result(*i as usize, 0),
+                    SequenceArgument::NestedResult(i, j) => {
+                        ArgumentSource::Result(*i as usize, *j as usize)
+                    }
+                    SequenceArgument::GasCoin => ArgumentSource::Object,
+                };
+                self.sources.insert((cmd_idx, param), source);
+            }
+        }
+    }
+
+    /// The command whose top-level frame is executing.
+    fn current_command(&self) -> usize {
+        let ordinal = self.calls_seen.saturating_sub(1);
+        self.calls.get(ordinal).copied().unwrap_or(ordinal)
+    }
+
     #[inline]
     fn max_u_bits(n: u32) -> Int {
         if n <= 63 {
@@ -264,27 +336,95 @@ impl ConcolicState {
         }
     }
 
-    fn resolve_arg(cmd_index: usize, param_index: usize, ty: &TypeTagWithRefs) -> SymbolValue {
-        if ty.ref_type.is_some() {
-            return SymbolValue::Unknown;
+    /// A fresh symbol for a primitive, fixed to `value` with `pin`.
+    fn leaf(&mut self, name: &str, value: PrimitiveValue, pin: bool) -> SymbolValue {
+        if self.bitvector {
+            let bv = BV::new_const(name, value.bitwidth());
+            if pin {
+                self.fixed.push(bv._eq(&Self::primitive_to_bits(value)));
+            }
+            SymbolValue::Bits(bv)
+        } else {
+            let int = Int::new_const(name);
+            if pin {
+                self.fixed.push(int._eq(&Self::primitive_to_int(value)));
+            }
+            SymbolValue::Value(int)
+        }
+    }
+
+    /// Give every integer and vector length in `value` a symbol named after
+    /// its path, e.g. `0.1`, `0.1.len`, `0.1[2]` or `0.1.balance.value`.
+    /// With `pin`, the symbols are also fixed to their concrete values.
+    fn symbolize(&mut self, name: &str, value: &SerializableMoveValue, pin: bool) -> SymbolValue {
+        if let Some(primitive) = primitive_value(value) {
+            return self.leaf(name, primitive, pin);
         }
-        match ty.type_ {
-            TypeTag::Bool
-            | TypeTag::U8
-            | TypeTag::U16
-            | TypeTag::U32
-            | TypeTag::U64
-            | TypeTag::U128
-            | TypeTag::U256 => {
-                let int = Int::new_const(format!("{}.{}", cmd_index, param_index));
-                SymbolValue::Value(int)
+        match value {
+            SerializableMoveValue::Vector(elems) => {
+                let len = self.leaf(
+                    &format!("{}.len", name),
+                    PrimitiveValue::U64(elems.len() as u64),
+                    pin,
+                );
+                let elems = elems
+                    .iter()
+                    .take(MAX_SYMBOLIC_ELEMENTS)
+                    .enumerate()
+                    .map(|(k, e)| self.symbolize(&format!("{}[{}]", name, k), e, pin))
+                    .collect();
+                SymbolValue::Vector(Box::new(len), elems)
             }
+            SerializableMoveValue::Struct(st) => SymbolValue::Struct(
+                st.fields
+                    .iter()
+                    .map(|(id, v)| self.symbolize(&format!("{}.{}", name, id), v, pin))
+                    .collect(),
+            ),
             _ => SymbolValue::Unknown,
         }
     }
 
-    fn resolve_value(value: &TraceValue) -> Int {
-        match extract_primitive_value(value) {
+    fn resolve_param(
+        &mut self,
+        cmd_index: usize,
+        param_index: usize,
+        value: &TraceValue,
+    ) -> SymbolValue {
+        let name = format!("{}.{}", cmd_index, param_index);
+        let source = match self.sources.get(&(cmd_index, param_index)) {
+            Some(source) => source.clone(),
+            // Without a linked sequence, treat structs as objects
+            None if self.calls.is_empty() => match value.snapshot() {
+                SerializableMoveValue::Struct(_) => ArgumentSource::Object,
+                _ => ArgumentSource::Pure,
+            },
+            // Parameters filled in by the runtime, e.g. `TxContext`
+            None => return SymbolValue::Unknown,
+        };
+        match source {
+            ArgumentSource::Pure => self.symbolize(&name, value.snapshot(), false),
+            ArgumentSource::Object => self.symbolize(&name, value.snapshot(), true),
+            ArgumentSource::Result(cmd, idx) => self
+                .results
+                .get(&cmd)
+                .and_then(|values| values.get(idx))
+                .cloned()
+                .unwrap_or(SymbolValue::Unknown),
+        }
+    }
+
+    /// Pop an operand of an arithmetic or comparison instruction. Vectors
+    /// and structs never take part in those, so they count as unknown.
+    fn pop_scalar(&mut self) -> SymbolValue {
+        match self.stack.pop() {
+            Some(v @ (SymbolValue::Value(_) | SymbolValue::Bits(_))) => v,
+            _ => SymbolValue::Unknown,
+        }
+    }
+
+    fn primitive_to_int(value: PrimitiveValue) -> Int {
+        match value {
             PrimitiveValue::Bool(b) => {
                 let int_val = if b { 1 } else { 0 };
                 Int::from_u64(int_val)
@@ -298,29 +438,281 @@ impl ConcolicState {
         }
     }
 
+    fn resolve_value(value: &TraceValue) -> Option<Int> {
+        extract_primitive_value(value).map(Self::primitive_to_int)
+    }
+
+    fn primitive_to_bits(value: PrimitiveValue) -> BV {
+        let bits = value.bitwidth();
+        match value {
+            PrimitiveValue::U128(_) | PrimitiveValue::U256(_) => {
+                BV::from_int(&Self::primitive_to_int(value), bits)
+            }
+            value => BV::from_u64(value.as_u256().unchecked_as_u64(), bits),
+        }
+    }
+
+    fn resolve_bits(value: &TraceValue) -> Option<BV> {
+        extract_primitive_value(value).map(Self::primitive_to_bits)
+    }
+
+    /// A symbolic operand as a bit-vector as wide as its concrete value.
+    fn bits_operand(symbol: SymbolValue, concrete: &TraceValue) -> Option<BV> {
+        let width = value_bitwidth(concrete)?;
+        match symbol {
+            SymbolValue::Bits(bv) if bv.get_size() == width => Some(bv),
+            SymbolValue::Value(int) => Some(BV::from_int(&int, width)),
+            _ => None,
+        }
+    }
+
+    fn process_binary_bits(&mut self, stack: &[TraceValue]) -> Option<(BV, BV)> {
+        let (rhs, lhs) = (self.pop_scalar(), self.pop_scalar());
+        let (true_lhs, true_rhs) = stack_top2(stack);
+        match (
+            Self::bits_operand(lhs, true_lhs),
+            Self::bits_operand(rhs, true_rhs),
+        ) {
+            (None, None) => None,
+            (l, r) => Some((
+                l.or_else(|| Self::resolve_bits(true_lhs))?,
+                r.or_else(|| Self::resolve_bits(true_rhs))?,
+            )),
+        }
+    }
+
+    fn bits_bool(b: &Bool) -> SymbolValue {
+        SymbolValue::Bits(b.ite(&BV::from_u64(1, 1), &BV::from_u64(0, 1)))
+    }
+
+    /// Push `op(l, r)` and return the condition under which it does not abort.
+    fn arith_bits(
+        &mut self,
+        stack: &[TraceValue],
+        op: impl Fn(&BV, &BV) -> BV,
+        safe: impl Fn(&BV, &BV) -> Option<Bool>,
+    ) -> Option<Bool> {
+        match self.process_binary_bits(stack) {
+            Some((l, r)) => {
+                self.stack.push(SymbolValue::Bits(op(&l, &r)));
+                safe(&l, &r)
+            }
+            None => {
+                self.stack.push(SymbolValue::Unknown);
+                None
+            }
+        }
+    }
+
+    /// Push `cmp(l, r)` and return it as it was taken in this trace.
+    fn compare_bits(
+        &mut self,
+        stack: &[TraceValue],
+        cmp: impl Fn(&BV, &BV) -> Bool,
+        holds: impl Fn(Ordering) -> bool,
+    ) -> Option<Bool> {
+        let (true_lhs, true_rhs) = stack_top2(stack);
+        match (
+            self.process_binary_bits(stack),
+            compare_value(true_lhs, true_rhs),
+        ) {
+            (Some((l, r)), Some(ordering)) => {
+                let cond = cmp(&l, &r);
+                self.stack.push(Self::bits_bool(&cond));
+                if holds(ordering) {
+                    Some(cond)
+                } else {
+                    Some(cond.not())
+                }
+            }
+            _ => {
+                self.stack.push(SymbolValue::Unknown);
+                None
+            }
+        }
+    }
+
+    fn cast_bits(&mut self, stack: &[TraceValue], bits: u32) -> Option<Bool> {
+        let symbol = self.pop_scalar();
+        let Some(bv) = Self::bits_operand(symbol, stack_top1(stack)) else {
+            self.stack.push(SymbolValue::Unknown);
+            return None;
+        };
+        let width = bv.get_size();
+        if bits < width {
+            self.stack.push(SymbolValue::Bits(bv.extract(bits - 1, 0)));
+            Some(
+                bv.extract(width - 1, bits)
+                    ._eq(&BV::from_u64(0, width - bits)),
+            )
+        } else {
+            self.stack
+                .push(SymbolValue::Bits(bv.zero_ext(bits - width)));
+            None
+        }
+    }
+
+    /// Move semantics over bit-vectors for the instructions that compute on
+    /// integers; `None` for the rest, which do not depend on the theory.
+    fn on_bits_instruction(
+        &mut self,
+        instruction: &Bytecode,
+        stack: &[TraceValue],
+    ) -> Option<Option<Bool>> {
+        // Shift amounts are u8, widen them to the shifted value
+        let amount = |l: &BV, r: &BV| r.zero_ext(l.get_size() - r.get_size());
+        // Every u8 amount is in range for a u256
+        let in_range = |l: &BV, r: &BV| match l.get_size() {
+            256 => Bool::from_bool(true),
+            width => r.bvult(&BV::from_u64(width as u64, r.get_size())),
+        };
+        let nonzero = |_: &BV, r: &BV| Some(r._eq(&BV::from_u64(0, r.get_size())).not());
+        let constraint = match instruction {
+            Bytecode::LdTrue => {
+                self.stack.push(SymbolValue::Bits(BV::from_u64(1, 1)));
+                None
+            }
+            Bytecode::LdFalse => {
+                self.stack.push(SymbolValue::Bits(BV::from_u64(0, 1)));
+                None
+            }
+            Bytecode::CastU8 => self.cast_bits(stack, 8),
+            Bytecode::CastU16 => self.cast_bits(stack, 16),
+            Bytecode::CastU32 => self.cast_bits(stack, 32),
+            Bytecode::CastU64 => self.cast_bits(stack, 64),
+            Bytecode::CastU128 => self.cast_bits(stack, 128),
+            Bytecode::CastU256 => self.cast_bits(stack, 256),
+            Bytecode::Add => self.arith_bits(
+                stack,
+                |l, r| l.bvadd(r),
+                |l, r| Some(l.bvadd_no_overflow(r, false)),
+            ),
+            Bytecode::Sub => self.arith_bits(stack, |l, r| l.bvsub(r), |l, r| Some(l.bvuge(r))),
+            Bytecode::Mul => self.arith_bits(
+                stack,
+                |l, r| l.bvmul(r),
+                |l, r| Some(l.bvmul_no_overflow(r, false)),
+            ),
+            Bytecode::Div => self.arith_bits(stack, |l, r| l.bvudiv(r), nonzero),
+            Bytecode::Mod => self.arith_bits(stack, |l, r| l.bvurem(r), nonzero),
+            Bytecode::And | Bytecode::BitAnd => {
+                self.arith_bits(stack, |l, r| l.bvand(r), |_, _| None)
+            }
+            Bytecode::Or | Bytecode::BitOr => self.arith_bits(stack, |l, r| l.bvor(r), |_, _| None),
+            Bytecode::Xor => self.arith_bits(stack, |l, r| l.bvxor(r), |_, _| None),
+            Bytecode::Shl => self.arith_bits(
+                stack,
+                |l, r| l.bvshl(&amount(l, r)),
+                // Like the integer theory, log when bits are shifted out
+                |l, r| {
+                    let shifted = l.bvshl(&amount(l, r));
+                    let lost = shifted.bvlshr(&amount(l, r))._eq(l).not();
+                    Some(Bool::and(&[&in_range(l, r), &lost]))
+                },
+            ),
+            Bytecode::Shr => self.arith_bits(
+                stack,
+                |l, r| l.bvlshr(&amount(l, r)),
+                |l, r| Some(in_range(l, r)),
+            ),
+            Bytecode::Not => {
+                let symbol = self.pop_scalar();
+                match Self::bits_operand(symbol, stack_top1(stack)) {
+                    Some(bv) => self.stack.push(SymbolValue::Bits(bv.bvnot())),
+                    None => self.stack.push(SymbolValue::Unknown),
+                }
+                None
+            }
+            Bytecode::Eq => self.compare_bits(stack, |l, r| l._eq(r), Ordering::is_eq),
+            Bytecode::Neq => self.compare_bits(stack, |l, r| l._eq(r).not(), Ordering::is_ne),
+            Bytecode::Lt => self.compare_bits(stack, |l, r| l.bvult(r), Ordering::is_lt),
+            Bytecode::Le => self.compare_bits(stack, |l, r| l.bvule(r), Ordering::is_le),
+            Bytecode::Gt => self.compare_bits(stack, |l, r| l.bvugt(r), Ordering::is_gt),
+            Bytecode::Ge => self.compare_bits(stack, |l, r| l.bvuge(r), Ordering::is_ge),
+            _ => return None,
+        };
+        Some(constraint)
+    }
+
     #[inline]
     fn process_binary_op(&mut self, stack: &[TraceValue]) -> Option<(Int, Int)> {
-        let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
+        let (rhs, lhs) = (self.pop_scalar(), self.pop_scalar());
         let stack_len = stack.len();
         let true_lhs = &stack[stack_len - 2];
         let true_rhs = &stack[stack_len - 1];
         let (new_l, new_r) = match (lhs, rhs) {
             (SymbolValue::Value(l), SymbolValue::Value(r)) => (l, r),
             (SymbolValue::Value(l), SymbolValue::Unknown) => {
-                let new_r = Self::resolve_value(true_rhs);
+                let new_r = Self::resolve_value(true_rhs)?;
                 (l, new_r)
             }
             (SymbolValue::Unknown, SymbolValue::Value(r)) => {
-                let new_l = Self::resolve_value(true_lhs);
+                let new_l = Self::resolve_value(true_lhs)?;
                 (new_l, r)
             }
-            (SymbolValue::Unknown, SymbolValue::Unknown) => {
+            _ => {
                 return None;
             }
         };
         Some((new_l, new_r))
     }
 
+    /// Push `cmp(l, r)` as 0 or 1 and return it as it was taken in this
+    /// trace.
+    fn compare_int(
+        &mut self,
+        stack: &[TraceValue],
+        cmp: impl Fn(&Int, &Int) -> Bool,
+        holds: impl Fn(Ordering) -> bool,
+    ) -> Option<Bool> {
+        let (true_lhs, true_rhs) = stack_top2(stack);
+        match (
+            self.process_binary_op(stack),
+            compare_value(true_lhs, true_rhs),
+        ) {
+            (Some((l, r)), Some(ordering)) => {
+                let cond = cmp(&l, &r);
+                let int = cond.ite(&Int::from_u64(1), &Int::from_u64(0));
+                self.stack.push(SymbolValue::Value(int));
+                if holds(ordering) {
+                    Some(cond)
+                } else {
+                    Some(cond.not())
+                }
+            }
+            _ => {
+                self.stack.push(SymbolValue::Unknown);
+                None
+            }
+        }
+    }
+
+    /// Drop the symbol of the local `reference` points into once it is
+    /// written through, e.g. a vector which got longer by a push.
+    fn forget_referenced(&mut self, reference: &TraceValue, trace_state: &TraceState) {
+        let (TraceValue::ImmRef { location, .. } | TraceValue::MutRef { location, .. }) = reference
+        else {
+            return;
+        };
+        let mut location = location;
+        while let Location::Indexed(parent, _) = location {
+            location = parent.as_ref();
+        }
+        let Location::Local(frame, idx) = location else {
+            return;
+        };
+        let Some(depth) = trace_state.call_stack.keys().position(|id| id == frame) else {
+            return;
+        };
+        if let Some(slot) = self
+            .locals
+            .get_mut(depth)
+            .and_then(|locals| locals.get_mut(*idx))
+        {
+            *slot = SymbolValue::Unknown;
+        }
+    }
+
     pub fn on_move_call_inner(&mut self) {
         self.locals.clear();
         self.stack.clear();
@@ -367,30 +759,26 @@ impl ConcolicState {
     pub fn on_open_frame_inner(&mut self, frame: &Box<Frame>, _trace_state: &TraceState) {
         let param_count = frame.parameters.len();
         if self.locals.is_empty() {
-            let mut locals = if frame.locals_types.is_empty() {
-                vec![SymbolValue::Unknown; param_count]
-            } else {
-                frame
-                    .locals_types
-                    .iter()
-                    .enumerate()
-                    .map(|(i, ty)| Self::resolve_arg(self.args.len(), i, ty))
-                    .collect::<Vec<_>>()
-            };
-            if locals.len() < param_count {
-                locals.resize(param_count, SymbolValue::Unknown);
+            self.calls_seen += 1;
+            let cmd_index = self.current_command();
+            let mut locals = Vec::with_capacity(frame.locals_types.len().max(param_count));
+            for (i, value) in frame.parameters.iter().enumerate() {
+                let symbol = self.resolve_param(cmd_index, i, value);
+                locals.push(symbol);
             }
-            self.args.push(
-                locals
-                    .iter()
-                    .take(param_count)
-                    .enumerate()
-                    .filter_map(|(i, v)| match v {
-                        SymbolValue::Value(bv) => Some((i, bv.clone())),
-                        SymbolValue::Unknown => None,
-                    })
-                    .collect(),
-            );
+            if locals.len() < frame.locals_types.len() {
+                locals.resize(frame.locals_types.len(), SymbolValue::Unknown);
+            }
+            if self.args.len() <= cmd_index {
+                self.args.resize(cmd_index + 1, BTreeMap::new());
+            }
+            self.args[cmd_index] = locals
+                .iter()
+                .take(param_count)
+                .enumerate()
+                .filter(|(_, v)| !matches!(v, SymbolValue::Unknown))
+                .map(|(i, v)| (i, v.clone()))
+                .collect();
             self.locals.push(locals);
             trace!("args: {:?}", self.args);
         } else {
@@ -416,6 +804,11 @@ impl ConcolicState {
 
     pub fn on_close_frame_inner(&mut self, _state: &TraceState) {
         self.locals.pop();
+        if self.locals.is_empty() {
+            // What is left on the stack is returned to the sequence
+            self.results
+                .insert(self.current_command(), self.stack.clone());
+        }
     }
 
     pub fn on_before_instruction_inner(
@@ -445,14 +838,54 @@ impl ConcolicState {
             "Before instruction at pc {}: {:?}, extra: {:?}. Current stack: {:?}",
             pc, instruction, extra, &trace_state.operand_stack
         );
+        if self.bitvector
+            && let Some(constraint) = self.on_bits_instruction(instruction, stack)
+        {
+            return constraint;
+        }
         match instruction {
-            Bytecode::Pop
-            | Bytecode::BrTrue(_)
-            | Bytecode::BrFalse(_)
-            | Bytecode::Abort
-            | Bytecode::VecImmBorrow(_)
-            | Bytecode::VecMutBorrow(_) => {
+            Bytecode::Pop | Bytecode::BrTrue(_) | Bytecode::BrFalse(_) | Bytecode::Abort => {
+                self.stack.pop();
+            }
+            Bytecode::VecImmBorrow(_) | Bytecode::VecMutBorrow(_) => {
                 self.stack.pop();
+                let index = value_to_u256(stack_top1(stack));
+                let elem = match (self.stack.pop(), index) {
+                    (Some(SymbolValue::Vector(_, elems)), Some(index))
+                        if index < U256::from(elems.len() as u64) =>
+                    {
+                        elems[index.unchecked_as_u64() as usize].clone()
+                    }
+                    _ => SymbolValue::Unknown,
+                };
+                self.stack.push(elem);
+            }
+            Bytecode::VecLen(_) => {
+                let len = match self.stack.pop() {
+                    Some(SymbolValue::Vector(len, _)) => *len,
+                    _ => SymbolValue::Unknown,
+                };
+                self.stack.push(len);
+            }
+            Bytecode::VecPopBack(_) => {
+                self.stack.pop();
+                self.stack.push(SymbolValue::Unknown);
+            }
+            Bytecode::ImmBorrowField(_)
+            | Bytecode::MutBorrowField(_)
+            | Bytecode::ImmBorrowFieldGeneric(_)
+            | Bytecode::MutBorrowFieldGeneric(_) => {
+                let field = match (self.stack.pop(), extra.as_ref()) {
+                    (
+                        Some(SymbolValue::Struct(fields)),
+                        Some(
+                            InstructionExtraInformation::BorrowField(idx)
+                            | InstructionExtraInformation::BorrowFieldGeneric(idx),
+                        ),
+                    ) => fields.get(*idx).cloned().unwrap_or(SymbolValue::Unknown),
+                    _ => SymbolValue::Unknown,
+                };
+                self.stack.push(field);
             }
             Bytecode::LdU8(_)
             | Bytecode::LdU16(_)
@@ -560,11 +993,17 @@ impl ConcolicState {
                 }
             }
             Bytecode::And | Bytecode::BitAnd => {
-                let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
+                let (rhs, lhs) = (self.pop_scalar(), self.pop_scalar());
                 let (true_lhs, true_rhs) = stack_top2(stack);
 
-                let bit_width = value_bitwidth(true_lhs);
-                let (true_l, true_r) = (value_to_u256(true_lhs), value_to_u256(true_rhs));
+                let (Some(bit_width), Some(true_l), Some(true_r)) = (
+                    value_bitwidth(true_lhs),
+                    value_to_u256(true_lhs),
+                    value_to_u256(true_rhs),
+                ) else {
+                    self.stack.push(SymbolValue::Unknown);
+                    return None;
+                };
                 match (lhs, rhs) {
                     (SymbolValue::Value(_l), SymbolValue::Value(_r)) => {
                         self.stack.push(SymbolValue::Unknown);
@@ -577,17 +1016,23 @@ impl ConcolicState {
                         let and = int_bvand_const(&r, true_l, bit_width);
                         self.stack.push(SymbolValue::Value(and));
                     }
-                    (SymbolValue::Unknown, SymbolValue::Unknown) => {
+                    _ => {
                         self.stack.push(SymbolValue::Unknown);
                     }
                 }
             }
             Bytecode::Or | Bytecode::BitOr => {
-                let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
+                let (rhs, lhs) = (self.pop_scalar(), self.pop_scalar());
                 let (true_lhs, true_rhs) = stack_top2(stack);
 
-                let bit_width = value_bitwidth(true_lhs);
-                let (true_l, true_r) = (value_to_u256(true_lhs), value_to_u256(true_rhs));
+                let (Some(bit_width), Some(true_l), Some(true_r)) = (
+                    value_bitwidth(true_lhs),
+                    value_to_u256(true_lhs),
+                    value_to_u256(true_rhs),
+                ) else {
+                    self.stack.push(SymbolValue::Unknown);
+                    return None;
+                };
                 match (lhs, rhs) {
                     (SymbolValue::Value(_l), SymbolValue::Value(_r)) => {
                         self.stack.push(SymbolValue::Unknown);
@@ -600,17 +1045,23 @@ impl ConcolicState {
                         let or = int_bvor_const(&r, true_l, bit_width);
                         self.stack.push(SymbolValue::Value(or));
                     }
-                    (SymbolValue::Unknown, SymbolValue::Unknown) => {
+                    _ => {
                         self.stack.push(SymbolValue::Unknown);
                     }
                 }
             }
             Bytecode::Xor => {
-                let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
+                let (rhs, lhs) = (self.pop_scalar(), self.pop_scalar());
                 let (true_lhs, true_rhs) = stack_top2(stack);
 
-                let bit_width = value_bitwidth(true_lhs);
-                let (true_l, true_r) = (value_to_u256(true_lhs), value_to_u256(true_rhs));
+                let (Some(bit_width), Some(true_l), Some(true_r)) = (
+                    value_bitwidth(true_lhs),
+                    value_to_u256(true_lhs),
+                    value_to_u256(true_rhs),
+                ) else {
+                    self.stack.push(SymbolValue::Unknown);
+                    return None;
+                };
                 match (lhs, rhs) {
                     (SymbolValue::Value(_l), SymbolValue::Value(_r)) => {
                         self.stack.push(SymbolValue::Unknown);
@@ -623,16 +1074,21 @@ impl ConcolicState {
                         let xor = int_bvxor_const(&r, true_l, bit_width);
                         self.stack.push(SymbolValue::Value(xor));
                     }
-                    (SymbolValue::Unknown, SymbolValue::Unknown) => {
+                    _ => {
                         self.stack.push(SymbolValue::Unknown);
                     }
                 }
             }
             Bytecode::Shl => {
-                let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
+                let (rhs, lhs) = (self.pop_scalar(), self.pop_scalar());
                 let (true_lhs, true_rhs) = stack_top2(stack);
-                let bit_width = value_bitwidth(true_lhs);
-                let true_r = value_to_u256(true_rhs).unchecked_as_u32();
+                let (Some(bit_width), Some(true_r)) =
+                    (value_bitwidth(true_lhs), value_to_u256(true_rhs))
+                else {
+                    self.stack.push(SymbolValue::Unknown);
+                    return None;
+                };
+                let true_r = true_r.unchecked_as_u32();
                 let threshold = Self::max_u_bits(bit_width);
                 match (lhs, rhs) {
                     (SymbolValue::Value(_l), SymbolValue::Value(_r)) => {
@@ -647,16 +1103,20 @@ impl ConcolicState {
                     (SymbolValue::Unknown, SymbolValue::Value(_r)) => {
                         self.stack.push(SymbolValue::Unknown);
                     }
-                    (SymbolValue::Unknown, SymbolValue::Unknown) => {
+                    _ => {
                         self.stack.push(SymbolValue::Unknown);
                     }
                 }
             }
             Bytecode::Shr => {
-                let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
+                let (rhs, lhs) = (self.pop_scalar(), self.pop_scalar());
                 let (_true_lhs, true_rhs) = stack_top2(stack);
 
-                let true_r = value_to_u256(true_rhs).unchecked_as_u32();
+                let Some(true_r) = value_to_u256(true_rhs) else {
+                    self.stack.push(SymbolValue::Unknown);
+                    return None;
+                };
+                let true_r = true_r.unchecked_as_u32();
                 match (lhs, rhs) {
                     (SymbolValue::Value(_l), SymbolValue::Value(_r)) => {
                         self.stack.push(SymbolValue::Unknown);
@@ -668,20 +1128,19 @@ impl ConcolicState {
                     (SymbolValue::Unknown, SymbolValue::Value(_r)) => {
                         self.stack.push(SymbolValue::Unknown);
                     }
-                    (SymbolValue::Unknown, SymbolValue::Unknown) => {
+                    _ => {
                         self.stack.push(SymbolValue::Unknown);
                     }
                 }
             }
             Bytecode::Not => {
                 if let Some(v) = self.stack.pop() {
-                    match v {
-                        SymbolValue::Value(n) => {
-                            let bit_width = value_bitwidth(stack_top1(stack));
+                    match (v, value_bitwidth(stack_top1(stack))) {
+                        (SymbolValue::Value(n), Some(bit_width)) => {
                             let not_n = int_bvnot(&n, bit_width);
                             self.stack.push(SymbolValue::Value(not_n));
                         }
-                        SymbolValue::Unknown => {
+                        _ => {
                             self.stack.push(SymbolValue::Unknown);
                         }
                     }
@@ -734,206 +1193,44 @@ impl ConcolicState {
                     warn!("Stack underflow at pc {}", pc);
                 }
             }
-            Bytecode::WriteRef | Bytecode::VecPushBack(_) => {
+            Bytecode::WriteRef => {
                 self.stack.pop();
                 self.stack.pop();
+                self.forget_referenced(stack_top1(stack), trace_state);
             }
-            Bytecode::Eq => {
-                let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
-                let (true_lhs, true_rhs) = stack_top2(stack);
-                let (new_l, new_r) = match (lhs, rhs) {
-                    (SymbolValue::Value(l), SymbolValue::Value(r)) => (l, r),
-                    (SymbolValue::Value(l), SymbolValue::Unknown) => {
-                        let new_r = Self::resolve_value(true_rhs);
-                        (l, new_r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Value(r)) => {
-                        let new_l = Self::resolve_value(true_lhs);
-                        (new_l, r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Unknown) => {
-                        self.stack.push(SymbolValue::Unknown);
-                        return None;
-                    }
-                };
-                if matches!(compare_value(true_lhs, true_rhs), Ordering::Equal) {
-                    let eq = new_l._eq(&new_r);
-                    let int = eq.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(int));
-                    return Some(eq);
-                } else {
-                    // different values are not equal
-                    let neq = new_l._eq(&new_r).not();
-                    let int = neq.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(int));
-                    return Some(neq);
-                }
+            Bytecode::VecPushBack(_) => {
+                self.stack.pop();
+                self.stack.pop();
+                let (vector, _) = stack_top2(stack);
+                self.forget_referenced(vector, trace_state);
             }
+            Bytecode::Eq => return self.compare_int(stack, |l, r| l._eq(r), Ordering::is_eq),
             Bytecode::Neq => {
-                let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
-                let (true_lhs, true_rhs) = stack_top2(stack);
-                let (new_l, new_r) = match (lhs, rhs) {
-                    (SymbolValue::Value(l), SymbolValue::Value(r)) => (l, r),
-                    (SymbolValue::Value(l), SymbolValue::Unknown) => {
-                        let new_r = Self::resolve_value(true_rhs);
-                        (l, new_r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Value(r)) => {
-                        let new_l = Self::resolve_value(true_lhs);
-                        (new_l, r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Unknown) => {
-                        self.stack.push(SymbolValue::Unknown);
-                        return None;
-                    }
-                };
-                if !matches!(compare_value(true_lhs, true_rhs), Ordering::Equal) {
-                    let neq = new_l._eq(&new_r).not();
-                    let bv = neq.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(bv));
-                    return Some(neq);
-                } else {
-                    // same values are equal
-                    let eq = new_l._eq(&new_r);
-                    let bv = eq.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(bv));
-                    return Some(eq.not());
-                }
+                return self.compare_int(stack, |l, r| l._eq(r).not(), Ordering::is_ne);
             }
-            Bytecode::Lt => {
-                let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
-                let (true_lhs, true_rhs) = stack_top2(stack);
-                let (new_l, new_r) = match (lhs, rhs) {
-                    (SymbolValue::Value(l), SymbolValue::Value(r)) => (l, r),
-                    (SymbolValue::Value(l), SymbolValue::Unknown) => {
-                        let new_r = Self::resolve_value(true_rhs);
-                        (l, new_r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Value(r)) => {
-                        let new_l = Self::resolve_value(true_lhs);
-                        (new_l, r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Unknown) => {
-                        self.stack.push(SymbolValue::Unknown);
-                        return None;
-                    }
-                };
-                if matches!(compare_value(true_lhs, true_rhs), Ordering::Less) {
-                    let lt = new_l.lt(&new_r);
-                    let int = lt.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(int));
-                    return Some(lt);
-                } else {
-                    // not less than
-                    let nlt = new_l.lt(&new_r).not();
-                    let int = nlt.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(int));
-                    return Some(nlt);
-                }
-            }
-            Bytecode::Le => {
-                let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
-                let (true_lhs, true_rhs) = stack_top2(stack);
-                let (new_l, new_r) = match (lhs, rhs) {
-                    (SymbolValue::Value(l), SymbolValue::Value(r)) => (l, r),
-                    (SymbolValue::Value(l), SymbolValue::Unknown) => {
-                        let new_r = Self::resolve_value(true_rhs);
-                        (l, new_r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Value(r)) => {
-                        let new_l = Self::resolve_value(true_lhs);
-                        (new_l, r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Unknown) => {
-                        self.stack.push(SymbolValue::Unknown);
-                        return None;
-                    }
-                };
-                if !matches!(compare_value(true_lhs, true_rhs), Ordering::Greater) {
-                    let lt = new_l.le(&new_r);
-                    let int = lt.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(int));
-                    return Some(lt);
-                } else {
-                    // not less than
-                    let nlt = new_l.le(&new_r).not();
-                    let int = nlt.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(int));
-                    return Some(nlt);
-                }
-            }
-            Bytecode::Gt => {
-                let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
-                let (true_lhs, true_rhs) = stack_top2(stack);
-                let (new_l, new_r) = match (lhs, rhs) {
-                    (SymbolValue::Value(l), SymbolValue::Value(r)) => (l, r),
-                    (SymbolValue::Value(l), SymbolValue::Unknown) => {
-                        let new_r = Self::resolve_value(true_rhs);
-                        (l, new_r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Value(r)) => {
-                        let new_l = Self::resolve_value(true_lhs);
-                        (new_l, r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Unknown) => {
-                        self.stack.push(SymbolValue::Unknown);
-                        return None;
-                    }
-                };
-                if matches!(compare_value(true_lhs, true_rhs), Ordering::Greater) {
-                    let lt = new_l.gt(&new_r);
-                    let int = lt.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(int));
-                    return Some(lt);
+            Bytecode::Lt => return self.compare_int(stack, |l, r| l.lt(r), Ordering::is_lt),
+            Bytecode::Le => return self.compare_int(stack, |l, r| l.le(r), Ordering::is_le),
+            Bytecode::Gt => return self.compare_int(stack, |l, r| l.gt(r), Ordering::is_gt),
+            Bytecode::Ge => return self.compare_int(stack, |l, r| l.ge(r), Ordering::is_ge),
+            Bytecode::VecPack(_, len) => {
+                let at = self.stack.len().saturating_sub(*len as usize);
+                let mut elems = self.stack.split_off(at);
+                elems.truncate(MAX_SYMBOLIC_ELEMENTS);
+                let len = if self.bitvector {
+                    SymbolValue::Bits(BV::from_u64(*len, 64))
                 } else {
-                    // not less than
-                    let nlt = new_l.gt(&new_r).not();
-                    let int = nlt.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(int));
-                    return Some(nlt);
-                }
-            }
-            Bytecode::Ge => {
-                let (rhs, lhs) = (self.stack.pop().unwrap(), self.stack.pop().unwrap());
-                let (true_lhs, true_rhs) = stack_top2(stack);
-                let (new_l, new_r) = match (lhs, rhs) {
-                    (SymbolValue::Value(l), SymbolValue::Value(r)) => (l, r),
-                    (SymbolValue::Value(l), SymbolValue::Unknown) => {
-                        let new_r = Self::resolve_value(true_rhs);
-                        (l, new_r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Value(r)) => {
-                        let new_l = Self::resolve_value(true_lhs);
-                        (new_l, r)
-                    }
-                    (SymbolValue::Unknown, SymbolValue::Unknown) => {
-                        self.stack.push(SymbolValue::Unknown);
-                        return None;
-                    }
+                    SymbolValue::Value(Int::from_u64(*len))
                 };
-                if !matches!(compare_value(true_lhs, true_rhs), Ordering::Less) {
-                    let lt = new_l.ge(&new_r);
-                    let int = lt.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(int));
-                    return Some(lt);
-                } else {
-                    // not less than
-                    let nlt = new_l.ge(&new_r).not();
-                    let int = nlt.ite(&Int::from_u64(1), &Int::from_u64(0));
-                    self.stack.push(SymbolValue::Value(int));
-                    return Some(nlt);
-                }
-            }
-            Bytecode::VecPack(_, len) => {
-                for _ in 0..*len {
-                    self.stack.pop();
-                }
-                self.stack.push(SymbolValue::Unknown); // represent the vector as unknown
+                self.stack.push(SymbolValue::Vector(Box::new(len), elems));
             }
             Bytecode::VecUnpack(_, len) => {
-                self.stack.pop();
-                for _ in 0..*len {
-                    self.stack.push(SymbolValue::Unknown); // represent each element as unknown
+                let elems = match self.stack.pop() {
+                    Some(SymbolValue::Vector(_, elems)) => elems,
+                    _ => vec![],
+                };
+                for k in 0..*len as usize {
+                    self.stack
+                        .push(elems.get(k).cloned().unwrap_or(SymbolValue::Unknown));
                 }
             }
             Bytecode::VecSwap(_) => {
@@ -941,25 +1238,29 @@ impl ConcolicState {
                 self.stack.pop();
                 self.stack.pop();
             }
-            Bytecode::Pack(_) | Bytecode::PackGeneric(_) => {
-                match extra.as_ref().unwrap() {
-                    InstructionExtraInformation::Pack(count)
-                    | InstructionExtraInformation::PackGeneric(count) => {
-                        for _ in 0..*count {
-                            self.stack.pop();
-                        }
-                        self.stack.push(SymbolValue::Unknown); // represent the struct as unknown
-                    }
-                    _ => unreachable!(),
+            Bytecode::Pack(_) | Bytecode::PackGeneric(_) => match extra.as_ref().unwrap() {
+                InstructionExtraInformation::Pack(count)
+                | InstructionExtraInformation::PackGeneric(count) => {
+                    let at = self.stack.len().saturating_sub(*count);
+                    let fields = self.stack.split_off(at);
+                    self.stack.push(SymbolValue::Struct(fields));
                 }
-            }
+                _ => unreachable!(),
+            },
             Bytecode::Unpack(_) | Bytecode::UnpackGeneric(_) => {
-                self.stack.pop();
+                let fields = match self.stack.pop() {
+                    Some(SymbolValue::Struct(fields)) => fields,
+                    _ => vec![],
+                };
                 match extra.as_ref().unwrap() {
                     InstructionExtraInformation::Unpack(count)
                     | InstructionExtraInformation::UnpackGeneric(count) => {
-                        for _ in 0..*count {
-                            self.stack.push(SymbolValue::Unknown); // represent each field as unknown
+                        if fields.len() == *count {
+                            self.stack.extend(fields);
+                        } else {
+                            for _ in 0..*count {
+                                self.stack.push(SymbolValue::Unknown); // represent each field as unknown
+                            }
                         }
                     }
                     _ => unreachable!(),
@@ -1007,3 +1308,26 @@ impl ConcolicState {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use move_core_types::account_address::AccountAddress;
+
+    use super::*;
+
+    #[test]
+    fn only_integers_compare() {
+        let value = |value| TraceValue::RuntimeValue { value };
+        let one = value(SerializableMoveValue::U64(1));
+        let two = value(SerializableMoveValue::U64(2));
+        let address = value(SerializableMoveValue::Address(AccountAddress::ONE));
+        assert_eq!(compare_value(&one, &two), Some(Ordering::Less));
+        assert_eq!(value_bitwidth(&two), Some(64));
+        assert_eq!(compare_value(&address, &address), None);
+        assert_eq!(
+            compare_value(&one, &value(SerializableMoveValue::U8(1))),
+            None
+        );
+        assert_eq!(value_to_u256(&address), None);
+    }
+}
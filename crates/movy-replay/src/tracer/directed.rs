@@ -0,0 +1,98 @@
+use std::{cell::RefCell, collections::BTreeSet, rc::Rc};
+
+use move_binary_format::file_format::Bytecode;
+use move_trace_format::format::{Frame, TraceIndex, TraceValue};
+use movy_sui::lcov::BytecodeLocation;
+use movy_types::input::FunctionIdent;
+
+use crate::tracer::{MovySuiTracerExt, state::TraceState};
+
+/// What a single execution touched that matters for directed fuzzing.
+#[derive(Debug, Clone, Default)]
+pub struct DirectedTrace {
+    pub functions: BTreeSet<FunctionIdent>,
+    pub reached_location: bool,
+}
+
+#[derive(Clone, Default)]
+pub struct DirectedCollector {
+    targets: Rc<BTreeSet<BytecodeLocation>>,
+    inner: Rc<RefCell<DirectedTrace>>,
+}
+
+impl DirectedCollector {
+    pub fn new(targets: BTreeSet<BytecodeLocation>) -> Self {
+        Self {
+            targets: Rc::new(targets),
+            inner: Rc::new(RefCell::new(DirectedTrace::default())),
+        }
+    }
+
+    pub fn tracer(&self) -> DirectedTracer {
+        DirectedTracer {
+            targets: self.targets.clone(),
+            trace: self.inner.clone(),
+            frames: Vec::new(),
+        }
+    }
+
+    /// Take the trace of the last execution, leaving an empty one behind.
+    pub fn take(&self) -> DirectedTrace {
+        std::mem::take(&mut *self.inner.borrow_mut())
+    }
+}
+
+pub struct DirectedTracer {
+    targets: Rc<BTreeSet<BytecodeLocation>>,
+    trace: Rc<RefCell<DirectedTrace>>,
+    frames: Vec<BytecodeLocation>,
+}
+
+impl MovySuiTracerExt for DirectedTracer {
+    fn open_frame(&mut self, _state: &TraceState, frame: &Box<Frame>, _gas_left: u64) {
+        self.trace.borrow_mut().functions.insert(FunctionIdent::new(
+            &(*frame.module.address()).into(),
+            frame.module.name().as_str(),
+            &frame.function_name,
+        ));
+        self.frames.push(BytecodeLocation {
+            module: frame.module.clone(),
+            function: frame.binary_member_index,
+            pc: 0,
+        });
+    }
+
+    fn close_frame(
+        &mut self,
+        _state: &TraceState,
+        _frame_id: TraceIndex,
+        _return_: &Vec<TraceValue>,
+        _gas_left: u64,
+    ) {
+        self.frames.pop();
+    }
+
+    fn before_instruction(
+        &mut self,
+        _state: &TraceState,
+        _tys: &Vec<sui_types::TypeTag>,
+        pc: u16,
+        _gas_left: u64,
+        _instruction: &Bytecode,
+    ) {
+        if self.targets.is_empty() {
+            return;
+        }
+        let Some(current) = self.frames.last() else {
+            return;
+        };
+        let location = BytecodeLocation {
+            module: current.module.clone(),
+            function: current.function,
+            pc,
+        };
+        if self.targets.contains(&location) {
+            self.trace.borrow_mut().reached_location = true;
+        }
+    }
+}
@@ -14,6 +14,8 @@ pub enum InstructionExtraInformation {
     UnpackVariant(usize),
     UnpackGeneric(usize),
     UnpackVariantGeneric(usize),
+    BorrowField(usize),
+    BorrowFieldGeneric(usize),
 }
 
 impl InstructionExtraInformation {
@@ -104,6 +106,21 @@ impl InstructionExtraInformation {
                 let field_count = variant_def.fields.len();
                 extra = Some(InstructionExtraInformation::PackVariantGeneric(field_count));
             }
+            B::ImmBorrowField(fidx) | B::MutBorrowField(fidx) => {
+                let module = resolver.module_ref(module_id, package_id)?;
+                let field_handle = module.field_handle_at(*fidx);
+                extra = Some(InstructionExtraInformation::BorrowField(
+                    field_handle.field as usize,
+                ));
+            }
+            B::ImmBorrowFieldGeneric(fidx) | B::MutBorrowFieldGeneric(fidx) => {
+                let module = resolver.module_ref(module_id, package_id)?;
+                let field_inst = module.field_instantiation_at(*fidx);
+                let field_handle = module.field_handle_at(field_inst.handle);
+                extra = Some(InstructionExtraInformation::BorrowFieldGeneric(
+                    field_handle.field as usize,
+                ));
+            }
             _ => {}
         }
         extra
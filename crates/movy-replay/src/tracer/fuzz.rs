@@ -15,7 +15,7 @@ use crate::tracer::{
     MovySuiTracerExt,
     concolic::ConcolicState,
     extra::InstructionExtraInformation,
-    op::{CastLog, CmpLog, CmpOp, Log, Magic, ShlLog},
+    op::{ArithLog, CastLog, CmpLog, CmpOp, Log, Magic, ShlLog, magic_pairs},
     oracle::SuiGeneralOracle,
     state::TraceState,
 };
@@ -206,6 +206,29 @@ where
         self.outcome
     }
 
+    pub fn concolic_mut(&mut self) -> &mut ConcolicState {
+        &mut self.outcome.concolic
+    }
+
+    /// Record `findings` with the current call stack, `pc` is the instruction
+    /// in the innermost function they were detected at.
+    fn record_findings(&mut self, findings: Vec<OracleFinding>, pc: Option<u16>) {
+        if findings.is_empty() {
+            return;
+        }
+        self.outcome.verdict = ExitKind::Crash;
+        let stack = self
+            .current_functions
+            .iter()
+            .map(|(_, function)| function.clone())
+            .collect::<Vec<_>>();
+        self.outcome.findings.extend(
+            findings
+                .into_iter()
+                .map(|finding| finding.with_trace(&stack, pc)),
+        );
+    }
+
     fn bin_ops(stack: &[TraceValue]) -> Result<(Magic, Magic), MovyError> {
         if stack.len() < 2 {
             return Err(eyre!("stack less than 2?!").into());
@@ -232,12 +255,7 @@ where
                 current_function,
                 self.state,
             )?;
-            if !findings.is_empty() {
-                self.outcome.verdict = ExitKind::Crash;
-            }
-            for info in findings {
-                self.outcome.findings.push(info);
-            }
+            self.record_findings(findings, Some(pc));
         } else {
             tracing::warn!("no current function in before_instruction?!");
         };
@@ -267,36 +285,62 @@ where
             | Bytecode::VariantSwitch(_) => {
                 self.coverage.will_branch();
             }
-            Bytecode::Lt
-            | Bytecode::Le
-            | Bytecode::Ge
-            | Bytecode::Gt
-            | Bytecode::Neq
-            | Bytecode::Eq => match Self::bin_ops(&state.operand_stack) {
-                Ok((lhs, rhs)) => {
-                    if let Some((_, current_function)) = self.current_functions.first() {
-                        let op = CmpOp::try_from(instruction)?;
-                        self.outcome
-                            .logs
-                            .entry(current_function.clone())
-                            .or_default()
-                            .push(Log::CmpLog(CmpLog {
-                                lhs,
-                                rhs,
-                                op,
-                                constraint,
-                            }));
-                    } else {
-                        warn!("Fail to track cmplog because of no current function")
+            Bytecode::Neq | Bytecode::Eq => {
+                let stack = &state.operand_stack;
+                if stack.len() < 2 {
+                    warn!("Can not track cmplog due to stack less than 2");
+                    return Ok(());
+                }
+                let pairs = magic_pairs(
+                    stack[stack.len() - 2].snapshot(),
+                    stack[stack.len() - 1].snapshot(),
+                );
+                if let Some((_, current_function)) = self.current_functions.first() {
+                    let op = CmpOp::try_from(instruction)?;
+                    let logs = self
+                        .outcome
+                        .logs
+                        .entry(current_function.clone())
+                        .or_default();
+                    // Vectors and structs may log several pairs, the constraint
+                    // of the whole comparison goes with the first
+                    let mut constraint = constraint;
+                    for (lhs, rhs) in pairs {
+                        logs.push(Log::CmpLog(CmpLog {
+                            lhs,
+                            rhs,
+                            op,
+                            constraint: constraint.take(),
+                        }));
                     }
+                } else {
+                    warn!("Fail to track cmplog because of no current function")
                 }
-                Err(e) => {
-                    if !matches!(instruction, Bytecode::Eq) && !matches!(instruction, Bytecode::Neq)
-                    {
+            }
+            Bytecode::Lt | Bytecode::Le | Bytecode::Ge | Bytecode::Gt => {
+                match Self::bin_ops(&state.operand_stack) {
+                    Ok((lhs, rhs)) => {
+                        if let Some((_, current_function)) = self.current_functions.first() {
+                            let op = CmpOp::try_from(instruction)?;
+                            self.outcome
+                                .logs
+                                .entry(current_function.clone())
+                                .or_default()
+                                .push(Log::CmpLog(CmpLog {
+                                    lhs,
+                                    rhs,
+                                    op,
+                                    constraint,
+                                }));
+                        } else {
+                            warn!("Fail to track cmplog because of no current function")
+                        }
+                    }
+                    Err(e) => {
                         warn!("Can not track cmplog due to {}", e);
                     }
                 }
-            },
+            }
             Bytecode::Shl => match Self::bin_ops(&state.operand_stack) {
                 Ok((lhs, rhs)) => {
                     if let Some((_, current_function)) = self.current_functions.first() {
@@ -340,6 +384,22 @@ where
                     warn!("Can not track castlog due to stack empty");
                 }
             }
+            Bytecode::Add
+            | Bytecode::Sub
+            | Bytecode::Mul
+            | Bytecode::Div
+            | Bytecode::Mod
+            | Bytecode::Shr => {
+                if constraint.is_some()
+                    && let Some((_, current_function)) = self.current_functions.first()
+                {
+                    self.outcome
+                        .logs
+                        .entry(current_function.clone())
+                        .or_default()
+                        .push(Log::ArithLog(ArithLog { constraint }));
+                }
+            }
             _ => {}
         }
         Ok(())
@@ -417,7 +477,7 @@ where
         &mut self,
         state: &TraceState,
         frame: &Box<move_trace_format::format::Frame>,
-        _gas_left: u64,
+        gas_left: u64,
     ) {
         if let Err(e) = self.resolver.may_load_package(&frame.version_id.into()) {
             tracing::error!("fail to load package: {}", e);
@@ -444,6 +504,19 @@ where
             ),
         ));
         self.coverage.call_package(package);
+        if let Some((_, current_function)) = self.current_functions.last() {
+            match self.oracles.open_frame(
+                frame,
+                state,
+                &self.outcome.concolic,
+                current_function,
+                gas_left,
+                self.state,
+            ) {
+                Ok(findings) => self.record_findings(findings, None),
+                Err(e) => tracing::warn!("we have an error during tracing: {}", e),
+            }
+        }
     }
 
     fn close_frame(
@@ -451,8 +524,17 @@ where
         state: &TraceState,
         _frame_id: move_trace_format::format::TraceIndex,
         _return_: &Vec<move_trace_format::format::TraceValue>,
-        _gas_left: u64,
+        gas_left: u64,
     ) {
+        if let Some((_, current_function)) = self.current_functions.last() {
+            match self
+                .oracles
+                .close_frame(state, current_function, gas_left, self.state)
+            {
+                Ok(findings) => self.record_findings(findings, None),
+                Err(e) => tracing::warn!("we have an error during tracing: {}", e),
+            }
+        }
         if !self.skip_concolic {
             self.outcome.concolic.on_close_frame_inner(state);
         }
@@ -8,6 +8,7 @@ use sui_types::TypeTag;
 use crate::tracer::state::TraceState;
 
 pub mod concolic;
+pub mod directed;
 pub mod extra;
 pub mod fuzz;
 pub mod lcov;
@@ -62,6 +62,9 @@ impl TryFrom<&SerializableMoveValue> for Magic {
             SerializableMoveValue::U128(v) => Ok(Self::U128(*v)),
             SerializableMoveValue::U256(v) => Ok(Self::U256(U256::from_be_bytes(v.to_be_bytes()))),
             SerializableMoveValue::Address(bytes) => Ok(Self::Bytes(bytes.to_vec())),
+            SerializableMoveValue::Vector(elems) => byte_string(elems)
+                .map(Self::Bytes)
+                .ok_or_else(|| eyre!("TraceValue is not a byte string {:?}", value).into()),
             SerializableMoveValue::Struct(data) => {
                 // TODO: more generic way to serialize struct into bytes
                 let SimplifiedMoveStruct { type_, .. } = data;
@@ -75,6 +78,67 @@ impl TryFrom<&SerializableMoveValue> for Magic {
     }
 }
 
+/// Pairs beyond this many per comparison are not logged.
+pub const MAX_MAGIC_PAIRS: usize = 32;
+
+fn byte_string(elems: &[SerializableMoveValue]) -> Option<Vec<u8>> {
+    elems
+        .iter()
+        .map(|e| match e {
+            SerializableMoveValue::U8(b) => Some(*b),
+            _ => None,
+        })
+        .collect()
+}
+
+fn collect_magic_pairs(
+    lhs: &SerializableMoveValue,
+    rhs: &SerializableMoveValue,
+    out: &mut Vec<(Magic, Magic)>,
+) {
+    if out.len() >= MAX_MAGIC_PAIRS {
+        return;
+    }
+    match (lhs, rhs) {
+        (SerializableMoveValue::Vector(l), SerializableMoveValue::Vector(r)) => {
+            if let (Some(l), Some(r)) = (byte_string(l), byte_string(r)) {
+                out.push((Magic::Bytes(l), Magic::Bytes(r)));
+            } else {
+                for (l, r) in l.iter().zip(r.iter()) {
+                    collect_magic_pairs(l, r, out);
+                }
+            }
+        }
+        (SerializableMoveValue::Struct(l), SerializableMoveValue::Struct(r)) => {
+            for ((_, l), (_, r)) in l.fields.iter().zip(r.fields.iter()) {
+                collect_magic_pairs(l, r, out);
+            }
+        }
+        (SerializableMoveValue::Variant(l), SerializableMoveValue::Variant(r)) => {
+            for ((_, l), (_, r)) in l.fields.iter().zip(r.fields.iter()) {
+                collect_magic_pairs(l, r, out);
+            }
+        }
+        (l, r) => {
+            if let (Ok(l), Ok(r)) = (Magic::try_from(l), Magic::try_from(r)) {
+                out.push((l, r));
+            }
+        }
+    }
+}
+
+/// The corresponding integers, addresses and byte strings of two values
+/// compared with `Eq` or `Neq`, e.g. the bytes of two `String`s or the
+/// addresses inside two structs.
+pub fn magic_pairs(
+    lhs: &SerializableMoveValue,
+    rhs: &SerializableMoveValue,
+) -> Vec<(Magic, Magic)> {
+    let mut out = vec![];
+    collect_magic_pairs(lhs, rhs, &mut out);
+    out
+}
+
 impl TryFrom<&TraceValue> for Magic {
     type Error = MovyError;
 
@@ -142,11 +206,19 @@ pub struct CastLog {
     pub constraint: Option<z3::ast::Bool>,
 }
 
+/// The condition under which an arithmetic instruction does not abort,
+/// only known in bit-vector mode.
+#[derive(PartialEq, Eq, Debug, Clone)]
+pub struct ArithLog {
+    pub constraint: Option<z3::ast::Bool>,
+}
+
 #[derive(PartialEq, Eq, Debug, Clone)]
 pub enum Log {
     CmpLog(CmpLog),
     ShlLog(ShlLog),
     CastLog(CastLog),
+    ArithLog(ArithLog),
 }
 
 impl Display for CmpLog {
@@ -154,3 +226,35 @@ impl Display for CmpLog {
         f.write_fmt(format_args!("{}{}{}", &self.lhs, &self.op, &self.rhs))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(v: &[u8]) -> SerializableMoveValue {
+        SerializableMoveValue::Vector(v.iter().map(|b| SerializableMoveValue::U8(*b)).collect())
+    }
+
+    #[test]
+    fn magic_pairs_split_nested_vectors() {
+        let lhs = SerializableMoveValue::Vector(vec![bytes(b"admin"), bytes(b"")]);
+        let rhs = SerializableMoveValue::Vector(vec![bytes(b"user"), bytes(b"x")]);
+        assert_eq!(
+            magic_pairs(&lhs, &rhs),
+            vec![
+                (
+                    Magic::Bytes(b"admin".to_vec()),
+                    Magic::Bytes(b"user".to_vec())
+                ),
+                (Magic::Bytes(vec![]), Magic::Bytes(b"x".to_vec())),
+            ]
+        );
+        assert_eq!(
+            magic_pairs(
+                &SerializableMoveValue::U64(1),
+                &SerializableMoveValue::U64(2)
+            ),
+            vec![(Magic::U64(1), Magic::U64(2))]
+        );
+    }
+}
@@ -25,6 +25,18 @@ pub trait SuiGeneralOracle<S> {
         _trace_state: &TraceState,
         _symbol_stack: &ConcolicState,
         _current_function: &FunctionIdent,
+        _gas_left: u64,
+        _state: &mut S,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        Ok(vec![])
+    }
+
+    /// Called before `current_function` returns or unwinds.
+    fn close_frame(
+        &mut self,
+        _trace_state: &TraceState,
+        _current_function: &FunctionIdent,
+        _gas_left: u64,
         _state: &mut S,
     ) -> Result<Vec<OracleFinding>, MovyError> {
         Ok(vec![])
@@ -105,15 +117,45 @@ where
         trace_state: &TraceState,
         symbol_stack: &ConcolicState,
         current_function: &FunctionIdent,
+        gas_left: u64,
+        state: &mut S,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        Ok(self
+            .0
+            .open_frame(
+                frame,
+                trace_state,
+                symbol_stack,
+                current_function,
+                gas_left,
+                state,
+            )?
+            .into_iter()
+            .chain(self.1.open_frame(
+                frame,
+                trace_state,
+                symbol_stack,
+                current_function,
+                gas_left,
+                state,
+            )?)
+            .collect())
+    }
+
+    fn close_frame(
+        &mut self,
+        trace_state: &TraceState,
+        current_function: &FunctionIdent,
+        gas_left: u64,
         state: &mut S,
     ) -> Result<Vec<OracleFinding>, MovyError> {
         Ok(self
             .0
-            .open_frame(frame, trace_state, symbol_stack, current_function, state)?
+            .close_frame(trace_state, current_function, gas_left, state)?
             .into_iter()
             .chain(
                 self.1
-                    .open_frame(frame, trace_state, symbol_stack, current_function, state)?,
+                    .close_frame(trace_state, current_function, gas_left, state)?,
             )
             .collect())
     }
@@ -197,14 +239,35 @@ where
         trace_state: &TraceState,
         symbol_stack: &ConcolicState,
         current_function: &FunctionIdent,
+        gas_left: u64,
         state: &mut S,
     ) -> Result<Vec<OracleFinding>, MovyError> {
         if self.disabled {
             return Ok(vec![]);
         }
 
+        self.oracle.open_frame(
+            frame,
+            trace_state,
+            symbol_stack,
+            current_function,
+            gas_left,
+            state,
+        )
+    }
+
+    fn close_frame(
+        &mut self,
+        trace_state: &TraceState,
+        current_function: &FunctionIdent,
+        gas_left: u64,
+        state: &mut S,
+    ) -> Result<Vec<OracleFinding>, MovyError> {
+        if self.disabled {
+            return Ok(vec![]);
+        }
         self.oracle
-            .open_frame(frame, trace_state, symbol_stack, current_function, state)
+            .close_frame(trace_state, current_function, gas_left, state)
     }
 
     fn before_instruction(
@@ -17,15 +17,11 @@ pub fn analyze(modules: &[ModuleAnalysis]) -> Vec<OracleFinding> {
                 continue;
             }
             if detect_bool_judgement(function) {
-                reports.push(OracleFinding {
-                    oracle: "StaticBoolJudgement".to_string(),
-                    severity: Severity::Minor,
-                    extra: json!({
+                reports.push(OracleFinding::new("StaticBoolJudgement", Severity::Minor, json!({
                         "module": module.qualified_module_name(),
                         "function": function.name.clone(),
                         "message": "Unnecessary bool judgement (boolean compared with boolean literal)"
-                    }),
-                });
+                    })));
             }
         }
     }
@@ -16,15 +16,15 @@ pub fn analyze(modules: &[ModuleAnalysis]) -> Vec<OracleFinding> {
                 continue;
             }
             if detect_infinite_loop(function) {
-                reports.push(OracleFinding {
-                    oracle: "StaticInfiniteLoop".to_string(),
-                    severity: Severity::Major,
-                    extra: json!({
+                reports.push(OracleFinding::new(
+                    "StaticInfiniteLoop",
+                    Severity::Major,
+                    json!({
                         "module": module.qualified_module_name(),
                         "function": function.name.clone(),
                         "message": "Potential infinite loop detected from constant branch condition"
                     }),
-                });
+                ));
             }
         }
     }
@@ -14,15 +14,11 @@ pub fn analyze(modules: &[ModuleAnalysis]) -> Vec<OracleFinding> {
                 continue;
             }
             if detect_precision_loss(function, module.global_env.symbol_pool()) {
-                reports.push(OracleFinding {
-                    oracle: "StaticPrecisionLoss".to_string(),
-                    severity: Severity::Medium,
-                    extra: json!({
+                reports.push(OracleFinding::new("StaticPrecisionLoss", Severity::Medium, json!({
                         "module": module.qualified_module_name(),
                         "function": function.name.clone(),
                         "message": "Potential precision loss from multiplication involving division/sqrt"
-                    }),
-                });
+                    })));
             }
         }
     }
@@ -14,15 +14,15 @@ pub fn analyze(modules: &[ModuleAnalysis]) -> Vec<OracleFinding> {
                 continue;
             }
             if detect_unnecessary_type_conversion(function) {
-                reports.push(OracleFinding {
-                    oracle: "StaticTypeConversion".to_string(),
-                    severity: Severity::Minor,
-                    extra: json!({
+                reports.push(OracleFinding::new(
+                    "StaticTypeConversion",
+                    Severity::Minor,
+                    json!({
                         "module": module.qualified_module_name(),
                         "function": function.name.clone(),
                         "message": "Unnecessary type conversion"
                     }),
-                });
+                ));
             }
         }
     }
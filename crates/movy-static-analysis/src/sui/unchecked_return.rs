@@ -14,16 +14,16 @@ pub fn analyze(modules: &[ModuleAnalysis]) -> Vec<OracleFinding> {
                 continue;
             }
             for fid in detect_unchecked_return(function) {
-                reports.push(OracleFinding {
-                    oracle: "StaticUncheckedReturn".to_string(),
-                    severity: Severity::Minor,
-                    extra: json!({
+                reports.push(OracleFinding::new(
+                    "StaticUncheckedReturn",
+                    Severity::Minor,
+                    json!({
                         "module": module.qualified_module_name(),
                         "function": function.name.clone(),
                         "callee": module.get_function_name(&fid),
                         "message": "Return value dropped without handling"
                     }),
-                });
+                ));
             }
         }
     }
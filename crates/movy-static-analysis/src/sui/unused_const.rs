@@ -67,16 +67,16 @@ where
                     .iter()
                     .map(|v| format!("{v:?}"))
                     .collect::<Vec<_>>();
-                reports.push(OracleFinding {
-                    oracle: "StaticUnusedConstant".to_string(),
-                    severity: Severity::Informational,
-                    extra: json!({
+                reports.push(OracleFinding::new(
+                    "StaticUnusedConstant",
+                    Severity::Informational,
+                    json!({
                         "package": pkg.to_string(),
                         "module": module.module_id.module_name.to_string(),
                         "unused_constants": unused_constants,
                         "message": "Constants are defined but never referenced"
                     }),
-                });
+                ));
             }
         }
     }
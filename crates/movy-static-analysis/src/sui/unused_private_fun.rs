@@ -126,16 +126,16 @@ where
                     .iter()
                     .map(|(module_id, func)| format!("{module_id}::{func}"))
                     .collect::<Vec<_>>();
-                reports.push(OracleFinding {
-                    oracle: "StaticUnusedPrivateFunction".to_string(),
-                    severity: Severity::Informational,
-                    extra: json!({
+                reports.push(OracleFinding::new(
+                    "StaticUnusedPrivateFunction",
+                    Severity::Informational,
+                    json!({
                         "package": pkg.to_string(),
                         "module": module.to_string(),
                         "functions": unused,
                         "message": "Private functions are never invoked"
                     }),
-                });
+                ));
             }
         }
         if !unused_friend_functions.is_empty() {
@@ -143,15 +143,15 @@ where
                 .iter()
                 .map(|(module_id, func)| format!("{module_id}::{func}"))
                 .collect::<Vec<_>>();
-            reports.push(OracleFinding {
-                oracle: "StaticUnusedFriendFunction".to_string(),
-                severity: Severity::Informational,
-                extra: json!({
+            reports.push(OracleFinding::new(
+                "StaticUnusedFriendFunction",
+                Severity::Informational,
+                json!({
                     "package": pkg.to_string(),
                     "functions": unused,
                     "message": "Friend functions are never invoked"
                 }),
-            });
+            ));
         }
     }
 
@@ -94,28 +94,28 @@ where
                 .filter_map(|(id, visited)| if !visited { Some(id) } else { None })
                 .collect::<Vec<_>>();
             if !unused_struct.is_empty() {
-                reports.push(OracleFinding {
-                    oracle: "StaticUnusedStruct".to_string(),
-                    severity: Severity::Informational,
-                    extra: json!({
+                reports.push(OracleFinding::new(
+                    "StaticUnusedStruct",
+                    Severity::Informational,
+                    json!({
                         "package": pkg.to_string(),
                         "module": module.module_id.module_name.to_string(),
                         "struct_indices": unused_struct,
                         "message": "Structs are defined but never used"
                     }),
-                });
+                ));
             }
             if !unused_enum.is_empty() {
-                reports.push(OracleFinding {
-                    oracle: "StaticUnusedEnum".to_string(),
-                    severity: Severity::Informational,
-                    extra: json!({
+                reports.push(OracleFinding::new(
+                    "StaticUnusedEnum",
+                    Severity::Informational,
+                    json!({
                         "package": pkg.to_string(),
                         "module": module.module_id.module_name.to_string(),
                         "enum_indices": unused_enum,
                         "message": "Enums are defined but never used"
                     }),
-                });
+                ));
             }
         }
     }
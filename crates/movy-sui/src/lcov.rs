@@ -9,7 +9,7 @@ use move_binary_format::file_format::FunctionDefinitionIndex;
 use move_bytecode_source_map::source_map::SourceMap;
 use move_compiler::{compiled_unit::CompiledUnit, shared::files::MappedFiles};
 use move_core_types::language_storage::ModuleId;
-use movy_types::{error::MovyError, input::MoveAddress};
+use movy_types::{abi::MoveModuleId, error::MovyError, input::MoveAddress};
 use serde::{Deserialize, Serialize};
 use sui_types::base_types::ObjectID;
 
@@ -178,6 +178,71 @@ impl LineCoverageMap {
         Ok(())
     }
 
+    /// All bytecode locations of `function` in `module` whose source maps to
+    /// `line`. Empty when the function is unknown or has no code at that line.
+    pub fn locations_for_line(
+        &self,
+        module: &MoveModuleId,
+        function: &str,
+        line: usize,
+    ) -> BTreeSet<BytecodeLocation> {
+        let is_module = |id: &ModuleId| {
+            MoveAddress::from(*id.address()) == module.module_address
+                && id.name().as_str() == module.module_name
+        };
+        let indices = self
+            .function_to_source
+            .iter()
+            .filter(|(loc, source)| is_module(&loc.module) && source.name == function)
+            .map(|(loc, _)| loc.function)
+            .collect::<BTreeSet<_>>();
+        self.pc_to_line
+            .iter()
+            .filter(|(loc, source)| {
+                is_module(&loc.module) && indices.contains(&loc.function) && source.line == line
+            })
+            .map(|(loc, _)| loc.clone())
+            .collect()
+    }
+
+    /// The source file and line of `pc` in `function` of `module`, if known.
+    pub fn source_line(
+        &self,
+        module: &MoveModuleId,
+        function: &str,
+        pc: u16,
+    ) -> Option<(PathBuf, usize)> {
+        self.function_to_source
+            .iter()
+            .filter(|(loc, source)| {
+                MoveAddress::from(*loc.module.address()) == module.module_address
+                    && loc.module.name().as_str() == module.module_name
+                    && source.name == function
+            })
+            .find_map(|(loc, _)| {
+                self.pc_to_line.get(&BytecodeLocation {
+                    module: loc.module.clone(),
+                    function: loc.function,
+                    pc,
+                })
+            })
+            .map(|source| (source.file.clone(), source.line))
+    }
+
+    /// Number of source lines `hits` cover, and of all lines with code.
+    pub fn covered_lines<'a, I>(&self, hits: I) -> (usize, usize)
+    where
+        I: IntoIterator<Item = &'a BytecodeLocation>,
+    {
+        let covered = hits
+            .into_iter()
+            .filter_map(|hit| self.pc_to_line.get(hit))
+            .collect::<BTreeSet<_>>()
+            .len();
+        let total = self.files.values().map(|file| file.lines.len()).sum();
+        (covered, total)
+    }
+
     /// Render an LCOV-format coverage report as a string. Pure
     /// function over the map's static metadata + the supplied
     /// per-run hits. Callers that want it on disk go through
@@ -273,3 +338,57 @@ fn line_for_loc(
     }
     files.start_position_opt(loc).map(|pos| pos.user_line())
 }
+
+#[cfg(test)]
+mod tests {
+    use move_core_types::{account_address::AccountAddress, identifier::Identifier};
+
+    use super::*;
+
+    #[test]
+    fn source_lines_resolve_by_function_name() {
+        let module = ModuleId::new(AccountAddress::ONE, Identifier::new("pool").unwrap());
+        let file = PathBuf::from("sources/pool.move");
+        let mut map = LineCoverageMap {
+            files: BTreeMap::new(),
+            function_to_source: BTreeMap::new(),
+            pc_to_line: BTreeMap::new(),
+        };
+        for (function, name) in [(0, "deposit"), (1, "swap")] {
+            map.function_to_source.insert(
+                FunctionLocation {
+                    module: module.clone(),
+                    function,
+                },
+                FunctionSource {
+                    file: file.clone(),
+                    name: name.to_string(),
+                },
+            );
+            map.pc_to_line.insert(
+                BytecodeLocation {
+                    module: module.clone(),
+                    function,
+                    pc: 3,
+                },
+                SourceLine {
+                    file: file.clone(),
+                    line: 10 + function as usize,
+                },
+            );
+        }
+
+        let id = MoveModuleId {
+            module_address: AccountAddress::ONE.into(),
+            module_name: "pool".to_string(),
+        };
+        assert_eq!(map.source_line(&id, "swap", 3), Some((file, 11)));
+        assert_eq!(map.source_line(&id, "swap", 4), None);
+        assert_eq!(map.source_line(&id, "withdraw", 3), None);
+        let other = MoveModuleId {
+            module_address: AccountAddress::TWO.into(),
+            module_name: "pool".to_string(),
+        };
+        assert_eq!(map.source_line(&other, "swap", 3), None);
+    }
+}
@@ -80,6 +80,62 @@ pub(crate) mod txns_query {
     pub struct Uint53(pub String);
 }
 
+// query FunctionTransactions(
+//   $function: String!, $checkpoint: UInt53!, $last: Int, $before: String
+// ) {
+//   transactions(
+//     last: $last, before: $before,
+//     filter: {function: $function, beforeCheckpoint: $checkpoint}
+//   ) {
+//     nodes {
+//       transactionBcs
+//       effects {
+//         checkpoint {
+//           sequenceNumber
+//         }
+//         effectsBcs
+//       }
+//     }
+//     pageInfo {
+//       hasPreviousPage
+//       startCursor
+//     }
+//   }
+// }
+
+pub(crate) mod function_txns_query {
+    use super::*;
+    use owned_objects::Uint53;
+    use txns_query::Transaction;
+
+    #[derive(cynic::QueryVariables, Debug)]
+    pub struct FunctionTransactionsVariables {
+        pub function: String,
+        pub checkpoint: Uint53,
+        pub last: Option<i32>,
+        pub before: Option<String>,
+    }
+
+    #[derive(cynic::QueryFragment, Debug)]
+    #[cynic(graphql_type = "Query", variables = "FunctionTransactionsVariables")]
+    pub struct FunctionTransactions {
+        #[arguments(last: $last, before: $before, filter: { function: $function, beforeCheckpoint: $checkpoint })]
+        pub transactions: Option<TransactionConnection>,
+    }
+
+    #[derive(cynic::QueryFragment, Debug)]
+    pub struct TransactionConnection {
+        pub nodes: Vec<Transaction>,
+        pub page_info: PageInfo,
+    }
+
+    #[derive(cynic::QueryFragment, Debug)]
+    pub struct PageInfo {
+        pub has_previous_page: bool,
+        pub start_cursor: Option<String>,
+    }
+}
+
 // query Objects($keys: [ObjectKey!]!) {
 //   multiGetObjects(keys: $keys) {
 //     objectBcs
@@ -722,6 +778,71 @@ impl GraphQlClientInner {
         Ok(mp)
     }
 
+    /// The latest `limit` transactions before `checkpoint` calling into
+    /// `function`, which is a `package`, `package::module` or a full function.
+    pub async fn transactions_calling(
+        &self,
+        function: String,
+        checkpoint: u64,
+        limit: usize,
+    ) -> Result<Vec<TransactionGraphQlResponse>, MovyError> {
+        let mut out = vec![];
+
+        let mut before = None;
+        while out.len() < limit {
+            let query = function_txns_query::FunctionTransactions::build(
+                function_txns_query::FunctionTransactionsVariables {
+                    function: function.clone(),
+                    checkpoint: owned_objects::Uint53(checkpoint),
+                    last: Some(MAX_PER_PAGE.min((limit - out.len()) as i32)),
+                    before,
+                },
+            );
+
+            let response = self
+                .run_query(&query)
+                .await
+                .map_err(|e| eyre!("Failed to run transaction query: {}", e))?;
+
+            let transactions = response
+                .data
+                .and_then(|v| v.transactions)
+                .ok_or_else(|| eyre!("no transactions from request"))?;
+
+            // Pages are in chronological order, walk backwards from the newest.
+            for tx_resp in transactions.nodes.into_iter().rev() {
+                let (Some(tx_bcs), Some(effects)) = (tx_resp.transaction_bcs, tx_resp.effects)
+                else {
+                    continue;
+                };
+                let (Some(effects_bcs), Some(tx_checkpoint)) =
+                    (effects.effects_bcs, effects.checkpoint)
+                else {
+                    continue;
+                };
+                out.push(TransactionGraphQlResponse {
+                    tx: base64_to_object(&tx_bcs.0)?,
+                    effects: base64_to_object(&effects_bcs.0)?,
+                    checkpoint: tx_checkpoint.sequence_number,
+                });
+            }
+
+            if transactions.page_info.has_previous_page {
+                before = Some(
+                    transactions
+                        .page_info
+                        .start_cursor
+                        .ok_or_else(|| eyre!("has previous but not cursor?!"))?,
+                );
+            } else {
+                break;
+            }
+        }
+
+        out.truncate(limit);
+        Ok(out)
+    }
+
     pub async fn query_objects(
         &self,
         keys: Vec<objects_query::ObjectKey>,
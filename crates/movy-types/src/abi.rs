@@ -26,6 +26,7 @@ pub const MOVY_INIT: &str = "movy_init";
 pub const MOVY_ORACLE: &str = "movy_oracle";
 pub const MOVY_PRE: &str = "movy_pre";
 pub const MOVY_POST: &str = "movy_post";
+pub const MOVY_EVENT: &str = "movy_event";
 pub const MOVY_SEQUENCE: &str = "ptb";
 
 bitflags::bitflags! {
@@ -1052,6 +1053,14 @@ impl MoveFunctionAbi {
         }
     }
 
+    pub fn try_derive_movy_event(&self) -> Option<&str> {
+        // movy_event_<EventName>
+        self.name
+            .strip_prefix(MOVY_EVENT)?
+            .strip_prefix('_')
+            .filter(|name| !name.is_empty())
+    }
+
     pub fn is_movy_pre_ptb(&self) -> bool {
         matches!(self.try_derive_movy_pre(), Some(func_name) if func_name == MOVY_SEQUENCE)
     }
@@ -571,7 +571,7 @@ impl Display for SequenceArgument {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq, Eq)]
 pub struct MoveCall {
     pub module_id: MoveAddress,
     pub module_name: String,
@@ -640,7 +640,7 @@ impl From<MoveCall> for ProgrammableMoveCall {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Hash)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, PartialEq, Eq)]
 pub enum MoveSequenceCall {
     // General Movecall
     Call(MoveCall),
@@ -659,6 +659,32 @@ pub enum MoveSequenceCall {
 }
 
 impl MoveSequenceCall {
+    pub fn arguments(&self) -> Vec<&SequenceArgument> {
+        match self {
+            MoveSequenceCall::Call(mc) => mc.arguments.iter().collect(),
+            MoveSequenceCall::TransferObjects(args, dst) => args.iter().chain([dst]).collect(),
+            MoveSequenceCall::SplitCoins(arg, args) | MoveSequenceCall::MergeCoins(arg, args) => {
+                [arg].into_iter().chain(args).collect()
+            }
+            MoveSequenceCall::Publish(_, _) => vec![],
+            MoveSequenceCall::MakeMoveVec(_, args) => args.iter().collect(),
+            MoveSequenceCall::Upgrade(_, _, _, ticket) => vec![ticket],
+        }
+    }
+
+    pub fn arguments_mut(&mut self) -> Vec<&mut SequenceArgument> {
+        match self {
+            MoveSequenceCall::Call(mc) => mc.arguments.iter_mut().collect(),
+            MoveSequenceCall::TransferObjects(args, dst) => args.iter_mut().chain([dst]).collect(),
+            MoveSequenceCall::SplitCoins(arg, args) | MoveSequenceCall::MergeCoins(arg, args) => {
+                [arg].into_iter().chain(args.iter_mut()).collect()
+            }
+            MoveSequenceCall::Publish(_, _) => vec![],
+            MoveSequenceCall::MakeMoveVec(_, args) => args.iter_mut().collect(),
+            MoveSequenceCall::Upgrade(_, _, _, ticket) => vec![ticket],
+        }
+    }
+
     fn fmt_with<F>(&self, mut fmt_arg: F) -> String
     where
         F: FnMut(&SequenceArgument) -> String,
@@ -724,7 +750,7 @@ impl Display for MoveSequenceCall {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Hash, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Hash, Default, PartialEq, Eq)]
 pub struct MoveSequence {
     pub inputs: Vec<InputArgument>,
     pub commands: Vec<MoveSequenceCall>,
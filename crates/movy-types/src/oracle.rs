@@ -1,8 +1,31 @@
-use std::fmt::Display;
+use std::{fmt::Display, path::PathBuf, str::FromStr};
 
+use fastcrypto::hash::{Blake2b256, HashFunction};
 use serde::{Deserialize, Serialize};
 
-use crate::input::MoveStructTag;
+use crate::{
+    abi::MoveModuleId,
+    input::{FunctionIdent, MoveAddress, MoveSequence, MoveStructTag},
+};
+
+/// Fields of `extra` which tell findings of the same oracle and kind apart,
+/// besides the location. Decoded events are left out, since their fields hold
+/// amounts and objects; oracles name their type and crash reason instead.
+const FINGERPRINT_KEYS: &[&str] = &[
+    "function",
+    "location",
+    "pc",
+    "type",
+    "coin_type",
+    "hook",
+    "round_trip",
+    "probe",
+    "abort_code",
+    "message",
+    "hooks",
+    "event_type",
+    "crash_reason",
+];
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub enum Severity {
@@ -14,11 +37,113 @@ pub enum Severity {
     Critical,
 }
 
+/// The bytecode, and source if known, an oracle detected a finding at.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FindingLocation {
+    pub module: MoveModuleId,
+    pub function: String,
+    pub pc: Option<u16>,
+    pub file: Option<PathBuf>,
+    pub line: Option<usize>,
+}
+
+impl FindingLocation {
+    pub fn new(function: &FunctionIdent, pc: Option<u16>) -> Self {
+        Self {
+            module: function.0.clone(),
+            function: function.1.clone(),
+            pc,
+            file: None,
+            line: None,
+        }
+    }
+}
+
+/// A finding of an oracle. `extra` is specific to each oracle, while the
+/// other fields are filled in the same way for all of them by the executor.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 pub struct OracleFinding {
     pub oracle: String,
     pub severity: Severity,
     pub extra: serde_json::Value,
+    #[serde(default)]
+    pub location: Option<FindingLocation>,
+    /// Functions on the call stack at detection, outermost first
+    #[serde(default)]
+    pub stack: Vec<FunctionIdent>,
+    /// Transactions committed before `sequence`, with their senders
+    #[serde(default)]
+    pub prelude: Vec<(MoveAddress, MoveSequence)>,
+    /// The sequence which triggered the finding, as executed
+    #[serde(default)]
+    pub sequence: Option<MoveSequence>,
+    /// Same for findings of the same oracle, kind and location across runs
+    #[serde(default)]
+    pub fingerprint: String,
+}
+
+impl OracleFinding {
+    pub fn new(oracle: impl Into<String>, severity: Severity, extra: serde_json::Value) -> Self {
+        Self {
+            oracle: oracle.into(),
+            severity,
+            extra,
+            location: None,
+            stack: vec![],
+            prelude: vec![],
+            sequence: None,
+            fingerprint: String::new(),
+        }
+    }
+
+    /// Record the call `stack` and the `pc` in its innermost function, unless
+    /// the oracle located the finding itself.
+    pub fn with_trace(mut self, stack: &[FunctionIdent], pc: Option<u16>) -> Self {
+        if self.location.is_none()
+            && let Some(function) = stack.last()
+        {
+            self.location = Some(FindingLocation::new(function, pc));
+        }
+        if self.stack.is_empty() {
+            self.stack = stack.to_vec();
+        }
+        self
+    }
+
+    /// The location named by `function` and `pc` in `extra`, for findings
+    /// detected after execution.
+    pub fn extra_location(&self) -> Option<FindingLocation> {
+        let function = FunctionIdent::from_str(self.extra.get("function")?.as_str()?).ok()?;
+        let pc = self
+            .extra
+            .get("pc")
+            .and_then(|pc| pc.as_u64())
+            .and_then(|pc| u16::try_from(pc).ok());
+        Some(FindingLocation::new(&function, pc))
+    }
+
+    /// Hash of the oracle, the kind and location of the finding, and the
+    /// fields of `extra` identifying it. Amounts, objects and other values
+    /// which differ between runs are left out.
+    pub fn compute_fingerprint(&self) -> String {
+        let mut identity = vec![self.oracle.clone()];
+        if let Some(kind) = self.extra.get("kind").and_then(|kind| kind.as_str()) {
+            identity.push(kind.to_string());
+        }
+        if let Some(location) = &self.location {
+            identity.push(format!(
+                "{}::{}@{:?}",
+                location.module, location.function, location.pc
+            ));
+        }
+        for key in FINGERPRINT_KEYS {
+            if let Some(value) = self.extra.get(key) {
+                identity.push(format!("{}={}", key, value));
+            }
+        }
+        let digest = Blake2b256::digest(identity.join("\n").as_bytes());
+        const_hex::encode(&digest.digest[..16])
+    }
 }
 
 impl Display for Severity {
@@ -59,3 +184,65 @@ impl From<sui_types::event::Event> for Event {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn fingerprints_ignore_amounts_but_not_locations() {
+        let finding = |function: &str, pc: u16, gain: u64| {
+            OracleFinding::new(
+                "OverflowOracle",
+                Severity::Medium,
+                json!({ "function": function, "pc": pc, "gain": gain }),
+            )
+        };
+        let a = finding("0x2::pool::swap", 3, 10);
+        assert_eq!(
+            a.compute_fingerprint(),
+            finding("0x2::pool::swap", 3, 20).compute_fingerprint()
+        );
+        assert_ne!(
+            a.compute_fingerprint(),
+            finding("0x2::pool::swap", 4, 10).compute_fingerprint()
+        );
+
+        let location = a.extra_location().unwrap();
+        assert_eq!(location.function, "swap");
+        assert_eq!(location.pc, Some(3));
+        let traced = a.with_trace(
+            &[FunctionIdent::from_str("0x2::router::route").unwrap()],
+            None,
+        );
+        assert_eq!(traced.location.unwrap().function, "route");
+        assert_eq!(traced.stack.len(), 1);
+    }
+
+    #[test]
+    fn fingerprints_ignore_decoded_events() {
+        let finding = |reason: &str, amount: u64| {
+            OracleFinding::new(
+                "TypedBugOracle",
+                Severity::Critical,
+                json!({
+                    "event_type": "0x1::oracle::Crash",
+                    "crash_reason": reason,
+                    "event": { "amount": amount },
+                    "reason": { "amount": amount },
+                }),
+            )
+        };
+        let a = finding("drained", 1);
+        assert_eq!(
+            a.compute_fingerprint(),
+            finding("drained", 2).compute_fingerprint()
+        );
+        assert_ne!(
+            a.compute_fingerprint(),
+            finding("minted", 1).compute_fingerprint()
+        );
+    }
+}
@@ -0,0 +1,191 @@
+use std::{collections::BTreeMap, path::PathBuf, str::FromStr};
+
+use clap::Args;
+use color_eyre::eyre::eyre;
+use movy_fuzz::{diff::VersionMapping, operations::sui_diff};
+use movy_replay::{env::SuiTestingEnv, meta::Metadata};
+use movy_sui::{
+    database::{cache::CachedStore, empty::EmptyStore, graphql::GraphQlDatabase},
+    rpc::grpc::SuiGrpcArg,
+    utils::TrivialBackStore,
+};
+use movy_types::{error::MovyError, input::MoveAddress};
+use serde::{Deserialize, Serialize};
+
+use crate::sui::{
+    env::{FuzzTargetArgs, SuiTargetArgs, manifest_package_name},
+    prepare::{detach_store, prepare_fuzz_context},
+    utils::{MovyInitRoles, RngSeed, SuiOnchainArguments, may_save_json_value},
+};
+
+/// The old version of a package for `--old`, an onchain address or a path.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum PackageSource {
+    Onchain(MoveAddress),
+    Local(PathBuf),
+}
+
+impl FromStr for PackageSource {
+    type Err = MovyError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("0x")
+            && let Ok(address) = MoveAddress::from_str(s)
+        {
+            return Ok(Self::Onchain(address));
+        }
+        Ok(Self::Local(PathBuf::from(s)))
+    }
+}
+
+#[derive(Args, Clone, Debug, Serialize, Deserialize)]
+pub struct SuiDiffFuzzArgs {
+    #[clap(flatten)]
+    pub roles: MovyInitRoles,
+    #[arg(
+        short,
+        long,
+        help = "rpc to use",
+        default_value = "https://fullnode.mainnet.sui.io"
+    )]
+    pub rpc: SuiGrpcArg,
+    #[arg(long, help = "Time limit of the fuzzing campaign")]
+    pub time_limit: Option<u64>,
+    #[arg(long, help = "Cycle limit fo the fuzzing campaign")]
+    pub cycle_limit: Option<u64>,
+    #[clap(flatten)]
+    pub seed: RngSeed,
+    #[arg(
+        short,
+        long,
+        help = "Ouput directory to save all contents, divergences are saved as crashes"
+    )]
+    pub output: Option<PathBuf>,
+    #[arg(
+        short,
+        long,
+        help = "Force removal of the output directory",
+        env = "MOVY_FORCE_REMOVAL"
+    )]
+    pub force_removal: bool,
+
+    #[arg(short, long, help = "Enable GraphQL fallback")]
+    pub graphql: bool,
+    #[arg(long, help = "Enable GraphQL during deployment")]
+    pub graphql_deployment: bool,
+
+    #[clap(flatten)]
+    pub onchain: SuiOnchainArguments,
+    #[clap(flatten)]
+    pub target: SuiTargetArgs,
+    #[clap(flatten)]
+    pub filters: FuzzTargetArgs,
+    #[arg(
+        long,
+        help = "The old version, an onchain package address or a local package"
+    )]
+    pub old: PackageSource,
+    #[arg(long, help = "The new version, a local package")]
+    pub new: PathBuf,
+}
+
+impl SuiDiffFuzzArgs {
+    pub async fn run(self) -> Result<(), MovyError> {
+        if let Some(output) = &self.output {
+            if output.exists() {
+                tracing::info!("We will remove {}", output.display());
+                if self.force_removal {
+                    std::fs::remove_dir_all(output)?;
+                } else {
+                    return Err(eyre!("The given output is already there, pass -f or env MOVY_FORCE_REMOVAl to always remove it").into());
+                }
+            }
+            std::fs::create_dir_all(output)?;
+        }
+        may_save_json_value(&self.output, "args.json", &self)?;
+
+        // The old version is fuzzed like any other target
+        let mut target = self.target.clone();
+        match &self.old {
+            PackageSource::Onchain(address) => {
+                target.onchains.get_or_insert_default().push(*address)
+            }
+            PackageSource::Local(path) => target.locals.get_or_insert_default().push(path.clone()),
+        }
+        let prepared = prepare_fuzz_context(
+            &self.roles,
+            &self.rpc,
+            &self.seed,
+            self.graphql_deployment,
+            &self.onchain,
+            &target,
+            &self.filters,
+            None,
+        )
+        .await?;
+        let meta = prepared.meta;
+        let old_package = match &self.old {
+            PackageSource::Onchain(address) => *address,
+            PackageSource::Local(path) => manifest_package_name(path)
+                .and_then(|name| prepared.name_mapping.get(&name).copied())
+                .ok_or_else(|| eyre!("can not find the deployed package of {}", path.display()))?,
+        };
+
+        // The new version goes into a copy of the same state
+        let backing = if self.graphql_deployment {
+            TrivialBackStore::T1(GraphQlDatabase::new_mystens(meta.checkpoint))
+        } else {
+            TrivialBackStore::T2(EmptyStore)
+        };
+        let new_store = CachedStore::new(backing);
+        new_store.restore_snapshot(prepared.env.inner().dump_snapshot());
+        let new_env = SuiTestingEnv::new(new_store.wrapped());
+        let rpc = GraphQlDatabase::new_mystens(meta.checkpoint);
+        let (new_package, _, new_abi, _) = new_env
+            .load_local(
+                &self.new,
+                self.roles.deployer,
+                self.roles.attacker,
+                meta.epoch,
+                meta.epoch_ms,
+                meta.gas_id.into(),
+                self.target.unpublished_dependencies,
+                !self.target.disable_dependency_checks,
+                self.target.trace_movy_init,
+                self.target.onchain_fallback,
+                &BTreeMap::new(),
+                &rpc,
+                None,
+                &self.target.isolation.with_extra_sources(),
+            )
+            .await?;
+        new_env.load_inner_types().await?;
+        tracing::info!(
+            "Comparing the old version at {} with the new one at {}",
+            old_package,
+            new_package
+        );
+        let new_meta =
+            Metadata::from_env(&new_env, BTreeMap::from([(new_abi.package_id, new_abi)])).await?;
+        let mapping = VersionMapping::new(&meta, &new_meta, old_package, new_package);
+
+        may_save_json_value(&self.output, "fuzz_meta.json", &meta)?;
+        may_save_json_value(&self.output, "version_mapping.json", &mapping)?;
+
+        let old_store = detach_store(prepared.env, self.graphql, meta.checkpoint);
+        let new_store = detach_store(new_env, self.graphql, meta.checkpoint);
+        tokio::task::spawn_blocking(move || {
+            sui_diff::diff_fuzz(
+                meta,
+                SuiTestingEnv::new(old_store.wrapped()),
+                SuiTestingEnv::new(new_store.wrapped()),
+                new_meta,
+                mapping,
+                &self.output,
+                self.time_limit,
+                self.cycle_limit,
+            )
+        })
+        .await??;
+        Ok(())
+    }
+}
@@ -8,7 +8,7 @@ use std::{
 use clap::Args;
 use color_eyre::eyre::eyre;
 use itertools::Itertools;
-use movy_fuzz::meta::FuzzFunctionScore;
+use movy_fuzz::meta::{FuzzFunctionScore, FuzzMetadata};
 use movy_replay::{
     db::{ObjectStoreCachedStore, ObjectStoreInfo},
     env::SuiTestingEnv,
@@ -57,6 +57,12 @@ pub struct SuiTargetArgs {
     pub unpublished_dependencies: bool,
     #[arg(long, help = "Disable building dependency checks")]
     pub disable_dependency_checks: bool,
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Move packages published by the attacker before fuzzing, e.g. fake coins or witnesses. Their types and functions become available to sequences."
+    )]
+    pub attacker_modules: Option<Vec<PathBuf>>,
     #[clap(flatten)]
     pub isolation: BuildIsolationArgs,
 }
@@ -131,6 +137,8 @@ impl BuildIsolationArgs {
 pub struct DeployResult {
     pub target_packages_deployed: Vec<MoveAddress>,
     pub abis: Vec<(MovePackageAbi, MovePackageAbi, Vec<String>)>,
+    /// Packages published by the attacker with `--attacker-modules`
+    pub attacker_abis: Vec<(MovePackageAbi, Vec<String>)>,
     pub name_mapping: BTreeMap<String, MoveAddress>,
 }
 
@@ -303,24 +311,53 @@ impl SuiTargetArgs {
             .into());
         }
 
+        let mut attacker_abis = vec![];
+        for path in self.attacker_modules.iter().flatten() {
+            let (address, abi, package_names) = env
+                .load_attacker(
+                    path.as_path(),
+                    attacker,
+                    epoch,
+                    epoch_ms,
+                    gas.into(),
+                    self.unpublished_dependencies,
+                    !self.disable_dependency_checks,
+                    &self.isolation.without_extra_sources(),
+                )
+                .await?;
+            add_attacker_names(&mut local_name_map, &package_names, address);
+            attacker_abis.push((abi, package_names));
+        }
+
         tracing::info!("Reload inner types...");
         env.load_inner_types().await?;
 
         Ok(DeployResult {
             target_packages_deployed: target_packages,
             abis: local_abis,
+            attacker_abis,
             name_mapping: local_name_map,
         })
     }
 }
 
-fn bundled_local_package_mapping(
-    local: &std::path::Path,
-) -> Result<Option<(String, MoveAddress)>, MovyError> {
+/// Name the attacker package, and the dependencies it brought along, after
+/// `address` unless a target or an earlier package already took the name.
+fn add_attacker_names(
+    local_name_map: &mut BTreeMap<String, MoveAddress>,
+    package_names: &[String],
+    address: MoveAddress,
+) {
+    for name in package_names.iter() {
+        local_name_map.entry(name.clone()).or_insert(address);
+    }
+}
+
+/// The `name` and `published-at` of the `[package]` section in the manifest
+/// of `local`, None if there is no manifest.
+fn read_manifest_package(local: &std::path::Path) -> Option<(Option<String>, Option<String>)> {
     let manifest = local.join("Move.toml");
-    let Ok(content) = fs::read_to_string(&manifest) else {
-        return Ok(None);
-    };
+    let content = fs::read_to_string(&manifest).ok()?;
 
     let mut in_package = false;
     let mut name = None::<String>;
@@ -344,6 +381,20 @@ fn bundled_local_package_mapping(
             }
         }
     }
+    Some((name, published_at))
+}
+
+/// The package name declared in the manifest of `local`.
+pub(crate) fn manifest_package_name(local: &std::path::Path) -> Option<String> {
+    read_manifest_package(local).and_then(|(name, _)| name)
+}
+
+fn bundled_local_package_mapping(
+    local: &std::path::Path,
+) -> Result<Option<(String, MoveAddress)>, MovyError> {
+    let Some((name, published_at)) = read_manifest_package(local) else {
+        return Ok(None);
+    };
 
     if name.as_deref() == Some("movy") && published_at.as_deref() == Some("0xdeadbeef") {
         return Ok(Some((
@@ -563,3 +614,147 @@ impl PrivilegeFunctionScoreSelector {
         })
     }
 }
+
+/// A `[pkg::]module::function[:line]` entry for `--target-location`.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TargetLocationSelector {
+    pub package: Option<PackageRef>,
+    pub module: String,
+    pub function: String,
+    pub line: Option<usize>,
+}
+
+impl std::str::FromStr for TargetLocationSelector {
+    type Err = MovyError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (path, line) = match s.rsplit_once(':') {
+            Some((path, line)) if !path.ends_with(':') => {
+                let line = line.parse::<usize>().map_err(|_| {
+                    MovyError::InvalidIdentifier(format!("Invalid line in target location: {}", s))
+                })?;
+                (path, Some(line))
+            }
+            _ => (s, None),
+        };
+        let parts = path.split("::").collect_vec();
+        let (package, module, function) = match parts.as_slice() {
+            [module, function] => (None, module, function),
+            [package, module, function] => (
+                Some(match MoveAddress::from_str(package) {
+                    Ok(addr) => PackageRef::Address(addr),
+                    Err(_) => PackageRef::Named(package.to_string()),
+                }),
+                module,
+                function,
+            ),
+            _ => {
+                return Err(MovyError::InvalidIdentifier(format!(
+                    "Invalid target location string: {}, expected [pkg::]module::function[:line]",
+                    s
+                )));
+            }
+        };
+        Ok(Self {
+            package,
+            module: module.to_string(),
+            function: function.to_string(),
+            line,
+        })
+    }
+}
+
+impl TargetLocationSelector {
+    /// Resolve to a function ident. Without an explicit package, the module
+    /// must be unique among the target packages.
+    pub fn to_ident(
+        &self,
+        local_name_map: &BTreeMap<String, MoveAddress>,
+        meta: &FuzzMetadata,
+    ) -> Result<FunctionIdent, MovyError> {
+        let address = match &self.package {
+            Some(package) => package.resolve(local_name_map)?,
+            None => {
+                let candidates = meta
+                    .target_packages
+                    .iter()
+                    .filter(|addr| {
+                        meta.get_package_metadata(addr).is_some_and(|abi| {
+                            abi.modules
+                                .iter()
+                                .any(|m| m.module_id.module_name == self.module)
+                        })
+                    })
+                    .collect_vec();
+                match candidates.as_slice() {
+                    [addr] => **addr,
+                    [] => {
+                        return Err(eyre!(
+                            "module {} is not found in the target packages",
+                            self.module
+                        )
+                        .into());
+                    }
+                    _ => {
+                        return Err(eyre!(
+                            "module {} is ambiguous, prefix it with a package",
+                            self.module
+                        )
+                        .into());
+                    }
+                }
+            }
+        };
+        let ident = FunctionIdent::new(&address, &self.module, &self.function);
+        if meta
+            .get_function(&address, &self.module, &self.function)
+            .is_none()
+        {
+            return Err(eyre!("target function {} is not found", ident).into());
+        }
+        Ok(ident)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::*;
+
+    #[test]
+    fn target_locations_parse_with_optional_package_and_line() {
+        let location = TargetLocationSelector::from_str("pool::swap").unwrap();
+        assert_eq!(location.package, None);
+        assert_eq!(location.module, "pool");
+        assert_eq!(location.function, "swap");
+        assert_eq!(location.line, None);
+
+        let location = TargetLocationSelector::from_str("0xa::pool::swap:42").unwrap();
+        assert_eq!(
+            location.package,
+            Some(PackageRef::Address(MoveAddress::from_str("0xa").unwrap()))
+        );
+        assert_eq!(location.line, Some(42));
+
+        let location = TargetLocationSelector::from_str("amm::pool::swap").unwrap();
+        assert_eq!(location.package, Some(PackageRef::Named("amm".to_string())));
+
+        assert!(TargetLocationSelector::from_str("swap").is_err());
+        assert!(TargetLocationSelector::from_str("pool::swap:x").is_err());
+        assert!(TargetLocationSelector::from_str("a::b::c::d").is_err());
+    }
+
+    #[test]
+    fn attacker_names_never_shadow_targets() {
+        let target = MoveAddress::from_str("0xa").unwrap();
+        let attacker = MoveAddress::from_str("0xb").unwrap();
+        let mut names = BTreeMap::from([("amm".to_string(), target)]);
+        add_attacker_names(
+            &mut names,
+            &["amm".to_string(), "exploit".to_string()],
+            attacker,
+        );
+        assert_eq!(names["amm"], target);
+        assert_eq!(names["exploit"], attacker);
+    }
+}
@@ -1,21 +1,28 @@
-use std::{path::PathBuf, sync::Arc};
+use std::{collections::BTreeSet, path::PathBuf, time::Duration};
 
 use clap::Args;
 use color_eyre::eyre::eyre;
-use movy_fuzz::operations::sui_fuzz;
+use movy_fuzz::{
+    dict::FuzzDictionary,
+    directed::DirectedMetadata,
+    flash::{FlashConfig, FlashProviderConfig},
+    history::seeds_from_transactions,
+    operations::campaign::FuzzCampaignBuilder,
+    oracles::config::OracleSuiteConfig,
+    round_trip::RoundTripConfig,
+    seed::load_seeds,
+};
 use movy_replay::{db::ObjectStoreCachedStore, env::SuiTestingEnv};
 use movy_sui::{
-    database::{cache::CachedStore, empty::EmptyStore, graphql::GraphQlDatabase},
     lcov::LineCoverageMap,
-    rpc::grpc::SuiGrpcArg,
-    utils::TrivialBackStore,
+    rpc::{graphql::GraphQlClient, grpc::SuiGrpcArg},
 };
 use movy_types::error::MovyError;
 use serde::{Deserialize, Serialize};
 
 use crate::sui::{
-    env::{FuzzTargetArgs, SuiTargetArgs},
-    prepare::prepare_fuzz_context,
+    env::{FuzzTargetArgs, SuiTargetArgs, TargetLocationSelector},
+    prepare::{detach_store, prepare_fuzz_context, resolve_type_tag},
     utils::{MovyInitRoles, RngSeed, SuiOnchainArguments, may_save_bytes, may_save_json_value},
 };
 
@@ -75,8 +82,73 @@ pub struct SuiFuzzArgs {
         default_value_t = false
     )]
     pub disable_defects_oracle: bool,
+    #[arg(
+        long,
+        help = "TOML/JSON file enabling, tuning and scoping individual oracles, see OracleSuiteConfig"
+    )]
+    pub oracles: Option<PathBuf>,
     #[arg(long, help = "Write line coverage in lcov format to this file")]
     pub lcov: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Direct the campaign towards [pkg::]module::function[:line], lines require --locals"
+    )]
+    pub target_location: Option<TargetLocationSelector>,
+    #[arg(
+        long,
+        help = "TOML/JSON dictionary of constants, addresses and types to seed mutations with"
+    )]
+    pub dict: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Directory of TOML/JSON seed sequences added to the corpus before fuzzing"
+    )]
+    pub seeds: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Seed the corpus with up to this many recent on-chain transactions of each --onchains/--histories package"
+    )]
+    pub history_seeds: Option<usize>,
+    #[arg(
+        long,
+        help = "Also mutate numeric fields of shared objects, findings then require the patched state"
+    )]
+    pub state_fuzz: bool,
+    #[arg(
+        long,
+        help = "Solve integers as bit-vectors of their width and flip the branches of a trace one by one"
+    )]
+    pub concolic_bv: bool,
+    #[arg(
+        long,
+        help = "Fuzz inside the flash loan of this provider from --flash-config, or of a cetus/nemo provider given as an inline TOML table"
+    )]
+    pub flash: Option<String>,
+    #[arg(
+        long,
+        help = "TOML/JSON file of flash loan providers, their packages must be loaded too"
+    )]
+    pub flash_config: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Draw a live status screen, requires building with the tui feature"
+    )]
+    pub tui: bool,
+    #[arg(
+        long,
+        help = "Local package of the next version, upgraded to right before the attacker transaction"
+    )]
+    pub upgrade: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Directory of TOML/JSON sequences the deployer must still be able to run after an attack"
+    )]
+    pub liveness_probes: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "TOML/JSON file of paired functions, e.g. deposit and withdraw, checked for rounding in the attacker's favor"
+    )]
+    pub round_trips: Option<PathBuf>,
 }
 
 impl SuiFuzzArgs {
@@ -92,6 +164,9 @@ impl SuiFuzzArgs {
             }
             std::fs::create_dir_all(output)?;
         }
+        if self.tui && !cfg!(feature = "tui") {
+            return Err(eyre!("--tui requires movy built with the tui feature").into());
+        }
         may_save_json_value(&self.output, "args.json", &self)?;
         let prepared = prepare_fuzz_context(
             &self.roles,
@@ -101,54 +176,195 @@ impl SuiFuzzArgs {
             &self.onchain,
             &self.target,
             &self.filters,
+            self.upgrade.as_deref(),
         )
         .await?;
         let testing_env = prepared.env;
-        let meta = prepared.meta;
+        let mut meta = prepared.meta;
+        if let Some(dict) = &self.dict {
+            meta.apply_dictionary(&FuzzDictionary::from_file(dict)?, &|ty: &str| {
+                resolve_type_tag(ty, &prepared.name_mapping)
+            })?;
+        }
+        let mut seeds = self
+            .seeds
+            .as_ref()
+            .map(|dir| {
+                load_seeds(
+                    dir,
+                    &meta,
+                    testing_env.inner(),
+                    &prepared.name_mapping,
+                    |ty| resolve_type_tag(ty, &prepared.name_mapping),
+                )
+            })
+            .transpose()?
+            .unwrap_or_default();
+        if let Some(limit) = self.history_seeds {
+            let graphql = GraphQlClient::new_mystens();
+            let packages = self
+                .target
+                .onchains
+                .iter()
+                .flatten()
+                .chain(self.target.histories.iter().flatten())
+                .collect::<BTreeSet<_>>();
+            let mut txs = vec![];
+            for package in packages {
+                tracing::info!(
+                    "Fetching at most {} transactions calling {}",
+                    limit,
+                    package
+                );
+                let fetched = graphql
+                    .transactions_calling(package.to_canonical_string(true), meta.checkpoint, limit)
+                    .await?;
+                txs.extend(fetched.into_iter().map(|resp| resp.tx));
+            }
+            seeds.extend(seeds_from_transactions(&meta, testing_env.inner(), &txs));
+        }
+        if let Some(dir) = &self.liveness_probes {
+            let probes = load_seeds(
+                dir,
+                &meta,
+                testing_env.inner(),
+                &prepared.name_mapping,
+                |ty| resolve_type_tag(ty, &prepared.name_mapping),
+            )?;
+            meta.liveness_probes = probes.into_iter().map(|input| input.sequence).collect();
+        }
+        if let Some(config) = &self.round_trips {
+            meta.round_trips = RoundTripConfig::from_file(config)?.build(
+                &meta,
+                testing_env.inner(),
+                &prepared.name_mapping,
+                &|ty: &str| resolve_type_tag(ty, &prepared.name_mapping),
+            )?;
+        }
+        let oracle_config = match &self.oracles {
+            Some(config) => OracleSuiteConfig::from_file(config)?,
+            None => OracleSuiteConfig::default(),
+        };
+        let resolve = |ty: &str| resolve_type_tag(ty, &prepared.name_mapping);
+        let flash = match (&self.flash, &self.flash_config) {
+            (Some(inline), _) if inline.trim_start().starts_with('{') => {
+                Some(FlashProviderConfig::from_inline(inline)?.build(
+                    &meta,
+                    testing_env.inner(),
+                    &prepared.name_mapping,
+                    &resolve,
+                )?)
+            }
+            (Some(name), Some(config)) => Some(FlashConfig::from_file(config)?.provider(
+                name,
+                &meta,
+                testing_env.inner(),
+                &prepared.name_mapping,
+                &resolve,
+            )?),
+            (Some(name), None) => {
+                return Err(eyre!(
+                    "--flash {} requires --flash-config, only cetus and nemo providers can be inline",
+                    name
+                )
+                .into());
+            }
+            (None, _) => None,
+        };
+        let target_line = self
+            .target_location
+            .as_ref()
+            .and_then(|location| location.line);
+        let line_map = LineCoverageMap::for_locals_with_package_ids(
+            self.target.locals.as_deref().unwrap_or_default(),
+            true,
+            &meta.target_packages,
+            &self.target.isolation.without_extra_sources(),
+        );
+        let line_map = if self.lcov.is_some() || target_line.is_some() {
+            line_map?
+        } else {
+            // Only used to locate findings in the sources then
+            line_map.unwrap_or_else(|e| {
+                tracing::warn!("Findings will lack source lines: {}", e);
+                None
+            })
+        };
         let lcov = self
             .lcov
             .as_ref()
             .map(|path| {
-                LineCoverageMap::for_locals_with_package_ids(
-                    self.target.locals.as_deref().unwrap_or_default(),
-                    true,
-                    &meta.target_packages,
-                    &self.target.isolation.without_extra_sources(),
-                )?
-                .map(|map| (path.clone(), map))
-                .ok_or_else(|| {
-                    MovyError::from(eyre!("--lcov requires at least one --locals package"))
-                })
+                line_map
+                    .clone()
+                    .map(|map| (path.clone(), map))
+                    .ok_or_else(|| {
+                        MovyError::from(eyre!("--lcov requires at least one --locals package"))
+                    })
+            })
+            .transpose()?;
+        let directed = self
+            .target_location
+            .as_ref()
+            .map(|location| {
+                let target = location.to_ident(&prepared.name_mapping, &meta)?;
+                let locations = match target_line {
+                    Some(line) => {
+                        let map = line_map.as_ref().ok_or_else(|| {
+                            eyre!("--target-location with a line requires at least one --locals package")
+                        })?;
+                        let locations = map.locations_for_line(&target.0, &target.1, line);
+                        if locations.is_empty() {
+                            return Err(eyre!("no bytecode of {} is at line {}", target, line).into());
+                        }
+                        locations
+                    }
+                    None => Default::default(),
+                };
+                DirectedMetadata::from_store(
+                    &meta,
+                    testing_env.inner(),
+                    &target,
+                    target_line,
+                    locations,
+                )
             })
             .transpose()?;
 
         may_save_json_value(&self.output, "fuzz_meta.json", &meta)?;
         may_save_bytes(&self.output, "env.bin", &testing_env.inner().dump().await?)?;
 
-        // Arc<T> is send only if T is Sync while RefCell is not.
-        let inner = testing_env.into_inner();
-        let inner = Arc::try_unwrap(inner).unwrap();
-        let dump = inner.inner.take();
-        let inner = if self.graphql {
-            TrivialBackStore::T1(GraphQlDatabase::new_mystens(meta.checkpoint))
-        } else {
-            TrivialBackStore::T2(EmptyStore)
-        };
-        let store = CachedStore::new(inner);
-        store.restore_snapshot(dump);
+        let store = detach_store(testing_env, self.graphql, meta.checkpoint);
         tokio::task::spawn_blocking(move || {
             let env = SuiTestingEnv::new(store.wrapped());
-            sui_fuzz::fuzz(
-                meta,
-                env,
-                &self.output,
-                self.time_limit,
-                self.cycle_limit,
-                self.typed_bug_abort,
-                self.disable_profit_oracle,
-                self.disable_defects_oracle,
-                lcov,
-            )
+            let mut builder = FuzzCampaignBuilder::new(meta, env)
+                .typed_bug_abort(self.typed_bug_abort)
+                .disable_profit_oracle(self.disable_profit_oracle)
+                .disable_defects_oracle(self.disable_defects_oracle)
+                .oracle_config(oracle_config)
+                .seeds(seeds)
+                .state_fuzz(self.state_fuzz)
+                .bitvector(self.concolic_bv)
+                .flash(flash)
+                .tui(self.tui);
+            if let Some(output) = &self.output {
+                builder = builder.output_dir(output);
+            }
+            if let Some(limit) = self.time_limit {
+                builder = builder.time_limit(Duration::from_secs(limit));
+            }
+            if let Some(limit) = self.cycle_limit {
+                builder = builder.cycle_limit(limit);
+            }
+            if let Some((path, map)) = lcov {
+                builder = builder.lcov(path, map);
+            }
+            if let Some(map) = line_map {
+                builder = builder.source_map(map);
+            }
+            if let Some(directed) = directed {
+                builder = builder.directed(directed);
+            }
+            builder.run()
         })
         .await??;
         Ok(())
@@ -54,6 +54,7 @@ impl SuiFuzzOneArgs {
             &self.onchain,
             &self.target,
             &self.filters,
+            None,
         )
         .await?;
         let lcov_map = self
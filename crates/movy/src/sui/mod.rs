@@ -2,12 +2,13 @@ use clap::{Args, Subcommand};
 use movy_types::error::MovyError;
 
 use crate::sui::{
-    deploy::SuiBuildDeployArgs, fuzz::SuiFuzzArgs, fuzz_one::SuiFuzzOneArgs,
-    replay::SuiReplaySeedArgs, static_analysis::SuiStaticAnalysisArgs, test::SuiTestArgs,
-    trace::SuiTraceArgs,
+    deploy::SuiBuildDeployArgs, diff_fuzz::SuiDiffFuzzArgs, fuzz::SuiFuzzArgs,
+    fuzz_one::SuiFuzzOneArgs, replay::SuiReplaySeedArgs, static_analysis::SuiStaticAnalysisArgs,
+    test::SuiTestArgs, trace::SuiTraceArgs,
 };
 
 pub mod deploy;
+pub mod diff_fuzz;
 pub mod env;
 pub mod fuzz;
 pub mod fuzz_one;
@@ -24,6 +25,8 @@ pub enum SuiSubcommand {
     Fuzz(SuiFuzzArgs),
     #[clap(name = "fuzz-one")]
     FuzzOne(SuiFuzzOneArgs),
+    #[clap(name = "diff-fuzz")]
+    DiffFuzz(SuiDiffFuzzArgs),
     Test(SuiTestArgs),
     BuildDeploy(SuiBuildDeployArgs),
     ReplaySeed(SuiReplaySeedArgs),
@@ -64,6 +67,7 @@ impl SuiArgs {
             SuiSubcommand::TraceTx(args) => args.run().await?,
             SuiSubcommand::Fuzz(args) => args.run().await?,
             SuiSubcommand::FuzzOne(args) => args.run().await?,
+            SuiSubcommand::DiffFuzz(args) => args.run().await?,
             SuiSubcommand::Test(args) => args.run().await?,
             SuiSubcommand::StaticAnalysis(args) => args.run().await?,
             SuiSubcommand::ReplaySeed(args) => args.run().await?,
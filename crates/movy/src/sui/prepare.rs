@@ -1,10 +1,14 @@
-use std::{collections::BTreeMap, str::FromStr, sync::Arc};
+use std::{collections::BTreeMap, path::Path, str::FromStr, sync::Arc};
 
-use movy_fuzz::meta::{FuzzFunctionScore, FuzzMetadata, TargetFilters};
+use color_eyre::eyre::eyre;
+use movy_fuzz::{
+    meta::{FuzzFunctionScore, FuzzMetadata, TargetFilters},
+    upgrade::{UpgradePlan, find_upgrade_cap},
+};
 use movy_replay::{
     db::{ObjectStoreInfo, ObjectStoreMintObject},
     env::SuiTestingEnv,
-    exec::very_big_gas,
+    exec::{SuiExecutor, very_big_gas},
 };
 use movy_sui::{
     database::{cache::CachedStore, empty::EmptyStore, graphql::GraphQlDatabase},
@@ -22,7 +26,7 @@ use sui_types::base_types::ObjectID;
 use crate::sui::{
     env::{
         DeployResult, FunctionSelector, FuzzTargetArgs, ModuleSelector, PackageSelector,
-        PrivilegeFunctionScoreSelector, SuiTargetArgs,
+        PrivilegeFunctionScoreSelector, SuiTargetArgs, manifest_package_name,
     },
     utils::{MovyInitRoles, RngSeed, SuiOnchainArguments},
 };
@@ -36,6 +40,25 @@ pub(crate) struct PreparedFuzzContext {
     pub name_mapping: BTreeMap<String, MoveAddress>,
 }
 
+/// Move the store out of its `Arc` so that it can be sent to the fuzzing
+/// thread. Arc<T> is send only if T is Sync while RefCell is not.
+pub(crate) fn detach_store(
+    env: SuiTestingEnv<PreparedStore>,
+    graphql: bool,
+    checkpoint: u64,
+) -> CachedStore<TrivialBackStore<GraphQlDatabase, EmptyStore>> {
+    let inner = Arc::try_unwrap(env.into_inner()).unwrap();
+    let dump = inner.inner.take();
+    let inner = if graphql {
+        TrivialBackStore::T1(GraphQlDatabase::new_mystens(checkpoint))
+    } else {
+        TrivialBackStore::T2(EmptyStore)
+    };
+    let store = CachedStore::new(inner);
+    store.restore_snapshot(dump);
+    store
+}
+
 fn resolve_modules(
     mods: &Option<Vec<ModuleSelector>>,
     local_name_map: &BTreeMap<String, MoveAddress>,
@@ -203,6 +226,7 @@ pub(crate) async fn prepare_fuzz_context(
     onchain: &SuiOnchainArguments,
     target: &SuiTargetArgs,
     filter_args: &FuzzTargetArgs,
+    upgrade: Option<&Path>,
 ) -> Result<PreparedFuzzContext, MovyError> {
     let mut rand = seed.rng();
     let graphql = GraphQlClient::new_mystens();
@@ -236,6 +260,7 @@ pub(crate) async fn prepare_fuzz_context(
     let DeployResult {
         target_packages_deployed: target_packages,
         abis: local_abis,
+        attacker_abis,
         name_mapping: mut local_name_map,
     } = target
         .build_env(
@@ -263,6 +288,16 @@ pub(crate) async fn prepare_fuzz_context(
         }
     }
 
+    let mut attacker_packages = vec![];
+    for (abi, names) in attacker_abis {
+        let package = abi.package_id;
+        abis.insert(package, abi);
+        for name in names {
+            local_name_map.entry(name).or_insert(package);
+        }
+        attacker_packages.push(package);
+    }
+
     for target in target_packages.iter() {
         if !abis.contains_key(target) {
             let abi = testing_env.inner().get_package_info(*target)?.unwrap();
@@ -292,7 +327,45 @@ pub(crate) async fn prepare_fuzz_context(
         exclude_types: resolve_type_tags(&filter_args.exclude_types, &local_name_map)?,
     };
 
-    let meta = FuzzMetadata::from_env(
+    // Version N+1 is only published for the metadata, the fuzzer upgrades in
+    // the middle of every input
+    let upgrade = match upgrade {
+        Some(path) => {
+            let package = match manifest_package_name(path)
+                .and_then(|name| local_name_map.get(&name).copied())
+            {
+                Some(package) => package,
+                None if target_packages.len() == 1 => target_packages[0],
+                None => {
+                    return Err(
+                        eyre!("can not tell which package {} upgrades", path.display()).into(),
+                    );
+                }
+            };
+            let (upgrade_cap, owner) = find_upgrade_cap(&testing_env, package).await?;
+            let plan = UpgradePlan::build(
+                path,
+                package,
+                upgrade_cap,
+                owner,
+                target.unpublished_dependencies,
+                !target.disable_dependency_checks,
+                &target.isolation.with_extra_sources(),
+            )?;
+            let snapshot = testing_env.inner().dump_snapshot();
+            let mut executor = SuiExecutor::new(testing_env.inner().clone())?;
+            plan.apply(&mut executor, primitives.epoch, primitives.epoch_ms)?;
+            tracing::info!(
+                "Fuzzing through the upgrade of {} to {}",
+                plan.package,
+                plan.upgraded
+            );
+            Some((plan, snapshot))
+        }
+        None => None,
+    };
+
+    let mut meta = FuzzMetadata::from_env(
         &testing_env,
         rand,
         resolve_function_scores(&filter_args.privilege_functions, &local_name_map)?,
@@ -308,6 +381,12 @@ pub(crate) async fn prepare_fuzz_context(
         filters,
     )
     .await?;
+    meta.add_attacker_functions(&attacker_packages);
+    if let Some((plan, snapshot)) = upgrade {
+        meta.set_upgrade(plan);
+        testing_env.inner().reset();
+        testing_env.inner().restore_snapshot(snapshot);
+    }
 
     Ok(PreparedFuzzContext {
         env: testing_env,
@@ -3,9 +3,12 @@ use std::{path::PathBuf, sync::Arc};
 use clap::Args;
 use color_eyre::eyre::eyre;
 use movy_fuzz::{
+    executor::commit_prelude,
     input::MoveFuzzInput,
     meta::FuzzMetadata,
     operations::sui_replay::{sui_fuzz_replay_seed, sui_plain_replay_seed},
+    oracles::config::OracleSuiteConfig,
+    state_fuzz::apply_state_patches,
 };
 use movy_replay::{
     env::SuiTestingEnv,
@@ -44,6 +47,11 @@ pub struct SuiReplaySeedArgs {
     pub lcov: Option<PathBuf>,
     #[arg(short, long, help = "Local packages to use for lcov source maps")]
     pub locals: Option<Vec<PathBuf>>,
+    #[arg(
+        long,
+        help = "TOML/JSON oracle settings the seed was fuzzed with, only used with --fuzz"
+    )]
+    pub oracles: Option<PathBuf>,
 }
 
 impl SuiReplaySeedArgs {
@@ -76,16 +84,28 @@ impl SuiReplaySeedArgs {
                 MovyError::from(eyre!("--lcov requires at least one --locals package"))
             })?;
             let coverage = LineCoverageCollector::new();
-            let executor = SuiExecutor::new(env.into_inner())?;
+            let mut executor = SuiExecutor::new(env.into_inner())?.layered();
+            apply_state_patches(&executor, &seed.state_patches)?;
+            let (sequence, epoch, epoch_ms) = commit_prelude(
+                &mut executor,
+                &seed.prelude,
+                &seed.sequence,
+                meta.attacker,
+                meta.gas_id,
+                meta.epoch,
+                meta.epoch_ms,
+                meta.upgrade.as_ref(),
+                None,
+            )?;
             let tracer = if self.trace {
                 SelectiveTracer::T1(TeeTracer(TreeTracer::new(), coverage.tracer()))
             } else {
                 SelectiveTracer::T2(coverage.tracer())
             };
             let out = executor.run_ptb_with_movy_tracer_gas(
-                seed.sequence.to_ptb()?,
-                meta.epoch,
-                meta.epoch_ms,
+                sequence.to_ptb()?,
+                epoch,
+                epoch_ms,
                 meta.attacker.into(),
                 meta.gas_id.into(),
                 Some(tracer),
@@ -96,7 +116,11 @@ impl SuiReplaySeedArgs {
             }
             map.write_lcov(coverage.hits(), lcov)?;
         } else if self.fuzz {
-            sui_fuzz_replay_seed(env, meta, seed)?;
+            let oracle_config = match &self.oracles {
+                Some(config) => OracleSuiteConfig::from_file(config)?,
+                None => OracleSuiteConfig::default(),
+            };
+            sui_fuzz_replay_seed(env, meta, seed, &oracle_config)?;
         } else {
             sui_plain_replay_seed(env, meta, seed, self.trace)?;
         }
@@ -85,6 +85,7 @@ impl SuiTestArgs {
             &self.onchain,
             &self.target,
             &self.filters,
+            None,
         )
         .await?;
         let mut meta = prepared.meta;